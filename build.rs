@@ -0,0 +1,24 @@
+//! Embeds every localisation file of `resources/localisation` into the binary, so all languages
+//! are available without the directory (see `localisator::bundled_languages`).
+use std::path::Path;
+
+fn main() {
+    let dir = Path::new("resources/localisation");
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let mut languages = Vec::new();
+    for entry in std::fs::read_dir(dir).expect("resources/localisation is missing") {
+        let path = entry.expect("unreadable localisation directory").path();
+        if path.extension().is_some_and(|ext| ext == "yaml") {
+            let language = path.file_stem().unwrap().to_string_lossy().into_owned();
+            languages.push((language, std::fs::canonicalize(&path).unwrap()));
+        }
+    }
+    languages.sort();
+    let mut code = String::from("&[\n");
+    for (language, path) in languages {
+        code.push_str(&format!("    ({:?}, include_str!({:?})),\n", language, path));
+    }
+    code.push(']');
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("bundled_languages.rs");
+    std::fs::write(out, code).unwrap();
+}
@@ -0,0 +1,255 @@
+use crate::error::ScanError;
+use crate::scanner::{
+    backoff_delay, connect_failed, identify_open_port, scan_port_with_options, scan_udp_port_with_options,
+    target_addr, CancelToken, Connection, PortState, Protocol, ScanOptions, ScanResult, ScanState, ScanStats,
+    AUTO_TUNE_TICK,
+};
+use crate::signatures::Signature;
+use indicatif::ProgressBar;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+
+/// Flags shared by the tasks of an async scan, like the atomics of `scan_work_parallel`.
+#[derive(Default)]
+struct ScanFlags {
+    found_open: AtomicBool,
+    network_failure: AtomicBool,
+    timed_out: AtomicBool,
+    ports_timed_out: AtomicU64,
+}
+
+/// Scan protocol-tagged ports of one host on a tokio runtime, see `Engine::Async`.
+///
+/// TCP connects are made asynchronously, so waiting on filtered ports doesn't tie up a thread
+/// each; at most `max_threads` ports are in flight at once, bounded by a semaphore. The probe of
+/// an open port, UDP ports and connects through `ssh_jump` or `source_ports` run on the blocking
+/// pool of the runtime, which is capped at `max_threads` as well. Results, retries, statistics,
+/// `first_open_only`, `target_timeout` and cancelling behave as with `scan_work_parallel`;
+/// `auto_tune` and `ramp_up` don't apply, the concurrency stays at `max_threads`.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `work` - The protocol-tagged ports to scan, already checked by the caller.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options.
+/// * `pb` - A reference to a ProgressBar counting scanned and skipped ports.
+/// * `on_result` - Called on the calling thread for every result as soon as it is found.
+///
+/// # Returns
+/// * `Ok((Vec<ScanResult>, ScanStats))` - The results sorted by port and protocol, and the stats.
+/// * `Err(ScanError)` - If the runtime can't be started.
+///
+pub fn scan_work_async<F>(
+    ip: Arc<IpAddr>,
+    mut work: impl Iterator<Item = (Protocol, u16)>,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+    mut on_result: F,
+) -> Result<(Vec<ScanResult>, ScanStats), ScanError>
+where
+    F: FnMut(&ScanResult),
+{
+    let started = Instant::now();
+    let threads = options.max_threads.max(1);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .max_blocking_threads(threads)
+        .build()
+        .map_err(ScanError::Io)?;
+    let limit = Arc::new(Semaphore::new(threads));
+    let shared_options = Arc::new(options.clone());
+    let state = Arc::new(ScanState::new(options.max_retries_total));
+    let flags = Arc::new(ScanFlags::default());
+    let cancelled = || options.cancel.as_ref().is_some_and(CancelToken::is_cancelled);
+    let source_ports_exhausted = || options.source_ports.as_ref().map_or(0, |range| range.exhausted());
+    // The range counter is shared by every clone of the options, so only count this scan
+    let exhausted_before = source_ports_exhausted();
+    let mut result = Vec::new();
+    let mut collect = |res: Option<ScanResult>| match res {
+        // Ports in flight may still turn up open; only the first one is kept
+        Some(res) if options.first_open_only && (res.state != PortState::Open || !result.is_empty()) => {}
+        Some(res) => {
+            on_result(&res);
+            result.push(res);
+        }
+        None => {}
+    };
+    runtime.block_on(async {
+        let mut tasks = JoinSet::new();
+        let mut queuing = true;
+        loop {
+            while let Some(joined) = tasks.try_join_next() {
+                collect(joined.ok().flatten());
+            }
+            // Nothing more is queued; the ports already queued are skipped by the tasks
+            if flags.network_failure.load(Ordering::SeqCst) {
+                queuing = false;
+            }
+            if options.target_timeout.is_some_and(|timeout| started.elapsed() >= timeout) && queuing {
+                flags.timed_out.store(true, Ordering::SeqCst);
+                queuing = false;
+                flags.ports_timed_out.fetch_add(work.by_ref().count() as u64, Ordering::SeqCst);
+            }
+            // Tasks still running are dropped with the runtime, unreported
+            if cancelled() {
+                break;
+            }
+            if !queuing {
+                if tasks.is_empty() {
+                    break;
+                }
+                if let Ok(Some(joined)) = timeout(AUTO_TUNE_TICK, tasks.join_next()).await {
+                    collect(joined.ok().flatten());
+                }
+                continue;
+            }
+            // Waiting for a free slot is bounded, so cancelling and timeouts are noticed
+            let Ok(Ok(permit)) = timeout(AUTO_TUNE_TICK, Arc::clone(&limit).acquire_owned()).await else {
+                continue;
+            };
+            let Some((protocol, port)) = work.next() else {
+                queuing = false;
+                continue;
+            };
+            tasks.spawn(scan_unit(
+                Arc::clone(&ip),
+                protocol,
+                port,
+                Arc::clone(&signatures),
+                Arc::clone(&shared_options),
+                Arc::clone(&state),
+                Arc::clone(&flags),
+                pb.clone(),
+                permit,
+            ));
+        }
+    });
+    let cancelled = cancelled();
+    // A cancelled scan returns right away; probes in flight finish in the background
+    runtime.shutdown_background();
+    result.sort_by_key(|r| (r.port, r.protocol));
+    let stats = ScanStats {
+        source_ports_exhausted: source_ports_exhausted() - exhausted_before,
+        cancelled,
+        network_failure: flags.network_failure.load(Ordering::SeqCst),
+        timed_out: flags.timed_out.load(Ordering::SeqCst),
+        ports_timed_out: flags.ports_timed_out.load(Ordering::SeqCst),
+        ..ScanStats::from_state(&state, options, threads)
+    };
+    Ok((result, stats))
+}
+
+/// Scan one port of an async scan, holding `_permit` until it is done.
+#[allow(clippy::too_many_arguments)]
+async fn scan_unit(
+    ip: Arc<IpAddr>,
+    protocol: Protocol,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+    options: Arc<ScanOptions>,
+    state: Arc<ScanState>,
+    flags: Arc<ScanFlags>,
+    progress: ProgressBar,
+    _permit: OwnedSemaphorePermit,
+) -> Option<ScanResult> {
+    // Ports still queued after the first open one are skipped in first-open-only mode
+    let res = match protocol {
+        _ if options.first_open_only && flags.found_open.load(Ordering::SeqCst) => None,
+        _ if options.cancel.as_ref().is_some_and(CancelToken::is_cancelled) => None,
+        _ if flags.network_failure.load(Ordering::SeqCst) => None,
+        _ if flags.timed_out.load(Ordering::SeqCst) => {
+            flags.ports_timed_out.fetch_add(1, Ordering::SeqCst);
+            None
+        }
+        // Connects through a jump host or from a source port range are blocking
+        Protocol::Tcp if options.ssh_jump.is_none() && options.source_ports.is_none() => {
+            scan_port_async(ip, port, signatures, Arc::clone(&options), Arc::clone(&state)).await
+        }
+        Protocol::Tcp => {
+            let (options, state) = (Arc::clone(&options), Arc::clone(&state));
+            tokio::task::spawn_blocking(move || scan_port_with_options(ip, port, signatures, &options, &state))
+                .await
+                .ok()
+                .flatten()
+        }
+        Protocol::Udp => {
+            let (options, state) = (Arc::clone(&options), Arc::clone(&state));
+            tokio::task::spawn_blocking(move || scan_udp_port_with_options(ip, port, signatures, &options, &state))
+                .await
+                .ok()
+                .flatten()
+        }
+    };
+    if res.as_ref().is_some_and(|res| res.state == PortState::Open) {
+        flags.found_open.store(true, Ordering::SeqCst);
+    }
+    let threshold = options.network_failure_threshold;
+    if threshold > 0 && state.network_errors_in_row() >= threshold {
+        flags.network_failure.store(true, Ordering::SeqCst);
+    }
+    progress.inc(1);
+    res
+}
+
+/// Scan a single TCP port with an async connect, like `scan_port_with_options`.
+///
+/// The connect is retried as `scan_port_with_options` does (see `connect_failed`); once it
+/// succeeds, the stream is handed to `identify_open_port` on the blocking pool.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `port` - The port number to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options holding the retry settings.
+/// * `state` - The state shared across the scan.
+///
+/// # Returns
+/// * `Some(ScanResult)` - The open port and an optional identified service name.
+/// * `None` - If the port is closed or unreachable.
+///
+async fn scan_port_async(
+    ip: Arc<IpAddr>,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+    options: Arc<ScanOptions>,
+    state: Arc<ScanState>,
+) -> Option<ScanResult> {
+    let addr = target_addr(*ip, port, options.scope_id);
+    let mut attempt = 0;
+    let (stream, latency) = loop {
+        let started = Instant::now();
+        let error = match timeout(options.connect_timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => {
+                let latency = started.elapsed();
+                state.record_attempt(latency, true);
+                break (stream, latency);
+            }
+            Ok(Err(e)) => e,
+            // Reported like a timed out `TcpStream::connect_timeout`
+            Err(_) => std::io::Error::from(ErrorKind::TimedOut),
+        };
+        if !connect_failed(&ip, port, &error, started.elapsed(), attempt, &options, &state) {
+            return None;
+        }
+        let delay = backoff_delay(&options, attempt);
+        if delay > Duration::ZERO {
+            tokio::time::sleep(delay).await;
+        }
+        attempt += 1;
+    };
+    // The probes read and write with blocking timeouts
+    let stream = stream.into_std().and_then(|stream| stream.set_nonblocking(false).map(|()| stream)).ok()?;
+    tokio::task::spawn_blocking(move || {
+        identify_open_port(ip, port, Connection::Direct(stream), latency, signatures, &options, &state)
+    })
+    .await
+    .ok()
+}
@@ -0,0 +1,179 @@
+use crate::error::ScanError;
+use crate::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Known good open ports and services per host, as recorded in an earlier report.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline {
+    hosts: HashMap<IpAddr, Vec<ScanResult>>,
+}
+
+/// A difference between a scan and the baseline worth alerting on.
+///
+/// Variants:
+/// * `NewPort` - The port is open but not in the baseline of its host.
+/// * `ChangedService` - The port runs a different service than in the baseline. `baseline` is
+///   `None` if the service was unidentified there.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deviation {
+    NewPort(ScanResult),
+    ChangedService {
+        result: ScanResult,
+        baseline: Option<String>,
+    },
+}
+
+impl Baseline {
+    /// The baseline ports of a host, if the host is part of the baseline.
+    pub fn ports(&self, ip: &IpAddr) -> Option<&[ScanResult]> {
+        self.hosts.get(ip).map(Vec::as_slice)
+    }
+}
+
+/// Parse a baseline from report JSON.
+///
+/// Accepts the same input as `--rescan-from`: a single report, an array of reports, or batch
+/// output (entries with an `error` are skipped). Every report needs an `ip` and `open_ports`, each
+/// port a `port`, `protocol` and optional `service`. Reports of the same host are merged.
+///
+/// # Arguments
+/// * `input` - The baseline as JSON.
+///
+/// # Returns
+/// * `Ok(Baseline)` - The known open ports per host.
+/// * `Err(ScanError)` - If the input is not valid JSON or an entry is not a valid report.
+///
+pub fn parse_baseline(input: &str) -> Result<Baseline, ScanError> {
+    let invalid = |detail: String| {
+        ScanError::Config(format!("{}: {}", crate::localisator::get("error_invalid_baseline"), detail))
+    };
+    let value: JsonValue = serde_json::from_str(input).map_err(|e| invalid(e.to_string()))?;
+    let entries = match value {
+        JsonValue::Array(entries) => entries,
+        other => vec![other],
+    };
+    let mut baseline = Baseline::default();
+    for entry in entries.iter().filter(|entry| entry.get("error").is_none()) {
+        let report = entry.get("report").unwrap_or(entry);
+        let (Some(ip), Some(open_ports)) = (
+            report.get("ip").and_then(|v| v.as_str()).and_then(|ip| ip.parse::<IpAddr>().ok()),
+            report.get("open_ports").and_then(|v| v.as_array()),
+        ) else {
+            return Err(invalid(report.to_string()));
+        };
+        let ports = baseline.hosts.entry(ip).or_default();
+        for port in open_ports {
+            let (Some(number), Some(protocol)) = (
+                port.get("port").and_then(|v| v.as_u64()).and_then(|n| u16::try_from(n).ok()),
+                port.get("protocol").and_then(|v| v.as_str()).and_then(|p| p.parse::<Protocol>().ok()),
+            ) else {
+                return Err(invalid(port.to_string()));
+            };
+            ports.push(ScanResult {
+                port: number,
+                service: port.get("service").and_then(|v| v.as_str()).map(str::to_string),
+                version: port.get("version").and_then(|v| v.as_str()).map(str::to_string),
+                protocol,
+                state: PortState::Open,
+                latency: None,
+                identified_by: IdentificationSource::None,
+                signature_file: None,
+                tls: None,
+            });
+        }
+    }
+    Ok(baseline)
+}
+
+/// Load a baseline from a report file, see `parse_baseline`.
+///
+/// # Arguments
+/// * `path` - The baseline file.
+///
+/// # Returns
+/// * `Ok(Baseline)` - The known open ports per host.
+/// * `Err(ScanError)` - If the file can't be read or is not a valid baseline.
+///
+pub fn load_baseline(path: &Path) -> Result<Baseline, ScanError> {
+    let input = std::fs::read_to_string(path).map_err(|e| {
+        ScanError::Config(format!("{}: {:?}: {}", crate::localisator::get("error_read_file"), path, e))
+    })?;
+    parse_baseline(&input)
+}
+
+/// Compare a scan with the baseline of its host.
+///
+/// Ports that closed since the baseline are not reported. A service only counts as changed if
+/// the scan identified it and it differs (case-insensitively) from the baseline, so a port that
+/// merely went unidentified doesn't raise an alert.
+///
+/// # Arguments
+/// * `baseline` - The known good state.
+/// * `report` - The report of the scan.
+///
+/// # Returns
+/// * The deviations, in the order of the report's open ports.
+///
+pub fn compare(baseline: &Baseline, report: &ScanReport) -> Vec<Deviation> {
+    let known = baseline.ports(&report.ip).unwrap_or_default();
+    report
+        .open_ports
+        .iter()
+        .filter_map(|res| {
+            let Some(expected) = known
+                .iter()
+                .find(|known| known.port == res.port && known.protocol == res.protocol)
+            else {
+                return Some(Deviation::NewPort(res.clone()));
+            };
+            let found = res.service.as_deref()?;
+            let unchanged = expected
+                .service
+                .as_deref()
+                .is_some_and(|service| service.eq_ignore_ascii_case(found));
+            (!unchanged).then(|| Deviation::ChangedService {
+                result: res.clone(),
+                baseline: expected.service.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Format a deviation as a compact one-line alert, e.g. `10.0.0.1: new port 8080/tcp (HTTP)`.
+///
+/// # Arguments
+/// * `ip` - The scanned host.
+/// * `deviation` - The deviation.
+///
+/// # Returns
+/// * The alert line, without a trailing newline.
+///
+pub fn format_deviation(ip: &IpAddr, deviation: &Deviation) -> String {
+    let service = |service: Option<&str>| {
+        service.map_or_else(|| crate::localisator::get("deviation_unidentified"), str::to_string)
+    };
+    match deviation {
+        Deviation::NewPort(res) => format!(
+            "{}: {} {}/{} ({})",
+            ip,
+            crate::localisator::get("deviation_new_port"),
+            res.port,
+            res.protocol,
+            service(res.service.as_deref())
+        ),
+        Deviation::ChangedService { result, baseline } => format!(
+            "{}: {} {}/{} ({} -> {})",
+            ip,
+            crate::localisator::get("deviation_changed_service"),
+            result.port,
+            result.protocol,
+            service(baseline.as_deref()),
+            service(result.service.as_deref())
+        ),
+    }
+}
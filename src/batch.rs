@@ -0,0 +1,238 @@
+use crate::error::ScanError;
+use crate::scanner::{CancelToken, ScanReport};
+use crate::signatures::Signature;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{mpsc, Arc};
+use threadpool::ThreadPool;
+
+/// Outcome of a single batch job.
+///
+/// # Fields
+/// * `job` - The index of the job in the submitted batch.
+/// * `report` - The scan report, if the job succeeded.
+/// * `error` - The error message, if the job was malformed or the scan failed.
+///
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub job: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<ScanReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Parse a batch of scan jobs from JSON.
+///
+/// # Arguments
+/// * `input` - A JSON array of jobs (e.g., `[{"ip": "10.0.0.1", "ports": "1-1024"}]`).
+///
+/// # Returns
+/// * `Ok(Vec<JsonValue>)` - The raw jobs. They are validated individually by `job_config`.
+/// * `Err(ScanError)` - If the input is not valid JSON or not an array.
+///
+pub fn parse_batch(input: &str) -> Result<Vec<JsonValue>, ScanError> {
+    match serde_json::from_str::<JsonValue>(input) {
+        Ok(JsonValue::Array(jobs)) => Ok(jobs),
+        Ok(_) => Err(ScanError::Config(crate::localisator::get("error_batch_not_array"))),
+        Err(e) => Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_batch_parse"),
+            e
+        ))),
+    }
+}
+
+/// Build jobs that re-scan the open ports of a previous report.
+///
+/// Accepts a single report, an array of reports, or batch output (entries with a `report`);
+/// entries with an `error` are skipped. Ports of the same host are merged, and hosts without
+/// open ports get no job.
+///
+/// # Arguments
+/// * `input` - The previous report as JSON.
+///
+/// # Returns
+/// * `Ok(Vec<JsonValue>)` - One job per host (e.g., `{"ip": "10.0.0.1", "ports": {"tcp": [22], "udp": [53]}}`),
+///   in the order the hosts first appear.
+/// * `Err(ScanError)` - If the input is not valid JSON or contains no report.
+///
+pub fn rescan_jobs(input: &str) -> Result<Vec<JsonValue>, ScanError> {
+    let value: JsonValue = serde_json::from_str(input).map_err(|e| {
+        ScanError::Config(format!("{}: {}", crate::localisator::get("error_rescan_report"), e))
+    })?;
+    let entries = match value {
+        JsonValue::Array(entries) => entries,
+        other => vec![other],
+    };
+    let mut hosts: Vec<(String, BTreeMap<String, BTreeSet<u64>>)> = Vec::new();
+    let mut found_report = false;
+    for entry in &entries {
+        if entry.get("error").is_some() {
+            continue;
+        }
+        let report = entry.get("report").unwrap_or(entry);
+        let (Some(ip), Some(open_ports)) = (
+            report.get("ip").and_then(|v| v.as_str()),
+            report.get("open_ports").and_then(|v| v.as_array()),
+        ) else {
+            return Err(ScanError::Config(crate::localisator::get("error_rescan_report")));
+        };
+        found_report = true;
+        let index = match hosts.iter().position(|(host, _)| host == ip) {
+            Some(index) => index,
+            None => {
+                hosts.push((ip.to_string(), BTreeMap::new()));
+                hosts.len() - 1
+            }
+        };
+        for port in open_ports {
+            if let (Some(number), Some(protocol)) = (
+                port.get("port").and_then(|v| v.as_u64()),
+                port.get("protocol").and_then(|v| v.as_str()),
+            ) {
+                hosts[index].1.entry(protocol.to_string()).or_default().insert(number);
+            }
+        }
+    }
+    if !found_report {
+        return Err(ScanError::Config(crate::localisator::get("error_rescan_report")));
+    }
+    Ok(hosts
+        .into_iter()
+        .filter(|(_, ports)| !ports.is_empty())
+        .map(|(ip, ports)| json!({ "ip": ip, "ports": ports }))
+        .collect())
+}
+
+/// Build the configuration for one batch job on top of the base configuration.
+///
+/// A job is an object with an `ip`, optional `ports` and optional `options`:
+/// * `ports` - A TCP port list (`"1-1024"` or `[22, 80]`), or a mapping of protocol to port list
+///   in the same format as `protocol_ports`.
+/// * `options` - Any config keys to override for this job (e.g., `max_threads`, `retries`).
+///
+/// # Arguments
+/// * `base` - The base configuration (config file and CLI). Its `ip` is never inherited.
+/// * `job` - The JSON job.
+///
+/// # Returns
+/// * `Ok(HashMap<String, YamlValue>)` - The job configuration, ready for `run_scan`.
+/// * `Err(ScanError)` - If the job is malformed.
+///
+pub fn job_config(
+    base: &HashMap<String, YamlValue>,
+    job: &JsonValue,
+) -> Result<HashMap<String, YamlValue>, ScanError> {
+    let invalid_job = || ScanError::Config(crate::localisator::get("error_batch_invalid_job"));
+    let job = job.as_object().ok_or_else(invalid_job)?;
+    let to_yaml = |value: &JsonValue| {
+        serde_yaml::to_value(value).map_err(|e| ScanError::Config(e.to_string()))
+    };
+    let mut config = base.clone();
+    config.remove("ip");
+    if let Some(options) = job.get("options") {
+        for (key, value) in options.as_object().ok_or_else(invalid_job)? {
+            config.insert(key.clone(), to_yaml(value)?);
+        }
+    }
+    if let Some(ip) = job.get("ip") {
+        config.insert("ip".to_string(), to_yaml(ip)?);
+    }
+    match job.get("ports") {
+        Some(ports @ JsonValue::Object(_)) => {
+            config.insert("protocol_ports".to_string(), to_yaml(ports)?);
+        }
+        Some(ports) => {
+            let mut map = serde_yaml::Mapping::new();
+            map.insert(YamlValue::String("tcp".to_string()), to_yaml(ports)?);
+            config.insert("protocol_ports".to_string(), YamlValue::Mapping(map));
+        }
+        None => {}
+    }
+    Ok(config)
+}
+
+/// Run a batch of jobs, at most `concurrency` at a time.
+///
+/// A malformed job or failed scan yields an error entry for that job; the other jobs still run.
+///
+/// # Arguments
+/// * `base` - The base configuration shared by all jobs.
+/// * `jobs` - The jobs as returned by `parse_batch`.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `concurrency` - The maximum number of jobs scanned at the same time (at least 1).
+///
+/// # Returns
+/// * One `BatchResult` per job, in submission order.
+///
+pub fn run_batch(
+    base: &HashMap<String, YamlValue>,
+    jobs: Vec<JsonValue>,
+    signatures: Arc<Vec<Signature>>,
+    concurrency: usize,
+) -> Vec<BatchResult> {
+    run_batch_with_cancel(base, jobs, signatures, concurrency, &CancelToken::new())
+}
+
+/// Run a batch of jobs like `run_batch` that can be cancelled from another thread.
+///
+/// Once `token` is cancelled, running scans return the open ports found so far (with
+/// `stats.cancelled` set) and jobs that haven't started yet yield an error entry.
+///
+/// # Arguments
+/// * `base` - The base configuration shared by all jobs.
+/// * `jobs` - The jobs as returned by `parse_batch`.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `concurrency` - The maximum number of jobs scanned at the same time (at least 1).
+/// * `token` - The token to cancel the batch with; the caller keeps a clone.
+///
+/// # Returns
+/// * One `BatchResult` per job, in submission order.
+///
+pub fn run_batch_with_cancel(
+    base: &HashMap<String, YamlValue>,
+    jobs: Vec<JsonValue>,
+    signatures: Arc<Vec<Signature>>,
+    concurrency: usize,
+    token: &CancelToken,
+) -> Vec<BatchResult> {
+    let pool = ThreadPool::new(concurrency.max(1));
+    let (tx, rx) = mpsc::channel();
+    for (index, job) in jobs.into_iter().enumerate() {
+        let config = job_config(base, &job);
+        let signatures = Arc::clone(&signatures);
+        let tx = tx.clone();
+        let token = token.clone();
+        pool.execute(move || {
+            let outcome = match config {
+                _ if token.is_cancelled() => {
+                    Err(ScanError::Config(crate::localisator::get("error_job_cancelled")))
+                }
+                config => config.and_then(|config| crate::run_scan_with_cancel(&config, signatures, &token)),
+            };
+            let _ = tx.send((index, outcome));
+        });
+    }
+    drop(tx);
+    let mut results: Vec<BatchResult> = rx
+        .iter()
+        .map(|(job, outcome)| match outcome {
+            Ok(report) => BatchResult {
+                job,
+                report: Some(report),
+                error: None,
+            },
+            Err(e) => BatchResult {
+                job,
+                report: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+    pool.join();
+    results.sort_by_key(|r| r.job);
+    results
+}
@@ -1,20 +1,2439 @@
 use crate::error::ScanError;
+use crate::output::{GroupBy, LatencyFilter, OutputFormat, OutputSink, ProgressFormat, SortBy, StdoutFormat};
+use ipnet::IpNet;
+use crate::scanner::{HttpProbePorts, Probe, Protocol, ScanOptions, SourcePortRange};
 use serde_yaml::Value as YamlValue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Configuration files looked for in the working directory, in order; the first one found is read.
+pub const DEFAULT_CONFIG_FILES: &[&str] = &["config.toml", "config.yaml"];
+
+/// The configuration file to read when none is given, see `DEFAULT_CONFIG_FILES`.
+///
+/// # Returns
+/// * The first of `DEFAULT_CONFIG_FILES` that exists, or `config.yaml` if none does.
+///
+pub fn default_config_path() -> &'static str {
+    DEFAULT_CONFIG_FILES
+        .iter()
+        .copied()
+        .find(|path| std::path::Path::new(path).is_file())
+        .unwrap_or("config.yaml")
+}
 
 /// Read and parse the configuration file.
 ///
+/// A file ending in `.toml` is parsed as TOML, anything else as YAML. Both give the same map,
+/// so every key is written the same way in either format (e.g., `max_threads = 50`).
+///
+/// # Arguments
+/// * `path` - A string slice that holds the path to the configuration file.
+///
+/// # Returns
+/// * `Ok(HashMap<String, YamlValue>)` - If the configuration is successfully read and parsed.
+/// * `Err(ScanError)` - If there is an error reading or parsing the configuration file.
+///
+pub fn read_config(path: &str) -> Result<HashMap<String, YamlValue>, ScanError> {
+    let content = std::fs::read_to_string(path)?;
+    let is_toml = std::path::Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    if is_toml {
+        return toml::from_str::<HashMap<String, YamlValue>>(&content)
+            .map_err(|e| ScanError::Config(e.to_string()));
+    }
+    serde_yaml::from_str::<HashMap<String, YamlValue>>(&content)
+        .map_err(|e| ScanError::Config(e.to_string()))
+}
+
+/// Serialize a configuration back to YAML, in the format `read_config` reads.
+///
+/// Keys are sorted, so dumps of the same configuration are identical and easy to diff.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(String)` - The configuration as a YAML document.
+/// * `Err(ScanError)` - If a value can't be serialized.
+///
+pub fn dump_config(config: &HashMap<String, YamlValue>) -> Result<String, ScanError> {
+    let sorted: BTreeMap<&String, &YamlValue> = config.iter().collect();
+    serde_yaml::to_string(&sorted).map_err(|e| ScanError::Config(e.to_string()))
+}
+
+/// Parse a single port number, rejecting port 0 and values above 65535.
+///
+/// # Arguments
+/// * `token` - The port as written in the config (e.g., "80").
+///
+/// # Returns
+/// * `Ok(u16)` - If the token is a valid port.
+/// * `Err(ScanError)` - If the token is not a valid port.
+///
+fn parse_port(token: &str) -> Result<u16, ScanError> {
+    match token.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_invalid_port_list"),
+            token
+        ))),
+    }
+}
+
+/// Parse a port step, which has to be at least 1.
+///
+/// # Arguments
+/// * `token` - The step as written in the config (e.g., "10").
+///
+/// # Returns
+/// * `Ok(u16)` - If the token is a valid step.
+/// * `Err(ScanError)` - If the token is not a number from 1 to 65535.
+///
+fn parse_port_step(token: &str) -> Result<u16, ScanError> {
+    match token.trim().parse::<u16>() {
+        Ok(step) if step > 0 => Ok(step),
+        _ => Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_invalid_port_step"),
+            token
+        ))),
+    }
+}
+
+/// Parse a comma-separated port list that may mix single ports and ranges. A range may have a
+/// step, e.g. `1-1000:10` for `1, 11, 21, ..., 991`.
+///
+/// # Arguments
+/// * `spec` - The port list (e.g., "22,80,1000-2000,3000-4000:100").
+///
+/// # Returns
+/// * `Ok(Vec<u16>)` - The sorted, deduplicated ports.
+/// * `Err(ScanError)` - If any token is not a valid port or range, or a step is below 1.
+///
+pub fn parse_port_list(spec: &str) -> Result<Vec<u16>, ScanError> {
+    let mut ports = Vec::new();
+    for token in spec.split(',') {
+        let invalid = || {
+            ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_port_list"),
+                token
+            ))
+        };
+        let (range, step) = match token.split_once(':') {
+            Some((range, step)) => (range, Some(parse_port_step(step)?)),
+            None => (token, None),
+        };
+        match (range.split_once('-'), step) {
+            (Some((start, end)), step) => {
+                let (start, end) = (parse_port(start)?, parse_port(end)?);
+                if start > end {
+                    return Err(invalid());
+                }
+                ports.extend((start..=end).step_by(step.unwrap_or(1) as usize));
+            }
+            (None, None) => ports.push(parse_port(token)?),
+            // A step needs a range to step through
+            (None, Some(_)) => return Err(invalid()),
+        }
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Format ports compactly, collapsing consecutive runs into ranges.
+///
+/// # Arguments
+/// * `ports` - The ports to format, sorted ascending.
+///
+/// # Returns
+/// * A port list in the format accepted by `parse_port_list` (e.g., "22,80,1000-2000").
+///
+pub fn format_port_list(ports: &[u16]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < ports.len() {
+        let start = ports[i];
+        let mut end = start;
+        while i + 1 < ports.len() && ports[i + 1] == end.wrapping_add(1) {
+            i += 1;
+            end = ports[i];
+        }
+        if start == end {
+            parts.push(start.to_string());
+        } else {
+            parts.push(format!("{}-{}", start, end));
+        }
+        i += 1;
+    }
+    parts.join(",")
+}
+
+/// Parse a port list given either as a string or as a sequence of ports and ranges.
+///
+/// # Arguments
+/// * `value` - The YAML value (e.g., `"53,123"` or `[53, "100-200"]`).
+///
+/// # Returns
+/// * `Ok(Vec<u16>)` - The sorted, deduplicated ports.
+/// * `Err(ScanError)` - If the value is malformed.
+///
+fn parse_port_value(value: &YamlValue) -> Result<Vec<u16>, ScanError> {
+    let spec = match value {
+        YamlValue::String(spec) => spec.clone(),
+        YamlValue::Number(n) => n.to_string(),
+        YamlValue::Sequence(seq) => seq
+            .iter()
+            .map(|item| match item {
+                YamlValue::String(s) => Ok(s.clone()),
+                YamlValue::Number(n) => Ok(n.to_string()),
+                other => Err(ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_port_list"),
+                    other
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(","),
+        other => {
+            return Err(ScanError::Config(format!(
+                "{}: {:?}",
+                crate::localisator::get("error_invalid_port_list"),
+                other
+            )))
+        }
+    };
+    parse_port_list(&spec)
+}
+
+/// Extract the optional per-protocol port sets.
+///
+/// The `protocol_ports` key maps protocol names to port lists, for example:
+/// ```yaml
+/// protocol_ports:
+///   tcp: "1-1024"
+///   udp: [53, 123, 161]
+/// ```
+/// The TCP ports of `ports` (e.g. `"22,80,1000-2000"`) and the ports of the services named in
+/// `ports_from_services` are added to them. When any of them is present it replaces the
+/// `start_port`/`end_port` range.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(BTreeMap<Protocol, Vec<u16>>))` - The ports to scan per protocol.
+/// * `Ok(None)` - If none of `protocol_ports`, `ports` and `ports_from_services` is set.
+/// * `Err(ScanError)` - If a protocol name, port list or service name is invalid.
+///
+pub fn get_protocol_ports(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<BTreeMap<Protocol, Vec<u16>>>, ScanError> {
+    let mut result = match config.get("protocol_ports") {
+        Some(YamlValue::Mapping(map)) => {
+            let mut result = BTreeMap::new();
+            for (key, value) in map {
+                let protocol: Protocol = key
+                    .as_str()
+                    .ok_or_else(|| {
+                        ScanError::Config(crate::localisator::get("error_invalid_protocol_ports"))
+                    })?
+                    .parse()?;
+                result.insert(protocol, parse_port_value(value)?);
+            }
+            Some(result)
+        }
+        Some(_) => {
+            return Err(ScanError::Config(crate::localisator::get(
+                "error_invalid_protocol_ports",
+            )))
+        }
+        None => None,
+    };
+    if let Some(value) = config.get("ports") {
+        let entry = result.get_or_insert_with(BTreeMap::new).entry(Protocol::Tcp).or_default();
+        entry.extend(parse_port_value(value)?);
+        entry.sort_unstable();
+        entry.dedup();
+    }
+    if let Some(names) = get_ports_from_services(config)? {
+        let merged = result.get_or_insert_with(BTreeMap::new);
+        for (protocol, ports) in crate::services::resolve_services(&names)? {
+            let entry = merged.entry(protocol).or_default();
+            entry.extend(ports);
+            entry.sort_unstable();
+            entry.dedup();
+        }
+    }
+    Ok(result)
+}
+
+/// Hosts with the TCP ports to scan on each, see `get_endpoints`.
+pub type Endpoints = Vec<(std::net::IpAddr, Vec<u16>)>;
+
+/// Extract the exact endpoints to scan, grouped by host.
+///
+/// `endpoints` is a comma-separated string or a list of `ip:port` pairs (e.g.
+/// `"1.2.3.4:22, [::1]:80"`); IPv6 addresses need brackets. Only TCP is scanned, and port
+/// ranges are not expanded.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Endpoints))` - Each host in order of first appearance, with its
+///   sorted, deduplicated ports.
+/// * `Ok(None)` - If `endpoints` is not set.
+/// * `Err(ScanError)` - If the value is empty or any entry is not a valid `ip:port` pair.
+///
+pub fn get_endpoints(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<Endpoints>, ScanError> {
+    let invalid = |key: &str, detail: &str| {
+        ScanError::Config(format!("{}: {}", crate::localisator::get(key), detail))
+    };
+    let entries: Vec<String> = match config.get("endpoints") {
+        None => return Ok(None),
+        Some(YamlValue::String(list)) => list.split(',').map(|e| e.trim().to_string()).collect(),
+        Some(YamlValue::Sequence(seq)) => seq
+            .iter()
+            .map(|v| v.as_str().map(|e| e.trim().to_string()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("error_invalid_endpoint", &format!("{:?}", seq)))?,
+        Some(other) => return Err(invalid("error_invalid_endpoint", &format!("{:?}", other))),
+    };
+    let mut hosts: Endpoints = Vec::new();
+    for entry in entries.iter().filter(|e| !e.is_empty()) {
+        let addr = match entry.parse::<std::net::SocketAddr>() {
+            Ok(addr) if addr.port() != 0 => addr,
+            // "::1:80" is ambiguous, the port can't be told apart from the address
+            Err(_) if entry.matches(':').count() > 1 && !entry.starts_with('[') => {
+                return Err(invalid("error_endpoint_ipv6_brackets", entry))
+            }
+            _ => return Err(invalid("error_invalid_endpoint", entry)),
+        };
+        match hosts.iter_mut().find(|(ip, _)| *ip == addr.ip()) {
+            Some((_, ports)) => ports.push(addr.port()),
+            None => hosts.push((addr.ip(), vec![addr.port()])),
+        }
+    }
+    if hosts.is_empty() {
+        return Err(invalid("error_invalid_endpoint", ""));
+    }
+    for (_, ports) in &mut hosts {
+        ports.sort_unstable();
+        ports.dedup();
+    }
+    Ok(Some(hosts))
+}
+
+/// Extract the service names whose ports should be scanned.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Vec<String>))` - The names from `ports_from_services`, given as a list or a
+///   comma-separated string (e.g., "http,https,domain").
+/// * `Ok(None)` - If `ports_from_services` is not set.
+/// * `Err(ScanError)` - If the value is neither a string nor a list of strings.
+///
+pub fn get_ports_from_services(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<Vec<String>>, ScanError> {
+    let invalid = || ScanError::Config(crate::localisator::get("error_invalid_ports_from_services"));
+    let names: Vec<String> = match config.get("ports_from_services") {
+        None => return Ok(None),
+        Some(YamlValue::String(names)) => names.split(',').map(|name| name.trim().to_string()).collect(),
+        Some(YamlValue::Sequence(seq)) => seq
+            .iter()
+            .map(|name| name.as_str().map(|name| name.trim().to_string()).ok_or_else(invalid))
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(invalid()),
+    };
+    let names: Vec<String> = names.into_iter().filter(|name| !name.is_empty()).collect();
+    if names.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Some(names))
+}
+
+/// Extract the protocols the start/end port range is scanned with.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Vec<Protocol>)` - `protocol` as a list: `"tcp"` (the default), `"udp"` or `"both"`.
+/// * `Err(ScanError)` - If `protocol` is set to anything else.
+///
+pub fn get_range_protocols(config: &HashMap<String, YamlValue>) -> Result<Vec<Protocol>, ScanError> {
+    match config.get("protocol").and_then(|v| v.as_str()) {
+        None => Ok(vec![Protocol::Tcp]),
+        Some(value) if value.eq_ignore_ascii_case("both") => Ok(vec![Protocol::Tcp, Protocol::Udp]),
+        Some(value) => value.parse().map(|protocol| vec![protocol]).map_err(|_| {
+            ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_protocol"),
+                value
+            ))
+        }),
+    }
+}
+
+/// Build the list of work units to scan and a description of the scanned ports.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `start_port` - The start of the range used when no per-protocol ports are set.
+/// * `end_port` - The end of the range used when no per-protocol ports are set.
+///
+/// # Returns
+/// * `Ok((Vec<(Protocol, u16)>, String))` - The `(protocol, port)` work units and a port range
+///   description (e.g., "tcp 1-1024, udp 53,123" or "1-1000"). The range is scanned with the
+///   protocols from `get_range_protocols`, both protocols of a port one after the other, and
+///   every `port_step`th port only (e.g. "1-1000:10"). With `randomize` the work units are
+///   shuffled, so the target sees the ports in no particular order; with `seed` too, the order is
+///   the same on every run.
+/// * `Err(ScanError)` - If `protocol_ports`, `ports`, `ports_from_services`, `protocol`, `port_step` or `seed` is invalid.
+///
+pub fn get_scan_work(
+    config: &HashMap<String, YamlValue>,
+    start_port: u16,
+    end_port: u16,
+) -> Result<(Vec<(Protocol, u16)>, String), ScanError> {
+    let (mut work, description): (Vec<(Protocol, u16)>, String) = match get_protocol_ports(config)? {
+        Some(map) => (
+            map.iter()
+                .flat_map(|(protocol, ports)| ports.iter().map(move |port| (*protocol, *port)))
+                .collect(),
+            map.iter()
+                .map(|(protocol, ports)| format!("{} {}", protocol, format_port_list(ports)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        None => {
+            let protocols = get_range_protocols(config)?;
+            let step = get_port_step(config)?;
+            let range = match step {
+                1 => format!("{}-{}", start_port, end_port),
+                step => format!("{}-{}:{}", start_port, end_port, step),
+            };
+            (
+                (start_port..=end_port)
+                    .step_by(step as usize)
+                    .flat_map(|port| protocols.iter().map(move |protocol| (*protocol, port)))
+                    .collect(),
+                match protocols.as_slice() {
+                    [Protocol::Tcp] => range,
+                    _ => protocols
+                        .iter()
+                        .map(|protocol| format!("{} {}", protocol, range))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                },
+            )
+        }
+    };
+    if get_randomize(config) {
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+        match get_seed(config)? {
+            // ChaCha8 keeps the order of a seed stable across rand releases and platforms, unlike StdRng
+            Some(seed) => work.shuffle(&mut rand_chacha::ChaCha8Rng::seed_from_u64(seed)),
+            None => work.shuffle(&mut rand::thread_rng()),
+        }
+    }
+    Ok((work, description))
+}
+
+/// Extract the step of the `start_port`/`end_port` range, to scan e.g. every 10th port only.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(u16)` - The `port_step` value, or 1 (every port) if unset.
+/// * `Err(ScanError)` - If the value is not a number from 1 to 65535.
+///
+pub fn get_port_step(config: &HashMap<String, YamlValue>) -> Result<u16, ScanError> {
+    match config.get("port_step") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_port_step"),
+                    value
+                ))
+            }),
+        None => Ok(1),
+    }
+}
+
+/// Check whether the ports of a scan should be scanned in random order.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `randomize` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_randomize(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("randomize")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Extract the seed of the random port order, to repeat the same order across runs.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(u64))` - The `seed` value.
+/// * `Ok(None)` - If unset; the order is then different on every run.
+/// * `Err(ScanError)` - If the value is not a non-negative integer.
+///
+pub fn get_seed(config: &HashMap<String, YamlValue>) -> Result<Option<u64>, ScanError> {
+    match config.get("seed") {
+        Some(value) => value.as_u64().map(Some).ok_or_else(|| {
+            ScanError::Config(format!(
+                "{}: {:?}",
+                crate::localisator::get("error_invalid_seed"),
+                value
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Parse the local port range outgoing connects are bound to.
+///
+/// # Arguments
+/// * `value` - The YAML value, a range (e.g., `"40000-40100"`) or a single port.
+///
+/// # Returns
+/// * `Ok(SourcePortRange)` - The source port range.
+/// * `Err(ScanError)` - If the value is not a valid, ascending port range.
+///
+fn parse_source_port_range(value: &YamlValue) -> Result<SourcePortRange, ScanError> {
+    let invalid = || {
+        ScanError::Config(format!(
+            "{}: {:?}",
+            crate::localisator::get("error_invalid_source_port_range"),
+            value
+        ))
+    };
+    let spec = match value {
+        YamlValue::String(spec) => spec.clone(),
+        YamlValue::Number(n) => n.to_string(),
+        _ => return Err(invalid()),
+    };
+    let (start, end) = spec.split_once('-').unwrap_or((&spec, &spec));
+    let start = parse_port(start).map_err(|_| invalid())?;
+    let end = parse_port(end).map_err(|_| invalid())?;
+    if start > end {
+        return Err(invalid());
+    }
+    Ok(SourcePortRange::new(start, end))
+}
+
+/// Parse the paths requested by the HTTP probe.
+///
+/// A path has to start with `/` and may only contain characters allowed unescaped in a URL path
+/// and query (letters, digits, `-._~!$&'()*+,;=:@/?`) and `%` escapes of two hex digits.
+///
+/// # Arguments
+/// * `value` - `probe_path` as written in the config: a path (e.g., `"/server-status"`) or a
+///   list of paths tried in order.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The paths, at least one.
+/// * `Err(ScanError)` - If the value is empty or a path isn't URL-safe.
+///
+fn parse_probe_paths(value: &YamlValue) -> Result<Vec<String>, ScanError> {
+    let invalid = |path: &dyn std::fmt::Debug| {
+        ScanError::Config(format!("{}: {:?}", crate::localisator::get("error_invalid_probe_path"), path))
+    };
+    let paths: Vec<&str> = match value {
+        YamlValue::String(path) => vec![path.as_str()],
+        YamlValue::Sequence(paths) => paths
+            .iter()
+            .map(|path| path.as_str().ok_or_else(|| invalid(path)))
+            .collect::<Result<_, _>>()?,
+        _ => return Err(invalid(value)),
+    };
+    if paths.is_empty() {
+        return Err(invalid(value));
+    }
+    for path in &paths {
+        let bytes = path.as_bytes();
+        let url_safe = bytes.iter().enumerate().all(|(i, &b)| match b {
+            b'%' => bytes.get(i + 1..i + 3).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)),
+            _ => b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?".contains(&b),
+        });
+        if !path.starts_with('/') || !url_safe {
+            return Err(invalid(path));
+        }
+    }
+    Ok(paths.into_iter().map(str::to_string).collect())
+}
+
+/// Parse a TCP probe payload, which may contain the escapes `\r`, `\n`, `\t`, `\0`, `\\` and `\xNN`.
+///
+/// # Arguments
+/// * `payload` - The payload as written in the config (e.g., `"HELP\r\n"`).
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The bytes to send.
+/// * `Err(ScanError)` - If the payload contains an unknown or incomplete escape.
+///
+fn parse_probe_payload(payload: &str) -> Result<Vec<u8>, ScanError> {
+    let invalid = || {
+        ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_invalid_probe_payload"),
+            payload
+        ))
+    };
+    let mut bytes = Vec::new();
+    let mut chars = payload.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next().ok_or_else(invalid)? {
+            'r' => bytes.push(b'\r'),
+            'n' => bytes.push(b'\n'),
+            't' => bytes.push(b'\t'),
+            '0' => bytes.push(0),
+            '\\' => bytes.push(b'\\'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 {
+                    return Err(invalid());
+                }
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| invalid())?);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Extract the scanner tuning options. Unset keys keep their `ScanOptions::default` value.
+///
+/// Keys:
+/// * `retries`, `retry_backoff_ms`, `max_retries_total` - How often and how fast to retry.
+/// * `source_port_range` - The local ports connects are made from.
+/// * `auto_tune`, `ramp_up_ms` - How the thread count is tuned and raised at the start.
+/// * `first_open_only` - Stop at the first open port.
+/// * `latency_histogram`, `raw_banners` - Extra data kept in the results.
+/// * `network_failure_threshold` - Network errors in a row that end the scan of a host.
+/// * `probe_payload`, `no_probe` - Replace the HTTP probe with a plain TCP probe.
+/// * `http_probe_ports`, `http_probe_skip_ports` - Limit the ports the HTTP probe is sent to.
+/// * `probe_path` - The paths the HTTP probe requests.
+/// * `banner_max_bytes` - How much of a banner is read.
+/// * `connect_timeout_ms` - The TCP connect timeout.
+/// * `per_target_timeout` - How long the scan of one target may take.
+/// * `tls_ports`, `tls_expiry_warning_days`, `insecure` - Certificate checks.
+/// * `tls_server_name` - The SNI name; the target `ip` if it is a hostname and this is unset.
+/// * `ip` - A link-local address with a zone sets `scope_id`.
+/// * `engine` - Scan with threads or the async engine.
+///
+/// `ssh_jump` is left unset, the caller opens the session, see `get_ssh_jump`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `max_threads` - The validated thread count from `get_config`.
+///
+/// # Returns
+/// * `Ok(ScanOptions)` - The options.
+/// * `Err(ScanError)` - If a value is invalid or both HTTP probe port lists are set.
+///
+pub fn get_scan_options(
+    config: &HashMap<String, YamlValue>,
+    max_threads: usize,
+) -> Result<ScanOptions, ScanError> {
+    let defaults = ScanOptions::default();
+    let retries = match config.get("retries").and_then(|v| v.as_u64()) {
+        Some(r) => u32::try_from(r).map_err(|_| {
+            ScanError::Config(format!("{}: {}", crate::localisator::get("error_invalid_retries"), r))
+        })?,
+        None => defaults.retries,
+    };
+    let retry_backoff = get_millis_duration(config, "retry_backoff_ms")?.unwrap_or(defaults.retry_backoff);
+    let max_retries_total = config.get("max_retries_total").and_then(|v| v.as_u64());
+    let source_ports = config
+        .get("source_port_range")
+        .map(parse_source_port_range)
+        .transpose()?;
+    let auto_tune = config
+        .get("auto_tune")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.auto_tune);
+    let first_open_only = get_first_open_only(config);
+    let latency_histogram = config
+        .get("latency_histogram")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.latency_histogram);
+    let raw_banners = config
+        .get("raw_banners")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.raw_banners);
+    let insecure_tls = config
+        .get("insecure")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.insecure_tls);
+    let network_failure_threshold = match config.get("network_failure_threshold") {
+        Some(value) => value.as_u64().ok_or_else(|| {
+            ScanError::Config(format!(
+                "{}: {:?}",
+                crate::localisator::get("error_invalid_network_failure_threshold"),
+                value
+            ))
+        })?,
+        None => defaults.network_failure_threshold,
+    };
+    let tls_ports = match config.get("tls_ports") {
+        Some(value) => parse_port_value(value)?,
+        None => defaults.tls_ports,
+    };
+    let tls_server_name = match config.get("tls_server_name") {
+        Some(value) => Some(
+            value
+                .as_str()
+                .filter(|name| crate::resolver::is_valid_hostname(name))
+                .ok_or_else(|| {
+                    ScanError::Config(format!(
+                        "{}: {:?}",
+                        crate::localisator::get("error_invalid_tls_server_name"),
+                        value
+                    ))
+                })?
+                .to_string(),
+        ),
+        // A hostname target selects its own certificate; an address gets the default one
+        None => config
+            .get("ip")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|ip| {
+                ip.parse::<std::net::IpAddr>().is_err()
+                    && crate::resolver::is_valid_hostname(ip)
+                    && !ip.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })
+            .map(str::to_string),
+    };
+    let tls_expiry_warning_days = match config.get("tls_expiry_warning_days") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| {
+                ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_tls_expiry_warning_days"),
+                    value
+                ))
+            })?,
+        None => defaults.tls_expiry_warning_days,
+    };
+    let ramp_up = get_millis_duration(config, "ramp_up_ms")?.unwrap_or(defaults.ramp_up);
+    let connect_timeout = match get_millis_duration(config, "connect_timeout_ms")? {
+        // A zero timeout is rejected by every connect
+        Some(timeout) if timeout.is_zero() => {
+            return Err(ScanError::Config(format!(
+                "{}: connect_timeout_ms: 0",
+                crate::localisator::get("error_invalid_duration")
+            )))
+        }
+        Some(timeout) => timeout,
+        None => defaults.connect_timeout,
+    };
+    let no_probe = config.get("no_probe").and_then(|v| v.as_bool()).unwrap_or(false);
+    let probe = match config.get("probe_payload").and_then(|v| v.as_str()) {
+        Some(payload) => Probe::Tcp(parse_probe_payload(payload)?),
+        None if no_probe => Probe::Tcp(Vec::new()),
+        None => defaults.probe,
+    };
+    let http_probe_ports = match (config.get("http_probe_ports"), config.get("http_probe_skip_ports")) {
+        (Some(_), Some(_)) => {
+            return Err(ScanError::Config(crate::localisator::get("error_http_probe_ports_conflict")))
+        }
+        (Some(only), None) => HttpProbePorts::Only(parse_port_value(only)?.into_iter().collect()),
+        (None, Some(skip)) => HttpProbePorts::Skip(parse_port_value(skip)?.into_iter().collect()),
+        (None, None) => defaults.http_probe_ports,
+    };
+    let probe_paths = match config.get("probe_path") {
+        Some(value) => parse_probe_paths(value)?,
+        None => defaults.probe_paths,
+    };
+    let banner_max_bytes = match config.get("banner_max_bytes") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| (1..=crate::scanner::MAX_BANNER_MAX_BYTES).contains(n))
+            .ok_or_else(|| {
+                ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_banner_max_bytes"),
+                    value
+                ))
+            })?,
+        None => defaults.banner_max_bytes,
+    };
+    Ok(ScanOptions {
+        max_threads,
+        retries,
+        retry_backoff,
+        max_retries_total,
+        source_ports,
+        auto_tune,
+        probe,
+        banner_max_bytes,
+        ramp_up,
+        ssh_jump: defaults.ssh_jump,
+        first_open_only,
+        cancel: defaults.cancel,
+        latency_histogram,
+        http_probe_ports,
+        connect_timeout,
+        probe_paths,
+        raw_banners,
+        network_failure_threshold,
+        tls_ports,
+        tls_server_name,
+        tls_expiry_warning_days,
+        scope_id: get_scope_id(config)?,
+        service_cache: defaults.service_cache,
+        target_timeout: get_target_timeout(config)?,
+        insecure_tls,
+        engine: config
+            .get("engine")
+            .and_then(|v| v.as_str())
+            .map_or(Ok(defaults.engine), str::parse)?,
+    })
+}
+
+/// Extract the SSH jump host the scan should be tunnelled through.
+///
+/// Only the destination is parsed, the session is opened by the caller.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(SshDestination))` - If `ssh_jump` is set to a valid `[user@]host[:port]`.
+/// * `Ok(None)` - If `ssh_jump` is not set.
+/// * `Err(ScanError)` - If `ssh_jump` is set but invalid.
+///
+pub fn get_ssh_jump(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<crate::tunnel::SshDestination>, ScanError> {
+    config
+        .get("ssh_jump")
+        .map(|value| {
+            value
+                .as_str()
+                .ok_or_else(|| {
+                    ScanError::Config(format!(
+                        "{}: {:?}",
+                        crate::localisator::get("error_invalid_ssh_jump"),
+                        value
+                    ))
+                })?
+                .parse()
+        })
+        .transpose()
+}
+
+/// Signatures directory used unless `signatures_path` or `signatures_bundle` is set.
+pub const DEFAULT_SIGNATURES_DIR: &str = "signatures";
+
+/// Extract where to load the signatures from.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * The `signatures_bundle` file if set, otherwise the `signatures_path` directory or file if
+///   set, otherwise the `signatures` directory.
+///
+pub fn get_signatures_path(config: &HashMap<String, YamlValue>) -> std::path::PathBuf {
+    config
+        .get("signatures_bundle")
+        .or_else(|| config.get("signatures_path"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_SIGNATURES_DIR)
+        .into()
+}
+
+/// Check whether the signatures are loaded from a bundle (`signatures_bundle`), see `get_signatures_path`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `signatures_bundle` is set.
+/// * `false` - Otherwise.
+///
+pub fn get_signatures_bundle(config: &HashMap<String, YamlValue>) -> bool {
+    config.get("signatures_bundle").and_then(|v| v.as_str()).is_some()
+}
+
+/// Check whether malformed signatures should be skipped with a warning instead of aborting.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `lenient_signatures` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_lenient_signatures(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("lenient_signatures")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether benchmark statistics (connect and HTTP probe timings, connection reuse) should be shown.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `benchmark` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_benchmark(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("benchmark")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Shortest interval between repeated scans, so every cycle gets its own log file.
+pub const MIN_REPEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Interval between repeated scans when `interval` is not set.
+pub const DEFAULT_REPEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Parse a duration such as `"500ms"`, `"60s"`, `"5m"` or `"1h"`. A bare number means seconds.
+///
+/// # Arguments
+/// * `value` - The duration as written in the config.
+///
+/// # Returns
+/// * `Some(Duration)` - The parsed duration.
+/// * `None` - If the value is not a valid duration.
+///
+pub fn parse_duration(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let millis = match unit.trim() {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => return None,
+    };
+    number.checked_mul(millis).map(std::time::Duration::from_millis)
+}
+
+/// Extract a duration key that is given in milliseconds, like `retry_backoff_ms`. Besides a
+/// number of milliseconds (`200`) it accepts a duration with a unit (`"200ms"`, `"1s"`, `"2m"`,
+/// see `parse_duration`).
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `key` - The key to read.
+///
+/// # Returns
+/// * `Ok(Some(Duration))` - The duration, if the key is set.
+/// * `Ok(None)` - If the key is not set.
+/// * `Err(ScanError)` - If the value is neither a number nor a duration.
+///
+pub fn get_millis_duration(
+    config: &HashMap<String, YamlValue>,
+    key: &str,
+) -> Result<Option<std::time::Duration>, ScanError> {
+    let Some(value) = config.get(key) else {
+        return Ok(None);
+    };
+    let duration = match value {
+        YamlValue::Number(n) => n.as_u64().map(std::time::Duration::from_millis),
+        // A bare number keeps meaning milliseconds, unlike in parse_duration
+        YamlValue::String(s) if !s.trim().is_empty() && s.trim().chars().all(|c| c.is_ascii_digit()) => {
+            s.trim().parse().ok().map(std::time::Duration::from_millis)
+        }
+        YamlValue::String(s) => parse_duration(s),
+        _ => None,
+    };
+    duration.map(Some).ok_or_else(|| {
+        ScanError::Config(format!(
+            "{}: {}: {:?}",
+            crate::localisator::get("error_invalid_duration"),
+            key,
+            value
+        ))
+    })
+}
+
+/// Extract the interval of a repeated scan.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Duration))` - If `repeat` is `true`: the `interval` (e.g., "60s"), or
+///   `DEFAULT_REPEAT_INTERVAL` if not set.
+/// * `Ok(None)` - If the scan runs once.
+/// * `Err(ScanError)` - If the interval is invalid or shorter than `MIN_REPEAT_INTERVAL`.
+///
+pub fn get_repeat_interval(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<std::time::Duration>, ScanError> {
+    if !config.get("repeat").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Ok(None);
+    }
+    let interval = match config.get("interval") {
+        None => return Ok(Some(DEFAULT_REPEAT_INTERVAL)),
+        Some(YamlValue::String(value)) => parse_duration(value),
+        Some(YamlValue::Number(n)) => n.as_u64().map(std::time::Duration::from_secs),
+        Some(_) => None,
+    };
+    match interval {
+        Some(interval) if interval >= MIN_REPEAT_INTERVAL => Ok(Some(interval)),
+        _ => Err(ScanError::Config(format!(
+            "{}: {:?}",
+            crate::localisator::get("error_invalid_interval"),
+            config.get("interval")
+        ))),
+    }
+}
+
+/// Extract the deadline of the whole run: resolving targets, loading signatures and scanning.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Duration))` - The `deadline` (e.g., "90s" or "10m"; a bare number means seconds).
+/// * `Ok(None)` - If no deadline is set.
+/// * `Err(ScanError)` - If the deadline is not a positive duration.
+///
+pub fn get_deadline(config: &HashMap<String, YamlValue>) -> Result<Option<std::time::Duration>, ScanError> {
+    let deadline = match config.get("deadline") {
+        None => return Ok(None),
+        Some(YamlValue::String(value)) => parse_duration(value),
+        Some(YamlValue::Number(n)) => n.as_u64().map(std::time::Duration::from_secs),
+        Some(_) => None,
+    };
+    match deadline {
+        Some(deadline) if !deadline.is_zero() => Ok(Some(deadline)),
+        _ => Err(ScanError::Config(format!(
+            "{}: deadline: {:?}",
+            crate::localisator::get("error_invalid_duration"),
+            config.get("deadline")
+        ))),
+    }
+}
+
+/// Extract how long the scan of a single host may take, see `ScanOptions::target_timeout`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Duration))` - The `per_target_timeout` (e.g., "30s"; a bare number means seconds).
+/// * `Ok(None)` - If hosts are scanned without a time limit.
+/// * `Err(ScanError)` - If the timeout is not a positive duration.
+///
+pub fn get_target_timeout(config: &HashMap<String, YamlValue>) -> Result<Option<std::time::Duration>, ScanError> {
+    let timeout = match config.get("per_target_timeout") {
+        None => return Ok(None),
+        Some(YamlValue::String(value)) => parse_duration(value),
+        Some(YamlValue::Number(n)) => n.as_u64().map(std::time::Duration::from_secs),
+        Some(_) => None,
+    };
+    match timeout {
+        Some(timeout) if !timeout.is_zero() => Ok(Some(timeout)),
+        _ => Err(ScanError::Config(format!(
+            "{}: per_target_timeout: {:?}",
+            crate::localisator::get("error_invalid_duration"),
+            config.get("per_target_timeout")
+        ))),
+    }
+}
+
+/// Extract the cache of identified services, shared by all scans of the run.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(ServiceCache))` - An empty cache if `service_cache` is `true`, whose entries are
+///   probed again after `service_cache_refresh` (e.g., "1h"; a bare number means seconds) if set.
+/// * `Ok(None)` - If caching is not enabled.
+/// * `Err(ScanError)` - If `service_cache_refresh` is not a positive duration.
+///
+pub fn get_service_cache(config: &HashMap<String, YamlValue>) -> Result<Option<crate::scanner::ServiceCache>, ScanError> {
+    let refresh = match config.get("service_cache_refresh") {
+        None => None,
+        Some(value) => {
+            let refresh = match value {
+                YamlValue::String(value) => parse_duration(value),
+                YamlValue::Number(n) => n.as_u64().map(std::time::Duration::from_secs),
+                _ => None,
+            };
+            match refresh {
+                Some(refresh) if !refresh.is_zero() => Some(refresh),
+                _ => {
+                    return Err(ScanError::Config(format!(
+                        "{}: service_cache_refresh: {:?}",
+                        crate::localisator::get("error_invalid_duration"),
+                        value
+                    )))
+                }
+            }
+        }
+    };
+    let enabled = config
+        .get("service_cache")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    Ok(enabled.then(|| crate::scanner::ServiceCache::new(refresh)))
+}
+
+/// Check whether repeated scans should only report changes since the previous cycle.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `changes_only` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_changes_only(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("changes_only")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Extract the latency range results are filtered by.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(LatencyFilter))` - If `min_latency` and/or `max_latency` is set (e.g., "100ms", "2s").
+/// * `Ok(None)` - If neither is set.
+/// * `Err(ScanError)` - If a value is not a valid duration or `min_latency` exceeds `max_latency`.
+///
+pub fn get_latency_filter(config: &HashMap<String, YamlValue>) -> Result<Option<LatencyFilter>, ScanError> {
+    let bound = |key: &str| -> Result<Option<std::time::Duration>, ScanError> {
+        let Some(value) = config.get(key) else {
+            return Ok(None);
+        };
+        let duration = match value {
+            YamlValue::String(value) => parse_duration(value),
+            YamlValue::Number(n) => n.as_u64().map(std::time::Duration::from_secs),
+            _ => None,
+        };
+        duration.map(Some).ok_or_else(|| {
+            ScanError::Config(format!(
+                "{}: {}: {:?}",
+                crate::localisator::get("error_invalid_latency_filter"),
+                key,
+                value
+            ))
+        })
+    };
+    let filter = LatencyFilter {
+        min: bound("min_latency")?,
+        max: bound("max_latency")?,
+    };
+    match filter {
+        LatencyFilter { min: None, max: None } => Ok(None),
+        LatencyFilter { min: Some(min), max: Some(max) } if min > max => Err(ScanError::Config(format!(
+            "{}: min_latency > max_latency",
+            crate::localisator::get("error_invalid_latency_filter")
+        ))),
+        filter => Ok(Some(filter)),
+    }
+}
+
+/// Extract where scan reports are stored besides the text log.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(OutputSink::Sqlite))` - The `db` file, if `output_format` is `"sqlite"`.
+/// * `Ok(Some(OutputSink::Prometheus))` - The `prometheus_file`, if `output_format` is `"prometheus"`.
+/// * `Ok(Some(OutputSink::MsgPack))` - The `msgpack_file`, if `output_format` is `"msgpack"`.
+/// * `Ok(Some(OutputSink::Markdown))` - The `markdown_file`, if `output_format` is `"markdown"`.
+/// * `Ok(None)` - If `output_format` is `"text"`, `"json"` (see `get_stdout_format`) or not set.
+/// * `Err(ScanError)` - If `output_format` is invalid, its file is missing, or `"sqlite"` or
+///   `"msgpack"` is used although Port Explorer was built without that feature.
+///
+pub fn get_output_sink(config: &HashMap<String, YamlValue>) -> Result<Option<OutputSink>, ScanError> {
+    let format = match config.get("output_format").and_then(|v| v.as_str()) {
+        Some(format) => format.parse()?,
+        None => OutputFormat::default(),
+    };
+    let path = |key: &str, missing: &str| match config.get(key).and_then(|v| v.as_str()) {
+        Some(path) => Ok(std::path::PathBuf::from(path)),
+        None => Err(ScanError::Config(crate::localisator::get(missing))),
+    };
+    match format {
+        OutputFormat::Text | OutputFormat::Json => Ok(None),
+        OutputFormat::Sqlite if !cfg!(feature = "sqlite") => Err(ScanError::Config(
+            crate::localisator::get("error_sqlite_disabled"),
+        )),
+        OutputFormat::Sqlite => path("db", "error_missing_db").map(|db| Some(OutputSink::Sqlite(db))),
+        OutputFormat::Prometheus => path("prometheus_file", "error_missing_prometheus_file")
+            .map(|file| Some(OutputSink::Prometheus(file))),
+        OutputFormat::MsgPack if !cfg!(feature = "msgpack") => Err(ScanError::Config(
+            crate::localisator::get("error_msgpack_disabled"),
+        )),
+        OutputFormat::MsgPack => path("msgpack_file", "error_missing_msgpack_file")
+            .map(|file| Some(OutputSink::MsgPack(file))),
+        OutputFormat::Markdown => path("markdown_file", "error_missing_markdown_file")
+            .map(|file| Some(OutputSink::Markdown(file))),
+    }
+}
+
+/// Extract the expected service of each port, for `compare_services` policy checks.
+///
+/// `expected_services` maps a port to a service name. A bare port means TCP; UDP ports are
+/// written as `"53/udp"`:
+/// ```yaml
+/// expected_services:
+///   22: SSH
+///   80: HTTP
+///   "53/udp": DNS
+/// ```
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(HashMap))` - The expected service per (port, protocol) if `compare_services` is `true`.
+/// * `Ok(None)` - If services are not compared.
+/// * `Err(ScanError)` - If `compare_services` is set but `expected_services` is missing or invalid.
+///
+pub fn get_expected_services(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<crate::output::ExpectedServices>, ScanError> {
+    if !config.get("compare_services").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Ok(None);
+    }
+    let invalid = |detail: String| {
+        ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_invalid_expected_services"),
+            detail
+        ))
+    };
+    let map = match config.get("expected_services") {
+        Some(YamlValue::Mapping(map)) => map,
+        Some(other) => return Err(invalid(format!("{:?}", other))),
+        None => return Err(invalid(crate::localisator::get("error_missing_expected_services"))),
+    };
+    let mut expected = HashMap::new();
+    for (key, value) in map {
+        let key_str = match key {
+            YamlValue::Number(n) => n.to_string(),
+            YamlValue::String(s) => s.trim().to_string(),
+            other => return Err(invalid(format!("{:?}", other))),
+        };
+        let (port, protocol) = match key_str.split_once('/') {
+            Some((port, protocol)) => (port, protocol.parse::<Protocol>().ok()),
+            None => (key_str.as_str(), Some(Protocol::Tcp)),
+        };
+        let (Ok(port), Some(protocol), Some(service)) = (port.parse::<u16>(), protocol, value.as_str()) else {
+            return Err(invalid(format!("{}: {:?}", key_str, value)));
+        };
+        expected.insert((port, protocol), service.to_string());
+    }
+    Ok(Some(expected))
+}
+
+/// Extract the baseline report scans are compared with.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Some(PathBuf)` - The `baseline` file, if set.
+/// * `None` - Otherwise.
+///
+pub fn get_baseline_path(config: &HashMap<String, YamlValue>) -> Option<std::path::PathBuf> {
+    config.get("baseline").and_then(|v| v.as_str()).map(Into::into)
+}
+
+/// Check whether the run should exit with a distinct code when a scan deviates from the baseline.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `fail_on_deviation` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_fail_on_deviation(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("fail_on_deviation")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether the run should exit with a distinct code when a port runs an unexpected service.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `fail_on_mismatch` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_fail_on_mismatch(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("fail_on_mismatch")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether verbose output (e.g. the identification rate) should be printed.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `verbose` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_verbose(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("verbose")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether a scan without open ports should count as a failure (exit code 3).
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `fail_on_empty` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_fail_on_empty(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("fail_on_empty")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Extract the order in which results are shown.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(SortBy)` - The `sort_by` value (`port`, `service` or `latency`), `port` if unset.
+/// * `Err(ScanError)` - If `sort_by` is set to an unknown order.
+///
+pub fn get_sort_by(config: &HashMap<String, YamlValue>) -> Result<SortBy, ScanError> {
+    config
+        .get("sort_by")
+        .and_then(|v| v.as_str())
+        .map_or(Ok(SortBy::default()), str::parse)
+}
+
+/// Extract how open ports are grouped on the console.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(GroupBy)` - The `group_by` value (`host` or `service`), `host` if unset.
+/// * `Err(ScanError)` - If `group_by` is set to an unknown grouping.
+///
+pub fn get_group_by(config: &HashMap<String, YamlValue>) -> Result<GroupBy, ScanError> {
+    config
+        .get("group_by")
+        .and_then(|v| v.as_str())
+        .map_or(Ok(GroupBy::default()), str::parse)
+}
+
+/// Extract what scans print on stdout.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(StdoutFormat)` - The `stdout_format` value (`text`, `json`, `msgpack` or `csv`). If unset,
+///   `json` for `output_format: json` and `text` otherwise.
+/// * `Err(ScanError)` - If `stdout_format` is set to an unknown format, or to `msgpack` although
+///   Port Explorer was built without the `msgpack` feature.
+///
+pub fn get_stdout_format(config: &HashMap<String, YamlValue>) -> Result<StdoutFormat, ScanError> {
+    let json_output = config
+        .get("output_format")
+        .and_then(|v| v.as_str())
+        .is_some_and(|format| format.parse::<OutputFormat>().is_ok_and(|format| format == OutputFormat::Json));
+    let default = if json_output { StdoutFormat::Json } else { StdoutFormat::default() };
+    match config.get("stdout_format").and_then(|v| v.as_str()).map_or(Ok(default), str::parse)? {
+        StdoutFormat::MsgPack if !cfg!(feature = "msgpack") => {
+            Err(ScanError::Config(crate::localisator::get("error_msgpack_disabled")))
+        }
+        format => Ok(format),
+    }
+}
+
+/// Extract how scan progress is shown on stderr.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(ProgressFormat)` - The `progress_format` value (`bar` or `json`), `bar` if unset.
+/// * `Err(ScanError)` - If `progress_format` is set to an unknown format.
+///
+pub fn get_progress_format(config: &HashMap<String, YamlValue>) -> Result<ProgressFormat, ScanError> {
+    config.get("progress_format").and_then(|v| v.as_str()).map_or(Ok(ProgressFormat::default()), str::parse)
+}
+
+/// The 100 TCP ports most commonly found open, scanned by the `quick` preset.
+pub const TOP_PORTS: &[u16] = &[
+    7, 9, 13, 21, 22, 23, 25, 26, 37, 53, 79, 80, 81, 88, 106, 110, 111, 113, 119, 135, 139, 143,
+    144, 179, 199, 389, 427, 443, 444, 445, 465, 513, 514, 515, 543, 544, 548, 554, 587, 631, 646,
+    873, 990, 993, 995, 1025, 1026, 1027, 1028, 1029, 1110, 1433, 1720, 1723, 1755, 1900, 2000,
+    2001, 2049, 2121, 2717, 3000, 3128, 3306, 3389, 3986, 4899, 5000, 5009, 5051, 5060, 5101,
+    5190, 5357, 5432, 5631, 5666, 5800, 5900, 6000, 6001, 6646, 7070, 8000, 8008, 8009, 8080,
+    8081, 8443, 8888, 9100, 9999, 10000, 32768, 49152, 49153, 49154, 49155, 49156, 49157,
+];
+
+/// Config keys selecting the ports to scan. If any of them is set, a preset's ports are ignored
+/// entirely, so e.g. `--start-port` with the `quick` preset doesn't also scan the top ports.
+const PORT_SELECTION_KEYS: &[&str] =
+    &["start_port", "end_port", "port_step", "protocol", "ports", "protocol_ports", "ports_from_services", "endpoints"];
+
+/// A built-in combination of scan settings, selected with `preset`.
+///
+/// * `Quick` - The `TOP_PORTS` with many threads and a short connect timeout.
+/// * `Full` - Every TCP port.
+/// * `Stealth` - Few threads, a longer connect timeout, randomized port order and no HTTP probe.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Quick,
+    Full,
+    Stealth,
+}
+
+/// FromStr implementation for Preset, accepting "quick", "full" and "stealth" (case-insensitive).
+///
+impl std::str::FromStr for Preset {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "quick" => Ok(Preset::Quick),
+            "full" => Ok(Preset::Full),
+            "stealth" => Ok(Preset::Stealth),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_preset"),
+                s
+            ))),
+        }
+    }
+}
+
+impl Preset {
+    /// Build the settings of the preset.
+    ///
+    /// # Returns
+    /// * The config keys the preset sets, see `apply_preset`.
+    ///
+    pub fn config(self) -> HashMap<String, YamlValue> {
+        let entries: Vec<(&str, YamlValue)> = match self {
+            Preset::Quick => vec![
+                ("protocol_ports", quick_ports()),
+                ("max_threads", YamlValue::from(500u64)),
+                ("connect_timeout_ms", YamlValue::from(100u64)),
+            ],
+            Preset::Full => vec![
+                ("start_port", YamlValue::from(1u64)),
+                ("end_port", YamlValue::from(65535u64)),
+            ],
+            Preset::Stealth => vec![
+                ("max_threads", YamlValue::from(4u64)),
+                ("connect_timeout_ms", YamlValue::from(1000u64)),
+                ("randomize", YamlValue::Bool(true)),
+                ("no_probe", YamlValue::Bool(true)),
+            ],
+        };
+        entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+    }
+}
+
+/// The `protocol_ports` of the `quick` preset: the `TOP_PORTS` over TCP.
+fn quick_ports() -> YamlValue {
+    let mut map = serde_yaml::Mapping::new();
+    map.insert(YamlValue::String(Protocol::Tcp.to_string()), YamlValue::String(format_port_list(TOP_PORTS)));
+    YamlValue::Mapping(map)
+}
+
+/// Fill in the settings of the `preset` the configuration selects, if any.
+///
+/// The preset only provides defaults: keys already set in the config file or on the command line
+/// keep their value, and its ports are dropped if any of the `PORT_SELECTION_KEYS` is set.
+///
+/// # Arguments
+/// * `config` - The merged configuration, updated in place.
+///
+/// # Returns
+/// * `Ok(())` - If no preset is selected or its settings were added.
+/// * `Err(ScanError)` - If `preset` is not a known preset.
+///
+pub fn apply_preset(config: &mut HashMap<String, YamlValue>) -> Result<(), ScanError> {
+    let Some(name) = config.get("preset") else {
+        return Ok(());
+    };
+    let preset: Preset = name
+        .as_str()
+        .ok_or_else(|| {
+            ScanError::Config(format!("{}: {:?}", crate::localisator::get("error_invalid_preset"), name))
+        })?
+        .parse()?;
+    let ports_selected = PORT_SELECTION_KEYS.iter().any(|key| config.contains_key(*key));
+    for (key, value) in preset.config() {
+        if ports_selected && PORT_SELECTION_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        config.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+/// Networks scanned targets are limited to in safe mode when `allowed_targets` is not set:
+/// loopback, private, link-local and unique local addresses.
+pub const DEFAULT_ALLOWED_TARGETS: &[&str] = &[
+    "127.0.0.0/8",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "169.254.0.0/16",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+/// Extract the networks scan targets are limited to.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Vec<IpNet>))` - If `safe_mode` is `true`: the `allowed_targets` networks (addresses or
+///   CIDR ranges), or `DEFAULT_ALLOWED_TARGETS` if unset.
+/// * `Ok(None)` - If safe mode is off, so any target may be scanned.
+/// * `Err(ScanError)` - If `allowed_targets` contains an invalid entry.
+///
+pub fn get_allowed_targets(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<Vec<IpNet>>, ScanError> {
+    if !config.get("safe_mode").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Ok(None);
+    }
+    let parse = |entry: &str| {
+        entry
+            .parse::<IpNet>()
+            .or_else(|_| entry.parse::<std::net::IpAddr>().map(IpNet::from))
+            .map_err(|_| {
+                ScanError::Config(format!(
+                    "{}: {}",
+                    crate::localisator::get("error_invalid_allowed_targets"),
+                    entry
+                ))
+            })
+    };
+    let networks = match config.get("allowed_targets") {
+        Some(YamlValue::Sequence(seq)) => seq
+            .iter()
+            .map(|v| {
+                v.as_str().map(parse).unwrap_or_else(|| {
+                    Err(ScanError::Config(format!(
+                        "{}: {:?}",
+                        crate::localisator::get("error_invalid_allowed_targets"),
+                        v
+                    )))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(YamlValue::String(list)) => list.split(',').map(|e| parse(e.trim())).collect::<Result<Vec<_>, _>>()?,
+        Some(other) => {
+            return Err(ScanError::Config(format!(
+                "{}: {:?}",
+                crate::localisator::get("error_invalid_allowed_targets"),
+                other
+            )))
+        }
+        None => DEFAULT_ALLOWED_TARGETS.iter().map(|e| parse(e)).collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok(Some(networks))
+}
+
+/// Extract the optional DNS server used to resolve hostname targets.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(SocketAddr))` - If `dns_server` is set to a valid address.
+/// * `Ok(None)` - If `dns_server` is not set, meaning the system resolver should be used.
+/// * `Err(ScanError)` - If `dns_server` is set but invalid.
+///
+pub fn get_dns_server(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<std::net::SocketAddr>, ScanError> {
+    config
+        .get("dns_server")
+        .and_then(|v| v.as_str())
+        .map(crate::resolver::parse_dns_server)
+        .transpose()
+}
+
+/// Extract how many DNS lookups may run at the same time.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(usize)` - The `max_dns_concurrent` value, or `resolver::DEFAULT_MAX_DNS_CONCURRENT` if unset.
+/// * `Err(ScanError)` - If the value is not a positive number.
+///
+pub fn get_max_dns_concurrent(config: &HashMap<String, YamlValue>) -> Result<usize, ScanError> {
+    match config.get("max_dns_concurrent") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_max_dns_concurrent"),
+                    value
+                ))
+            }),
+        None => Ok(crate::resolver::DEFAULT_MAX_DNS_CONCURRENT),
+    }
+}
+
+/// Largest number of hosts a network target may expand to.
+pub const MAX_NETWORK_HOSTS: usize = 65536;
+
+/// Largest number of hosts a network target may expand to with `allow_large_range`, a /8.
+pub const MAX_LARGE_NETWORK_HOSTS: usize = 1 << 24;
+
+/// Extract the largest number of hosts the `ip` network or range may expand to.
+///
 /// # Arguments
-/// * `path` - A string slice that holds the path to the configuration file.
+/// * `config` - A reference to a HashMap containing configuration parameters.
 ///
 /// # Returns
-/// * `Ok(HashMap<String, YamlValue>)` - If the configuration is successfully read and parsed.
-/// * `Err(ScanError)` - If there is an error reading or parsing the configuration file.
+/// * `MAX_LARGE_NETWORK_HOSTS` - If `allow_large_range` is set to `true`.
+/// * `MAX_NETWORK_HOSTS` - Otherwise.
 ///
-pub fn read_config(path: &str) -> Result<HashMap<String, YamlValue>, ScanError> {
-    let content = std::fs::read_to_string(path)?;
-    serde_yaml::from_str::<HashMap<String, YamlValue>>(&content)
-        .map_err(|e| ScanError::Config(e.to_string()))
+pub fn get_max_network_hosts(config: &HashMap<String, YamlValue>) -> usize {
+    match config.get("allow_large_range").and_then(|v| v.as_bool()) {
+        Some(true) => MAX_LARGE_NETWORK_HOSTS,
+        _ => MAX_NETWORK_HOSTS,
+    }
+}
+
+/// Ports probed by the discovery sweep when `discovery_ports` is not set.
+pub const DEFAULT_DISCOVERY_PORTS: &[u16] = &[80, 443];
+
+/// Split an address range (e.g., "10.0.0.1-10.0.0.9") into its first and last address.
+/// Anything else, like a hostname with a dash, gives `None`.
+fn split_ip_range(value: &str) -> Option<(std::net::IpAddr, std::net::IpAddr)> {
+    let (start, end) = value.trim().split_once('-')?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+/// Expand an inclusive address range given as `start-end` into its addresses, counting across
+/// octet boundaries (e.g., "192.168.0.250-192.168.1.5").
+///
+/// # Arguments
+/// * `value` - The target, e.g. the `ip` value.
+///
+/// # Returns
+/// * `Ok(Some(Vec<IpAddr>))` - Every address from start to end, both included.
+/// * `Ok(None)` - If `value` is not a range of two addresses.
+/// * `Err(ScanError)` - If the start is after the end, the addresses are of different IP
+///   versions, or the range has more than `MAX_NETWORK_HOSTS` addresses.
+///
+pub fn parse_ip_range(value: &str) -> Result<Option<Vec<std::net::IpAddr>>, ScanError> {
+    parse_ip_range_limited(value, MAX_NETWORK_HOSTS)
+}
+
+/// Expand an address range like `parse_ip_range`, allowing up to `max_hosts` addresses.
+fn parse_ip_range_limited(value: &str, max_hosts: usize) -> Result<Option<Vec<std::net::IpAddr>>, ScanError> {
+    use std::net::IpAddr;
+    let Some((start, end)) = split_ip_range(value) else {
+        return Ok(None);
+    };
+    let invalid = || {
+        ScanError::Config(format!("{}: {}", crate::localisator::get("error_invalid_ip_range"), value.trim()))
+    };
+    let (first, last) = match (start, end) {
+        (IpAddr::V4(start), IpAddr::V4(end)) => (u32::from(start) as u128, u32::from(end) as u128),
+        (IpAddr::V6(start), IpAddr::V6(end)) => (u128::from(start), u128::from(end)),
+        _ => return Err(invalid()),
+    };
+    if first > last {
+        return Err(invalid());
+    }
+    if last - first >= max_hosts as u128 {
+        return Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_network_too_large"),
+            value.trim()
+        )));
+    }
+    let hosts = (first..=last)
+        .map(|n| match start {
+            IpAddr::V4(_) => IpAddr::V4((n as u32).into()),
+            IpAddr::V6(_) => IpAddr::V6(n.into()),
+        })
+        .collect();
+    Ok(Some(hosts))
+}
+
+/// Resolve a hostname `ip` to every address it has, if `resolve_all` is set.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `ip` - The `ip` value.
+///
+/// # Returns
+/// * `Ok(Some(Vec<IpAddr>))` - The distinct addresses, in resolver order, if there are several.
+/// * `Ok(None)` - If `resolve_all` is not set, `ip` is not a hostname or it has a single address,
+///   which `get_config` resolves as usual.
+/// * `Err(ScanError)` - If the hostname can't be resolved.
+///
+fn get_resolved_hosts(config: &HashMap<String, YamlValue>, ip: &str) -> Result<Option<Vec<std::net::IpAddr>>, ScanError> {
+    let resolve_all = config.get("resolve_all").and_then(|v| v.as_bool()).unwrap_or(false);
+    let hostname = ip.parse::<std::net::IpAddr>().is_err() && !is_dotted_number(ip) && crate::resolver::is_valid_hostname(ip);
+    if !resolve_all || !hostname {
+        return Ok(None);
+    }
+    let dns_server = get_dns_server(config)?;
+    crate::resolver::set_max_concurrent_lookups(get_max_dns_concurrent(config)?);
+    let mut hosts = crate::resolver::resolve_host(ip, dns_server)?;
+    let mut seen = std::collections::HashSet::new();
+    hosts.retain(|host| seen.insert(*host));
+    Ok((hosts.len() > 1).then_some(hosts))
+}
+
+/// Expand a network target (e.g., `ip: "192.168.0.0/24"`) or an address range (e.g.,
+/// `ip: "192.168.0.250-192.168.1.5"`, see `parse_ip_range`) into the hosts to scan. With
+/// `resolve_all`, a hostname with several addresses is expanded into them too.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(Vec<IpAddr>))` - The host addresses of the network (without network and broadcast
+///   address for IPv4 networks larger than /31), every address of the range, or every address
+///   of the hostname.
+/// * `Ok(None)` - If `ip` is neither a network nor a range (a single address or hostname).
+/// * `Err(ScanError)` - If the network or range has more hosts than `get_max_network_hosts`
+///   allows, the range is invalid, or a `ScanError::NotAllowed` if safe mode is on and the target isn't
+///   entirely within `allowed_targets`.
+///
+pub fn get_network_hosts(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<Vec<std::net::IpAddr>>, ScanError> {
+    let Some(ip) = config.get("ip").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    let max_hosts = get_max_network_hosts(config);
+    if !ip.contains('/') {
+        let hosts = match parse_ip_range_limited(ip, max_hosts)? {
+            Some(hosts) => hosts,
+            None => match get_resolved_hosts(config, ip)? {
+                Some(hosts) => hosts,
+                None => return Ok(None),
+            },
+        };
+        if let Some(allowed) = get_allowed_targets(config)? {
+            if !hosts.iter().all(|host| allowed.iter().any(|net| net.contains(host))) {
+                return Err(ScanError::NotAllowed(format!(
+                    "{}: {}",
+                    crate::localisator::get("error_target_not_allowed"),
+                    ip.trim()
+                )));
+            }
+        }
+        return Ok(Some(hosts));
+    }
+    let Ok(network) = ip.trim().parse::<IpNet>() else {
+        return Ok(None);
+    };
+    if let Some(allowed) = get_allowed_targets(config)? {
+        if !allowed.iter().any(|net| net.contains(&network)) {
+            return Err(ScanError::NotAllowed(format!(
+                "{}: {}",
+                crate::localisator::get("error_target_not_allowed"),
+                network
+            )));
+        }
+    }
+    let hosts: Vec<std::net::IpAddr> = network.hosts().take(max_hosts + 1).collect();
+    if hosts.len() > max_hosts {
+        return Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_network_too_large"),
+            network
+        )));
+    }
+    Ok(Some(hosts))
+}
+
+/// Extract the target list files of an inventory scan, see `targets::parse_target_list`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some((current, previous)))` - The `targets_file` to scan, and the `previous_targets_file`
+///   if only the hosts added since that list should be scanned.
+/// * `Ok(None)` - If `targets_file` is not set.
+/// * `Err(ScanError)` - If `previous_targets_file` is set without `targets_file`.
+///
+pub fn get_target_lists(
+    config: &HashMap<String, YamlValue>,
+) -> Result<Option<(std::path::PathBuf, Option<std::path::PathBuf>)>, ScanError> {
+    let path = |key: &str| config.get(key).and_then(|v| v.as_str()).map(std::path::PathBuf::from);
+    match (path("targets_file"), path("previous_targets_file")) {
+        (Some(current), previous) => Ok(Some((current, previous))),
+        (None, Some(_)) => Err(ScanError::Config(crate::localisator::get("error_previous_targets_without_targets"))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Check whether the hosts removed from the target list are scanned again to confirm they are gone.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `recheck_removed` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_recheck_removed(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("recheck_removed")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether a network target should be swept for live hosts before the port scan.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `discover_first` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_discover_first(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("discover_first")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether each host should only be scanned until its first open port is found.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `first_open_only` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_first_open_only(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("first_open_only")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Extract the ports probed by the discovery sweep.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Vec<u16>)` - The ports from `discovery_ports`, or `DEFAULT_DISCOVERY_PORTS` if not set.
+/// * `Err(ScanError)` - If the port list is invalid.
+///
+pub fn get_discovery_ports(config: &HashMap<String, YamlValue>) -> Result<Vec<u16>, ScanError> {
+    match config.get("discovery_ports") {
+        Some(value) => parse_port_value(value),
+        None => Ok(DEFAULT_DISCOVERY_PORTS.to_vec()),
+    }
+}
+
+/// Parse an IPv6 address with a zone identifier, e.g. `fe80::1%eth0` or `fe80::1%2`.
+///
+/// Link-local addresses are only unique per interface, so connecting to one needs the index of
+/// the interface (the scope id) the neighbour is reached through.
+///
+/// # Arguments
+/// * `value` - The address and its zone, separated by `%`.
+///
+/// # Returns
+/// * `Ok((Ipv6Addr, u32))` - The address and the scope id: the index of the named interface, or
+///   the zone itself if it is a number.
+/// * `Err(ScanError)` - If the address is not IPv6, or the zone is empty or names no interface
+///   of this host.
+///
+pub fn parse_scoped_ip(value: &str) -> Result<(std::net::Ipv6Addr, u32), ScanError> {
+    let invalid = |key: &str| ScanError::Config(format!("{} \"{}\"", crate::localisator::get(key), value));
+    let (addr, zone) = value.split_once('%').ok_or_else(|| invalid("error_invalid_ip"))?;
+    let addr = addr.parse::<std::net::Ipv6Addr>().map_err(|_| invalid("error_invalid_ip"))?;
+    let scope_id = match zone.parse::<u32>() {
+        Ok(index) if index > 0 => Some(index),
+        Ok(_) => None,
+        Err(_) => interface_index(zone),
+    };
+    scope_id.map(|scope_id| (addr, scope_id)).ok_or_else(|| invalid("error_invalid_zone"))
+}
+
+/// Look up the index of a network interface by name (e.g., `eth0`).
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `name` is a valid NUL-terminated string that outlives the call
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index > 0).then_some(index)
+}
+
+/// Interface names can't be looked up on this platform, only numeric zones are supported.
+#[cfg(not(unix))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+/// Extract the scope id of a link-local `ip` with a zone, see `parse_scoped_ip`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(u32)` - The scope id, or `0` if `ip` has no zone.
+/// * `Err(ScanError)` - If the zone is invalid.
+///
+pub fn get_scope_id(config: &HashMap<String, YamlValue>) -> Result<u32, ScanError> {
+    match config.get("ip").and_then(|v| v.as_str()) {
+        Some(ip) if ip.contains('%') => Ok(parse_scoped_ip(ip)?.1),
+        _ => Ok(0),
+    }
+}
+
+/// Extract the result sinks that receive every open port as it is found.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Vec<SinkSpec>)` - The sinks of `sinks`, a single sink or a list (e.g.
+///   `["json:results.jsonl", "tcp:127.0.0.1:9000"]`), or none if not set.
+/// * `Err(ScanError)` - If a sink is invalid.
+///
+pub fn get_sinks(config: &HashMap<String, YamlValue>) -> Result<Vec<crate::sinks::SinkSpec>, ScanError> {
+    let invalid = |value: &YamlValue| {
+        ScanError::Config(format!("{}: {:?}", crate::localisator::get("error_invalid_sink"), value))
+    };
+    match config.get("sinks") {
+        None => Ok(Vec::new()),
+        Some(YamlValue::String(sink)) => Ok(vec![sink.parse()?]),
+        Some(YamlValue::Sequence(sinks)) => sinks
+            .iter()
+            .map(|sink| sink.as_str().ok_or_else(|| invalid(sink))?.parse())
+            .collect(),
+        Some(value) => Err(invalid(value)),
+    }
+}
+
+/// Extract the command run for every open port found, see `hooks::OpenHook`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Some(OpenHook))` - The hook of `on_open`, running at most `on_open_concurrency` commands
+///   at once (default `DEFAULT_HOOK_CONCURRENCY`).
+/// * `Ok(None)` - If `on_open` is not set.
+/// * `Err(ScanError)` - If `on_open` is not a non-empty string or `on_open_concurrency` is not a
+///   positive number.
+///
+pub fn get_open_hook(config: &HashMap<String, YamlValue>) -> Result<Option<crate::hooks::OpenHook>, ScanError> {
+    let command = match config.get("on_open") {
+        None => return Ok(None),
+        Some(YamlValue::String(command)) if !command.trim().is_empty() => command,
+        Some(value) => {
+            return Err(ScanError::Config(format!(
+                "{}: {:?}",
+                crate::localisator::get("error_invalid_on_open"),
+                value
+            )))
+        }
+    };
+    let concurrency = match config.get("on_open_concurrency") {
+        None => crate::hooks::DEFAULT_HOOK_CONCURRENCY,
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 => n as usize,
+            _ => {
+                return Err(ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_on_open_concurrency"),
+                    value
+                )))
+            }
+        },
+    };
+    Ok(Some(crate::hooks::OpenHook::new(command.as_str(), concurrency)))
+}
+
+/// Check whether loaded signatures are sorted and deduplicated.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `false` - If `dedupe_signatures` is set to `false`.
+/// * `true` - Otherwise.
+///
+pub fn get_dedupe_signatures(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("dedupe_signatures")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
+}
+
+/// Check whether the signatures embedded in the binary are used alongside the loaded ones.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `false` - If `builtin_signatures` is set to `false`.
+/// * `true` - Otherwise.
+///
+pub fn get_builtin_signatures(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("builtin_signatures")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
+}
+
+/// Check whether an empty signature set aborts the scan instead of only printing a warning.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `require_signatures` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_require_signatures(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("require_signatures")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Number of ports of the scan range added to the discovery ports for the reachability precheck.
+pub const PRECHECK_SAMPLE_PORTS: usize = 3;
+
+/// Extract the ports probed by the reachability precheck of a single target: the discovery ports
+/// and the first `PRECHECK_SAMPLE_PORTS` TCP ports of the scan.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `work` - The protocol-tagged ports of the scan (see `get_scan_work`).
+///
+/// # Returns
+/// * `Ok(Vec<u16>)` - The sorted, deduplicated sample ports.
+/// * `Err(ScanError)` - If `discovery_ports` is invalid.
+///
+pub fn get_precheck_ports(config: &HashMap<String, YamlValue>, work: &[(Protocol, u16)]) -> Result<Vec<u16>, ScanError> {
+    let mut ports = get_discovery_ports(config)?;
+    ports.extend(
+        work.iter()
+            .filter(|(protocol, _)| *protocol == Protocol::Tcp)
+            .map(|(_, port)| *port)
+            .take(PRECHECK_SAMPLE_PORTS),
+    );
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Check whether a single target has to answer the reachability precheck for the scan to start.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `require_reachable` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_require_reachable(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("require_reachable")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check whether a value consists only of digits and dots, i.e. is meant as an IPv4 address.
+pub(crate) fn is_dotted_number(value: &str) -> bool {
+    value.contains('.') && value.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Every top-level key the configuration (file, command line, batch job options) may hold. Any
+/// other key has no effect and is reported by `get_config`.
+pub const KNOWN_KEYS: &[&str] = &[
+    "allow_large_range", "allowed_targets", "append_log", "auto_tune", "banner_max_bytes",
+    "baseline", "batch_jobs", "benchmark", "builtin_signatures", "changes_only", "compare_services",
+    "connect_timeout_ms", "db", "deadline", "dedupe_signatures", "discover_first",
+    "discovery_ports", "dns_server", "end_port", "endpoints", "engine", "expected_services",
+    "fail_on_deviation", "fail_on_empty", "fail_on_mismatch", "first_open_only", "group_by",
+    "http_probe_ports", "http_probe_skip_ports", "insecure", "interval", "ip", "language",
+    "latency_histogram", "lenient_signatures", "markdown_file", "max_dns_concurrent", "max_latency",
+    "max_retries_total", "max_threads", "min_latency", "msgpack_file", "network_failure_threshold",
+    "no_probe", "on_open", "on_open_concurrency", "output_format", "per_target_timeout",
+    "port_step", "ports", "ports_from_services", "preset", "previous_targets_file", "probe_path",
+    "probe_payload", "progress_format", "prometheus_file", "protocol", "protocol_ports",
+    "ramp_up_ms", "randomize", "raw_banners", "recheck_removed", "repeat", "require_reachable",
+    "require_signatures", "resolve_all", "retries", "retry_backoff_ms", "safe_mode", "seed",
+    "service_cache", "service_cache_refresh", "signatures_bundle", "signatures_path", "sinks",
+    "sort_by", "source_port_range", "ssh_jump", "start_port", "stdout_format", "strict_config",
+    "targets_file", "tls_expiry_warning_days", "tls_ports", "tls_server_name", "verbose",
+];
+
+/// A problem with a config key that `get_config` reports as a warning, or as an error with
+/// `strict_config`.
+///
+/// * `UnknownKey` - The key is not in `KNOWN_KEYS` and is ignored. `suggestion` is the known key
+///   it was probably meant to be, e.g. `max_threads` for `treads`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    UnknownKey { key: String, suggestion: Option<&'static str> },
+}
+
+/// Display implementation for ConfigWarning, e.g. `Unknown config key "treads" is ignored (did you mean "max_threads"?)`.
+///
+impl std::fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigWarning::UnknownKey { key, suggestion } => {
+                write!(f, "{}", crate::localisator::get("warning_unknown_config_key").replace("{key}", key))?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " ({} \"{}\"?)", crate::localisator::get("did_you_mean"), suggestion)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Number of single-character edits (insertions, deletions, substitutions) turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Find the known key an unknown key was probably meant to be. The key is compared with every
+/// known key and with its trailing words (`threads` of `max_threads`), so both a typo and a
+/// missing prefix are recognised.
+fn suggest_key(key: &str) -> Option<&'static str> {
+    KNOWN_KEYS
+        .iter()
+        .filter_map(|known| {
+            let distance = std::iter::once(*known)
+                .chain(known.match_indices('_').map(|(i, _)| &known[i + 1..]))
+                .map(|candidate| edit_distance(key, candidate))
+                .min()?;
+            (distance <= 2 && distance < key.chars().count() / 2 + 1).then_some((distance, *known))
+        })
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Check the configuration for keys that have no effect.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * The warnings, sorted by key. Empty if every key is known.
+///
+pub fn get_config_warnings(config: &HashMap<String, YamlValue>) -> Vec<ConfigWarning> {
+    let mut unknown: Vec<&String> = config.keys().filter(|key| !KNOWN_KEYS.contains(&key.as_str())).collect();
+    unknown.sort();
+    unknown
+        .into_iter()
+        .map(|key| ConfigWarning::UnknownKey { key: key.clone(), suggestion: suggest_key(key) })
+        .collect()
+}
+
+/// Prefix of the environment variables setting config keys, e.g. `PORT_EXPLORER_MAX_THREADS`.
+pub const ENV_PREFIX: &str = "PORT_EXPLORER_";
+
+/// Keys whose environment variable has to hold a non-negative integer.
+const ENV_INTEGER_KEYS: &[&str] = &[
+    "banner_max_bytes", "end_port", "max_dns_concurrent", "max_retries_total", "max_threads",
+    "network_failure_threshold", "on_open_concurrency", "port_step", "retries", "seed", "start_port",
+    "tls_expiry_warning_days",
+];
+
+/// The environment variable setting a config key, e.g. `PORT_EXPLORER_START_PORT` for `start_port`.
+pub fn env_var_name(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_ascii_uppercase())
+}
+
+/// Merge the config keys set in the environment into the configuration, see `apply_env_from`.
+///
+/// # Arguments
+/// * `config` - The configuration read from the file, updated in place.
+///
+/// # Returns
+/// * `Ok(())` - If every variable that is set holds a valid value.
+/// * `Err(ScanError)` - If a number is invalid, naming the variable.
+///
+pub fn apply_env(config: &mut HashMap<String, YamlValue>) -> Result<(), ScanError> {
+    apply_env_from(config, |name| std::env::var(name).ok())
+}
+
+/// Merge the config keys set in variables into the configuration, overriding the file.
+///
+/// Every key of `KNOWN_KEYS` is looked up as `env_var_name(key)`. Counts and ports (e.g.
+/// `PORT_EXPLORER_MAX_THREADS=50`) have to be non-negative integers. Other values are read as
+/// YAML, so `true`, `[53, 123]` or `{tcp: "22,80"}` are typed like in the config file, and
+/// anything else (e.g. `10.0.0.0/24`, `fe80::1%eth0`) is kept as a string.
+///
+/// # Arguments
+/// * `config` - The configuration read from the file, updated in place.
+/// * `lookup` - Gets the value of a variable, `None` if it isn't set.
+///
+/// # Returns
+/// * `Ok(())` - If every variable that is set holds a valid value.
+/// * `Err(ScanError)` - If a number is invalid, naming the variable.
+///
+pub fn apply_env_from(
+    config: &mut HashMap<String, YamlValue>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), ScanError> {
+    for key in KNOWN_KEYS {
+        let name = env_var_name(key);
+        let Some(raw) = lookup(&name) else {
+            continue;
+        };
+        let value = if ENV_INTEGER_KEYS.contains(key) {
+            raw.trim().parse::<u64>().map(YamlValue::from).map_err(|_| {
+                ScanError::Config(format!(
+                    "{}: {}={}",
+                    crate::localisator::get("error_invalid_env_number"),
+                    name,
+                    raw
+                ))
+            })?
+        } else {
+            match serde_yaml::from_str::<YamlValue>(&raw) {
+                Ok(YamlValue::Null) | Err(_) => YamlValue::String(raw),
+                Ok(value) => value,
+            }
+        };
+        config.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Check whether config warnings should abort instead of being printed.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `true` - If `strict_config` is set to `true`.
+/// * `false` - Otherwise.
+///
+pub fn get_strict_config(config: &HashMap<String, YamlValue>) -> bool {
+    config
+        .get("strict_config")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Report the config warnings of `get_config_warnings` on stderr, each only once per run, since
+/// the configuration is validated again for every host and cycle.
+fn print_config_warnings(warnings: Vec<ConfigWarning>) {
+    static PRINTED: std::sync::Mutex<Vec<ConfigWarning>> = std::sync::Mutex::new(Vec::new());
+    let mut printed = PRINTED.lock().unwrap_or_else(|e| e.into_inner());
+    for warning in warnings {
+        if !printed.contains(&warning) {
+            eprintln!("{}", warning);
+            printed.push(warning);
+        }
+    }
+}
+
+/// Guess the likely mistake behind an `ip` value that is neither an address nor a hostname.
+///
+/// # Arguments
+/// * `value` - The invalid `ip` value.
+///
+/// # Returns
+/// * `Some((hint, suggestion))` - The localisation key of a hint, and the value probably meant if
+///   it can be derived.
+/// * `None` - If no common mistake was recognised.
+///
+fn invalid_ip_hint(value: &str) -> Option<(&'static str, Option<String>)> {
+    let is_host = |host: &str| {
+        host.parse::<std::net::IpAddr>().is_ok() || crate::resolver::is_valid_hostname(host)
+    };
+    let trimmed = value.trim();
+    if trimmed != value && is_host(trimmed) {
+        return Some(("ip_hint_whitespace", Some(trimmed.to_string())));
+    }
+    if let Some((_, rest)) = trimmed.split_once("://") {
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = match authority.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr.ip().to_string(),
+            Err(_) => authority.rsplit_once(':').map_or(authority, |(host, _)| host).to_string(),
+        };
+        return Some(("ip_hint_url", Some(host).filter(|host| is_host(host))));
+    }
+    if let Some((addr, _)) = trimmed.split_once('/') {
+        return match trimmed.parse::<IpNet>() {
+            Ok(net) => {
+                let host = net.hosts().next().unwrap_or(net.addr());
+                Some(("ip_hint_network", Some(host.to_string())))
+            }
+            Err(_) if addr.parse::<std::net::IpAddr>().is_ok() => Some(("ip_hint_prefix", None)),
+            Err(_) => None,
+        };
+    }
+    if let Some((start, _)) = split_ip_range(trimmed) {
+        return Some(("ip_hint_network", Some(start.to_string())));
+    }
+    if let Ok(addr) = trimmed.parse::<std::net::SocketAddr>() {
+        return Some(("ip_hint_port", Some(addr.ip().to_string())));
+    }
+    if let Some((host, port)) = trimmed.rsplit_once(':') {
+        if port.parse::<u16>().is_ok() && !host.contains(':') && is_host(host) {
+            return Some(("ip_hint_port", Some(host.to_string())));
+        }
+    }
+    if is_dotted_number(trimmed) {
+        let parts: Vec<&str> = trimmed.split('.').collect();
+        if parts.len() == 4 && parts.iter().any(|part| part.parse::<u8>().is_err() && !part.is_empty()) {
+            return Some(("ip_hint_octet", None));
+        }
+        return Some(("ip_hint_parts", None));
+    }
+    None
+}
+
+/// Thread count used when `max_threads` is not set and the available parallelism is unknown.
+pub const FALLBACK_MAX_THREADS: usize = 100;
+
+/// Default threads per CPU core; connects mostly wait on the network, so a core serves many.
+pub const THREADS_PER_CORE: usize = 32;
+
+/// Upper bound of the default thread count, however many cores there are.
+pub const MAX_DEFAULT_THREADS: usize = 1024;
+
+/// Derive the default thread count from the parallelism of the machine.
+///
+/// # Arguments
+/// * `parallelism` - The result of `std::thread::available_parallelism`.
+///
+/// # Returns
+/// * `THREADS_PER_CORE` threads per core, at most `MAX_DEFAULT_THREADS`, or
+///   `FALLBACK_MAX_THREADS` if the parallelism can't be determined.
+///
+pub fn default_max_threads_for(parallelism: std::io::Result<std::num::NonZeroUsize>) -> usize {
+    parallelism.map_or(FALLBACK_MAX_THREADS, |cores| {
+        cores.get().saturating_mul(THREADS_PER_CORE).min(MAX_DEFAULT_THREADS)
+    })
+}
+
+/// The default thread count of this machine, see `default_max_threads_for`.
+pub fn default_max_threads() -> usize {
+    default_max_threads_for(std::thread::available_parallelism())
+}
+
+/// Get the thread count: `max_threads` if set, otherwise `default_max_threads`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(usize)` - The thread count, at least 1.
+/// * `Err(ScanError)` - If `max_threads` is set but not a positive number.
+///
+pub fn get_max_threads(config: &HashMap<String, YamlValue>) -> Result<usize, ScanError> {
+    match config.get("max_threads") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                ScanError::Config(format!(
+                    "{}: {:?}",
+                    crate::localisator::get("error_invalid_max_threads"),
+                    value
+                ))
+            }),
+        None => Ok(default_max_threads()),
+    }
 }
 
 /// Extract and validate configuration parameters.
@@ -23,8 +2442,12 @@ pub fn read_config(path: &str) -> Result<HashMap<String, YamlValue>, ScanError>
 /// * `config` - A reference to a HashMap containing configuration parameters.
 ///
 /// # Returns
-/// * `Ok((Arc<IpAddr>, u16, u16, usize, String))` - If all parameters are valid.
-/// * `Err(ScanError)` - If any parameter is missing or invalid.
+/// * `Ok((Arc<IpAddr>, u16, u16, usize, String))` - If all parameters are valid. A hostname `ip` is
+///   resolved (via `dns_server` if set, at most `max_dns_concurrent` lookups at a time) to its first address.
+/// * `Err(ScanError)` - If any parameter is missing or invalid, or the hostname can't be resolved.
+///   A `ScanError::NotAllowed` if safe mode is on and the target is outside `allowed_targets`.
+///   With `strict_config`, unknown keys are an error too; otherwise they are printed as warnings
+///   (see `get_config_warnings`).
 ///
 pub fn get_config(
     config: &HashMap<String, YamlValue>,
@@ -35,16 +2458,53 @@ pub fn get_config(
         None => "en".to_string(),
     };
     crate::localisator::init(&language);
+    let warnings = get_config_warnings(config);
+    if get_strict_config(config) && !warnings.is_empty() {
+        let list: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        return Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_strict_config"),
+            list.join("; ")
+        )));
+    }
+    print_config_warnings(warnings);
     let ip: std::net::IpAddr = match config.get("ip").and_then(|v| v.as_str()) {
-        Some(ip) => ip
-            .parse()
-            .map_err(|_| ScanError::Config(crate::localisator::get("error_invalid_ip")))?,
+        // A link-local address with its zone, e.g. "fe80::1%eth0"
+        Some(ip) if ip.contains('%') => std::net::IpAddr::V6(parse_scoped_ip(ip)?.0),
+        Some(ip) => match ip.parse() {
+            Ok(addr) => addr,
+            // A dotted number like "10.0.0.256" is a mistyped address, not a hostname
+            Err(_) if !is_dotted_number(ip) && split_ip_range(ip).is_none() && crate::resolver::is_valid_hostname(ip) => {
+                let dns_server = get_dns_server(config)?;
+                crate::resolver::set_max_concurrent_lookups(get_max_dns_concurrent(config)?);
+                crate::resolver::resolve_host(ip, dns_server)?[0]
+            }
+            Err(_) => {
+                let mut msg = format!("{} \"{}\"", crate::localisator::get("error_invalid_ip"), ip);
+                if let Some((hint, suggestion)) = invalid_ip_hint(ip) {
+                    msg.push_str(&format!(": {}", crate::localisator::get(hint)));
+                    if let Some(suggestion) = suggestion {
+                        msg.push_str(&format!(" \"{}\"", suggestion));
+                    }
+                }
+                return Err(ScanError::Config(msg));
+            }
+        },
         None => {
             return Err(ScanError::Config(crate::localisator::get(
                 "error_ip_not_found",
             )))
         }
     };
+    if let Some(allowed) = get_allowed_targets(config)? {
+        if !allowed.iter().any(|net| net.contains(&ip)) {
+            return Err(ScanError::NotAllowed(format!(
+                "{}: {}",
+                crate::localisator::get("error_target_not_allowed"),
+                ip
+            )));
+        }
+    }
     let start_port = config
         .get("start_port")
         .and_then(|v| v.as_u64())
@@ -53,10 +2513,7 @@ pub fn get_config(
         .get("end_port")
         .and_then(|v| v.as_u64())
         .unwrap_or(65535) as u16;
-    let max_threads = config
-        .get("max_threads")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(100) as usize;
+    let max_threads = get_max_threads(config)?;
     Ok((
         std::sync::Arc::new(ip),
         start_port,
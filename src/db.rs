@@ -0,0 +1,133 @@
+use crate::error::ScanError;
+#[cfg(feature = "sqlite")]
+use crate::scanner::PortState;
+use crate::scanner::ScanReport;
+use std::path::Path;
+
+/// Schema of the scan database. Every scan is one row in `scans`, its open ports are rows in
+/// `results` referring to it.
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    target TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    port_range TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    open_ports INTEGER NOT NULL,
+    identification_rate REAL
+);
+CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    port INTEGER NOT NULL,
+    protocol TEXT NOT NULL,
+    state TEXT NOT NULL,
+    service TEXT,
+    latency_ms REAL,
+    version TEXT
+);
+CREATE INDEX IF NOT EXISTS results_scan_id ON results(scan_id);
+";
+
+/// Name of a port state as stored in the database, matching the JSON reports.
+#[cfg(feature = "sqlite")]
+fn state_name(state: PortState) -> &'static str {
+    match state {
+        PortState::Open => "open",
+        PortState::OpenFiltered => "open_filtered",
+    }
+}
+
+/// Append a scan report to a SQLite database, creating the database and its schema if needed.
+///
+/// The scan row and its result rows are written in one transaction.
+///
+/// # Arguments
+/// * `path` - The database file.
+/// * `report` - The report of the finished scan.
+/// * `started_at` - When the scan started.
+///
+/// # Returns
+/// * `Ok(i64)` - The id of the new scan row.
+/// * `Err(ScanError)` - If the database can't be opened or written.
+///
+#[cfg(feature = "sqlite")]
+pub fn save_report(
+    path: &Path,
+    report: &ScanReport,
+    started_at: chrono::DateTime<chrono::Local>,
+) -> Result<i64, ScanError> {
+    let db_error = |e: rusqlite::Error| {
+        ScanError::Io(std::io::Error::other(format!(
+            "{}: {:?}: {}",
+            crate::localisator::get("error_db_write"),
+            path,
+            e
+        )))
+    };
+    let mut conn = rusqlite::Connection::open(path).map_err(db_error)?;
+    conn.execute_batch(SCHEMA).map_err(db_error)?;
+    // Databases created before versions were captured lack the column
+    let has_version = conn
+        .prepare("SELECT 1 FROM pragma_table_info('results') WHERE name = 'version'")
+        .and_then(|mut query| query.exists([]))
+        .map_err(db_error)?;
+    if !has_version {
+        conn.execute("ALTER TABLE results ADD COLUMN version TEXT", []).map_err(db_error)?;
+    }
+    let tx = conn.transaction().map_err(db_error)?;
+    tx.execute(
+        "INSERT INTO scans (started_at, target, ip, port_range, duration_ms, open_ports, identification_rate)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            started_at.to_rfc3339(),
+            report.target,
+            report.ip.to_string(),
+            report.port_range,
+            report.duration_ms as i64,
+            report.open_ports.len() as i64,
+            report.identification_rate,
+        ],
+    )
+    .map_err(db_error)?;
+    let scan_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO results (scan_id, port, protocol, state, service, latency_ms, version)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(db_error)?;
+        for res in &report.open_ports {
+            insert
+                .execute(rusqlite::params![
+                    scan_id,
+                    res.port,
+                    res.protocol.to_string(),
+                    state_name(res.state),
+                    res.service,
+                    res.latency.map(|d| d.as_secs_f64() * 1000.0),
+                    res.version,
+                ])
+                .map_err(db_error)?;
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(scan_id)
+}
+
+/// Fallback when Port Explorer is built without the `sqlite` feature.
+///
+/// # Returns
+/// * `Err(ScanError)` - Always, as SQLite output is not available.
+///
+#[cfg(not(feature = "sqlite"))]
+pub fn save_report(
+    _path: &Path,
+    _report: &ScanReport,
+    _started_at: chrono::DateTime<chrono::Local>,
+) -> Result<i64, ScanError> {
+    Err(ScanError::Config(crate::localisator::get("error_sqlite_disabled")))
+}
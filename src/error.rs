@@ -6,6 +6,8 @@ use std::fmt;
 pub enum ScanError {
     Config(String),
     Io(std::io::Error),
+    Resolve(String),
+    NotAllowed(String),
 }
 
 /// Display implementation for ScanError
@@ -15,6 +17,8 @@ impl fmt::Display for ScanError {
         match self {
             ScanError::Config(msg) => write!(f, "Config error: {}", msg),
             ScanError::Io(e) => write!(f, "IO error: {}", e),
+            ScanError::Resolve(msg) => write!(f, "Resolve error: {}", msg),
+            ScanError::NotAllowed(msg) => write!(f, "Not allowed: {}", msg),
         }
     }
 }
@@ -0,0 +1,137 @@
+use crate::scanner::{PortState, ScanResult};
+use std::net::IpAddr;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use threadpool::ThreadPool;
+
+/// Default number of `on_open` commands that may run at the same time.
+pub const DEFAULT_HOOK_CONCURRENCY: usize = 4;
+
+/// Placeholders substituted in an `on_open` command, with the environment variable each value
+/// is also passed in. The `HOOK_` prefix keeps them apart from the config variables read by
+/// `config::apply_env`, so a Port Explorer run started by the hook isn't reconfigured.
+pub const HOOK_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{host}", "PORT_EXPLORER_HOOK_HOST"),
+    ("{ip}", "PORT_EXPLORER_HOOK_IP"),
+    ("{port}", "PORT_EXPLORER_HOOK_PORT"),
+    ("{protocol}", "PORT_EXPLORER_HOOK_PROTOCOL"),
+    ("{service}", "PORT_EXPLORER_HOOK_SERVICE"),
+    ("{state}", "PORT_EXPLORER_HOOK_STATE"),
+];
+
+/// Shell command run for every open port found, see `on_open` in the README.
+///
+/// Commands run on a pool of `concurrency` threads, so the scan never waits for them; ports
+/// found while all threads are busy are queued. A command that can't be started or exits with
+/// an error is reported as a warning and doesn't affect the scan.
+///
+pub struct OpenHook {
+    command: String,
+    pool: Mutex<ThreadPool>,
+}
+
+impl OpenHook {
+    /// Create a hook running `command` at most `concurrency` times at once (at least once).
+    pub fn new(command: impl Into<String>, concurrency: usize) -> Self {
+        OpenHook {
+            command: command.into(),
+            pool: Mutex::new(ThreadPool::new(concurrency.max(1))),
+        }
+    }
+
+    /// Queue the command for an open port.
+    ///
+    /// # Arguments
+    /// * `host` - The target as given (e.g., a hostname), substituted for `{host}`.
+    /// * `ip` - The address that was scanned, substituted for `{ip}`.
+    /// * `r` - The open port.
+    ///
+    pub fn on_open(&self, host: &str, ip: &IpAddr, r: &ScanResult) {
+        let values = hook_values(host, ip, r);
+        let command = substitute(&self.command, &values);
+        let pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        pool.execute(move || run_command(&command, &values));
+    }
+
+    /// Wait until every queued command has finished, e.g. before the process exits.
+    pub fn wait(&self) {
+        let pool = self.pool.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        pool.join();
+    }
+}
+
+/// The values of the placeholders for an open port, in the order of `HOOK_PLACEHOLDERS`.
+fn hook_values(host: &str, ip: &IpAddr, r: &ScanResult) -> Vec<String> {
+    vec![
+        host.to_string(),
+        ip.to_string(),
+        r.port.to_string(),
+        r.protocol.to_string(),
+        r.service.clone().unwrap_or_default(),
+        match r.state {
+            PortState::Open => "open",
+            PortState::OpenFiltered => "open_filtered",
+        }
+        .to_string(),
+    ]
+}
+
+/// Substitute the placeholders of `HOOK_PLACEHOLDERS` in a command.
+///
+/// Values are inserted verbatim, without any shell quoting, and are not themselves searched for
+/// placeholders.
+///
+/// # Arguments
+/// * `command` - The command with placeholders (e.g., `notify-send "port {port} open on {host}"`).
+/// * `values` - The values, in the order of `HOOK_PLACEHOLDERS`.
+///
+/// # Returns
+/// * The command with every known placeholder replaced; unknown ones are kept as they are.
+///
+pub fn substitute(command: &str, values: &[String]) -> String {
+    let mut substituted = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match HOOK_PLACEHOLDERS.iter().zip(values).find(|((placeholder, _), _)| rest.starts_with(placeholder)) {
+            Some(((placeholder, _), value)) => {
+                substituted.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                substituted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// Run a hook command through the shell and warn if it fails. Its output goes to stderr, so it
+/// can't mix with JSON on stdout.
+fn run_command(command: &str, values: &[String]) {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .envs(HOOK_PLACEHOLDERS.iter().map(|(_, var)| *var).zip(values))
+        .stdin(Stdio::null())
+        .stdout(std::io::stderr());
+    match shell.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("{} ({}): {}", crate::localisator::get("warning_hook_failed"), command, status),
+        Err(e) => eprintln!("{} ({}): {}", crate::localisator::get("warning_hook_failed"), command, e),
+    }
+}
@@ -1,5 +1,101 @@
+pub mod async_engine;
+pub mod baseline;
+pub mod batch;
 pub mod config;
+pub mod db;
 pub mod error;
+pub mod hooks;
 pub mod localisator;
+pub mod markdown;
+pub mod msgpack;
+pub mod output;
+pub mod prometheus;
+pub mod resolver;
 pub mod signatures;
-pub mod scanner;
\ No newline at end of file
+pub mod sinks;
+pub mod scanner;
+pub mod server;
+pub mod services;
+pub mod targets;
+pub mod tls;
+pub mod tui;
+pub mod tunnel;
+
+use error::ScanError;
+use indicatif::ProgressBar;
+use scanner::{scan_work_parallel, CancelToken, ScanOptions, ScanReport};
+use serde_yaml::Value as YamlValue;
+use signatures::Signature;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Run a single scan described by a configuration and collect its report.
+///
+/// This is the library entry point used by batch and server mode. Nothing is printed or logged.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `signatures` - An Arc containing a vector of service signatures.
+///
+/// # Returns
+/// * `Ok(ScanReport)` - The report of the finished scan.
+/// * `Err(ScanError)` - If the configuration is invalid or the scan failed.
+///
+pub fn run_scan(
+    config: &HashMap<String, YamlValue>,
+    signatures: Arc<Vec<Signature>>,
+) -> Result<ScanReport, ScanError> {
+    run_scan_with_cancel(config, signatures, &CancelToken::new())
+}
+
+/// Run a single scan like `run_scan` that can be cancelled from another thread.
+///
+/// Once `token` is cancelled, no further ports are scanned and the report of the ports found so
+/// far is returned promptly, with `stats.cancelled` set.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `token` - The token to cancel the scan with; the caller keeps a clone.
+///
+/// # Returns
+/// * `Ok(ScanReport)` - The report of the finished or cancelled scan.
+/// * `Err(ScanError)` - If the configuration is invalid or the scan failed.
+///
+pub fn run_scan_with_cancel(
+    config: &HashMap<String, YamlValue>,
+    signatures: Arc<Vec<Signature>>,
+    token: &CancelToken,
+) -> Result<ScanReport, ScanError> {
+    let started = Instant::now();
+    let (ip, start_port, end_port, max_threads, _language) = config::get_config(config)?;
+    let options = ScanOptions {
+        cancel: Some(token.clone()),
+        ssh_jump: config::get_ssh_jump(config)?.map(|d| crate::tunnel::ssh_jump(&d)).transpose()?,
+        ..config::get_scan_options(config, max_threads)?
+    };
+    let (work, port_range) = config::get_scan_work(config, start_port, end_port)?;
+    let sort_by = config::get_sort_by(config)?;
+    let pb = ProgressBar::hidden();
+    let (mut open_ports, stats) =
+        scan_work_parallel(Arc::clone(&ip), work, signatures, &options, &pb, |_| {})?;
+    output::sort_results(&mut open_ports, sort_by);
+    if let Some(filter) = config::get_latency_filter(config)? {
+        output::filter_by_latency(&mut open_ports, &filter);
+    }
+    Ok(ScanReport {
+        target: config
+            .get("ip")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        ip: *ip,
+        port_range,
+        duration_ms: started.elapsed().as_millis() as u64,
+        identification_rate: output::identification_rate(&open_ports).1,
+        open_ports,
+        stats,
+        conflicts: Vec::new(),
+    })
+}
@@ -4,24 +4,130 @@ use std::fs;
 use std::sync::Mutex;
 
 static LOC_MAP: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static OVERRIDES: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static LANGUAGE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new("en".to_string()));
 
-/// Initialise the localisation map from a YAML file for the given language.
-/// The file should be located at "resources/localisation/{language}.yaml".
-/// It should contain key-value pairs for all localised strings.
+/// Directory of the localisation files, relative to the working directory.
+const LOCALISATION_DIR: &str = "resources/localisation";
+
+/// The files of `LOCALISATION_DIR` as `(language, content)`, embedded at build time by `build.rs`.
+static BUNDLED: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/bundled_languages.rs"));
+
+/// Initialise the localisation map for the given language.
+///
+/// The strings are taken from the language file bundled into the binary, so no files are needed
+/// at runtime. If "resources/localisation/{language}.yaml" exists on disk, its entries override
+/// the bundled ones, which also allows languages that aren't bundled.
+/// A file contains key-value pairs for all localised strings. A key with plural forms maps
+/// to one string per plural category instead, e.g. `open_ports_found: {one: "{n} open port", other: "{n} open ports"}`.
 ///
 /// # Arguments
 /// * `language` - The language code (e.g., "en", "fr")
 ///
 pub fn init(language: &str) {
-    let path = format!("resources/localisation/{}.yaml", language);
-    let map = match fs::read_to_string(&path) {
-        Ok(content) => {
-            serde_yaml::from_str::<HashMap<String, String>>(&content).unwrap_or_default()
+    *LOC_MAP.lock().unwrap() = load(language);
+    *LANGUAGE.lock().unwrap() = language.to_string();
+}
+
+/// Load the strings of a language: the bundled file, overridden by the file on disk if present.
+fn load(language: &str) -> HashMap<String, String> {
+    let bundled = BUNDLED.iter().find(|(code, _)| *code == language).map(|(_, content)| *content);
+    let on_disk = fs::read_to_string(format!("{}/{}.yaml", LOCALISATION_DIR, language)).ok();
+    let mut map = HashMap::new();
+    for content in bundled.into_iter().chain(on_disk.as_deref()) {
+        let entries = serde_yaml::from_str::<HashMap<String, serde_yaml::Value>>(content).unwrap_or_default();
+        insert_entries(&mut map, entries);
+    }
+    map
+}
+
+/// Get the display name of a language from the `language_name` key of its file, without
+/// changing the loaded language.
+///
+/// # Arguments
+/// * `language` - The language code (e.g., "en", "fr")
+///
+/// # Returns
+/// The name (e.g., "Deutsch"), or None if the language has no file or its file no name.
+///
+pub fn language_name(language: &str) -> Option<String> {
+    load(language).remove("language_name")
+}
+
+/// Languages bundled into the binary.
+///
+/// # Returns
+/// The language codes, sorted alphabetically.
+///
+pub fn bundled_languages() -> Vec<&'static str> {
+    BUNDLED.iter().map(|(code, _)| *code).collect()
+}
+
+/// Languages `init` can load: the bundled ones and those with a file in "resources/localisation".
+/// A missing directory only leaves the bundled languages.
+///
+/// # Returns
+/// The language codes, sorted alphabetically and without duplicates.
+///
+pub fn available_languages() -> Vec<String> {
+    let mut languages: Vec<String> = bundled_languages().into_iter().map(str::to_string).collect();
+    if let Ok(entries) = fs::read_dir(LOCALISATION_DIR) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "yaml") {
+                if let Some(code) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    languages.push(code.to_string());
+                }
+            }
         }
-        Err(_) => HashMap::new(),
-    };
-    let mut loc = LOC_MAP.lock().unwrap();
-    *loc = map;
+    }
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
+/// Add the entries of a language file to the localisation map, replacing existing keys.
+fn insert_entries(map: &mut HashMap<String, String>, entries: HashMap<String, serde_yaml::Value>) {
+    for (key, value) in entries {
+        match value {
+            serde_yaml::Value::String(text) => {
+                map.insert(key, text);
+            }
+            // Plural forms are stored as "key.category"
+            serde_yaml::Value::Mapping(forms) => {
+                for (category, text) in forms {
+                    if let (Some(category), Some(text)) = (category.as_str(), text.as_str()) {
+                        map.insert(format!("{}.{}", key, category), text.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Override a localised string at runtime.
+/// Overrides win over the values loaded by `init` and are kept when `init` is called again.
+///
+/// # Arguments
+/// * `key` - The localisation key (e.g., "scan_complete")
+/// * `value` - The string to use instead of the loaded one
+///
+pub fn set(key: &str, value: &str) {
+    let mut overrides = OVERRIDES.lock().unwrap();
+    overrides.insert(key.to_string(), value.to_string());
+}
+
+/// List all known localisation keys, from the loaded language file and any overrides.
+///
+/// # Returns
+/// The keys, sorted alphabetically and without duplicates.
+///
+pub fn keys() -> Vec<String> {
+    let mut keys: Vec<String> = LOC_MAP.lock().unwrap().keys().cloned().collect();
+    keys.extend(OVERRIDES.lock().unwrap().keys().cloned());
+    keys.sort();
+    keys.dedup();
+    keys
 }
 
 /// Get a localised string for the given key.
@@ -31,9 +137,79 @@ pub fn init(language: &str) {
 /// * `key` - The localisation key
 ///
 /// # Returns
-/// A localised string for the given key. If the key is not found, returns the key itself.
+/// A localised string for the given key, preferring runtime overrides. If the key is not found, returns the key itself.
 ///
 pub fn get(key: &str) -> String {
+    if let Some(value) = OVERRIDES.lock().unwrap().get(key) {
+        return value.clone();
+    }
     let loc = LOC_MAP.lock().unwrap();
     loc.get(key).cloned().unwrap_or_else(|| key.to_string())
 }
+
+/// Select the plural category of a count for a language, following the CLDR plural rules.
+///
+/// # Arguments
+/// * `language` - The language code (e.g., "en", "fr")
+/// * `n` - The count
+///
+/// # Returns
+/// The plural category: "one", "few", "many" or "other". Languages without a dedicated rule use
+/// the English rule (one for 1, other otherwise).
+///
+pub fn plural_category(language: &str, n: u64) -> &'static str {
+    let (mod10, mod100) = (n % 10, n % 100);
+    match language {
+        "ja" | "ko" | "zh" | "vi" | "th" | "id" => "other",
+        "fr" | "pt" if n <= 1 => "one",
+        "fr" | "pt" => "other",
+        "ru" | "uk" | "be" => {
+            if mod10 == 1 && mod100 != 11 {
+                "one"
+            } else if (2..=4).contains(&mod10) && !(12..=14).contains(&mod100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => {
+            if n == 1 {
+                "one"
+            } else if (2..=4).contains(&mod10) && !(12..=14).contains(&mod100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "cs" | "sk" => match n {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+/// Get the localised string for a count, using the plural form the current language needs.
+///
+/// Looks up `key.<category>`, then `key.other`, then `key`, and replaces `{n}` with the count.
+///
+/// # Arguments
+/// * `key` - The localisation key of a plural entry (e.g., "open_ports_found")
+/// * `n` - The count
+///
+/// # Returns
+/// The localised string for the count. If no form is found, returns the key itself.
+///
+pub fn get_plural(key: &str, n: u64) -> String {
+    let category = plural_category(&LANGUAGE.lock().unwrap(), n);
+    let lookup = |k: String| {
+        let value = get(&k);
+        (value != k).then_some(value)
+    };
+    lookup(format!("{}.{}", key, category))
+        .or_else(|| lookup(format!("{}.other", key)))
+        .unwrap_or_else(|| get(key))
+        .replace("{n}", &n.to_string())
+}
@@ -1,16 +1,26 @@
-use clap::Parser;
-mod config;
-mod error;
-mod localisator;
-mod signatures;
-mod scanner;
+use clap::{Parser, Subcommand};
+use port_explorer::output::{
+    ExpectedServices, GroupBy, HostResult, LatencyFilter, OutputSink, ProgressEvents, ProgressFormat, SortBy, StdoutFormat,
+};
+use port_explorer::{baseline, batch, config, db, localisator, markdown, msgpack, output, prometheus, server, targets, tui};
 
 use chrono::Local;
-use indicatif::{ProgressBar, ProgressStyle};
-use signatures::load_signatures;
-use std::io::Write;
-use std::sync::Arc;
-use scanner::{format_duration, scan_ports_parallel};
+use indicatif::ProgressBar;
+use port_explorer::error::ScanError;
+use port_explorer::signatures::{
+    check_signatures_loaded, load_validated_signatures_at, load_validated_signatures_with,
+    load_validated_signatures_with_builtin, Signature,
+};
+use port_explorer::hooks::OpenHook;
+use port_explorer::sinks::{self, ResultSink, Sinks};
+use port_explorer::tunnel::{self, SshJump};
+use std::io::{IsTerminal, Read, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+use port_explorer::scanner::{
+    discover_hosts, find_first_open, format_duration, scan_hosts_parallel, scan_work_parallel, CancelToken, Protocol, ScanOptions, ScanReport,
+    ScanResult, ScanStats, ServiceCache,
+};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// Command-line arguments for Port Explorer
 /// 
@@ -18,13 +28,96 @@ use scanner::{format_duration, scan_ports_parallel};
 /// * `ip` - Target IP address (e.g., "192.168.1
 /// * `start_port` - Starting port number (e.g., 1)
 /// * `end_port` - Ending port number (e.g., 65535)
-/// * `max_threads` - Maximum number of threads to use (e.g., 100)
+/// * `port_step` - Scan only every nth port of the start/end range (e.g., 10)
+/// * `randomize` - Scan the ports in random order
+/// * `seed` - Seed of the random port order, to repeat it across runs (e.g., 42)
+/// * `max_threads` - Maximum number of threads to use (e.g., 100; default 32 per CPU core, at most 1024)
 /// * `language` - Language code for localization (e.g., "en", "es")
+/// * `list_languages` - Print the available languages with their names and exit
+/// * `dns` - DNS server used to resolve hostname targets (e.g., "10.0.0.53")
+/// * `max_dns_concurrent` - Maximum number of DNS lookups running at the same time
+/// * `append_log` - Log file to append to instead of creating a timestamped one
+/// * `tui` - Show a live terminal UI while scanning
+/// * `ports` - Ports to scan (e.g., "22,80,443,8080" or "22,80,1000-2000"), replacing the start/end range
+/// * `tcp_ports` - TCP ports to scan (e.g., "1-1024"), replacing the start/end range
+/// * `udp_ports` - UDP ports to scan (e.g., "53,123,161")
+/// * `protocol` - Protocols the start/end range is scanned with: "tcp", "udp" or "both"
+/// * `ports_from_services` - Service names whose ports are scanned (e.g., "http,https,domain")
+/// * `retries` - Retries per port after a failed connect or unanswered UDP probe
+/// * `retry_backoff_ms` - Delay before the first retry of a port, doubled per retry
+/// * `max_retries_total` - Cap on retry attempts across the whole scan
+/// * `source_port_range` - Local port range outgoing connects are bound to (e.g., "40000-40100")
+/// * `loc` - Localisation overrides as `key=value` pairs (e.g., `scan_complete="Done!"`)
+/// * `sort_by` - Order of the results: "port", "service" or "latency"
+/// * `stdout_format` - What scans print on stdout: "text", "json" (one report per host and line, no log file), "msgpack" or "csv"
+/// * `progress_format` - How progress is shown on stderr: "bar" or "json" (one event per line, e.g. for a GUI)
+/// * `group_by` - Grouping of the open ports: "host" or "service" (one tree of all hosts' endpoints per service)
+/// * `ramp_up_ms` - Raise the thread count gradually to `max_threads` over this many milliseconds
+/// * `ssh_jump` - SSH jump host TCP connects are forwarded through (e.g., "user@bastion:22")
+/// * `auto_tune` - Tune the thread count to the measured throughput, up to `max_threads`
+/// * `engine` - How ports are scanned concurrently: "threads" (default) or "async" (tokio, at most `max_threads` connects in flight)
+/// * `min_latency` - Only show ports that answered at least this slowly (e.g., "100ms")
+/// * `max_latency` - Only show ports that answered at most this slowly (e.g., "2s")
+/// * `output_format` - Where results are stored besides the console: "text" (log file), "sqlite", "prometheus", "msgpack", "markdown" or "json" (the reports as JSON on stdout, like `stdout_format: json`)
+/// * `db` - SQLite database file for `--output-format sqlite`
+/// * `prometheus_file` - Prometheus textfile for `--output-format prometheus`
+/// * `msgpack_file` - MessagePack file for `--output-format msgpack`
+/// * `markdown_file` - Markdown file for `--output-format markdown`
+/// * `sink` - Also stream every open port as it is found to these sinks (e.g., "json:results.jsonl", "tcp:127.0.0.1:9000")
+/// * `on_open` - Shell command run for every open port found (e.g., `notify-send "port {port} open on {host}"`)
+/// * `baseline` - Report only deviations from the open ports and services of this report file
+/// * `fail_on_deviation` - Exit with code 6 if any scan deviates from the baseline
+/// * `compare_services` - Flag open ports whose service differs from `expected_services`
+/// * `fail_on_mismatch` - Exit with code 5 if any port runs an unexpected service
+/// * `lenient_signatures` - Warn about and skip malformed signatures instead of aborting
+/// * `signatures_bundle` - Load the signatures from a single bundle file instead of the `signatures` directory
+/// * `signatures` - Load the signatures from this directory or single signature file instead of the `signatures` directory
+/// * `benchmark` - Show connect and HTTP probe timings and connection reuse after the scan
+/// * `fail_on_empty` - Exit with code 3 if the scan found no open ports
+/// * `strict_config` - Abort on unknown config keys instead of warning about them
+/// * `no_dedupe_signatures` - Keep signatures in loaded order, duplicates included, instead of sorting and deduplicating
+/// * `no_builtin_signatures` - Use only the loaded signatures, not the ones embedded in the binary
+/// * `require_reachable` - Abort a single-target scan if the target answers on none of a few sample ports
+/// * `require_signatures` - Abort if no signature could be loaded instead of scanning without identification
+/// * `resolve_all` - Scan every address a hostname target resolves to instead of only the first
+/// * `allow_large_range` - Allow a network or range target of more than 65536 hosts, up to a /8
+/// * `discover_first` - Sweep a network target for live hosts and port scan only those
+/// * `first_open_only` - Stop scanning a host at its first open port, e.g. for liveness sweeps
+/// * `latency_histogram` - Show a histogram of all connect latencies, failed connects included
+/// * `raw_banners` - Print service names exactly as received instead of stripping escape sequences
+/// * `insecure` - Accept invalid TLS certificates in the HTTPS probe
+/// * `no_probe` - Don't send HTTP probes; identify services from their banner only
+/// * `verbose` - Print additional statistics, e.g. the identification rate
+/// * `endpoints` - Scan exactly these `ip:port` pairs instead of `ip` and a port range
+/// * `repeat` - Re-run the scan every `interval` until Ctrl-C
+/// * `interval` - Time between repeated scans (e.g., "60s", "5m")
+/// * `deadline` - Time budget of the whole run, setup included (e.g., "90s", "10m")
+/// * `per_target_timeout` - Time budget of each host; its remaining ports are skipped once it runs out (e.g., "30s")
+/// * `changes_only` - In repeated scans, only report ports that opened or closed since the last cycle
+/// * `service_cache` - Reuse the services identified in earlier cycles for ports that stayed open
+/// * `refresh` - Probe cached ports again once their service is this old (e.g., "1h")
+/// * `probe_payload` - Payload sent to open TCP ports instead of an HTTP request (e.g., "HELP\r\n")
+/// * `probe_path` - Paths the HTTP probe requests, in order until a signature matches (e.g., "/server-status")
+/// * `http_probe_ports` - Only send the HTTP probe to these ports (e.g., "80,443,8000-8100")
+/// * `http_probe_skip_ports` - Never send the HTTP probe to these ports (e.g., "22,3306")
+/// * `tls_ports` - Open ports whose TLS certificate is reported (e.g., "443,8443")
+/// * `tls_server_name` - Hostname sent with SNI when reading certificates (e.g., "www.example.com")
+/// * `safe_mode` - Only scan targets in `allowed_targets` (private networks by default)
+/// * `preset` - Built-in settings (quick, full or stealth) that other arguments and the config override
+/// * `dump_config` - Print the effective configuration as YAML and exit without scanning
+/// * `dump_config_and_run` - Print the effective configuration as YAML, then scan
+/// * `rescan_from` - Previous JSON report whose open ports (per host) are scanned again
+/// * `targets_file` - File listing the hosts to scan, one address, network or range per line
+/// * `previous_targets_file` - Earlier version of `targets_file`; only hosts added since are scanned
+/// * `recheck_removed` - Also scan the hosts removed since `previous_targets_file` to confirm they are gone
+/// * `command` - Optional subcommand, e.g. `serve` to run as an HTTP service
+/// * `batch` - Read a JSON array of scan jobs from stdin and print a JSON array of reports
+/// * `batch_jobs` - Number of batch jobs scanned at the same time
 /// 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Target IP address
+    /// Target IP address, hostname, network (e.g. 10.0.0.0/24) or address range (e.g. 10.0.0.10-10.0.0.50)
     #[arg(long)]
     ip: Option<String>,
 
@@ -36,6 +129,18 @@ struct Args {
     #[arg(long)]
     end_port: Option<u16>,
 
+    /// Scan only every Nth port of the start/end range, for a quick sparse survey
+    #[arg(long, value_name = "N")]
+    port_step: Option<u16>,
+
+    /// Scan the ports in random order instead of ascending
+    #[arg(long)]
+    randomize: bool,
+
+    /// Seed of the random port order, so --randomize shuffles the same way on every run
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
     /// Max threads
     #[arg(long)]
     max_threads: Option<usize>,
@@ -43,139 +148,2036 @@ struct Args {
     /// Language
     #[arg(long)]
     language: Option<String>,
+
+    /// Print the available language codes (bundled and from resources/localisation) with their names and exit
+    #[arg(long)]
+    list_languages: bool,
+
+    /// DNS server for hostname resolution
+    #[arg(long)]
+    dns: Option<String>,
+
+    /// Maximum number of DNS lookups running at the same time, independent of max threads
+    #[arg(long, value_name = "N")]
+    max_dns_concurrent: Option<usize>,
+
+    /// Append to this log file instead of creating a new one
+    #[arg(long)]
+    append_log: Option<String>,
+
+    /// Show a live terminal UI while scanning
+    #[arg(long)]
+    tui: bool,
+
+    /// Ports to scan instead of the start/end range, single ports and ranges, e.g. "22,80,1000-2000"
+    #[arg(long, value_name = "LIST")]
+    ports: Option<String>,
+
+    /// TCP ports to scan, e.g. "1-1024"
+    #[arg(long)]
+    tcp_ports: Option<String>,
+
+    /// UDP ports to scan, e.g. "53,123,161"
+    #[arg(long)]
+    udp_ports: Option<String>,
+
+    /// Protocols to scan the start/end range with: tcp, udp or both
+    #[arg(long, value_name = "PROTOCOL")]
+    protocol: Option<String>,
+
+    /// Scan the ports of these services, resolved via /etc/services, e.g. "http,https,domain"
+    #[arg(long, value_name = "NAMES")]
+    ports_from_services: Option<String>,
+
+    /// Retries per port
+    #[arg(long)]
+    retries: Option<u32>,
+
+    /// Delay before the first retry of a port in milliseconds, doubled per retry
+    #[arg(long)]
+    retry_backoff_ms: Option<u64>,
+
+    /// Cap on retry attempts across the whole scan
+    #[arg(long)]
+    max_retries_total: Option<u64>,
+
+    /// Local port range outgoing TCP connects are bound to, e.g. "40000-40100"
+    #[arg(long)]
+    source_port_range: Option<String>,
+
+    /// Override a localisation string, e.g. scan_complete="Done!" (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_loc_override)]
+    loc: Vec<(String, String)>,
+
+    /// Sort results by port, service or latency
+    #[arg(long, value_name = "port|service|latency")]
+    sort_by: Option<String>,
+
+    /// Print one JSON report per host and line (or one MessagePack report per host) on stdout instead of the text results, e.g. for jq
+    #[arg(long, visible_alias = "format", value_name = "text|json|msgpack|csv")]
+    stdout_format: Option<String>,
+
+    /// Show progress on stderr as a bar (default) or as JSON Lines events, e.g. for a GUI wrapping the scan
+    #[arg(long, value_name = "bar|json")]
+    progress_format: Option<String>,
+
+    /// Group open ports by host (default) or by service, listing the host:port endpoints of every service
+    #[arg(long, value_name = "host|service")]
+    group_by: Option<String>,
+
+    /// Tune the thread count to the measured throughput, using max threads as the ceiling
+    #[arg(long)]
+    auto_tune: bool,
+
+    /// Scan with async connects on a tokio runtime instead of a thread per port (engine: async)
+    #[arg(long = "async")]
+    async_engine: bool,
+
+    /// Raise the thread count gradually to max_threads over this many milliseconds at the start
+    #[arg(long, value_name = "MS")]
+    ramp_up_ms: Option<u64>,
+
+    /// Scan through an SSH jump host, e.g. "user@bastion" (needs the "ssh" build feature)
+    #[arg(long, value_name = "USER@HOST[:PORT]")]
+    ssh_jump: Option<String>,
+
+    /// Only show ports whose connect latency is at least this, e.g. "100ms"
+    #[arg(long, value_name = "DURATION")]
+    min_latency: Option<String>,
+
+    /// Only show ports whose connect latency is at most this, e.g. "2s"
+    #[arg(long, value_name = "DURATION")]
+    max_latency: Option<String>,
+
+    /// Also store results as "text" (log file, the default), "sqlite" (needs --db), "prometheus" (needs --prometheus-file), "msgpack" (needs --msgpack-file), "markdown" (needs --markdown-file) or "json" (reports as JSON on stdout)
+    #[arg(long, value_name = "FORMAT")]
+    output_format: Option<String>,
+
+    /// SQLite database that --output-format sqlite appends scans to
+    #[arg(long, value_name = "PATH")]
+    db: Option<String>,
+
+    /// Prometheus textfile that --output-format prometheus writes metrics to
+    #[arg(long, value_name = "PATH")]
+    prometheus_file: Option<String>,
+
+    /// MessagePack file that --output-format msgpack writes the reports to
+    #[arg(long, value_name = "PATH")]
+    msgpack_file: Option<String>,
+
+    /// Markdown file that --output-format markdown writes the report to
+    #[arg(long, value_name = "PATH")]
+    markdown_file: Option<String>,
+
+    /// Stream every open port as it is found to a sink: "json:<file>" or "tcp:<host>:<port>" (repeatable)
+    #[arg(long, value_name = "SINK")]
+    sink: Vec<String>,
+
+    /// Run this shell command for every open port found, with {host}, {ip}, {port}, {protocol},
+    /// {service} and {state} substituted, e.g. 'notify-send "port {port} open on {host}"'
+    #[arg(long, value_name = "COMMAND")]
+    on_open: Option<String>,
+
+    /// Report only new open ports and changed services compared to this report (JSON)
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<String>,
+
+    /// Exit with code 6 if any scan deviates from the baseline
+    #[arg(long)]
+    fail_on_deviation: bool,
+
+    /// Flag open ports whose identified service differs from `expected_services` in the config
+    #[arg(long)]
+    compare_services: bool,
+
+    /// Exit with code 5 if any port runs an unexpected service
+    #[arg(long)]
+    fail_on_mismatch: bool,
+
+    /// Warn about and skip malformed signatures instead of aborting
+    #[arg(long)]
+    lenient_signatures: bool,
+
+    /// Load the signatures from a single YAML bundle instead of the signatures directory
+    #[arg(long, value_name = "PATH")]
+    signatures_bundle: Option<String>,
+
+    /// Load the signatures from this directory or single YAML file instead of ./signatures
+    #[arg(long, value_name = "PATH")]
+    signatures: Option<String>,
+
+    /// Show connect and HTTP probe timings and connection reuse after the scan
+    #[arg(long)]
+    benchmark: bool,
+
+    /// Exit with code 3 if no open ports were found
+    #[arg(long)]
+    fail_on_empty: bool,
+
+    /// Abort if the target answers on none of a few sample ports before the scan (otherwise only warn)
+    #[arg(long)]
+    require_reachable: bool,
+
+    /// Abort if no signature could be loaded (e.g. every signature file was skipped) instead of only warning
+    #[arg(long)]
+    require_signatures: bool,
+
+    /// Keep signatures in the order they were loaded, duplicates included, to debug overlapping signature files
+    #[arg(long)]
+    no_dedupe_signatures: bool,
+
+    /// Use only the signatures from the signatures directory, file or bundle, not the built-in ones
+    #[arg(long)]
+    no_builtin_signatures: bool,
+
+    /// Abort if the config has unknown keys (e.g. typos) instead of only warning
+    #[arg(long)]
+    strict_config: bool,
+
+    /// Scan every address a hostname target resolves to instead of only the first
+    #[arg(long)]
+    resolve_all: bool,
+
+    /// Allow a network or range target of more than 65536 hosts, up to a /8
+    #[arg(long)]
+    allow_large_range: bool,
+
+    /// Sweep a network target (e.g. 192.168.0.0/24) for live hosts first and scan only those
+    #[arg(long)]
+    discover_first: bool,
+
+    /// Stop scanning each host at its first open port and report only that port
+    #[arg(long)]
+    first_open_only: bool,
+
+    /// Show a histogram of all connect latencies (open and failed) after the scan
+    #[arg(long)]
+    latency_histogram: bool,
+
+    /// Print service names exactly as received instead of stripping terminal escape sequences and escaping control characters
+    #[arg(long)]
+    raw_banners: bool,
+
+    /// Accept self-signed, expired and other invalid certificates when probing HTTPS
+    #[arg(long)]
+    insecure: bool,
+
+    /// Don't send HTTP probes; identify services from the banner they send by themselves
+    #[arg(long)]
+    no_probe: bool,
+
+    /// Print additional statistics, e.g. the identification rate
+    #[arg(long)]
+    verbose: bool,
+
+    /// Scan exactly these ip:port pairs (e.g. "1.2.3.4:22,[::1]:80") instead of --ip and a port range
+    #[arg(long, value_name = "ENDPOINTS")]
+    endpoints: Option<String>,
+
+    /// Re-run the scan every interval until Ctrl-C
+    #[arg(long)]
+    repeat: bool,
+
+    /// Time between repeated scans, e.g. "60s", "5m" or "1h"
+    #[arg(long, value_name = "DURATION")]
+    interval: Option<String>,
+
+    /// Only report ports that opened or closed since the previous cycle
+    #[arg(long)]
+    changes_only: bool,
+
+    /// Don't probe ports again that stayed open since an earlier cycle, reuse their service
+    #[arg(long)]
+    service_cache: bool,
+
+    /// With --service-cache, probe cached ports again once their service is this old, e.g. "1h"
+    #[arg(long, value_name = "DURATION")]
+    refresh: Option<String>,
+
+    /// Time budget of the whole run, e.g. "90s" or "10m", covering DNS resolution and signature
+    /// loading too; when it runs out the partial results are reported and the exit code is 4
+    #[arg(long, value_name = "DURATION")]
+    deadline: Option<String>,
+
+    /// Time budget of each host, e.g. "30s"; once it runs out the host's remaining ports are
+    /// skipped and the scan moves on to the next host
+    #[arg(long, value_name = "DURATION")]
+    per_target_timeout: Option<String>,
+
+    /// Send this payload to open TCP ports instead of an HTTP request, e.g. "HELP\r\n"
+    #[arg(long, value_name = "PAYLOAD")]
+    probe_payload: Option<String>,
+
+    /// Path the HTTP probe requests instead of "/", e.g. "/server-status" (repeatable, tried in order)
+    #[arg(long, value_name = "PATH")]
+    probe_path: Vec<String>,
+
+    /// Only send the HTTP probe to these ports, e.g. "80,443,8000-8100"; others are identified by their banner
+    #[arg(long, value_name = "PORTS", conflicts_with = "http_probe_skip_ports")]
+    http_probe_ports: Option<String>,
+
+    /// Never send the HTTP probe to these ports, e.g. "22,3306"; they are identified by their banner
+    #[arg(long, value_name = "PORTS")]
+    http_probe_skip_ports: Option<String>,
+
+    /// Report the TLS certificate of these open ports, e.g. "443,8443" (subject, issuer, validity, SANs)
+    #[arg(long, value_name = "PORTS")]
+    tls_ports: Option<String>,
+
+    /// Hostname sent with SNI when reading certificates; defaults to the target if it is a hostname
+    #[arg(long, value_name = "NAME")]
+    tls_server_name: Option<String>,
+
+    /// Read scan jobs as JSON from stdin and print JSON reports
+    #[arg(long)]
+    batch: bool,
+
+    /// Number of batch jobs scanned at the same time
+    #[arg(long)]
+    batch_jobs: Option<usize>,
+
+    /// Re-scan only the open ports (per host) of a previous JSON report
+    #[arg(long, value_name = "PREVIOUS_JSON")]
+    rescan_from: Option<String>,
+
+    /// Scan the hosts listed in this file, one address, network or range per line
+    #[arg(long, value_name = "PATH")]
+    targets_file: Option<String>,
+
+    /// Scan only the hosts of --targets-file that this earlier target list lacks
+    #[arg(long, value_name = "PATH", requires = "targets_file")]
+    previous_targets_file: Option<String>,
+
+    /// Also scan the hosts removed since --previous-targets-file, to confirm they are gone
+    #[arg(long)]
+    recheck_removed: bool,
+
+    /// Only scan targets in the allowed targets (private networks by default)
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Start from built-in settings: quick (top 100 ports, fast), full (all ports) or stealth (slow, random order, no HTTP probe)
+    #[arg(long, value_name = "quick|full|stealth")]
+    preset: Option<String>,
+
+    /// Print the effective configuration (config file, arguments and preset merged) as YAML and exit
+    #[arg(long, conflicts_with = "dump_config_and_run")]
+    dump_config: bool,
+
+    /// Print the effective configuration as YAML, then scan with it
+    #[arg(long)]
+    dump_config_and_run: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands of Port Explorer
+///
+/// Variants:
+/// * `Serve` - Accept scan requests over HTTP and answer with JSON reports
+///
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve scan requests over HTTP (POST /scan)
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: std::net::IpAddr,
+
+        /// Maximum number of scans running at the same time
+        #[arg(long, default_value_t = 4)]
+        max_scans: usize,
+    },
+}
+
+/// Exit codes of Port Explorer, for scripts and CI.
+///
+/// Variants:
+/// * `Success` - The scan finished (with open ports, or without when `fail_on_empty` is off)
+/// * `Config` - Invalid configuration, arguments, input or signatures, or a target that is not allowed
+/// * `Network` - The target couldn't be resolved or a network/IO operation failed
+/// * `NoOpenPorts` - The scan finished without open ports and `fail_on_empty` is set
+/// * `Interrupted` - The scan was interrupted (Ctrl-C)
+/// * `ServiceMismatch` - A port runs an unexpected service and `fail_on_mismatch` is set
+/// * `Deviation` - A scan deviates from the baseline and `fail_on_deviation` is set
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitCode {
+    Success = 0,
+    Config = 1,
+    Network = 2,
+    NoOpenPorts = 3,
+    Interrupted = 4,
+    ServiceMismatch = 5,
+    Deviation = 6,
+}
+
+impl ExitCode {
+    /// Exit code for an error that aborts the run.
+    fn from_error(error: &ScanError) -> Self {
+        match error {
+            ScanError::Config(_) | ScanError::NotAllowed(_) => ExitCode::Config,
+            ScanError::Io(_) | ScanError::Resolve(_) => ExitCode::Network,
+        }
+    }
+
+    /// Exit code for a finished run that found `open_ports` open ports, taking the unexpected
+    /// services and baseline deviations counted during the run into account.
+    fn from_outcome(open_ports: usize, config: &std::collections::HashMap<String, serde_yaml::Value>) -> Self {
+        if DEVIATIONS.load(Ordering::SeqCst) > 0 && config::get_fail_on_deviation(config) {
+            ExitCode::Deviation
+        } else if SERVICE_MISMATCHES.load(Ordering::SeqCst) > 0 && config::get_fail_on_mismatch(config) {
+            ExitCode::ServiceMismatch
+        } else if open_ports == 0 && config::get_fail_on_empty(config) {
+            ExitCode::NoOpenPorts
+        } else {
+            ExitCode::Success
+        }
+    }
+}
+
+/// Print an error and exit with the code of its kind.
+///
+/// # Arguments
+/// * `error` - The error that aborts the run.
+///
+fn exit_with_error(error: &ScanError) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(ExitCode::from_error(error) as i32);
+}
+
+/// Parse a `key=value` localisation override from the command line.
+///
+/// # Arguments
+/// * `arg` - The raw argument (e.g., `scan_complete=Done!`).
+///
+/// # Returns
+/// * `Ok((String, String))` - The key and value.
+/// * `Err(String)` - If the argument has no `=` or an empty key.
+///
+fn parse_loc_override(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", arg)),
+    }
+}
+
+/// Load and validate the signatures the configuration points to.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The signatures, possibly none unless `require_signatures` is set.
+/// * `Err(ScanError)` - If the signatures can't be loaded, or none were and `require_signatures` is set.
+///
+fn load_signatures(config: &std::collections::HashMap<String, serde_yaml::Value>) -> Result<Vec<Signature>, ScanError> {
+    let path = config::get_signatures_path(config);
+    let bundle = config::get_signatures_bundle(config);
+    let (lenient, dedupe) = (config::get_lenient_signatures(config), config::get_dedupe_signatures(config));
+    let signatures = if config::get_builtin_signatures(config) {
+        // Without the default directory, e.g. when run from elsewhere, the built-in signatures are used alone
+        let missing_default = path == std::path::Path::new(config::DEFAULT_SIGNATURES_DIR) && !path.exists();
+        load_validated_signatures_with_builtin((!missing_default).then_some(path.as_path()), bundle, lenient, dedupe)?
+    } else if bundle {
+        load_validated_signatures_with(&path, lenient, dedupe)?
+    } else {
+        load_validated_signatures_at(&path, lenient, dedupe)?
+    };
+    check_signatures_loaded(&signatures, &path, config::get_require_signatures(config))?;
+    Ok(signatures)
+}
+
+/// Read the jobs re-scanning the open ports of a previous report.
+///
+/// # Arguments
+/// * `path` - The path of the previous JSON report.
+///
+/// # Returns
+/// * `Ok(Vec<serde_json::Value>)` - One job per host with open ports.
+/// * `Err(ScanError)` - If the file can't be read or is not a report.
+///
+fn read_rescan_jobs(path: &str) -> Result<Vec<serde_json::Value>, ScanError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ScanError::Config(format!("{}: {}: {}", localisator::get("error_read_file"), path, e))
+    })?;
+    batch::rescan_jobs(&content)
+}
+
+/// Run batch mode: read jobs from stdin (or a previous report), scan them and print the reports as JSON to stdout.
+///
+/// # Arguments
+/// * `config` - The base configuration shared by all jobs.
+/// * `batch_jobs` - Number of jobs scanned at the same time, overriding the `batch_jobs` config key.
+/// * `rescan_from` - A previous report whose open ports are re-scanned instead of reading jobs from stdin.
+///
+/// # Returns
+/// * The number of open ports found across all jobs.
+///
+fn run_batch(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    batch_jobs: Option<usize>,
+    rescan_from: Option<&str>,
+) -> usize {
+    let language = config.get("language").and_then(|v| v.as_str()).unwrap_or("en");
+    localisator::init(language);
+    let jobs = match rescan_from {
+        Some(path) => read_rescan_jobs(path),
+        None => {
+            let mut input = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+                eprintln!("{}: {}", localisator::get("error_batch_read"), e);
+                std::process::exit(ExitCode::Config as i32);
+            }
+            batch::parse_batch(&input)
+        }
+    };
+    let jobs = match jobs {
+        Ok(jobs) => jobs,
+        Err(e) => exit_with_error(&e),
+    };
+    let signatures = match load_signatures(config) {
+        Ok(sigs) => Arc::new(sigs),
+        Err(e) => exit_with_error(&e),
+    };
+    let output_sink = match config::get_output_sink(config) {
+        Ok(output_sink) => output_sink,
+        Err(e) => exit_with_error(&e),
+    };
+    let concurrency = batch_jobs
+        .or_else(|| config.get("batch_jobs").and_then(|v| v.as_u64()).map(|n| n as usize))
+        .unwrap_or(1);
+    let token = DEADLINE.get().cloned().unwrap_or_default();
+    let results = batch::run_batch_with_cancel(config, jobs, signatures, concurrency, &token);
+    match serde_json::to_string_pretty(&results) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(ExitCode::Config as i32);
+        }
+    }
+    // stdout carries the JSON results, so output errors only go to stderr
+    let reports: Vec<&ScanReport> = results.iter().filter_map(|result| result.report.as_ref()).collect();
+    match &output_sink {
+        Some(OutputSink::Sqlite(db)) => {
+            for report in &reports {
+                let started_at = Local::now() - chrono::Duration::milliseconds(report.duration_ms as i64);
+                if let Err(e) = db::save_report(db, report, started_at) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+        Some(OutputSink::Prometheus(file)) => {
+            if let Err(e) = prometheus::write_metrics(file, &reports) {
+                eprintln!("{}", e);
+            }
+        }
+        Some(OutputSink::MsgPack(file)) => {
+            if let Err(e) = msgpack::write_reports(file, &reports) {
+                eprintln!("{}", e);
+            }
+        }
+        Some(OutputSink::Markdown(file)) => {
+            if let Err(e) = markdown::write_report(file, &reports) {
+                eprintln!("{}", e);
+            }
+        }
+        None => {}
+    }
+    results
+        .iter()
+        .filter_map(|result| result.report.as_ref())
+        .map(|report| report.open_ports.len())
+        .sum()
+}
+
+/// Run server mode: accept scan requests over HTTP until the process is stopped.
+///
+/// # Arguments
+/// * `config` - The base configuration applied to every request.
+/// * `addr` - The address to listen on.
+/// * `max_scans` - The maximum number of concurrent scans.
+///
+fn run_server(config: std::collections::HashMap<String, serde_yaml::Value>, addr: std::net::SocketAddr, max_scans: usize) {
+    let language = config.get("language").and_then(|v| v.as_str()).unwrap_or("en");
+    localisator::init(language);
+    let signatures = match load_signatures(&config) {
+        Ok(sigs) => Arc::new(sigs),
+        Err(e) => exit_with_error(&e),
+    };
+    let server = match server::ScanServer::bind(addr, config, signatures, max_scans) {
+        Ok(server) => server,
+        Err(e) => exit_with_error(&e),
+    };
+    println!("{} http://{}", localisator::get("serve_listening"), addr);
+    server.run();
+}
+
+/// The main entry point of the application.
+///
+fn main() {
+    let args = Args::parse();
+    for (key, value) in &args.loc {
+        localisator::set(key, value);
+    }
+    if args.list_languages {
+        for language in localisator::available_languages() {
+            match localisator::language_name(&language) {
+                Some(name) => println!("{}\t{}", language, name),
+                None => println!("{}", language),
+            }
+        }
+        return;
+    }
+    let config_path = config::default_config_path();
+    let mut config = config::read_config(config_path).unwrap_or_default();
+    // The environment overrides the config file, and the command line overrides both; errors in
+    // the environment are shown in the language the run will use
+    let language = args
+        .language
+        .clone()
+        .or_else(|| std::env::var(config::env_var_name("language")).ok())
+        .or_else(|| config.get("language").and_then(|v| v.as_str()).map(str::to_string));
+    localisator::init(language.as_deref().unwrap_or("en"));
+    config::apply_env(&mut config).unwrap_or_else(|e| exit_with_error(&e));
+    // Override config with CLI args if provided
+    if let Some(ip) = &args.ip {
+        config.insert("ip".to_string(), serde_yaml::Value::String(ip.clone()));
+    }
+    if let Some(start_port) = args.start_port {
+        config.insert("start_port".to_string(), serde_yaml::Value::Number(start_port.into()));
+    }
+    if let Some(end_port) = args.end_port {
+        config.insert("end_port".to_string(), serde_yaml::Value::Number(end_port.into()));
+    }
+    if let Some(port_step) = args.port_step {
+        config.insert("port_step".to_string(), serde_yaml::Value::Number(port_step.into()));
+    }
+    if args.randomize {
+        config.insert("randomize".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(seed) = args.seed {
+        config.insert("seed".to_string(), serde_yaml::Value::Number(seed.into()));
+    }
+    if let Some(max_threads) = args.max_threads {
+        config.insert("max_threads".to_string(), serde_yaml::Value::Number((max_threads as u64).into()));
+    }
+    if let Some(language) = &args.language {
+        config.insert("language".to_string(), serde_yaml::Value::String(language.clone()));
+    }
+    if let Some(dns) = &args.dns {
+        config.insert("dns_server".to_string(), serde_yaml::Value::String(dns.clone()));
+    }
+    if let Some(max_dns_concurrent) = args.max_dns_concurrent {
+        config.insert("max_dns_concurrent".to_string(), serde_yaml::Value::from(max_dns_concurrent as u64));
+    }
+    if let Some(retries) = args.retries {
+        config.insert("retries".to_string(), serde_yaml::Value::Number(retries.into()));
+    }
+    if let Some(retry_backoff_ms) = args.retry_backoff_ms {
+        config.insert("retry_backoff_ms".to_string(), serde_yaml::Value::Number(retry_backoff_ms.into()));
+    }
+    if let Some(max_retries_total) = args.max_retries_total {
+        config.insert("max_retries_total".to_string(), serde_yaml::Value::Number(max_retries_total.into()));
+    }
+    if let Some(source_port_range) = &args.source_port_range {
+        config.insert("source_port_range".to_string(), serde_yaml::Value::String(source_port_range.clone()));
+    }
+    if let Some(sort_by) = &args.sort_by {
+        config.insert("sort_by".to_string(), serde_yaml::Value::String(sort_by.clone()));
+    }
+    if let Some(format) = &args.stdout_format {
+        config.insert("stdout_format".to_string(), serde_yaml::Value::String(format.clone()));
+    }
+    if let Some(format) = &args.progress_format {
+        config.insert("progress_format".to_string(), serde_yaml::Value::String(format.clone()));
+    }
+    if let Some(group_by) = &args.group_by {
+        config.insert("group_by".to_string(), serde_yaml::Value::String(group_by.clone()));
+    }
+    if args.safe_mode {
+        config.insert("safe_mode".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.auto_tune {
+        config.insert("auto_tune".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.async_engine {
+        config.insert("engine".to_string(), serde_yaml::Value::from("async"));
+    }
+    if let Some(ramp_up_ms) = args.ramp_up_ms {
+        config.insert("ramp_up_ms".to_string(), serde_yaml::Value::from(ramp_up_ms));
+    }
+    if let Some(ssh_jump) = &args.ssh_jump {
+        config.insert("ssh_jump".to_string(), serde_yaml::Value::String(ssh_jump.clone()));
+    }
+    for (key, value) in [("min_latency", &args.min_latency), ("max_latency", &args.max_latency)] {
+        if let Some(value) = value {
+            config.insert(key.to_string(), serde_yaml::Value::String(value.clone()));
+        }
+    }
+    if let Some(output_format) = &args.output_format {
+        config.insert("output_format".to_string(), serde_yaml::Value::String(output_format.clone()));
+    }
+    if let Some(db) = &args.db {
+        config.insert("db".to_string(), serde_yaml::Value::String(db.clone()));
+    }
+    if let Some(prometheus_file) = &args.prometheus_file {
+        config.insert("prometheus_file".to_string(), serde_yaml::Value::String(prometheus_file.clone()));
+    }
+    if let Some(msgpack_file) = &args.msgpack_file {
+        config.insert("msgpack_file".to_string(), serde_yaml::Value::String(msgpack_file.clone()));
+    }
+    if let Some(markdown_file) = &args.markdown_file {
+        config.insert("markdown_file".to_string(), serde_yaml::Value::String(markdown_file.clone()));
+    }
+    if !args.sink.is_empty() {
+        let sinks = args.sink.iter().cloned().map(serde_yaml::Value::String).collect();
+        config.insert("sinks".to_string(), serde_yaml::Value::Sequence(sinks));
+    }
+    if let Some(on_open) = &args.on_open {
+        config.insert("on_open".to_string(), serde_yaml::Value::String(on_open.clone()));
+    }
+    if let Some(baseline) = &args.baseline {
+        config.insert("baseline".to_string(), serde_yaml::Value::String(baseline.clone()));
+    }
+    if args.fail_on_deviation {
+        config.insert("fail_on_deviation".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.compare_services {
+        config.insert("compare_services".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.fail_on_mismatch {
+        config.insert("fail_on_mismatch".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.lenient_signatures {
+        config.insert("lenient_signatures".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(bundle) = &args.signatures_bundle {
+        config.insert("signatures_bundle".to_string(), serde_yaml::Value::String(bundle.clone()));
+    }
+    if let Some(path) = &args.signatures {
+        config.insert("signatures_path".to_string(), serde_yaml::Value::String(path.clone()));
+    }
+    if let Some(names) = &args.ports_from_services {
+        config.insert("ports_from_services".to_string(), serde_yaml::Value::String(names.clone()));
+    }
+    if args.benchmark {
+        config.insert("benchmark".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.fail_on_empty {
+        config.insert("fail_on_empty".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.strict_config {
+        config.insert("strict_config".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.resolve_all {
+        config.insert("resolve_all".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.allow_large_range {
+        config.insert("allow_large_range".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.no_dedupe_signatures {
+        config.insert("dedupe_signatures".to_string(), serde_yaml::Value::Bool(false));
+    }
+    if args.no_builtin_signatures {
+        config.insert("builtin_signatures".to_string(), serde_yaml::Value::Bool(false));
+    }
+    if args.require_signatures {
+        config.insert("require_signatures".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.require_reachable {
+        config.insert("require_reachable".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.discover_first {
+        config.insert("discover_first".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.first_open_only {
+        config.insert("first_open_only".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.latency_histogram {
+        config.insert("latency_histogram".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.raw_banners {
+        config.insert("raw_banners".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.insecure {
+        config.insert("insecure".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.verbose {
+        config.insert("verbose".to_string(), serde_yaml::Value::Bool(true));
+    }
+    for (key, path) in [("targets_file", &args.targets_file), ("previous_targets_file", &args.previous_targets_file)] {
+        if let Some(path) = path {
+            config.insert(key.to_string(), serde_yaml::Value::String(path.clone()));
+        }
+    }
+    if args.recheck_removed {
+        config.insert("recheck_removed".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(endpoints) = &args.endpoints {
+        config.insert("endpoints".to_string(), serde_yaml::Value::String(endpoints.clone()));
+    }
+    if args.repeat {
+        config.insert("repeat".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(interval) = &args.interval {
+        config.insert("interval".to_string(), serde_yaml::Value::String(interval.clone()));
+    }
+    if args.changes_only {
+        config.insert("changes_only".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if args.service_cache {
+        config.insert("service_cache".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(refresh) = &args.refresh {
+        config.insert("service_cache_refresh".to_string(), serde_yaml::Value::String(refresh.clone()));
+    }
+    if let Some(deadline) = &args.deadline {
+        config.insert("deadline".to_string(), serde_yaml::Value::String(deadline.clone()));
+    }
+    if let Some(timeout) = &args.per_target_timeout {
+        config.insert("per_target_timeout".to_string(), serde_yaml::Value::String(timeout.clone()));
+    }
+    if args.no_probe {
+        config.insert("no_probe".to_string(), serde_yaml::Value::Bool(true));
+    }
+    if let Some(payload) = &args.probe_payload {
+        config.insert("probe_payload".to_string(), serde_yaml::Value::String(payload.clone()));
+    }
+    if !args.probe_path.is_empty() {
+        let paths = args.probe_path.iter().cloned().map(serde_yaml::Value::String).collect();
+        config.insert("probe_path".to_string(), serde_yaml::Value::Sequence(paths));
+    }
+    // A list on the command line replaces whichever list the config file sets
+    if let Some(ports) = &args.http_probe_ports {
+        config.remove("http_probe_skip_ports");
+        config.insert("http_probe_ports".to_string(), serde_yaml::Value::String(ports.clone()));
+    }
+    if let Some(ports) = &args.http_probe_skip_ports {
+        config.remove("http_probe_ports");
+        config.insert("http_probe_skip_ports".to_string(), serde_yaml::Value::String(ports.clone()));
+    }
+    if let Some(ports) = &args.tls_ports {
+        config.insert("tls_ports".to_string(), serde_yaml::Value::String(ports.clone()));
+    }
+    if let Some(name) = &args.tls_server_name {
+        config.insert("tls_server_name".to_string(), serde_yaml::Value::String(name.clone()));
+    }
+    if let Some(append_log) = &args.append_log {
+        config.insert("append_log".to_string(), serde_yaml::Value::String(append_log.clone()));
+    }
+    if let Some(protocol) = &args.protocol {
+        config.insert("protocol".to_string(), serde_yaml::Value::String(protocol.clone()));
+    }
+    if let Some(ports) = &args.ports {
+        config.insert("ports".to_string(), serde_yaml::Value::String(ports.clone()));
+    }
+    for (protocol, ports) in [(Protocol::Tcp, &args.tcp_ports), (Protocol::Udp, &args.udp_ports)] {
+        if let Some(ports) = ports {
+            let entry = config
+                .entry("protocol_ports".to_string())
+                .or_insert_with(|| serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+            if let Some(map) = entry.as_mapping_mut() {
+                map.insert(
+                    serde_yaml::Value::String(protocol.to_string()),
+                    serde_yaml::Value::String(ports.clone()),
+                );
+            }
+        }
+    }
+    if let Some(preset) = &args.preset {
+        config.insert("preset".to_string(), serde_yaml::Value::String(preset.clone()));
+    }
+    localisator::init(config.get("language").and_then(|v| v.as_str()).unwrap_or("en"));
+    // The preset fills in only what neither the config file nor the command line set
+    if let Err(e) = config::apply_preset(&mut config) {
+        exit_with_error(&e);
+    }
+    if args.dump_config || args.dump_config_and_run {
+        match config::dump_config(&config) {
+            Ok(yaml) if args.dump_config => {
+                print!("{}", yaml);
+                return;
+            }
+            // Keep stdout free for the JSON reports of the scan that follows
+            Ok(yaml) if matches!(config::get_stdout_format(&config), Ok(StdoutFormat::Json | StdoutFormat::MsgPack | StdoutFormat::Csv)) => {
+                eprint!("{}", yaml)
+            }
+            Ok(yaml) => print!("{}", yaml),
+            Err(e) => exit_with_error(&e),
+        }
+    }
+    if let Some(Command::Serve { port, bind, max_scans }) = args.command {
+        run_server(config, std::net::SocketAddr::new(bind, port), max_scans);
+        return;
+    }
+    match config::get_stdout_format(&config) {
+        Ok(format) => {
+            let _ = STDOUT_FORMAT.set(format);
+        }
+        Err(e) => exit_with_error(&e),
+    }
+    match config::get_progress_format(&config) {
+        Ok(format) => {
+            let _ = PROGRESS_FORMAT.set(format);
+        }
+        Err(e) => exit_with_error(&e),
+    }
+    match config::get_service_cache(&config) {
+        Ok(Some(cache)) => {
+            let _ = SERVICE_CACHE.set(cache);
+        }
+        Ok(None) => {}
+        Err(e) => exit_with_error(&e),
+    }
+    match config::get_deadline(&config) {
+        Ok(Some(deadline)) => start_deadline(deadline),
+        Ok(None) => {}
+        Err(e) => exit_with_error(&e),
+    }
+    let repeat_interval = match config::get_repeat_interval(&config) {
+        Ok(interval) => interval,
+        Err(e) => exit_with_error(&e),
+    };
+    let repeating = repeat_interval.is_some();
+    // A repeated scan stops after the current cycle on the first Ctrl-C and exits on the second
+    let _ = ctrlc::set_handler(move || {
+        if !repeating || STOP_REQUESTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(ExitCode::Interrupted as i32);
+        }
+    });
+    if repeating
+        && (args.batch
+            || args.rescan_from.is_some()
+            || config.contains_key("endpoints")
+            || config.contains_key("targets_file")
+            || matches!(config::get_network_hosts(&config), Ok(Some(_))))
+    {
+        exit_with_error(&ScanError::Config(localisator::get("error_repeat_single_target")));
+    }
+    match config::get_sinks(&config) {
+        Ok(specs) if !args.batch => {
+            let _ = RESULT_SINKS.set(sinks::open_sinks(&specs));
+        }
+        Ok(_) => {}
+        Err(e) => exit_with_error(&e),
+    }
+    match config::get_open_hook(&config) {
+        Ok(Some(hook)) if !args.batch => {
+            let _ = OPEN_HOOK.set(hook);
+        }
+        Ok(_) => {}
+        Err(e) => exit_with_error(&e),
+    }
+    // Connecting before any scan starts aborts the run if the jump host can't be used
+    match config::get_ssh_jump(&config).and_then(|d| d.map(|d| tunnel::ssh_jump(&d)).transpose()) {
+        Ok(Some(jump)) => {
+            let _ = SSH_JUMP.set(jump);
+        }
+        Ok(None) => {}
+        Err(e) => exit_with_error(&e),
+    }
+    let open_ports = if args.batch {
+        run_batch(&config, args.batch_jobs, args.rescan_from.as_deref())
+    } else if let Some(path) = &args.rescan_from {
+        let jobs = match read_rescan_jobs(path) {
+            Ok(jobs) => jobs,
+            Err(e) => exit_with_error(&e),
+        };
+        if jobs.is_empty() {
+            status(&localisator::get("rescan_nothing"));
+        }
+        let multiple = jobs.len() > 1;
+        let mut open_ports = 0;
+        for job in jobs {
+            if deadline_exceeded() {
+                break;
+            }
+            let job_config = match batch::job_config(&config, &job) {
+                Ok(job_config) => job_config,
+                Err(e) => exit_with_error(&e),
+            };
+            // Several hosts are scanned within the same second, so their logs need distinct names
+            let log_suffix = job["ip"].as_str().filter(|_| multiple).map(str::to_string);
+            open_ports += scan_and_report(&job_config, args.tui, log_suffix.as_deref(), None).len();
+        }
+        open_ports
+    } else if let Some((current, previous)) = config::get_target_lists(&config).unwrap_or_else(|e| exit_with_error(&e)) {
+        scan_target_lists(&config, &current, previous.as_deref(), args.tui)
+    } else {
+        match config::get_endpoints(&config) {
+            Ok(Some(endpoints)) => scan_endpoints(&config, endpoints, args.tui),
+            Ok(None) => match config::get_network_hosts(&config) {
+                Ok(Some(hosts)) => scan_network(&config, hosts, args.tui),
+                Ok(None) => {
+                    precheck_target(&config);
+                    match repeat_interval {
+                        Some(interval) => scan_repeatedly(&config, args.tui, interval),
+                        None => scan_and_report(&config, args.tui, None, None).len(),
+                    }
+                }
+                Err(e) => exit_with_error(&e),
+            },
+            Err(e) => exit_with_error(&e),
+        }
+    };
+    print_service_groups();
+    print_timed_out_hosts();
+    if let Some(hook) = OPEN_HOOK.get() {
+        hook.wait();
+    }
+    if deadline_exceeded() {
+        std::process::exit(ExitCode::Interrupted as i32);
+    }
+    std::process::exit(ExitCode::from_outcome(open_ports, &config) as i32);
+}
+
+/// Number of open ports found running an unexpected service, see `compare_services`.
+static SERVICE_MISMATCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of deviations from the baseline found, see `baseline`.
+static DEVIATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Latest report of every host scanned in this run, for the Prometheus textfile or MessagePack
+/// file. Each write replaces the whole file, so it has to hold all hosts of a network or endpoint scan.
+static LATEST_REPORTS: Mutex<Vec<ScanReport>> = Mutex::new(Vec::new());
+
+/// Open ports of every host scanned so far with `group_by: service`, with the host they were
+/// found on. They are listed together by `print_service_groups`.
+static SERVICE_GROUPS: Mutex<Vec<HostResult>> = Mutex::new(Vec::new());
+
+/// Hosts whose scan was cut short by `per_target_timeout`, listed by `print_timed_out_hosts`.
+static TIMED_OUT_HOSTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Sinks receiving every open port as it is found and every report, see `config::get_sinks`.
+static RESULT_SINKS: OnceLock<Sinks> = OnceLock::new();
+
+/// Command run for every open port found, see `config::get_open_hook`.
+static OPEN_HOOK: OnceLock<OpenHook> = OnceLock::new();
+
+/// The jump host all scans of this run are tunnelled through, see `config::get_ssh_jump`.
+static SSH_JUMP: OnceLock<Arc<SshJump>> = OnceLock::new();
+
+/// The result sinks of this run; none until they are opened in `main`.
+fn result_sinks() -> &'static Sinks {
+    RESULT_SINKS.get_or_init(Sinks::new)
+}
+
+/// What scans print on stdout, see `stdout_format`. The text results until it is set in `main`.
+static STDOUT_FORMAT: OnceLock<StdoutFormat> = OnceLock::new();
+
+/// How progress is shown on stderr, see `progress_format`. The bar until it is set in `main`.
+static PROGRESS_FORMAT: OnceLock<ProgressFormat> = OnceLock::new();
+
+/// Whether progress is shown as JSON events (`progress_format: json`) instead of the bar.
+fn progress_events() -> bool {
+    PROGRESS_FORMAT.get() == Some(&ProgressFormat::Json)
 }
 
-/// Format a duration into a human-readable string.
+/// Create the progress bar of a scan phase; with `progress_format: json` it stays hidden and
+/// its progress is printed as events instead.
 ///
 /// # Arguments
-/// * `duration` - The duration to format.
+/// * `len` - The number of work units the bar tracks.
 ///
-/// Returns
-/// * A formatted string representing the duration in the largest appropriate units.
-/// The main entry point of the application.
+/// # Returns
+/// * The progress bar, and the events printing its progress if they were asked for. Pass both
+///   to `finish_progress` when the phase is done.
 ///
-fn main() {
-    let args = Args::parse();
-    let scan_start = std::time::Instant::now();
-    let config_path = "config.yaml";
-    let mut config = match config::read_config(config_path) {
-        Ok(cfg) => cfg,
-        Err(_) => std::collections::HashMap::new(),
+fn scan_progress(len: u64) -> (ProgressBar, Option<ProgressEvents>) {
+    let pb = output::progress_bar(len, output::PROGRESS_TEMPLATE, std::io::stderr().is_terminal() && !progress_events());
+    let events = progress_events().then(|| ProgressEvents::start(&pb));
+    (pb, events)
+}
+
+/// Clear the progress bar of a finished scan phase and print its final progress event, if any.
+fn finish_progress(pb: &ProgressBar, events: Option<ProgressEvents>) {
+    pb.finish_and_clear();
+    if let Some(events) = events {
+        events.finish();
+    }
+}
+
+/// Whether stdout carries reports (`stdout_format: json`, `msgpack` or `csv`) instead of the text results.
+fn reports_on_stdout() -> bool {
+    STDOUT_FORMAT.get().is_some_and(|format| *format != StdoutFormat::Text)
+}
+
+/// Print a report (or another value of the machine-readable output) on stdout, as a JSON line
+/// or, with `stdout_format: msgpack`, as a MessagePack value.
+fn print_report<T: serde::Serialize>(value: &T) {
+    let printed = match STDOUT_FORMAT.get() {
+        Some(StdoutFormat::MsgPack) => msgpack::encode(value).and_then(|bytes| {
+            let mut stdout = std::io::stdout().lock();
+            Ok(stdout.write_all(&bytes).and_then(|()| stdout.flush())?)
+        }),
+        _ => serde_json::to_string(value)
+            .map(|json| println!("{}", json))
+            .map_err(|e| ScanError::Config(e.to_string())),
     };
-    // Override config with CLI args if provided
-    if let Some(ip) = &args.ip {
-        config.insert("ip".to_string(), serde_yaml::Value::String(ip.clone()));
+    if let Err(e) = printed {
+        eprintln!("{}", e);
     }
-    if let Some(start_port) = args.start_port {
-        config.insert("start_port".to_string(), serde_yaml::Value::Number(start_port.into()));
+}
+
+/// Print open ports on stdout as CSV rows (`stdout_format: csv`), after the header if none was
+/// printed yet, so a network scan prints one header for all hosts.
+fn print_csv(ip: &std::net::IpAddr, results: &[ScanResult]) {
+    static HEADER: std::sync::Once = std::sync::Once::new();
+    let mut stdout = std::io::stdout().lock();
+    let mut printed = Ok(());
+    HEADER.call_once(|| printed = output::write_csv_header(&mut stdout));
+    if let Err(e) = printed.and_then(|()| output::write_csv_rows(ip, results, &mut stdout)) {
+        eprintln!("{}", e);
     }
-    if let Some(end_port) = args.end_port {
-        config.insert("end_port".to_string(), serde_yaml::Value::Number(end_port.into()));
+}
+
+/// Print a line that isn't part of a scan's results, e.g. progress of a repeated or network scan.
+/// It goes to stderr when stdout carries reports, so it can't corrupt them.
+fn status(line: &str) {
+    if reports_on_stdout() {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
     }
-    if let Some(max_threads) = args.max_threads {
-        config.insert("max_threads".to_string(), serde_yaml::Value::Number((max_threads as u64).into()));
+}
+
+/// Print the open ports collected in `SERVICE_GROUPS` as a tree grouped by service, and clear them.
+fn print_service_groups() {
+    let results = std::mem::take(&mut *SERVICE_GROUPS.lock().unwrap());
+    if !results.is_empty() {
+        print!("{}", output::format_service_groups(&results));
     }
-    if let Some(language) = &args.language {
-        config.insert("language".to_string(), serde_yaml::Value::String(language.clone()));
+}
+
+/// Print the hosts collected in `TIMED_OUT_HOSTS`, if any, and clear them.
+fn print_timed_out_hosts() {
+    let hosts = std::mem::take(&mut *TIMED_OUT_HOSTS.lock().unwrap());
+    if !hosts.is_empty() {
+        eprintln!("{} {}", localisator::get("timed_out_hosts"), hosts.join(", "));
     }
-    let (ip, start_port, end_port, max_threads, _language) = match config::get_config(&config) {
-        Ok(vals) => vals,
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+}
+
+/// Store the report of a scan in the configured output sink.
+///
+/// # Arguments
+/// * `sink` - Where to store the report.
+/// * `report` - The report of the finished scan.
+/// * `started_at` - When the scan started.
+///
+/// # Returns
+/// * `Ok(String)` - The line telling where the report was stored.
+/// * `Err(ScanError)` - If the sink can't be written.
+///
+fn store_report(sink: &OutputSink, report: &ScanReport, started_at: chrono::DateTime<Local>) -> Result<String, ScanError> {
+    match sink {
+        OutputSink::Sqlite(db) => {
+            let scan_id = db::save_report(db, report, started_at)?;
+            Ok(format!("{} #{} ({})\n", localisator::get("db_saved_scan"), scan_id, db.display()))
         }
-    };
-    let signatures = match load_signatures() {
-        Ok(sigs) => Arc::new(sigs),
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+        OutputSink::Prometheus(file) => {
+            prometheus::write_metrics(file, &latest_reports(report).iter().collect::<Vec<_>>())?;
+            Ok(format!("{} {}\n", localisator::get("prometheus_written"), file.display()))
+        }
+        OutputSink::MsgPack(file) => {
+            msgpack::write_reports(file, &latest_reports(report).iter().collect::<Vec<_>>())?;
+            Ok(format!("{} {}\n", localisator::get("msgpack_written"), file.display()))
+        }
+        OutputSink::Markdown(file) => {
+            markdown::write_report(file, &latest_reports(report).iter().collect::<Vec<_>>())?;
+            Ok(format!("{} {}\n", localisator::get("markdown_written"), file.display()))
+        }
+    }
+}
+
+/// Add a report to `LATEST_REPORTS`, replacing the previous report of its host.
+///
+/// # Arguments
+/// * `report` - The report of the finished scan.
+///
+/// # Returns
+/// * The latest report of every host scanned so far, locked.
+///
+fn latest_reports(report: &ScanReport) -> std::sync::MutexGuard<'static, Vec<ScanReport>> {
+    let mut reports = LATEST_REPORTS.lock().unwrap_or_else(|e| e.into_inner());
+    // A repeated scan replaces the report of its previous cycle
+    reports.retain(|previous| previous.ip != report.ip);
+    reports.push(report.clone());
+    reports
+}
+
+/// Set by Ctrl-C to end a repeated scan after the current cycle.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// How long the phase running when the deadline passes may take to wrap up, e.g. to report the
+/// partial results of a cancelled scan, before the process exits anyway.
+const DEADLINE_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Cancelled when the `deadline` of the run has passed; every scan of the run holds a clone.
+static DEADLINE: OnceLock<CancelToken> = OnceLock::new();
+
+/// Start the deadline of the run, see `config::get_deadline`.
+///
+/// When it passes, running scans are cancelled and report the ports found so far, no further
+/// host, cycle or job is started, and the run ends with `ExitCode::Interrupted`. A phase that
+/// can't be cancelled, like a hung DNS lookup or signature loading, is cut off after
+/// `DEADLINE_GRACE`.
+///
+/// # Arguments
+/// * `deadline` - The time budget of the run, counted from now.
+///
+fn start_deadline(deadline: std::time::Duration) {
+    let token = DEADLINE.get_or_init(CancelToken::new).clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(deadline);
+        eprintln!("{} ({})", localisator::get("deadline_exceeded"), format_duration(deadline));
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+        token.cancel();
+        std::thread::sleep(DEADLINE_GRACE);
+        std::process::exit(ExitCode::Interrupted as i32);
+    });
+}
+
+/// Whether the deadline of the run has passed.
+fn deadline_exceeded() -> bool {
+    DEADLINE.get().is_some_and(CancelToken::is_cancelled)
+}
+
+/// Services identified during this run, see `config::get_service_cache`.
+static SERVICE_CACHE: OnceLock<ServiceCache> = OnceLock::new();
+
+/// The scan options of a configuration, cancelled by the deadline of the run if one is set and
+/// sharing the service cache and jump host of the run.
+fn scan_options(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    max_threads: usize,
+) -> Result<port_explorer::scanner::ScanOptions, ScanError> {
+    let options = config::get_scan_options(config, max_threads)?;
+    Ok(port_explorer::scanner::ScanOptions {
+        cancel: DEADLINE.get().cloned(),
+        service_cache: SERVICE_CACHE.get().cloned(),
+        ssh_jump: SSH_JUMP.get().cloned(),
+        ..options
+    })
+}
+
+/// Scan the target over and over, waiting `interval` between the starts of two cycles.
+///
+/// The first cycle reports all open ports; later cycles only report the changes if
+/// `changes_only` is set. Ctrl-C stops after the current cycle.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `tui` - Whether to show the live terminal UI (if stdout is a terminal).
+/// * `interval` - Time between the starts of two cycles.
+///
+/// # Returns
+/// * The number of open ports found in the last cycle.
+///
+fn scan_repeatedly(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    tui: bool,
+    interval: std::time::Duration,
+) -> usize {
+    let changes_only = config::get_changes_only(config);
+    let mut previous: Option<Vec<ScanResult>> = None;
+    for cycle in 1.. {
+        let started = std::time::Instant::now();
+        status(&format!(
+            "[{}] {} {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            localisator::get("repeat_cycle"),
+            cycle
+        ));
+        let compare = previous.as_deref().filter(|_| changes_only);
+        let open_ports = scan_and_report(config, tui, None, compare);
+        print_service_groups();
+        print_timed_out_hosts();
+        previous = Some(open_ports);
+        while !STOP_REQUESTED.load(Ordering::SeqCst) && started.elapsed() < interval {
+            std::thread::sleep(interval.saturating_sub(started.elapsed()).min(std::time::Duration::from_millis(100)));
+        }
+        if STOP_REQUESTED.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    previous.map_or(0, |open_ports| open_ports.len())
+}
+
+/// Scan exactly the given endpoints, one report per host.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `endpoints` - The hosts with their TCP ports, see `config::get_endpoints`.
+/// * `tui` - Whether to show the live terminal UI (if stdout is a terminal).
+///
+/// # Returns
+/// * The number of open ports found on all hosts.
+///
+fn scan_endpoints(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    endpoints: config::Endpoints,
+    tui: bool,
+) -> usize {
+    let mut open_ports = 0;
+    for (host, ports) in endpoints {
+        if deadline_exceeded() {
+            break;
         }
+        let mut host_config = config.clone();
+        host_config.insert("ip".to_string(), serde_yaml::Value::String(host.to_string()));
+        // Only the listed ports are scanned, so no other port selection may add to them
+        host_config.remove("ports_from_services");
+        let mut protocol_ports = serde_yaml::Mapping::new();
+        protocol_ports.insert(
+            serde_yaml::Value::String(Protocol::Tcp.to_string()),
+            serde_yaml::Value::Sequence(ports.into_iter().map(serde_yaml::Value::from).collect()),
+        );
+        host_config.insert("protocol_ports".to_string(), serde_yaml::Value::Mapping(protocol_ports));
+        open_ports += scan_and_report(&host_config, tui, Some(&host.to_string()), None).len();
+    }
+    open_ports
+}
+
+/// Scan the hosts of a target list, or only those added since a previous version of it.
+///
+/// With a previous list, the added and removed hosts are listed (`+`/`-`) before the added hosts
+/// are scanned. With `recheck_removed`, the removed hosts are scanned afterwards under their own
+/// heading, to confirm they are really gone.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `current` - The target list to scan.
+/// * `previous` - The previous target list, if only the added hosts should be scanned.
+/// * `tui` - Whether to show the live terminal UI for each host (if stdout is a terminal).
+///
+/// # Returns
+/// * The number of open ports found across all scanned hosts.
+///
+fn scan_target_lists(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    current: &std::path::Path,
+    previous: Option<&std::path::Path>,
+    tui: bool,
+) -> usize {
+    let current = targets::read_target_list(config, current).unwrap_or_else(|e| exit_with_error(&e));
+    let Some(previous) = previous else {
+        return scan_network(config, current, tui);
     };
-    let ports: Vec<u16> = (start_port..=end_port).collect();
-    let pb = ProgressBar::new(ports.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%)")
-            .expect(&localisator::get("error_progress_bar_template"))
-            .progress_chars("=>-")
-    );
-    let open_ports =
-        match scan_ports_parallel(ip.clone(), ports, signatures.clone(), max_threads, &pb) {
-            Ok(ports) => ports,
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
+    let previous = targets::read_target_list(config, previous).unwrap_or_else(|e| exit_with_error(&e));
+    let delta = targets::target_delta(&previous, &current);
+    status(&format!("{} {}", localisator::get("targets_added"), delta.added.len()));
+    for host in &delta.added {
+        status(&format!("+ {}", host));
+    }
+    status(&format!("{} {}", localisator::get("targets_removed"), delta.removed.len()));
+    for host in &delta.removed {
+        status(&format!("- {}", host));
+    }
+    let mut open_ports = 0;
+    if !delta.added.is_empty() {
+        status(&localisator::get("targets_scanning_added"));
+        open_ports += scan_network(config, delta.added, tui);
+    }
+    if config::get_recheck_removed(config) && !delta.removed.is_empty() {
+        status(&localisator::get("targets_rechecking_removed"));
+        open_ports += scan_network(config, delta.removed, tui);
+    }
+    open_ports
+}
+
+/// Check that a single target answers on a small sample of ports before the full scan, so a
+/// mistyped or offline host is noticed up front. A refused connect counts as an answer.
+///
+/// If the target doesn't answer, the scan is aborted with a network error when
+/// `require_reachable` is set, and only a warning is printed otherwise.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+///
+fn precheck_target(config: &std::collections::HashMap<String, serde_yaml::Value>) {
+    let (ip, start_port, end_port, max_threads, _language) = config::get_config(config).unwrap_or_else(|e| exit_with_error(&e));
+    let options = scan_options(config, max_threads).unwrap_or_else(|e| exit_with_error(&e));
+    let (work, _) = config::get_scan_work(config, start_port, end_port).unwrap_or_else(|e| exit_with_error(&e));
+    let ports = config::get_precheck_ports(config, &work).unwrap_or_else(|e| exit_with_error(&e));
+    if !discover_hosts(vec![*ip], &ports, &options, &indicatif::ProgressBar::hidden()).is_empty() {
+        return;
+    }
+    if config::get_require_reachable(config) {
+        exit_with_error(&ScanError::Io(std::io::Error::new(
+            std::io::ErrorKind::HostUnreachable,
+            format!("{}: {}", localisator::get("error_target_unreachable"), ip),
+        )));
+    }
+    eprintln!("{}: {}", localisator::get("warning_target_unreachable"), ip);
+}
+
+/// Scan every host of a network target, after an optional discovery sweep.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan; its `ip` is the network.
+/// * `hosts` - The hosts of the network.
+/// * `tui` - Whether to show the live terminal UI for each host (if stdout is a terminal).
+///
+/// # Returns
+/// * The number of open ports found across all hosts.
+///
+fn scan_network(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    hosts: Vec<std::net::IpAddr>,
+    tui: bool,
+) -> usize {
+    let hosts = if config::get_discover_first(config) {
+        let ports = config::get_discovery_ports(config).unwrap_or_else(|e| exit_with_error(&e));
+        let max_threads = config::get_max_threads(config).unwrap_or_else(|e| exit_with_error(&e));
+        let options = scan_options(config, max_threads).unwrap_or_else(|e| exit_with_error(&e));
+        let (pb, events) = scan_progress(hosts.len() as u64);
+        let total = hosts.len();
+        let live = discover_hosts(hosts, &ports, &options, &pb);
+        finish_progress(&pb, events);
+        status(&format!("{} {}/{}", localisator::get("discovery_live_hosts"), live.len(), total));
+        live
+    } else {
+        hosts
+    };
+    if config::get_first_open_only(config) {
+        return sweep_first_open(config, hosts);
+    }
+    let Some(first) = hosts.first() else {
+        return 0;
+    };
+    // The settings are the same for every host, but get_config needs a single address
+    let mut first_config = config.clone();
+    first_config.insert("ip".to_string(), serde_yaml::Value::String(first.to_string()));
+    let (_, plan) = scan_plan(&first_config).unwrap_or_else(|e| exit_with_error(&e));
+    // One bar counts the ports of all hosts
+    let (pb, events) = scan_progress((hosts.len() * plan.work.len()) as u64);
+    let mut open_ports = 0;
+    if shows_tui(tui) {
+        // The live UI shows one host at a time
+        for ip in hosts {
+            if deadline_exceeded() {
+                break;
             }
+            let target = ip.to_string();
+            let host = Host { target: &target, ip, log_suffix: Some(&target) };
+            open_ports += scan_host(config, &plan, &host, true, None, Some(&pb)).len();
+        }
+    } else {
+        // Hosts are scanned one after another, so each starts when the previous one is reported
+        let mut started = (std::time::Instant::now(), Local::now());
+        let on_host = |ip: &std::net::IpAddr, results: &[ScanResult], stats: ScanStats| {
+            let target = ip.to_string();
+            let host = Host { target: &target, ip: *ip, log_suffix: Some(&target) };
+            let (scan_start, started_at) = started;
+            let scan = HostScan { open_ports: results.to_vec(), stats, scan_start, started_at };
+            // The shared bar is still drawn, so it is hidden while the results are printed
+            open_ports += pb.suspend(|| report_host(config, &plan, &host, scan, None)).len();
+            started = (std::time::Instant::now(), Local::now());
         };
-    pb.finish_with_message(localisator::get("scan_complete"));
-    let ip_str = config.get("ip").and_then(|v| v.as_str()).unwrap_or("");
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    
-    let log_path = "logs";
-    if let Err(e) = std::fs::create_dir_all(log_path) {
-        eprintln!("{}: {}", localisator::get("error_log_dir_create"), e);
-        return;
+        let on_result = |ip: &std::net::IpAddr, res: &ScanResult| announce_open_port(&ip.to_string(), ip, res);
+        if let Err(e) =
+            scan_hosts_parallel(&hosts, &plan.work, plan.signatures.clone(), &plan.options, &pb, on_result, on_host)
+        {
+            exit_with_error(&e);
+        }
     }
-    
-    let log_file_path = std::path::Path::new(log_path).join(format!("scan_{}.log", timestamp));
-    let mut log = match std::fs::File::create(&log_file_path) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("{}: {}", localisator::get("error_log_file_create"), e);
-            return;
+    finish_progress(&pb, events);
+    open_ports
+}
+
+/// Find the first open port of every host of a network target, scanning all hosts with one
+/// shared pool, and print one line per responsive host.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan; its `ip` is the network.
+/// * `hosts` - The hosts of the network.
+///
+/// # Returns
+/// * The number of responsive hosts, each with one open port.
+///
+fn sweep_first_open(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    hosts: Vec<std::net::IpAddr>,
+) -> usize {
+    let Some(first) = hosts.first() else {
+        return 0;
+    };
+    // Ports, threads and probe are the same for every host, but get_config needs a single address
+    let mut host_config = config.clone();
+    host_config.insert("ip".to_string(), serde_yaml::Value::String(first.to_string()));
+    let (_, start_port, end_port, max_threads, _language) =
+        config::get_config(&host_config).unwrap_or_else(|e| exit_with_error(&e));
+    let signatures = load_signatures(config).unwrap_or_else(|e| exit_with_error(&e));
+    let options = scan_options(config, max_threads).unwrap_or_else(|e| exit_with_error(&e));
+    let (work, _) = config::get_scan_work(&host_config, start_port, end_port).unwrap_or_else(|e| exit_with_error(&e));
+    let total = hosts.len();
+    let (pb, events) = scan_progress((total * work.len()) as u64);
+    let found = find_first_open(hosts, &work, Arc::new(signatures), &options, &pb).unwrap_or_else(|e| exit_with_error(&e));
+    finish_progress(&pb, events);
+    if progress_events() {
+        for (host, res) in &found {
+            eprintln!("{}", output::open_event(&host.to_string(), host, res));
         }
+    }
+    if STDOUT_FORMAT.get() == Some(&StdoutFormat::Csv) {
+        for (host, res) in &found {
+            print_csv(host, std::slice::from_ref(res));
+        }
+    } else if reports_on_stdout() {
+        for (host, res) in &found {
+            print_report(&serde_json::json!({ "ip": host, "open_port": res }));
+        }
+    } else if config::get_group_by(config).unwrap_or_else(|e| exit_with_error(&e)) == GroupBy::Service {
+        let mut groups = SERVICE_GROUPS.lock().unwrap();
+        groups.extend(found.iter().map(|(host, res)| (host.to_string(), res.clone())));
+    } else {
+        for (host, res) in &found {
+            println!("{}: {}/{}: {}", host, res.port, res.protocol, res.label());
+        }
+    }
+    status(&format!("{} {}/{}", localisator::get("responsive_hosts"), found.len(), total));
+    found.len()
+}
+
+/// The settings of a scan that are the same for every host of a run. `scan_plan` resolves them
+/// once, so the hosts of a network don't load the signatures or build the options again.
+///
+/// # Fields
+/// * `signatures` - The validated signatures.
+/// * `options` - The scan options.
+/// * `work` - The protocol-tagged ports scanned on every host.
+/// * `port_range` - The scanned ports, as shown in the reports.
+/// * `sort_by` - The order of the reported open ports.
+/// * `group_by` - Whether the open ports of all hosts are listed together by service.
+/// * `expected_services` - The expected service of each port, if `compare_services` is set.
+/// * `latency_filter` - The latency range results must fall in to be shown, if set.
+/// * `output_sink` - Where the reports are stored besides the text log, if anywhere.
+/// * `baseline` - The baseline the reports are compared with, if set.
+///
+struct ScanPlan {
+    signatures: Arc<Vec<Signature>>,
+    options: ScanOptions,
+    work: Vec<(Protocol, u16)>,
+    port_range: String,
+    sort_by: SortBy,
+    group_by: GroupBy,
+    expected_services: Option<ExpectedServices>,
+    latency_filter: Option<LatencyFilter>,
+    output_sink: Option<OutputSink>,
+    baseline: Option<baseline::Baseline>,
+}
+
+/// Resolve the settings of a scan, see `ScanPlan`.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan; its `ip` must be a single host.
+///
+/// # Returns
+/// * `Ok((Arc<IpAddr>, ScanPlan))` - The host of the configuration and the settings.
+/// * `Err(ScanError)` - If a setting is invalid, or the signatures or the baseline can't be loaded.
+///
+fn scan_plan(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+) -> Result<(Arc<std::net::IpAddr>, ScanPlan), ScanError> {
+    let (ip, start_port, end_port, max_threads, _language) = config::get_config(config)?;
+    let signatures = Arc::new(load_signatures(config)?);
+    let options = scan_options(config, max_threads)?;
+    let (work, port_range) = config::get_scan_work(config, start_port, end_port)?;
+    let plan = ScanPlan {
+        signatures,
+        options,
+        work,
+        port_range,
+        sort_by: config::get_sort_by(config)?,
+        group_by: config::get_group_by(config)?,
+        expected_services: config::get_expected_services(config)?,
+        latency_filter: config::get_latency_filter(config)?,
+        output_sink: config::get_output_sink(config)?,
+        baseline: config::get_baseline_path(config).map(|path| baseline::load_baseline(&path)).transpose()?,
+    };
+    Ok((ip, plan))
+}
+
+/// A host scanned with a `ScanPlan`.
+///
+/// # Fields
+/// * `target` - The target as given, shown in the reports and passed to the `on_open` hook.
+/// * `ip` - The address scanned.
+/// * `log_suffix` - Optional suffix for the log file name (e.g., the target IP), to tell logs of
+///   several scans started in the same second apart.
+///
+struct Host<'a> {
+    target: &'a str,
+    ip: std::net::IpAddr,
+    log_suffix: Option<&'a str>,
+}
+
+/// The outcome of scanning a host, see `report_host`.
+///
+/// # Fields
+/// * `open_ports` - The open ports found, in scan order.
+/// * `stats` - The statistics of the scan.
+/// * `scan_start` - When the scan of the host started, for its duration.
+/// * `started_at` - The wall clock time the scan of the host started.
+///
+struct HostScan {
+    open_ports: Vec<ScanResult>,
+    stats: ScanStats,
+    scan_start: std::time::Instant,
+    started_at: chrono::DateTime<Local>,
+}
+
+/// Whether a scan shows the live terminal UI: if it was asked for, stdout is a terminal and
+/// carries neither reports nor progress events.
+fn shows_tui(tui: bool) -> bool {
+    tui && !reports_on_stdout() && !progress_events() && std::io::stdout().is_terminal()
+}
+
+/// Hand an open port to the result sinks, the `on_open` hook and the progress events as soon as
+/// it is found.
+///
+/// # Arguments
+/// * `target` - The target as given.
+/// * `ip` - The address the port was found on.
+/// * `res` - The open port.
+///
+fn announce_open_port(target: &str, ip: &std::net::IpAddr, res: &ScanResult) {
+    result_sinks().on_result(res);
+    if let Some(hook) = OPEN_HOOK.get() {
+        hook.on_open(target, ip, res);
+    }
+    if progress_events() {
+        eprintln!("{}", output::open_event(target, ip, res));
+    }
+}
+
+/// Scan the target of a configuration, print the results and write the log.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `tui` - Whether to show the live terminal UI (if stdout is a terminal).
+/// * `log_suffix` - Optional suffix for the log file name (e.g., the target IP), to tell logs of
+///   several scans started in the same second apart.
+/// * `previous` - The open ports of the previous cycle of a repeated scan. If set, only the
+///   ports that opened or closed since then are listed.
+///
+/// # Returns
+/// * The open ports found, in the configured sort order.
+///
+fn scan_and_report(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    tui: bool,
+    log_suffix: Option<&str>,
+    previous: Option<&[ScanResult]>,
+) -> Vec<ScanResult> {
+    let (ip, plan) = scan_plan(config).unwrap_or_else(|e| exit_with_error(&e));
+    let target = config.get("ip").and_then(|v| v.as_str()).unwrap_or("");
+    let host = Host { target, ip: *ip, log_suffix };
+    scan_host(config, &plan, &host, tui, previous, None)
+}
+
+/// Scan a host with the settings of a plan, print the results and write the log.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `plan` - The settings resolved from `config`.
+/// * `host` - The host to scan.
+/// * `tui` - Whether to show the live terminal UI (if stdout is a terminal).
+/// * `previous` - The open ports of the previous cycle of a repeated scan, see `scan_and_report`.
+/// * `progress` - A progress bar shared by several scans, e.g. the hosts of a network, which the
+///   caller finishes. Without one, the scan shows its own bar.
+///
+/// # Returns
+/// * The open ports found, in the configured sort order.
+///
+fn scan_host(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    plan: &ScanPlan,
+    host: &Host,
+    tui: bool,
+    previous: Option<&[ScanResult]>,
+    progress: Option<&ProgressBar>,
+) -> Vec<ScanResult> {
+    let scan_start = std::time::Instant::now();
+    let started_at = Local::now();
+    // Progress output is only noise when stderr goes to a file or pipe
+    let (pb, events) = match progress {
+        Some(pb) => (pb.clone(), None),
+        None => scan_progress(plan.work.len() as u64),
+    };
+    let (target, addr) = (host.target.to_string(), host.ip);
+    let on_result = move |res: &ScanResult| announce_open_port(&target, &addr, res);
+    let ip = Arc::new(host.ip);
+    // The TUI needs a terminal; otherwise fall back to the normal progress bar output
+    let scan_result = if shows_tui(tui) {
+        tui::scan_with_tui(host.target, ip, plan.work.clone(), plan.signatures.clone(), &plan.options, &pb, on_result)
+    } else {
+        scan_work_parallel(ip, plan.work.iter().copied(), plan.signatures.clone(), &plan.options, &pb, on_result)
+    };
+    let (open_ports, stats) = match scan_result {
+        Ok(res) => res,
+        Err(e) => exit_with_error(&e),
     };
+    if progress.is_none() {
+        pb.finish_with_message(localisator::get("scan_complete"));
+    }
+    if let Some(events) = events {
+        events.finish();
+    }
+    let scan = HostScan { open_ports, stats, scan_start, started_at };
+    match progress {
+        // A shared bar is still drawn, so it is hidden while the results are printed
+        Some(pb) => pb.suspend(|| report_host(config, plan, host, scan, previous)),
+        None => report_host(config, plan, host, scan, previous),
+    }
+}
+
+/// Print the results of a scanned host, store its report and write the log.
+///
+/// # Arguments
+/// * `config` - The configuration of the scan.
+/// * `plan` - The settings resolved from `config`.
+/// * `host` - The scanned host.
+/// * `scan` - The outcome of the scan.
+/// * `previous` - The open ports of the previous cycle of a repeated scan. If set, only the
+///   ports that opened or closed since then are listed.
+///
+/// # Returns
+/// * The open ports found, in the configured sort order.
+///
+fn report_host(
+    config: &std::collections::HashMap<String, serde_yaml::Value>,
+    plan: &ScanPlan,
+    host: &Host,
+    scan: HostScan,
+    previous: Option<&[ScanResult]>,
+) -> Vec<ScanResult> {
+    let HostScan { mut open_ports, stats, scan_start, started_at } = scan;
+    let (ip_str, ip, log_suffix) = (host.target, host.ip, host.log_suffix);
+    let (options, port_range, group_by) = (&plan.options, &plan.port_range, plan.group_by);
+    let (expected_services, output_sink, baseline) = (&plan.expected_services, &plan.output_sink, &plan.baseline);
+    output::sort_results(&mut open_ports, plan.sort_by);
+    let latency_filtered = plan.latency_filter.map(|filter| output::filter_by_latency(&mut open_ports, &filter));
+    // With a baseline the console only shows deviations, so e.g. cron only mails when something changed
+    let alerts_only = baseline.is_some();
+    // JSON on stdout replaces the text results entirely
+    let json_stdout = reports_on_stdout();
+    macro_rules! console {
+        ($($arg:tt)*) => {
+            if !alerts_only && !json_stdout {
+                print!($($arg)*);
+            }
+        };
+    }
     let scan_duration = scan_start.elapsed();
     let scan_duration_str = format_duration(scan_duration);
-    let header = format!(
-        "{} {}\n{} {}-{}\n{} {}\n{} {}\n",
+    let mut log_text = format!(
+        "{} {}\n{} {}\n{} {}\n{} {}\n",
         localisator::get("scan_started"),
         Local::now().format("%Y-%m-%d %H:%M:%S"),
         localisator::get("port_range"),
-        start_port,
-        end_port,
+        port_range,
         localisator::get("duration"),
         scan_duration_str,
         localisator::get("target"),
         ip_str
     );
-    let _ = log.write_all(header.as_bytes());
     let open_ports_count = open_ports.len();
-    if open_ports_count == 0 {
+    let mismatch = |res: &ScanResult| {
+        expected_services
+            .as_ref()
+            .and_then(|expected| output::service_mismatch(res, expected))
+    };
+    let verbose = config::get_verbose(config);
+    let port_label = |res: &ScanResult| {
+        let mut label = match mismatch(res) {
+            Some(wanted) => format!("{} [{} {}]", res.label(), localisator::get("unexpected_service"), wanted),
+            None => res.label(),
+        };
+        if verbose {
+            match &res.signature_file {
+                Some(file) => label.push_str(&format!(
+                    " ({} {}, {} {})",
+                    localisator::get("identified_by"),
+                    res.identified_by,
+                    localisator::get("signature_from"),
+                    file
+                )),
+                None => label.push_str(&format!(" ({} {})", localisator::get("identified_by"), res.identified_by)),
+            }
+            if let Some(cert) = &res.tls {
+                label.push_str(&format!("\n    {}", output::format_certificate(cert)));
+            }
+        }
+        label
+    };
+    if let Some(previous) = previous {
+        // Repeated scans only report what changed since the previous cycle
+        let (opened, closed) = output::diff_results(previous, &open_ports);
+        let mut changes = String::new();
+        for res in &opened {
+            changes.push_str(&format!("+ {}/{}: {}\n", res.port, res.protocol, port_label(res)));
+        }
+        for res in &closed {
+            changes.push_str(&format!("- {}/{}: {}\n", res.port, res.protocol, res.label()));
+        }
+        if changes.is_empty() {
+            changes = format!("{}\n", localisator::get("repeat_no_changes"));
+        }
+        console!("{}", changes);
+        log_text.push_str(&changes);
+        console!("{}\n", localisator::get_plural("open_ports_found", open_ports_count as u64));
+    } else if open_ports_count == 0 {
         let msg = format!("{} {}\n", localisator::get("no_open_ports"), ip_str);
-        print!("{}", msg);
-        let _ = log.write_all(msg.as_bytes());
-        print!(
-            "{} {}-{}\n{} {}\n{} 0\n",
+        console!("{}", msg);
+        log_text.push_str(&msg);
+        console!(
+            "{} {}\n{} {}\n{}\n",
             localisator::get("scanned_ports"),
-            start_port,
-            end_port,
+            port_range,
             localisator::get("duration"),
             scan_duration_str,
-            localisator::get("open_ports_count"),
+            localisator::get_plural("open_ports_found", 0),
         );
     } else {
+        // Grouped by service, the ports of all hosts are listed together once the run is done
+        let grouped = group_by == GroupBy::Service && !alerts_only && !json_stdout;
+        if grouped {
+            let mut groups = SERVICE_GROUPS.lock().unwrap();
+            groups.extend(open_ports.iter().map(|res| (ip_str.to_string(), res.clone())));
+        }
         let ports_header = format!("{} {}:\n", localisator::get("open_ports"), ip_str);
-        print!("{}", ports_header);
-        let _ = log.write_all(ports_header.as_bytes());
-        for (port, service) in &open_ports {
-            let line = match service {
-                Some(name) => format!("{}: {}\n", port, name),
-                None => format!("{}: {}\n", port, localisator::get("open")),
-            };
-            print!("{}", line);
-            let _ = log.write_all(line.as_bytes());
+        console!("{}", ports_header);
+        log_text.push_str(&ports_header);
+        let groups = output::group_by_protocol(&open_ports);
+        // A scan of both protocols lists TCP and UDP results in separate sections
+        let segmented = groups.len() > 1;
+        for (protocol, group) in groups {
+            if segmented {
+                let header = format!("{} ({}):\n", protocol.to_string().to_uppercase(), group.len());
+                if !grouped {
+                    console!("{}", header);
+                }
+                log_text.push_str(&header);
+            }
+            for res in group {
+                let line = format!("{}/{}: {}\n", res.port, res.protocol, port_label(res));
+                if !grouped {
+                    console!("{}", line);
+                }
+                log_text.push_str(&line);
+            }
         }
-        print!(
-            "{} {}-{}\n{} {}\n{} {}\n",
+        console!(
+            "{} {}\n{} {}\n{}\n",
             localisator::get("scanned_ports"),
-            start_port,
-            end_port,
+            port_range,
             localisator::get("duration"),
             scan_duration_str,
-            localisator::get("open_ports_count"),
-            open_ports_count
+            localisator::get_plural("open_ports_found", open_ports_count as u64),
+        );
+    }
+    if expected_services.is_some() {
+        let mismatches = open_ports.iter().filter(|res| mismatch(res).is_some()).count();
+        SERVICE_MISMATCHES.fetch_add(mismatches, Ordering::SeqCst);
+        let line = format!("{} {}\n", localisator::get("service_mismatches"), mismatches);
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if let Some(hidden) = latency_filtered {
+        let line = format!("{} {}\n", localisator::get("latency_filtered"), hidden);
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if options.retries > 0 {
+        let mut retry_line = format!("{} {}", localisator::get("retries_used"), stats.retries_used);
+        if let Some(cap) = stats.retry_cap {
+            retry_line.push_str(&format!("/{}", cap));
+        }
+        if stats.retry_cap_reached {
+            retry_line.push_str(&format!(" ({})", localisator::get("retry_cap_reached")));
+        }
+        retry_line.push('\n');
+        console!("{}", retry_line);
+        log_text.push_str(&retry_line);
+    }
+    if options.auto_tune {
+        let line = format!("{} {}\n", localisator::get("auto_tuned_threads"), stats.threads);
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if verbose {
+        if let (identified, Some(rate)) = output::identification_rate(&open_ports) {
+            let line = format!(
+                "{} {}/{} ({:.0}%)\n",
+                localisator::get("identification_rate"),
+                identified,
+                open_ports_count,
+                rate * 100.0
+            );
+            console!("{}", line);
+            log_text.push_str(&line);
+        }
+    }
+    let port_states = format!(
+        "{} {}, {} {}\n",
+        localisator::get("closed_ports"),
+        stats.closed,
+        localisator::get("filtered_ports"),
+        stats.filtered
+    );
+    console!("{}", port_states);
+    log_text.push_str(&port_states);
+    let bytes_line = format!("{} {}\n", localisator::get("bytes_received"), stats.bytes_received);
+    console!("{}", bytes_line);
+    log_text.push_str(&bytes_line);
+    if stats.cached_services > 0 {
+        let line = format!("{} {}\n", localisator::get("cached_services"), stats.cached_services);
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if verbose {
+        let failures = &stats.failures;
+        let line = format!(
+            "{} {} {}, {} {}, {} {}, {} {}, {} {}\n",
+            localisator::get("failure_reasons"),
+            localisator::get("failure_refused"),
+            failures.refused,
+            localisator::get("failure_timed_out"),
+            failures.timed_out,
+            localisator::get("failure_host_unreachable"),
+            failures.host_unreachable,
+            localisator::get("failure_network_unreachable"),
+            failures.network_unreachable,
+            localisator::get("failure_other"),
+            failures.other
+        );
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if stats.source_ports_exhausted > 0 {
+        let line = format!("{} {}\n", localisator::get("source_ports_exhausted"), stats.source_ports_exhausted);
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if stats.cancelled {
+        let line = format!("{}\n", localisator::get("scan_interrupted"));
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if stats.network_failure {
+        let line = format!("{}\n", localisator::get("network_failure_abort"));
+        console!("{}", line);
+        log_text.push_str(&line);
+    }
+    if stats.timed_out {
+        let line = format!("{} {}\n", localisator::get("target_timed_out"), stats.ports_timed_out);
+        console!("{}", line);
+        log_text.push_str(&line);
+        TIMED_OUT_HOSTS.lock().unwrap().push(ip_str.to_string());
+    }
+    if config::get_benchmark(config) {
+        let format_ms = |ms: Option<f64>| ms.map_or_else(|| "-".to_string(), |ms| format!("{:.2} ms", ms));
+        let lines = format!(
+            "{} {}\n{} {} ({} {}, {} {})\n{} {}\n",
+            localisator::get("benchmark_avg_connect"),
+            format_ms(stats.avg_connect_ms),
+            localisator::get("benchmark_http_probes"),
+            stats.http_probes,
+            stats.http_reused,
+            localisator::get("benchmark_http_reused"),
+            stats.http_fresh,
+            localisator::get("benchmark_http_fresh"),
+            localisator::get("benchmark_avg_http_probe"),
+            format_ms(stats.avg_http_probe_ms),
         );
+        console!("{}", lines);
+        log_text.push_str(&lines);
+    }
+    if let Some(histogram) = &stats.latency_histogram {
+        let lines = output::format_latency_histogram(histogram);
+        console!("{}", lines);
+        log_text.push_str(&lines);
+    }
+    let sinks = result_sinks();
+    let report = (output_sink.is_some() || baseline.is_some() || json_stdout || !sinks.is_empty()).then(|| ScanReport {
+        target: ip_str.to_string(),
+        ip,
+        port_range: port_range.clone(),
+        duration_ms: scan_duration.as_millis() as u64,
+        identification_rate: output::identification_rate(&open_ports).1,
+        open_ports: open_ports.clone(),
+        stats,
+        conflicts: Vec::new(),
+    });
+    if let Some(report) = &report {
+        sinks.finish(report);
+    }
+    if let (Some(output_sink), Some(report)) = (&output_sink, &report) {
+        match store_report(output_sink, report, started_at) {
+            Ok(line) => {
+                console!("{}", line);
+                log_text.push_str(&line);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    if let (Some(baseline), Some(report)) = (&baseline, &report) {
+        let deviations = baseline::compare(baseline, report);
+        DEVIATIONS.fetch_add(deviations.len(), Ordering::SeqCst);
+        for deviation in &deviations {
+            let line = format!("{}\n", baseline::format_deviation(&report.ip, deviation));
+            if json_stdout {
+                eprint!("{}", line);
+            } else {
+                print!("{}", line);
+            }
+            log_text.push_str(&line);
+        }
+    }
+    if let Some(report) = report.as_ref().filter(|_| json_stdout) {
+        match STDOUT_FORMAT.get() {
+            Some(StdoutFormat::Csv) => print_csv(&report.ip, &report.open_ports),
+            _ => print_report(report),
+        }
+    }
+
+    if let Some(append_path) = config.get("append_log").and_then(|v| v.as_str()) {
+        if let Err(e) = output::append_log_section(std::path::Path::new(append_path), &log_text) {
+            eprintln!("{}: {}", localisator::get("error_log_file_append"), e);
+        }
+        return open_ports;
+    }
+    if json_stdout {
+        return open_ports;
+    }
+
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let log_path = "logs";
+    if let Err(e) = std::fs::create_dir_all(log_path) {
+        eprintln!("{}: {}", localisator::get("error_log_dir_create"), e);
+        return open_ports;
     }
+
+    let log_file_path = std::path::Path::new(log_path).join(match log_suffix {
+        Some(suffix) => format!("scan_{}_{}.log", timestamp, suffix.replace([':', '/'], "_")),
+        None => format!("scan_{}.log", timestamp),
+    });
+    let mut log = match std::fs::File::create(&log_file_path) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("{}: {}", localisator::get("error_log_file_create"), e);
+            return open_ports;
+        }
+    };
+    let _ = log.write_all(log_text.as_bytes());
+    open_ports
 }
@@ -0,0 +1,144 @@
+use crate::error::ScanError;
+use crate::localisator;
+use crate::scanner::{format_duration, PortState, ScanReport};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+/// Escape text for Markdown, e.g. a service name in a table cell.
+///
+/// Characters with a meaning in Markdown are escaped with a backslash, so a `|` can't end a
+/// table cell and `*` or `<` don't change the formatting. Line breaks become spaces, since a
+/// table row can't span lines.
+///
+/// # Arguments
+/// * `value` - The raw text.
+///
+/// # Returns
+/// * The escaped text.
+///
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '|' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format scan reports as a Markdown document, e.g. to paste into a ticket or wiki.
+///
+/// The document starts with a summary table of all hosts (open ports, identification rate,
+/// duration), followed by one section per host with a table of its open ports: port, protocol,
+/// state, service (with its version, if captured) and latency. A host without open ports gets a sentence saying so instead of an
+/// empty table, and a host cut short by `per_target_timeout` a note on the ports not scanned.
+///
+/// # Arguments
+/// * `reports` - The reports to include, one per host.
+///
+/// # Returns
+/// * The document, ending with a newline.
+///
+pub fn format_report(reports: &[&ScanReport]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", localisator::get("markdown_title"));
+    let _ = writeln!(out, "## {}\n", localisator::get("markdown_summary"));
+    let _ = writeln!(
+        out,
+        "| {} | {} | {} | {} | {} | {} |",
+        localisator::get("markdown_host"),
+        localisator::get("markdown_address"),
+        localisator::get("markdown_scanned_ports"),
+        localisator::get("markdown_open_ports"),
+        localisator::get("markdown_identified"),
+        localisator::get("markdown_duration")
+    );
+    out.push_str("|---|---|---|---:|---:|---:|\n");
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            escape(&report.target),
+            report.ip,
+            escape(&report.port_range),
+            report.open_ports.len(),
+            report.identification_rate.map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0)),
+            format_duration(Duration::from_millis(report.duration_ms))
+        );
+    }
+    for report in reports {
+        out.push('\n');
+        if report.target == report.ip.to_string() {
+            let _ = writeln!(out, "## {}\n", report.ip);
+        } else {
+            let _ = writeln!(out, "## {} ({})\n", escape(&report.target), report.ip);
+        }
+        if report.stats.timed_out {
+            let _ = writeln!(out, "{} {}\n", localisator::get("markdown_timed_out"), report.stats.ports_timed_out);
+        }
+        if report.open_ports.is_empty() {
+            let _ = writeln!(out, "{}", localisator::get("markdown_no_open_ports"));
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            localisator::get("markdown_port"),
+            localisator::get("markdown_protocol"),
+            localisator::get("markdown_state"),
+            localisator::get("markdown_service"),
+            localisator::get("markdown_latency")
+        );
+        out.push_str("|---:|---|---|---|---:|\n");
+        for res in &report.open_ports {
+            let state = match res.state {
+                PortState::Open => localisator::get("open"),
+                PortState::OpenFiltered => localisator::get("open_filtered"),
+            };
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                res.port,
+                res.protocol,
+                escape(&state),
+                res.service.as_ref().map_or("-".to_string(), |_| escape(&res.label())),
+                res.latency.map_or("-".to_string(), |latency| format!("{:.1} ms", latency.as_secs_f64() * 1000.0))
+            );
+        }
+    }
+    out
+}
+
+/// Write scan reports to a Markdown file, replacing its previous content.
+///
+/// The document is written to a temporary file next to `path` first and then renamed, so a
+/// reader never sees a half-written file.
+///
+/// # Arguments
+/// * `path` - The file (e.g., `scan.md`).
+/// * `reports` - The reports to include, see `format_report`.
+///
+/// # Returns
+/// * `Ok(())` - If the file was written.
+/// * `Err(ScanError)` - If the file can't be written.
+///
+pub fn write_report(path: &Path, reports: &[&ScanReport]) -> Result<(), ScanError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, format_report(reports))
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            ScanError::Io(std::io::Error::other(format!(
+                "{}: {:?}: {}",
+                localisator::get("error_markdown_write"),
+                path,
+                e
+            )))
+        })
+}
@@ -0,0 +1,63 @@
+use crate::error::ScanError;
+use crate::scanner::ScanReport;
+use serde::Serialize;
+use std::path::Path;
+
+/// Encode a value as MessagePack, with struct fields as named map entries and addresses as
+/// strings like in the JSON reports, so consumers can read it without knowing the field order.
+///
+/// # Arguments
+/// * `value` - The value to encode, e.g. a `ScanReport`.
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The encoded value.
+/// * `Err(ScanError)` - If the value can't be encoded.
+///
+#[cfg(feature = "msgpack")]
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ScanError> {
+    let mut bytes = Vec::new();
+    let mut serializer = rmp_serde::Serializer::new(&mut bytes).with_struct_map().with_human_readable();
+    value
+        .serialize(&mut serializer)
+        .map_err(|e| ScanError::Io(std::io::Error::other(e.to_string())))?;
+    Ok(bytes)
+}
+
+/// Fallback when Port Explorer is built without the `msgpack` feature.
+///
+/// # Returns
+/// * `Err(ScanError)` - Always, as MessagePack output is not available.
+///
+#[cfg(not(feature = "msgpack"))]
+pub fn encode<T: Serialize + ?Sized>(_value: &T) -> Result<Vec<u8>, ScanError> {
+    Err(ScanError::Config(crate::localisator::get("error_msgpack_disabled")))
+}
+
+/// Write scan reports to a MessagePack file as one array, replacing its previous content.
+///
+/// The reports are written to a temporary file next to `path` first and then renamed, so a
+/// reader never sees a half-written file.
+///
+/// # Arguments
+/// * `path` - The file (e.g., `scan.msgpack`).
+/// * `reports` - The reports to write, one per host.
+///
+/// # Returns
+/// * `Ok(())` - If the file was written.
+/// * `Err(ScanError)` - If the reports can't be encoded or the file can't be written.
+///
+pub fn write_reports(path: &Path, reports: &[&ScanReport]) -> Result<(), ScanError> {
+    let bytes = encode(reports)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, bytes)
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            ScanError::Io(std::io::Error::other(format!(
+                "{}: {:?}: {}",
+                crate::localisator::get("error_msgpack_write"),
+                path,
+                e
+            )))
+        })
+}
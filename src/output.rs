@@ -0,0 +1,768 @@
+use crate::error::ScanError;
+use crate::scanner::{LatencyHistogram, Protocol, ScanResult};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Line written before every scan section in an appended log file.
+pub const SECTION_DELIMITER: &str =
+    "============================================================";
+
+/// Template of the scan progress bar.
+pub const PROGRESS_TEMPLATE: &str =
+    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%)";
+
+/// The service expected on each (port, protocol), see `config::get_expected_services`.
+pub type ExpectedServices = HashMap<(u16, Protocol), String>;
+
+/// Find the service a port was expected to run, if it runs something else.
+///
+/// Only identified services are compared; an unidentified port never counts as a mismatch.
+/// Service names are compared case-insensitively.
+///
+/// # Arguments
+/// * `result` - The open port.
+/// * `expected` - The expected service per (port, protocol).
+///
+/// # Returns
+/// * `Some(&str)` - The expected service, if the port runs a different one.
+/// * `None` - If the service matches, is unidentified or nothing is expected for the port.
+///
+pub fn service_mismatch<'a>(
+    result: &ScanResult,
+    expected: &'a ExpectedServices,
+) -> Option<&'a str> {
+    let wanted = expected.get(&(result.port, result.protocol))?;
+    let found = result.service.as_deref()?;
+    (!found.eq_ignore_ascii_case(wanted)).then_some(wanted.as_str())
+}
+
+/// Split results into one group per protocol, keeping their order within each group.
+///
+/// # Arguments
+/// * `results` - The results, e.g. after `sort_results`.
+///
+/// # Returns
+/// * The protocols that have results, TCP first, each with its results.
+///
+pub fn group_by_protocol(results: &[ScanResult]) -> Vec<(Protocol, Vec<&ScanResult>)> {
+    [Protocol::Tcp, Protocol::Udp]
+        .into_iter()
+        .map(|protocol| (protocol, results.iter().filter(|r| r.protocol == protocol).collect::<Vec<_>>()))
+        .filter(|(_, group)| !group.is_empty())
+        .collect()
+}
+
+/// An open port with the host it was found on.
+pub type HostResult = (String, ScanResult);
+
+/// The endpoints offering a service, `None` for the unidentified ones, see `group_by_service`.
+pub type ServiceGroup<'a> = (Option<&'a str>, Vec<&'a HostResult>);
+
+/// Split the open ports of one or more hosts into one group per identified service.
+///
+/// Services are compared case-insensitively and ordered by name, with the unidentified ports
+/// last. Within a group the endpoints keep their order.
+///
+/// # Arguments
+/// * `results` - The open ports, each with the host it was found on.
+///
+/// # Returns
+/// * The services that have results (`None` for unidentified ports), each with its endpoints.
+///
+pub fn group_by_service(results: &[HostResult]) -> Vec<ServiceGroup<'_>> {
+    let mut groups: Vec<ServiceGroup> = Vec::new();
+    for entry in results {
+        let service = entry.1.service.as_deref();
+        let same = |name: &Option<&str>| match (name, service) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        match groups.iter_mut().find(|(name, _)| same(name)) {
+            Some((_, group)) => group.push(entry),
+            None => groups.push((service, vec![entry])),
+        }
+    }
+    groups.sort_by_cached_key(|(name, _)| (name.is_none(), name.map(str::to_lowercase)));
+    groups
+}
+
+/// Format the open ports of one or more hosts as a tree grouped by service, see `group_by_service`.
+///
+/// ```text
+/// ssh (2)
+/// ├─ 10.0.0.1:22/tcp
+/// └─ 10.0.0.2:2222/tcp
+/// ```
+///
+/// # Arguments
+/// * `results` - The open ports, each with the host it was found on.
+///
+/// # Returns
+/// * The tree after a localised header line, each line ending with a newline. Unidentified
+///   ports are listed under the localised "unknown" group.
+///
+pub fn format_service_groups(results: &[HostResult]) -> String {
+    let mut text = format!("{}\n", crate::localisator::get("services_header"));
+    for (service, group) in group_by_service(results) {
+        let name = service.map_or_else(|| crate::localisator::get("unknown_service_group"), str::to_string);
+        text.push_str(&format!("{} ({})\n", name, group.len()));
+        for (i, (host, res)) in group.iter().enumerate() {
+            let branch = if i + 1 == group.len() { "└─" } else { "├─" };
+            // IPv6 hosts need brackets to tell the address from the port
+            let host = if host.contains(':') { format!("[{}]", host) } else { host.clone() };
+            text.push_str(&format!("{} {}:{}/{}\n", branch, host, res.port, res.protocol));
+        }
+    }
+    text
+}
+
+/// Compare the open ports of two scans of the same target.
+///
+/// A port counts as the same if port number and protocol match; service changes are ignored.
+///
+/// # Arguments
+/// * `previous` - The open ports of the earlier scan.
+/// * `current` - The open ports of the later scan.
+///
+/// # Returns
+/// * `(opened, closed)` - The ports open only in `current`, and the ports open only in `previous`.
+///
+pub fn diff_results(previous: &[ScanResult], current: &[ScanResult]) -> (Vec<ScanResult>, Vec<ScanResult>) {
+    let contains = |results: &[ScanResult], res: &ScanResult| {
+        results.iter().any(|r| r.port == res.port && r.protocol == res.protocol)
+    };
+    let opened = current.iter().filter(|res| !contains(previous, res)).cloned().collect();
+    let closed = previous.iter().filter(|res| !contains(current, res)).cloned().collect();
+    (opened, closed)
+}
+
+/// Count how many open ports had their service identified.
+///
+/// # Arguments
+/// * `results` - The open ports of a scan.
+///
+/// # Returns
+/// * `(identified, rate)` - The number of identified ports, and their fraction of all open ports
+///   (`None` if no port is open).
+///
+pub fn identification_rate(results: &[ScanResult]) -> (usize, Option<f64>) {
+    let identified = results.iter().filter(|r| r.service.is_some()).count();
+    let rate = (!results.is_empty()).then(|| identified as f64 / results.len() as f64);
+    (identified, rate)
+}
+
+/// Make untrusted text, e.g. from a scanned host or a signature bundle, safe to print on a terminal or write to a log.
+///
+/// Terminal escape sequences (CSI such as `ESC [ 2 J`, OSC such as window title changes, and
+/// other `ESC` sequences) are removed. Any other control character, including 8-bit C1 controls,
+/// `DEL`, line breaks and tabs, as well as the Unicode bidirectional overrides that can reorder
+/// what is shown, are replaced by a visible escape such as `\x07` or `\u{202e}`.
+///
+/// # Arguments
+/// * `text` - The untrusted text, e.g. a service name or banner.
+///
+/// # Returns
+/// * The text without escape sequences and with every remaining control character escaped.
+///
+pub fn sanitize_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters and intermediates up to a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, DCS, SOS, PM, APC: a string ended by BEL or ST (ESC \)
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Any other escape sequence is ESC and one more character
+                _ => {}
+            },
+            '\u{9b}' => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            c if (c as u32) < 0x80 && c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c if c.is_control() || matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}') => {
+                out.push_str(&format!("\\u{{{:x}}}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Create the scan progress bar without ever failing the scan.
+///
+/// If the template doesn't compile, a warning is printed and the default bar style is used.
+/// An invisible bar still counts progress, so callers (e.g. the TUI) can read it.
+///
+/// # Arguments
+/// * `len` - The number of work units the bar tracks.
+/// * `template` - The indicatif template of the bar (e.g., `PROGRESS_TEMPLATE`).
+/// * `visible` - Whether the bar is drawn to stderr; pass `false` when stderr isn't a terminal.
+///
+/// # Returns
+/// * The progress bar.
+///
+pub fn progress_bar(len: u64, template: &str, visible: bool) -> ProgressBar {
+    let target = if visible {
+        ProgressDrawTarget::stderr()
+    } else {
+        ProgressDrawTarget::hidden()
+    };
+    let pb = ProgressBar::with_draw_target(Some(len), target);
+    match ProgressStyle::default_bar().template(template) {
+        Ok(style) => pb.set_style(style.progress_chars("=>-")),
+        Err(e) => eprintln!("{}: {}", crate::localisator::get("warning_progress_bar_template"), e),
+    }
+    pb
+}
+
+/// How scan progress is shown on stderr.
+///
+/// * `Bar` - The indicatif progress bar (the default).
+/// * `Json` - JSON Lines events instead of the bar, e.g. for a GUI wrapping the scan: one
+///   `{"event":"progress","done":..,"total":..}` line whenever progress was made and one
+///   `{"event":"open",..}` line per open port.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressFormat {
+    #[default]
+    Bar,
+    Json,
+}
+
+/// FromStr implementation for ProgressFormat, accepting "bar" and "json" (case-insensitive).
+///
+impl FromStr for ProgressFormat {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bar" => Ok(ProgressFormat::Bar),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_progress_format"),
+                s
+            ))),
+        }
+    }
+}
+
+/// How often `ProgressEvents` checks a progress bar for new progress.
+pub const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Format a progress event, see `ProgressFormat::Json`.
+///
+/// # Arguments
+/// * `done` - The work units finished so far.
+/// * `total` - The work units of the scan.
+///
+/// # Returns
+/// * The event as a single JSON line, without the newline.
+///
+pub fn progress_event(done: u64, total: u64) -> String {
+    #[derive(Serialize)]
+    struct Progress {
+        event: &'static str,
+        done: u64,
+        total: u64,
+    }
+    serde_json::to_string(&Progress { event: "progress", done, total }).unwrap_or_default()
+}
+
+/// Format the event of an open port, see `ProgressFormat::Json`.
+///
+/// # Arguments
+/// * `host` - The target as given (e.g., a hostname).
+/// * `ip` - The address that was scanned.
+/// * `r` - The open port.
+///
+/// # Returns
+/// * The event as a single JSON line, without the newline.
+///
+pub fn open_event(host: &str, ip: &std::net::IpAddr, r: &ScanResult) -> String {
+    #[derive(Serialize)]
+    struct Open<'a> {
+        event: &'static str,
+        host: &'a str,
+        ip: &'a std::net::IpAddr,
+        port: u16,
+        protocol: Protocol,
+        state: crate::scanner::PortState,
+        service: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        version: Option<&'a str>,
+    }
+    serde_json::to_string(&Open {
+        event: "open",
+        host,
+        ip,
+        port: r.port,
+        protocol: r.protocol,
+        state: r.state,
+        service: r.service.as_deref(),
+        version: r.version.as_deref(),
+    })
+    .unwrap_or_default()
+}
+
+/// Progress events of a scan, printed on stderr while it runs, see `ProgressFormat::Json`.
+///
+/// A thread polls the scan's progress bar every `PROGRESS_EVENT_INTERVAL` and prints an event
+/// whenever the position changed, so the bar itself can stay hidden. `finish` prints the final
+/// position.
+///
+pub struct ProgressEvents {
+    stop: std::sync::mpsc::Sender<()>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl ProgressEvents {
+    /// Start printing the progress of `pb`.
+    pub fn start(pb: &ProgressBar) -> Self {
+        let (stop, stopped) = std::sync::mpsc::channel();
+        let pb = pb.clone();
+        let thread = std::thread::spawn(move || {
+            let mut last = None;
+            loop {
+                let done = pb.position();
+                if last != Some(done) {
+                    eprintln!("{}", progress_event(done, pb.length().unwrap_or(0)));
+                    last = Some(done);
+                }
+                match stopped.recv_timeout(PROGRESS_EVENT_INTERVAL) {
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+            }
+            let done = pb.position();
+            if last != Some(done) {
+                eprintln!("{}", progress_event(done, pb.length().unwrap_or(0)));
+            }
+        });
+        ProgressEvents { stop, thread }
+    }
+
+    /// Stop printing events, after printing the final position if it wasn't printed yet.
+    pub fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+/// Append a delimited scan section to a shared log file.
+///
+/// The file is created if missing and opened in append mode. An exclusive advisory lock is
+/// held while the section is written in a single call, so concurrent invocations appending
+/// to the same file don't interleave their output.
+///
+/// # Arguments
+/// * `path` - The path of the log file to append to.
+/// * `content` - The log text of a single scan.
+///
+/// # Returns
+/// * `Ok(())` - If the section was written.
+/// * `Err(std::io::Error)` - If the file could not be opened, locked or written.
+///
+pub fn append_log_section(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.lock()?;
+    let mut section = format!("{}\n{}", SECTION_DELIMITER, content);
+    if !section.ends_with('\n') {
+        section.push('\n');
+    }
+    let result = file.write_all(section.as_bytes()).and_then(|_| file.flush());
+    file.unlock()?;
+    result
+}
+
+/// Order in which scan results are shown and written.
+///
+/// * `Port` - By port, then protocol (the scanner's own order).
+/// * `Service` - By identified service name (case-insensitive), unidentified ports last.
+/// * `Latency` - Fastest answer first, ports without a measured latency last.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortBy {
+    #[default]
+    Port,
+    Service,
+    Latency,
+}
+
+/// FromStr implementation for SortBy, accepting "port", "service" and "latency" (case-insensitive).
+///
+impl FromStr for SortBy {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "port" => Ok(SortBy::Port),
+            "service" => Ok(SortBy::Service),
+            "latency" => Ok(SortBy::Latency),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_sort_by"),
+                s
+            ))),
+        }
+    }
+}
+
+/// How open ports are grouped on the console.
+///
+/// * `Host` - Per scanned host, as found (the default).
+/// * `Service` - Per identified service across all hosts of the run, unidentified ports last.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupBy {
+    #[default]
+    Host,
+    Service,
+}
+
+/// FromStr implementation for GroupBy, accepting "host" and "service" (case-insensitive).
+///
+impl FromStr for GroupBy {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "host" => Ok(GroupBy::Host),
+            "service" => Ok(GroupBy::Service),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_group_by"),
+                s
+            ))),
+        }
+    }
+}
+
+/// What a scan prints on stdout.
+///
+/// * `Text` - The human-readable results (the default).
+/// * `Json` - One JSON report per scanned host and line (JSON Lines), e.g. for piping into `jq`.
+///   Status lines go to stderr instead and the text log file is not written.
+/// * `MsgPack` - Like `Json`, but every report is a MessagePack value (`msgpack` feature).
+/// * `Csv` - Like `Json`, but one `ip,port,service,latency_ms,version` row per open port after a single
+///   header line (see `write_csv`), e.g. for spreadsheets.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StdoutFormat {
+    #[default]
+    Text,
+    Json,
+    MsgPack,
+    Csv,
+}
+
+/// FromStr implementation for StdoutFormat, accepting "text", "json", "msgpack" and "csv" (case-insensitive).
+///
+impl FromStr for StdoutFormat {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(StdoutFormat::Text),
+            "json" => Ok(StdoutFormat::Json),
+            "msgpack" => Ok(StdoutFormat::MsgPack),
+            "csv" => Ok(StdoutFormat::Csv),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_stdout_format"),
+                s
+            ))),
+        }
+    }
+}
+
+/// Header line of the CSV output, see `write_csv`.
+pub const CSV_HEADER: &str = "ip,port,service,latency_ms,version";
+
+/// Quote a CSV field if it contains a comma, quote or line break, doubling any quotes (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write the header line of the CSV output, see `write_csv`.
+pub fn write_csv_header(mut writer: impl Write) -> std::io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)
+}
+
+/// Write one CSV row per open port, without the header, e.g. to append the ports of another host.
+///
+/// # Arguments
+/// * `ip` - The scanned address.
+/// * `results` - The open ports.
+/// * `writer` - Where the rows are written.
+///
+/// # Returns
+/// * `Ok(())` - If the rows were written.
+/// * `Err(std::io::Error)` - If the writer failed.
+///
+pub fn write_csv_rows(ip: &std::net::IpAddr, results: &[ScanResult], mut writer: impl Write) -> std::io::Result<()> {
+    for r in results {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            ip,
+            r.port,
+            csv_field(r.service.as_deref().unwrap_or("")),
+            r.latency.map_or(String::new(), |latency| format!("{:.3}", latency.as_secs_f64() * 1000.0)),
+            csv_field(r.version.as_deref().unwrap_or(""))
+        )?;
+    }
+    Ok(())
+}
+
+/// Write open ports as CSV: the `CSV_HEADER` line, then one row per port.
+///
+/// An unidentified service, a version that wasn't captured and a missing latency are empty
+/// fields; services with commas or quotes are quoted. Without open ports only the header is written.
+///
+/// # Arguments
+/// * `ip` - The scanned address.
+/// * `results` - The open ports.
+/// * `writer` - Where the CSV is written (e.g., stdout or a `Vec<u8>`).
+///
+/// # Returns
+/// * `Ok(())` - If the CSV was written.
+/// * `Err(std::io::Error)` - If the writer failed.
+///
+pub fn write_csv(ip: &std::net::IpAddr, results: &[ScanResult], mut writer: impl Write) -> std::io::Result<()> {
+    write_csv_header(&mut writer)?;
+    write_csv_rows(ip, results, writer)
+}
+
+/// Where scan results are written besides the console.
+///
+/// Variants:
+/// * `Text` - A text log file (the default).
+/// * `Sqlite` - A scan row and one row per open port in a SQLite database (`sqlite` feature).
+/// * `Prometheus` - Metrics in a Prometheus textfile, e.g. for the node_exporter textfile collector.
+/// * `MsgPack` - The reports as a MessagePack array (`msgpack` feature).
+/// * `Markdown` - A Markdown document with a summary and a table of open ports per host.
+/// * `Json` - No file; the reports are printed as JSON on stdout, like `StdoutFormat::Json`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Sqlite,
+    Prometheus,
+    MsgPack,
+    Markdown,
+    Json,
+}
+
+/// Where the report of a scan is stored besides the text log, see `config::get_output_sink`.
+///
+/// Variants:
+/// * `Sqlite` - The SQLite database file.
+/// * `Prometheus` - The Prometheus textfile.
+/// * `MsgPack` - The MessagePack file.
+/// * `Markdown` - The Markdown file.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputSink {
+    Sqlite(PathBuf),
+    Prometheus(PathBuf),
+    MsgPack(PathBuf),
+    Markdown(PathBuf),
+}
+
+/// FromStr implementation for OutputFormat, accepting "text", "sqlite", "prometheus", "msgpack", "markdown" and "json" (case-insensitive).
+///
+impl FromStr for OutputFormat {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            "prometheus" => Ok(OutputFormat::Prometheus),
+            "msgpack" => Ok(OutputFormat::MsgPack),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_output_format"),
+                s
+            ))),
+        }
+    }
+}
+
+/// Range of connect latencies a result must fall in to be shown. Either bound is optional.
+///
+/// # Fields
+/// * `min` - Results answering faster than this are omitted.
+/// * `max` - Results answering slower than this are omitted.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyFilter {
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+}
+
+impl LatencyFilter {
+    /// Check whether a result is shown. Results without a measured latency never are, as they
+    /// can't be placed in the range.
+    pub fn matches(&self, result: &ScanResult) -> bool {
+        result.latency.is_some_and(|latency| {
+            self.min.is_none_or(|min| latency >= min) && self.max.is_none_or(|max| latency <= max)
+        })
+    }
+}
+
+/// Remove the results outside a latency range.
+///
+/// # Arguments
+/// * `results` - The results to filter in place, keeping their order.
+/// * `filter` - The latency range.
+///
+/// # Returns
+/// * The number of removed results.
+///
+pub fn filter_by_latency(results: &mut Vec<ScanResult>, filter: &LatencyFilter) -> usize {
+    let before = results.len();
+    results.retain(|res| filter.matches(res));
+    before - results.len()
+}
+
+/// Sort scan results for display. Ties are always broken by port, then protocol.
+///
+/// # Arguments
+/// * `results` - The results to sort in place.
+/// * `sort_by` - The sort order.
+///
+pub fn sort_results(results: &mut [ScanResult], sort_by: SortBy) {
+    match sort_by {
+        SortBy::Port => results.sort_by_key(|r| (r.port, r.protocol)),
+        SortBy::Service => results.sort_by_cached_key(|r| {
+            (
+                r.service.is_none(),
+                r.service.as_ref().map(|s| s.to_lowercase()),
+                r.port,
+                r.protocol,
+            )
+        }),
+        SortBy::Latency => {
+            results.sort_by_key(|r| (r.latency.is_none(), r.latency, r.port, r.protocol))
+        }
+    }
+}
+
+/// Width of the longest bar of a latency histogram, in characters.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// Format a latency histogram as text, one line per bucket with the number of successful and
+/// failed connects and a bar scaled to the largest bucket. Empty buckets before the first and
+/// after the last used bucket are left out.
+///
+/// # Arguments
+/// * `histogram` - The histogram of a scan.
+///
+/// # Returns
+/// * The histogram lines, each ending with a newline, after a localised header line.
+///
+pub fn format_latency_histogram(histogram: &LatencyHistogram) -> String {
+    let mut out = format!("{}\n", crate::localisator::get("latency_histogram"));
+    let used = |bucket: &&crate::scanner::LatencyBucket| bucket.open + bucket.failed > 0;
+    let (Some(first), Some(last)) = (
+        histogram.buckets.iter().position(|b| used(&b)),
+        histogram.buckets.iter().rposition(|b| used(&b)),
+    ) else {
+        return out;
+    };
+    let max = histogram.buckets.iter().map(|b| b.open + b.failed).max().unwrap_or(1);
+    let format_ms = |ms: u64| match ms {
+        ms if ms >= 1000 && ms % 1000 == 0 => format!("{}s", ms / 1000),
+        ms => format!("{}ms", ms),
+    };
+    let mut lower = None;
+    for (index, bucket) in histogram.buckets.iter().enumerate() {
+        let label = match bucket.upper_ms {
+            Some(upper) => format!("< {}", format_ms(upper)),
+            None => format!(">= {}", lower.map_or_else(String::new, format_ms)),
+        };
+        lower = bucket.upper_ms;
+        if index < first || index > last {
+            continue;
+        }
+        let count = bucket.open + bucket.failed;
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(max) as usize);
+        out.push_str(&format!(
+            "  {:>8}  {:>6} {}  {:>6} {}  {}\n",
+            label,
+            bucket.open,
+            crate::localisator::get("open"),
+            bucket.failed,
+            crate::localisator::get("histogram_failed"),
+            bar
+        ));
+    }
+    out
+}
+
+/// Format the certificate of a TLS port for verbose output, e.g.
+/// `TLS: CN=example.com, issued by CN=R3, valid 2025-01-01T00:00:00Z to 2025-04-01T00:00:00Z,
+/// SAN example.com, www.example.com [expires in 12 days]`.
+///
+/// # Arguments
+/// * `cert` - The certificate of the port.
+///
+/// # Returns
+/// * The description on one line, flagging an expired or soon expiring certificate.
+///
+pub fn format_certificate(cert: &crate::tls::TlsCertificate) -> String {
+    let mut line = format!(
+        "TLS: {}, {} {}, {} {} - {}",
+        cert.subject,
+        crate::localisator::get("tls_issued_by"),
+        cert.issuer,
+        crate::localisator::get("tls_valid"),
+        cert.not_before,
+        cert.not_after
+    );
+    if !cert.sans.is_empty() {
+        line.push_str(&format!(", SAN {}", cert.sans.join(", ")));
+    }
+    if cert.expired {
+        line.push_str(&format!(" [{}]", crate::localisator::get("tls_expired")));
+    } else if cert.expires_soon {
+        line.push_str(&format!(
+            " [{}]",
+            crate::localisator::get_plural("tls_expires_in_days", cert.days_left as u64)
+        ));
+    }
+    line
+}
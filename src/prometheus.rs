@@ -0,0 +1,107 @@
+use crate::error::ScanError;
+use crate::scanner::ScanReport;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Escape a label value for the Prometheus text format: backslashes, double quotes and
+/// line feeds are escaped, everything else is kept as is.
+///
+/// # Arguments
+/// * `value` - The raw label value.
+///
+/// # Returns
+/// * The escaped value, to be put between double quotes.
+///
+pub fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format scan reports as metrics in the Prometheus text format, e.g. for the node_exporter
+/// textfile collector.
+///
+/// Every open port becomes a `port_explorer_open_port` sample with the labels `host`, `port`,
+/// `protocol` and `service` (empty if unidentified). Per host, `port_explorer_open_ports` counts
+/// the open ports and `port_explorer_scan_duration_seconds` holds the duration of the scan.
+///
+/// # Arguments
+/// * `reports` - The reports to export, one per host.
+///
+/// # Returns
+/// * The metrics, ending with a newline.
+///
+pub fn format_metrics(reports: &[&ScanReport]) -> String {
+    let mut out = String::new();
+    out.push_str("# HELP port_explorer_open_port Open port found by the last scan.\n");
+    out.push_str("# TYPE port_explorer_open_port gauge\n");
+    for report in reports {
+        let host = escape_label_value(&report.ip.to_string());
+        for res in &report.open_ports {
+            let _ = writeln!(
+                out,
+                "port_explorer_open_port{{host=\"{}\",port=\"{}\",protocol=\"{}\",service=\"{}\"}} 1",
+                host,
+                res.port,
+                res.protocol,
+                escape_label_value(res.service.as_deref().unwrap_or(""))
+            );
+        }
+    }
+    out.push_str("# HELP port_explorer_open_ports Number of open ports found by the last scan.\n");
+    out.push_str("# TYPE port_explorer_open_ports gauge\n");
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "port_explorer_open_ports{{host=\"{}\"}} {}",
+            escape_label_value(&report.ip.to_string()),
+            report.open_ports.len()
+        );
+    }
+    out.push_str("# HELP port_explorer_scan_duration_seconds Duration of the last scan.\n");
+    out.push_str("# TYPE port_explorer_scan_duration_seconds gauge\n");
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "port_explorer_scan_duration_seconds{{host=\"{}\"}} {}",
+            escape_label_value(&report.ip.to_string()),
+            report.duration_ms as f64 / 1000.0
+        );
+    }
+    out
+}
+
+/// Write scan reports to a Prometheus textfile, replacing its previous content.
+///
+/// The metrics are written to a temporary file next to `path` first and then renamed, so a
+/// collector never reads a half-written file.
+///
+/// # Arguments
+/// * `path` - The textfile (e.g., `/var/lib/node_exporter/port_explorer.prom`).
+/// * `reports` - The reports to export, see `format_metrics`.
+///
+/// # Returns
+/// * `Ok(())` - If the file was written.
+/// * `Err(ScanError)` - If the file can't be written.
+///
+pub fn write_metrics(path: &Path, reports: &[&ScanReport]) -> Result<(), ScanError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, format_metrics(reports))
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            ScanError::Io(std::io::Error::other(format!(
+                "{}: {:?}: {}",
+                crate::localisator::get("error_prometheus_write"),
+                path,
+                e
+            )))
+        })
+}
@@ -0,0 +1,219 @@
+use crate::error::ScanError;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::Resolver;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// Default port used for DNS servers given without an explicit port.
+const DNS_PORT: u16 = 53;
+
+/// Default number of DNS lookups that may run at the same time, see `max_dns_concurrent`.
+pub const DEFAULT_MAX_DNS_CONCURRENT: usize = 4;
+
+/// Counting semaphore bounding how many DNS lookups run at the same time, independent of the
+/// scan concurrency.
+///
+/// The limit can be changed while lookups are running; lookups already holding a permit finish,
+/// and new ones wait until fewer than the new limit are running.
+///
+#[derive(Debug)]
+pub struct LookupLimit {
+    running: Mutex<usize>,
+    limit: AtomicUsize,
+    freed: Condvar,
+}
+
+/// A running lookup's share of a `LookupLimit`, released when dropped.
+#[derive(Debug)]
+pub struct LookupPermit<'a>(&'a LookupLimit);
+
+impl LookupLimit {
+    /// Create a limit allowing `limit` lookups at the same time (at least one).
+    pub const fn new(limit: usize) -> Self {
+        LookupLimit {
+            running: Mutex::new(0),
+            limit: AtomicUsize::new(if limit == 0 { 1 } else { limit }),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Change the number of lookups allowed at the same time (at least one).
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit.max(1), Ordering::SeqCst);
+        self.freed.notify_all();
+    }
+
+    /// The number of lookups allowed at the same time.
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    /// Wait until a lookup may start.
+    ///
+    /// # Returns
+    /// * The permit, which lets the next waiting lookup start once dropped.
+    ///
+    pub fn acquire(&self) -> LookupPermit<'_> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        while *running >= self.limit() {
+            running = self.freed.wait(running).unwrap_or_else(|e| e.into_inner());
+        }
+        *running += 1;
+        LookupPermit(self)
+    }
+}
+
+/// Drop implementation for LookupPermit: the lookup is done, so a waiting one may start.
+///
+impl Drop for LookupPermit<'_> {
+    fn drop(&mut self) {
+        let mut running = self.0.running.lock().unwrap_or_else(|e| e.into_inner());
+        *running -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// The limit every `resolve_host` call of the process shares, so concurrent batch jobs, server
+/// requests and target lists don't flood the resolver together.
+static LOOKUPS: LookupLimit = LookupLimit::new(DEFAULT_MAX_DNS_CONCURRENT);
+
+/// Set how many DNS lookups of the process may run at the same time, see `LookupLimit`.
+///
+/// # Arguments
+/// * `limit` - The number of lookups (at least one).
+///
+pub fn set_max_concurrent_lookups(limit: usize) {
+    LOOKUPS.set_limit(limit);
+}
+
+/// Check whether a string is a syntactically valid DNS hostname.
+///
+/// # Arguments
+/// * `host` - The hostname to check (e.g., "example.com").
+///
+/// # Returns
+/// * `true` - If every label is 1-63 alphanumeric or hyphen characters and does not start or end with a hyphen.
+/// * `false` - Otherwise.
+///
+pub fn is_valid_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || host.len() > 253 {
+        return false;
+    }
+    host.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// Parse a DNS server address from config or CLI.
+///
+/// # Arguments
+/// * `value` - An IP address with an optional port (e.g., "10.0.0.53", "10.0.0.53:5353", "[::1]:53").
+///
+/// # Returns
+/// * `Ok(SocketAddr)` - The DNS server address, using port 53 if none was given.
+/// * `Err(ScanError)` - If the value is not a valid address.
+///
+pub fn parse_dns_server(value: &str) -> Result<SocketAddr, ScanError> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    value
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .map_err(|_| {
+            ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_dns_server"),
+                value
+            ))
+        })
+}
+
+/// Resolve a hostname to its IP addresses.
+///
+/// # Arguments
+/// * `host` - The hostname to resolve.
+/// * `dns_server` - An optional DNS server to query. If `None`, the system resolver is used.
+///
+/// Waits while the maximum number of lookups (see `set_max_concurrent_lookups`) is running.
+///
+/// # Returns
+/// * `Ok(Vec<IpAddr>)` - The resolved addresses, in the order returned by the resolver.
+/// * `Err(ScanError)` - A `ScanError::Resolve` if the lookup failed, timed out or returned no addresses.
+///
+pub fn resolve_host(host: &str, dns_server: Option<SocketAddr>) -> Result<Vec<IpAddr>, ScanError> {
+    let _permit = LOOKUPS.acquire();
+    let resolve_error = |reason: String| {
+        ScanError::Resolve(format!(
+            "{} {}: {}",
+            crate::localisator::get("error_dns_resolution"),
+            host,
+            reason
+        ))
+    };
+    let addrs: Vec<IpAddr> = match dns_server {
+        Some(server) => {
+            let group =
+                NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true);
+            let config = ResolverConfig::from_parts(None, vec![], group);
+            let resolver = Resolver::new(config, ResolverOpts::default())
+                .map_err(|e| resolve_error(e.to_string()))?;
+            resolver
+                .lookup_ip(host)
+                .map_err(|e| resolve_error(e.to_string()))?
+                .iter()
+                .collect()
+        }
+        None => (host, 0)
+            .to_socket_addrs()
+            .map_err(|e| resolve_error(e.to_string()))?
+            .map(|addr| addr.ip())
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(resolve_error(crate::localisator::get("error_no_addresses")));
+    }
+    Ok(addrs)
+}
+
+/// The outcome of resolving one hostname, see `resolve_host`.
+pub type Lookup = Result<Vec<IpAddr>, ScanError>;
+
+/// Resolve several hostnames in parallel, at most `set_max_concurrent_lookups` at a time.
+///
+/// # Arguments
+/// * `hosts` - The hostnames to resolve.
+/// * `dns_server` - An optional DNS server to query. If `None`, the system resolver is used.
+///
+/// # Returns
+/// * The result of `resolve_host` for every hostname, in the order of `hosts`.
+///
+pub fn resolve_hosts<S: AsRef<str> + Sync>(hosts: &[S], dns_server: Option<SocketAddr>) -> Vec<Lookup> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Lookup>>> = Mutex::new((0..hosts.len()).map(|_| None).collect());
+    let workers = LOOKUPS.limit().min(hosts.len());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(host) = hosts.get(index) else {
+                    break;
+                };
+                let result = resolve_host(host.as_ref(), dns_server);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.expect("every host is resolved by a worker"))
+        .collect()
+}
@@ -1,12 +1,127 @@
-use crate::signatures::{identify_service, Signature};
+use crate::signatures::{
+    banner_bytes_wanted, match_banner, match_http_response, render_service_name, HttpResponse, Signature,
+};
 use reqwest::blocking::Client;
 use reqwest::header::USER_AGENT;
-use std::net::{IpAddr, TcpStream};
-use std::sync::Arc;
-use std::time::Duration;
+use serde::{Serialize, Serializer};
+use socket2::{Domain, Socket, Type};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6, TcpStream, UdpSocket};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::time::{Duration, Instant};
 use threadpool::ThreadPool;
 use indicatif::ProgressBar;
+use once_cell::sync::Lazy;
 use crate::error::ScanError;
+use crate::tls::{probe_certificate, TlsCertificate, DEFAULT_TLS_EXPIRY_WARNING_DAYS};
+use crate::tunnel::{SshJump, TunnelStream};
+
+/// How long to wait for a reply to a UDP probe before reporting the port as open or filtered.
+const UDP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Concurrency the auto-tuner starts with.
+const AUTO_TUNE_START: usize = 16;
+
+/// How often the collector of a scan resizes the pool, for the auto-tuner and the ramp-up.
+pub const AUTO_TUNE_TICK: Duration = Duration::from_millis(250);
+
+/// Number of jobs per pool thread that are queued ahead, see `queue_work`.
+const QUEUED_PER_THREAD: usize = 2;
+
+/// Number of samples in the auto-tuner's sliding throughput window.
+const AUTO_TUNE_SAMPLES: usize = 4;
+
+/// Minimum throughput gain (relative) for the auto-tuner to keep raising concurrency.
+const AUTO_TUNE_MIN_GAIN: f64 = 1.1;
+
+/// Default timeout of a TCP connect, see `ScanOptions::connect_timeout`.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Default number of network-level connect errors in a row after which a scan is aborted, see
+/// `ScanOptions::network_failure_threshold`.
+pub const DEFAULT_NETWORK_FAILURE_THRESHOLD: u64 = 50;
+
+/// Timeout of an HTTP probe.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long the jump host may take to open a connection to a scanned port. It has to connect
+/// and answer over the SSH connection, so this is longer than a direct connect timeout.
+const TUNNEL_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a discovery probe waits for a host to answer.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long to wait for a service banner to reach the bytes the signatures need. This is a hard
+/// deadline for the whole banner, so neither a silent nor a slowly trickling service stalls a worker.
+const BANNER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default cap on the raw bytes read from a service banner.
+pub const DEFAULT_BANNER_MAX_BYTES: usize = 65536;
+
+/// Largest accepted `banner_max_bytes`.
+pub const MAX_BANNER_MAX_BYTES: usize = 1024 * 1024;
+
+/// How long an idle HTTP connection is kept in the pool.
+const HTTP_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// HTTP client shared by all probes, so connections to the same origin are pooled across scans.
+///
+/// The client doesn't tell whether a request reused a connection, so `idle` mirrors its pool:
+/// origins whose last response left the connection open (keep-alive), and since when.
+///
+struct HttpProbePool {
+    client: Client,
+    idle: Mutex<HashMap<String, Instant>>,
+}
+
+/// The shared probe client, or why it couldn't be built (e.g. a broken TLS backend).
+static HTTP_POOL: Lazy<Result<HttpProbePool, String>> = Lazy::new(|| build_http_pool(false));
+
+/// The probe client for `ScanOptions::insecure_tls`, which accepts any certificate.
+static INSECURE_HTTP_POOL: Lazy<Result<HttpProbePool, String>> = Lazy::new(|| build_http_pool(true));
+
+/// Build a probe client, accepting invalid TLS certificates if `insecure` is set.
+fn build_http_pool(insecure: bool) -> Result<HttpProbePool, String> {
+    Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .pool_idle_timeout(HTTP_POOL_IDLE_TIMEOUT)
+        .danger_accept_invalid_certs(insecure)
+        .build()
+        .map_err(|e| e.to_string())
+        .map(|client| HttpProbePool {
+            client,
+            idle: Mutex::new(HashMap::new()),
+        })
+}
+
+/// Makes sure a broken probe client is only reported once, not for every port.
+static HTTP_CLIENT_WARNING: Once = Once::new();
+
+/// Ensures the warning about a jump host refusing to forward connections is printed only once.
+static SSH_FORWARDING_WARNING: Once = Once::new();
+
+/// Check that the HTTP client used for probes could be built, so a broken client fails the scan
+/// up front instead of leaving every port unidentified.
+///
+/// # Returns
+/// * `Ok(())` - If the client is ready.
+/// * `Err(ScanError)` - If building the client failed, with the reason.
+///
+pub fn check_http_client() -> Result<(), ScanError> {
+    match HTTP_POOL.as_ref() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_http_client"),
+            e
+        ))),
+    }
+}
 
 /// Format a duration into a human-readable string.
 /// 
@@ -16,65 +131,1976 @@ use crate::error::ScanError;
 /// Returns
 /// * A formatted string representing the duration in the largest appropriate units.
 ///
-pub fn format_duration(duration: Duration) -> String {
-    let total_ms = duration.as_millis();
-    let total_ns = duration.as_nanos();
-    let total_secs = duration.as_secs();
-    let hours = total_secs / 3600;
-    let minutes = (total_secs % 3600) / 60;
-    let seconds = total_secs % 60;
-    let millis = duration.subsec_millis();
-    if hours > 0 {
-        format!("{}h {}m {}s", hours, minutes, seconds)
-    } else if minutes > 0 {
-        format!("{}m {}s", minutes, seconds)
-    } else if seconds > 0 {
-        format!("{}s {}ms", seconds, millis)
-    } else if millis > 0 {
-        format!("{}ms", total_ms)
-    } else {
-        format!("{}ns", total_ns)
+pub fn format_duration(duration: Duration) -> String {
+    let total_ms = duration.as_millis();
+    let total_ns = duration.as_nanos();
+    let total_secs = duration.as_secs();
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = total_secs % 60;
+    let millis = duration.subsec_millis();
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else if seconds > 0 {
+        format!("{}s {}ms", seconds, millis)
+    } else if millis > 0 {
+        format!("{}ms", total_ms)
+    } else {
+        format!("{}ns", total_ns)
+    }
+}
+
+/// Transport protocol of a scanned port.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Display implementation for Protocol, using the lowercase names also accepted in the config.
+///
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// FromStr implementation for Protocol, accepting "tcp" and "udp" (case-insensitive).
+///
+impl FromStr for Protocol {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_protocol"),
+                s
+            ))),
+        }
+    }
+}
+
+/// State of a port that was reported by a scan.
+///
+/// * `Open` - The port accepted a connection or answered a probe.
+/// * `OpenFiltered` - A UDP port that neither answered nor was reported unreachable, so it is
+///   either open or filtered.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortState {
+    Open,
+    OpenFiltered,
+}
+
+/// Which probe stage identified the service of a port.
+///
+/// * `HttpBody` - A signature matched the body (or only the status) of the HTTP response.
+/// * `HttpHeader` - A signature matched a header of the HTTP response.
+/// * `HttpsBody` - Like `HttpBody`, for a service that only answered the probe over TLS.
+/// * `HttpsHeader` - Like `HttpHeader`, for a service that only answered the probe over TLS.
+/// * `Banner` - A signature matched what the service sent on its own, or its reply to the TCP
+///   probe payload or UDP datagram.
+/// * `TlsCert` - No probe matched, but a signature matched the names in the certificate of a
+///   `tls_ports` port (see `TlsCertificate::identity_text`).
+/// * `None` - No signature matched.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdentificationSource {
+    HttpBody,
+    HttpHeader,
+    HttpsBody,
+    HttpsHeader,
+    Banner,
+    TlsCert,
+    #[default]
+    None,
+}
+
+/// Display implementation for IdentificationSource, using the names of the JSON reports.
+///
+impl fmt::Display for IdentificationSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdentificationSource::HttpBody => write!(f, "http-body"),
+            IdentificationSource::HttpHeader => write!(f, "http-header"),
+            IdentificationSource::HttpsBody => write!(f, "https-body"),
+            IdentificationSource::HttpsHeader => write!(f, "https-header"),
+            IdentificationSource::Banner => write!(f, "banner"),
+            IdentificationSource::TlsCert => write!(f, "tls-cert"),
+            IdentificationSource::None => write!(f, "none"),
+        }
+    }
+}
+
+/// Result of scanning a single port.
+///
+/// # Fields
+/// * `port` - The scanned port number.
+/// * `service` - The identified service name, if any signature matched.
+/// * `version` - The service version captured by the matching signature's `version` group, if any.
+/// * `protocol` - The transport protocol the port was scanned with.
+/// * `state` - Whether the port is known to be open or possibly filtered.
+/// * `latency` - Time until the port answered (TCP connect or UDP reply), if it did.
+/// * `identified_by` - The probe stage that identified `service`, or `None` if it is unidentified.
+/// * `signature_file` - The file of the signature that identified `service`, if it was loaded from one.
+/// * `tls` - The certificate the port presented, if it is one of the options' `tls_ports`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanResult {
+    pub port: u16,
+    pub service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub protocol: Protocol,
+    pub state: PortState,
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Option<Duration>,
+    pub identified_by: IdentificationSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsCertificate>,
+}
+
+/// Serialize an optional duration as fractional milliseconds.
+fn serialize_millis<S: Serializer>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(d) => serializer.serialize_some(&(d.as_secs_f64() * 1000.0)),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl ScanResult {
+    /// Label shown for the port: the identified service and its version if captured (e.g.
+    /// `nginx 1.25.3`), or the localised port state if unidentified.
+    pub fn label(&self) -> String {
+        match (&self.service, self.state) {
+            (Some(name), _) => match &self.version {
+                Some(version) => format!("{} {}", name, version),
+                None => name.clone(),
+            },
+            (None, PortState::Open) => crate::localisator::get("open"),
+            (None, PortState::OpenFiltered) => crate::localisator::get("open_filtered"),
+        }
+    }
+}
+
+/// Tuning options for a scan.
+///
+/// # Fields
+/// * `max_threads` - The maximum number of threads to use for scanning.
+/// * `retries` - How often a port is retried after a failed connect (TCP) or an unanswered probe (UDP).
+/// * `retry_backoff` - Delay before the first retry of a port, doubled for every further retry.
+/// * `max_retries_total` - Optional cap on retry attempts across the whole scan. Once it is used up,
+///   the remaining ports are scanned without retries.
+/// * `source_ports` - Optional local port range that outgoing TCP connects are bound to.
+/// * `auto_tune` - Start with a modest thread count and raise it while throughput improves,
+///   using `max_threads` as the ceiling.
+/// * `probe` - How open TCP ports are probed to identify their service.
+/// * `banner_max_bytes` - The most bytes read from a service banner, however much the service sends.
+/// * `ramp_up` - Time over which concurrency rises linearly from one thread to `max_threads` at
+///   the start of a scan, so the target isn't hit by a burst of connects. Zero disables it.
+/// * `ssh_jump` - Optional SSH jump host that TCP connects are forwarded through instead of being
+///   made directly (see `tunnel::ssh_jump`). UDP can't be scanned through it.
+/// * `first_open_only` - Stop scanning a host once one open port is found; the result then holds
+///   only that port.
+/// * `cancel` - Optional token that stops the scan early when triggered, see `CancelToken`.
+/// * `latency_histogram` - Add a histogram of all TCP connect latencies, failed connects
+///   included, to the scan statistics.
+/// * `http_probe_ports` - The open TCP ports `Probe::Http` sends its request to; the others are
+///   identified by their banner alone.
+/// * `connect_timeout` - How long a direct TCP connect may take before the port counts as filtered.
+/// * `probe_paths` - The paths `Probe::Http` requests, in order until a signature matches (`/` by default).
+/// * `raw_banners` - Report service names and signature files exactly as loaded. By default escape
+///   sequences are stripped and control characters escaped (see `output::sanitize_text`), so they
+///   can't manipulate the terminal or a log viewer.
+/// * `network_failure_threshold` - Abort the scan after this many TCP ports in a row failed with
+///   a network-level error (host or network unreachable), as the target's network has most
+///   likely gone away. The remaining ports are not scanned and `stats.network_failure` is set.
+///   Zero disables the check.
+/// * `tls_ports` - Open TCP ports (sorted) whose TLS certificate is read and reported, see
+///   `tls::probe_certificate`.
+/// * `tls_server_name` - The hostname sent with SNI when reading certificates, if any.
+/// * `tls_expiry_warning_days` - How many days before expiry a certificate is flagged as expiring soon.
+/// * `scope_id` - The interface index IPv6 targets are connected through, needed for link-local
+///   addresses (e.g., `fe80::1%eth0`); `0` for none. See `target_addr`.
+/// * `service_cache` - Services identified by earlier scans, reused for TCP ports that are still
+///   open instead of probing them again, see `ServiceCache`.
+/// * `target_timeout` - How long the scan of one host may take. Once it has passed, the host's
+///   remaining ports are not scanned and `stats.timed_out` is set, so one slow host can't stall
+///   a scan of many.
+/// * `insecure_tls` - Whether the HTTPS probe, tried when a port doesn't answer plaintext HTTP,
+///   accepts self-signed, expired and other invalid certificates.
+/// * `engine` - Whether ports are scanned on a thread pool or asynchronously, see `Engine`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    pub max_threads: usize,
+    pub retries: u32,
+    pub retry_backoff: Duration,
+    pub max_retries_total: Option<u64>,
+    pub source_ports: Option<SourcePortRange>,
+    pub auto_tune: bool,
+    pub probe: Probe,
+    pub banner_max_bytes: usize,
+    pub ramp_up: Duration,
+    pub ssh_jump: Option<Arc<SshJump>>,
+    pub first_open_only: bool,
+    pub cancel: Option<CancelToken>,
+    pub latency_histogram: bool,
+    pub http_probe_ports: HttpProbePorts,
+    pub connect_timeout: Duration,
+    pub probe_paths: Vec<String>,
+    pub raw_banners: bool,
+    pub network_failure_threshold: u64,
+    pub tls_ports: Vec<u16>,
+    pub tls_server_name: Option<String>,
+    pub tls_expiry_warning_days: u32,
+    pub scope_id: u32,
+    pub service_cache: Option<ServiceCache>,
+    pub target_timeout: Option<Duration>,
+    pub insecure_tls: bool,
+    pub engine: Engine,
+}
+
+/// Token to cancel a running scan from another thread, e.g. from a UI button.
+///
+/// Clones share the same flag, so the token can be handed to the scan (`ScanOptions::cancel`) and
+/// kept by the caller. Checking and triggering it is a single atomic operation.
+///
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token that is not cancelled yet.
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Cancel every scan holding a clone of this token. Cancelling twice has no further effect.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// PartialEq implementation for CancelToken: tokens are equal if they are clones of each other.
+///
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Key of a `ServiceCache` entry: the address, protocol and port.
+type CacheKey = (IpAddr, Protocol, u16);
+
+/// Services identified by earlier scans of a host, so repeated scans (e.g. `--repeat`) don't
+/// probe a port again while it stays open.
+///
+/// Entries are keyed on the address, protocol and port. A port found closed or filtered drops its
+/// entry, so it is probed again once it reopens; an entry older than `refresh` is probed again
+/// too. Only TCP ports are cached, since the UDP probe is what tells whether a UDP port is open.
+/// Clones share the entries, like `CancelToken`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ServiceCache {
+    entries: Arc<Mutex<HashMap<CacheKey, (ScanResult, Instant)>>>,
+    refresh: Option<Duration>,
+}
+
+impl ServiceCache {
+    /// Create an empty cache whose entries are used for at most `refresh`, or forever if `None`.
+    pub fn new(refresh: Option<Duration>) -> Self {
+        ServiceCache {
+            entries: Arc::default(),
+            refresh,
+        }
+    }
+
+    /// The cached result of a port, if there is one younger than `refresh`.
+    pub fn get(&self, ip: IpAddr, protocol: Protocol, port: u16) -> Option<ScanResult> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&(ip, protocol, port))
+            .filter(|(_, cached_at)| self.refresh.is_none_or(|refresh| cached_at.elapsed() < refresh))
+            .map(|(result, _)| result.clone())
+    }
+
+    /// Cache the result of an open port, replacing its previous entry.
+    pub fn insert(&self, ip: IpAddr, result: &ScanResult) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert((ip, result.protocol, result.port), (result.clone(), Instant::now()));
+    }
+
+    /// Drop the entry of a port that is no longer open.
+    pub fn remove(&self, ip: IpAddr, protocol: Protocol, port: u16) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&(ip, protocol, port));
+    }
+
+    /// Number of cached ports.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Check whether no port is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// PartialEq implementation for ServiceCache: caches are equal if they are clones of each other.
+///
+impl PartialEq for ServiceCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+/// How the ports of a scan are scanned concurrently.
+///
+/// Both engines report the same `ScanResult`s.
+///
+/// * `Threads` - A pool of `max_threads` OS threads, each blocking on its connect (the default).
+/// * `Async` - Non-blocking TCP connects on a tokio runtime, at most `max_threads` at a time;
+///   only open ports occupy a thread while their service is identified. See `async_engine`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    #[default]
+    Threads,
+    Async,
+}
+
+/// FromStr implementation for Engine, accepting "threads" and "async" (case-insensitive).
+///
+impl FromStr for Engine {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "threads" => Ok(Engine::Threads),
+            "async" => Ok(Engine::Async),
+            _ => Err(ScanError::Config(format!(
+                "{}: {}",
+                crate::localisator::get("error_invalid_engine"),
+                s
+            ))),
+        }
+    }
+}
+
+/// How an open TCP port is probed to identify its service.
+///
+/// The probe never affects the port state, which is decided by the connect alone.
+///
+/// * `Http` - Send an HTTP request and fall back to the banner if the port doesn't speak HTTP.
+/// * `Tcp` - Send the payload (nothing if empty) on the connection and match the reply.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Probe {
+    #[default]
+    Http,
+    Tcp(Vec<u8>),
+}
+
+/// Which open TCP ports get the HTTP request of `Probe::Http`. Skipping ports that clearly aren't
+/// web services (e.g. SSH or databases) saves the request and its timeout on them.
+///
+/// * `All` - Every port (the default).
+/// * `Only` - Only the listed ports.
+/// * `Skip` - Every port except the listed ones.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HttpProbePorts {
+    #[default]
+    All,
+    Only(BTreeSet<u16>),
+    Skip(BTreeSet<u16>),
+}
+
+impl HttpProbePorts {
+    /// Check whether a port gets the HTTP probe.
+    pub fn probes(&self, port: u16) -> bool {
+        match self {
+            HttpProbePorts::All => true,
+            HttpProbePorts::Only(ports) => ports.contains(&port),
+            HttpProbePorts::Skip(ports) => !ports.contains(&port),
+        }
+    }
+}
+
+/// Outcome of a TCP connect, judged on the TCP level alone.
+///
+/// * `Open` - The connect succeeded.
+/// * `Closed` - The host answered with a reset, so it is up but nothing listens.
+/// * `Filtered` - No answer (timeout) or an ICMP error, e.g. because a firewall drops the packets.
+/// * `LocalError` - The local host couldn't make the attempt (no free file descriptor or port).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectOutcome {
+    Open,
+    Closed,
+    Filtered,
+    LocalError,
+}
+
+/// Classify the result of a TCP connect.
+///
+/// # Arguments
+/// * `result` - The result of the connect.
+///
+/// # Returns
+/// * The `ConnectOutcome` of the attempt.
+///
+pub fn classify_connect<T>(result: &std::io::Result<T>) -> ConnectOutcome {
+    match result {
+        Ok(_) => ConnectOutcome::Open,
+        Err(e) if is_resource_error(e) => ConnectOutcome::LocalError,
+        Err(e) => classify_failure(e).outcome(),
+    }
+}
+
+/// Why a TCP connect to a port that isn't open failed, as reported by the OS.
+///
+/// * `Refused` - The host answered with a reset (`ConnectionRefused`, `ConnectionReset`).
+/// * `TimedOut` - No answer before the timeout, typically a firewall dropping the packets.
+/// * `HostUnreachable` - An ICMP host unreachable, e.g. because the host is down.
+/// * `NetworkUnreachable` - No route to the target network.
+/// * `Other` - Any other error.
+///
+/// `Refused` makes a port closed, everything else filtered (see `ConnectFailure::outcome`).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectFailure {
+    Refused,
+    TimedOut,
+    HostUnreachable,
+    NetworkUnreachable,
+    Other,
+}
+
+impl ConnectFailure {
+    /// The coarse `ConnectOutcome` this failure is reported as by default.
+    pub fn outcome(self) -> ConnectOutcome {
+        match self {
+            ConnectFailure::Refused => ConnectOutcome::Closed,
+            _ => ConnectOutcome::Filtered,
+        }
+    }
+}
+
+/// Classify the error of a failed TCP connect.
+///
+/// # Arguments
+/// * `error` - The error returned by the connect.
+///
+/// # Returns
+/// * The `ConnectFailure` the error kind maps to.
+///
+pub fn classify_failure(error: &std::io::Error) -> ConnectFailure {
+    match error.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset => ConnectFailure::Refused,
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => ConnectFailure::TimedOut,
+        ErrorKind::HostUnreachable => ConnectFailure::HostUnreachable,
+        ErrorKind::NetworkUnreachable => ConnectFailure::NetworkUnreachable,
+        _ => ConnectFailure::Other,
+    }
+}
+
+/// Number of TCP ports per `ConnectFailure`, shown with `verbose`.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ConnectFailures {
+    pub refused: u64,
+    pub timed_out: u64,
+    pub host_unreachable: u64,
+    pub network_unreachable: u64,
+    pub other: u64,
+}
+
+/// Default implementation for ScanOptions: `config::default_max_threads` threads, no retries,
+/// OS-chosen source ports, HTTP probes and banners of up to `DEFAULT_BANNER_MAX_BYTES`.
+///
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            max_threads: crate::config::default_max_threads(),
+            retries: 0,
+            retry_backoff: Duration::from_millis(100),
+            max_retries_total: None,
+            source_ports: None,
+            auto_tune: false,
+            probe: Probe::Http,
+            banner_max_bytes: DEFAULT_BANNER_MAX_BYTES,
+            ramp_up: Duration::ZERO,
+            ssh_jump: None,
+            first_open_only: false,
+            cancel: None,
+            latency_histogram: false,
+            http_probe_ports: HttpProbePorts::All,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            probe_paths: vec!["/".to_string()],
+            raw_banners: false,
+            network_failure_threshold: DEFAULT_NETWORK_FAILURE_THRESHOLD,
+            tls_ports: Vec::new(),
+            tls_server_name: None,
+            tls_expiry_warning_days: DEFAULT_TLS_EXPIRY_WARNING_DAYS,
+            scope_id: 0,
+            service_cache: None,
+            target_timeout: None,
+            insecure_tls: false,
+            engine: Engine::Threads,
+        }
+    }
+}
+
+/// Local port range that outgoing TCP connects are bound to, used in rotation.
+///
+/// Clones share the rotation cursor and the exhaustion counter, so all workers of a scan
+/// spread their connects over the whole range. Every connect in flight occupies one source
+/// port, so a small range limits the effective concurrency.
+///
+#[derive(Debug, Clone)]
+pub struct SourcePortRange {
+    start: u16,
+    end: u16,
+    next: Arc<AtomicUsize>,
+    exhausted: Arc<AtomicU64>,
+}
+
+impl SourcePortRange {
+    /// Create a range of source ports from `start` to `end` (inclusive).
+    pub fn new(start: u16, end: u16) -> Self {
+        SourcePortRange {
+            start: start.min(end),
+            end: start.max(end),
+            next: Arc::new(AtomicUsize::new(0)),
+            exhausted: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// First port of the range.
+    pub fn start(&self) -> u16 {
+        self.start
+    }
+
+    /// Last port of the range.
+    pub fn end(&self) -> u16 {
+        self.end
+    }
+
+    /// Number of connects that failed because no port of the range could be bound.
+    pub fn exhausted(&self) -> u64 {
+        self.exhausted.load(Ordering::SeqCst)
+    }
+
+    /// Connect to `addr` from the next free port of the range.
+    ///
+    /// Ports that are busy are skipped. If no port of the range can be bound, the connect
+    /// fails with `ErrorKind::AddrNotAvailable` and is counted in `exhausted`.
+    ///
+    /// # Arguments
+    /// * `addr` - The address to connect to.
+    /// * `timeout` - The connect timeout.
+    ///
+    /// # Returns
+    /// * `Ok(TcpStream)` - The connected stream.
+    /// * `Err(std::io::Error)` - If the connect failed or the range is exhausted.
+    ///
+    pub fn connect(&self, addr: SocketAddr, timeout: Duration) -> std::io::Result<TcpStream> {
+        let len = usize::from(self.end - self.start) + 1;
+        let local_ip = match addr {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        for _ in 0..len {
+            let offset = self.next.fetch_add(1, Ordering::SeqCst) % len;
+            let local = SocketAddr::new(local_ip, self.start + offset as u16);
+            let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+            socket.set_reuse_address(true)?;
+            if socket.bind(&local.into()).is_err() {
+                continue;
+            }
+            match socket.connect_timeout(&addr.into(), timeout) {
+                Ok(()) => return Ok(socket.into()),
+                // The address pair is still in use from an earlier connect: try another port
+                Err(e) if matches!(e.kind(), ErrorKind::AddrInUse | ErrorKind::AddrNotAvailable) => {
+                    continue
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.exhausted.fetch_add(1, Ordering::SeqCst);
+        Err(std::io::Error::new(
+            ErrorKind::AddrNotAvailable,
+            crate::localisator::get("error_source_ports_exhausted"),
+        ))
+    }
+}
+
+/// PartialEq implementation for SourcePortRange, comparing only the port bounds.
+///
+impl PartialEq for SourcePortRange {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+/// Shared budget of retry attempts across a whole scan.
+///
+#[derive(Debug, Default)]
+pub struct RetryBudget {
+    cap: Option<u64>,
+    used: AtomicU64,
+    exhausted: AtomicBool,
+}
+
+impl RetryBudget {
+    /// Create a budget allowing at most `cap` retries, or unlimited retries if `None`.
+    pub fn new(cap: Option<u64>) -> Self {
+        RetryBudget {
+            cap,
+            used: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Take one retry from the budget.
+    ///
+    /// # Returns
+    /// * `true` - If a retry may be attempted.
+    /// * `false` - If the cap has been reached.
+    ///
+    pub fn try_take(&self) -> bool {
+        let taken = self
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| match self.cap {
+                Some(cap) if used >= cap => None,
+                _ => Some(used + 1),
+            })
+            .is_ok();
+        if !taken {
+            self.exhausted.store(true, Ordering::SeqCst);
+        }
+        taken
+    }
+
+    /// Number of retries taken so far.
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Whether a retry was ever refused because the cap was reached.
+    pub fn exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::SeqCst)
+    }
+}
+
+/// State shared by all workers of a scan.
+///
+/// # Fields
+/// * `retries` - The retry budget of the scan.
+/// * `resource_errors` - Number of probes that failed because the local host ran out of
+///   resources (file descriptors, ephemeral ports), as opposed to closed or filtered ports.
+/// * `connects`, `connect_micros` - Number and total time of successful TCP connects.
+/// * `http_probes`, `http_reused`, `http_probe_micros` - Number of HTTP probes, how many of
+///   them reused a pooled connection, and their total time.
+/// * `closed`, `filtered` - Number of TCP ports found closed or filtered.
+/// * `failures` - Number of TCP ports per `ConnectFailure`, indexed by the variant.
+/// * `latency_open`, `latency_failed` - Number of successful and failed TCP connect attempts
+///   per bucket of `LATENCY_BUCKETS_MS`.
+/// * `bytes_received` - Bytes read by the probes: banners, HTTP responses and UDP replies.
+/// * `network_errors_in_row` - Number of TCP ports that failed with a network-level error since
+///   the last port that connected or failed otherwise.
+/// * `cached_services` - Number of open ports whose service was taken from the `ServiceCache`.
+///
+#[derive(Debug, Default)]
+pub struct ScanState {
+    pub retries: RetryBudget,
+    resource_errors: AtomicU64,
+    connects: AtomicU64,
+    connect_micros: AtomicU64,
+    http_probes: AtomicU64,
+    http_reused: AtomicU64,
+    http_probe_micros: AtomicU64,
+    closed: AtomicU64,
+    filtered: AtomicU64,
+    failures: [AtomicU64; 5],
+    latency_open: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    latency_failed: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    bytes_received: AtomicU64,
+    network_errors_in_row: AtomicU64,
+    cached_services: AtomicU64,
+}
+
+impl ScanState {
+    /// Create the state for a scan allowing at most `max_retries_total` retries, or unlimited if `None`.
+    pub fn new(max_retries_total: Option<u64>) -> Self {
+        ScanState {
+            retries: RetryBudget::new(max_retries_total),
+            ..ScanState::default()
+        }
+    }
+
+    /// Count `error` if it was caused by the local host running out of resources.
+    pub fn record_error(&self, error: &std::io::Error) {
+        if is_resource_error(error) {
+            self.resource_errors.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Number of resource errors recorded so far.
+    pub fn resource_errors(&self) -> u64 {
+        self.resource_errors.load(Ordering::SeqCst)
+    }
+
+    /// Record a successful TCP connect that took `elapsed`.
+    pub fn record_connect(&self, elapsed: Duration) {
+        self.network_errors_in_row.store(0, Ordering::SeqCst);
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        self.connect_micros.fetch_add(elapsed.as_micros() as u64, Ordering::SeqCst);
+    }
+
+    /// Record a TCP connect attempt that took `elapsed` in the latency histogram, whether it
+    /// succeeded or not. Attempts that failed for lack of local resources aren't recorded.
+    pub fn record_attempt(&self, elapsed: Duration, open: bool) {
+        let ms = elapsed.as_millis();
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|upper| ms < u128::from(*upper)).unwrap_or(LATENCY_BUCKETS_MS.len());
+        let counts = if open { &self.latency_open } else { &self.latency_failed };
+        counts[bucket].fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The latency histogram of the TCP connect attempts recorded so far.
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        let upper = LATENCY_BUCKETS_MS.iter().map(|ms| Some(*ms)).chain(std::iter::once(None));
+        LatencyHistogram {
+            buckets: upper
+                .zip(self.latency_open.iter().zip(&self.latency_failed))
+                .map(|(upper_ms, (open, failed))| LatencyBucket {
+                    upper_ms,
+                    open: open.load(Ordering::SeqCst),
+                    failed: failed.load(Ordering::SeqCst),
+                })
+                .collect(),
+        }
+    }
+
+    /// Record an HTTP probe that took `elapsed` and either reused a pooled connection or opened a fresh one.
+    pub fn record_http_probe(&self, elapsed: Duration, reused: bool) {
+        self.http_probes.fetch_add(1, Ordering::SeqCst);
+        if reused {
+            self.http_reused.fetch_add(1, Ordering::SeqCst);
+        }
+        self.http_probe_micros.fetch_add(elapsed.as_micros() as u64, Ordering::SeqCst);
+    }
+
+    /// Record `n` bytes read by a probe.
+    pub fn record_bytes(&self, n: usize) {
+        self.bytes_received.fetch_add(n as u64, Ordering::SeqCst);
+    }
+
+    /// Record an open port whose service was taken from the `ServiceCache`.
+    pub fn record_cached_service(&self) {
+        self.cached_services.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Number of open ports whose service was taken from the `ServiceCache` so far.
+    pub fn cached_services(&self) -> u64 {
+        self.cached_services.load(Ordering::SeqCst)
+    }
+
+    /// Number of bytes read by the probes so far.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::SeqCst)
+    }
+
+    /// Record the final outcome of a TCP port that wasn't open.
+    pub fn record_outcome(&self, outcome: ConnectOutcome) {
+        match outcome {
+            ConnectOutcome::Closed => self.closed.fetch_add(1, Ordering::SeqCst),
+            ConnectOutcome::Filtered => self.filtered.fetch_add(1, Ordering::SeqCst),
+            ConnectOutcome::Open | ConnectOutcome::LocalError => 0,
+        };
+    }
+
+    /// Record the final error of a TCP port that wasn't open, counting both its outcome and
+    /// the precise reason. Errors of the local host are not counted.
+    pub fn record_failure(&self, error: &std::io::Error) {
+        if is_resource_error(error) {
+            return;
+        }
+        let failure = classify_failure(error);
+        self.record_outcome(failure.outcome());
+        self.failures[failure as usize].fetch_add(1, Ordering::SeqCst);
+        if matches!(failure, ConnectFailure::HostUnreachable | ConnectFailure::NetworkUnreachable) {
+            self.network_errors_in_row.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.network_errors_in_row.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Number of TCP ports in a row that failed with a network-level error, see `record_failure`.
+    pub fn network_errors_in_row(&self) -> u64 {
+        self.network_errors_in_row.load(Ordering::SeqCst)
+    }
+
+    /// Number of TCP ports per failure reason recorded so far.
+    pub fn failures(&self) -> ConnectFailures {
+        let count = |failure: ConnectFailure| self.failures[failure as usize].load(Ordering::SeqCst);
+        ConnectFailures {
+            refused: count(ConnectFailure::Refused),
+            timed_out: count(ConnectFailure::TimedOut),
+            host_unreachable: count(ConnectFailure::HostUnreachable),
+            network_unreachable: count(ConnectFailure::NetworkUnreachable),
+            other: count(ConnectFailure::Other),
+        }
+    }
+
+    /// Number of TCP ports found closed.
+    pub fn closed(&self) -> u64 {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Number of TCP ports found filtered.
+    pub fn filtered(&self) -> u64 {
+        self.filtered.load(Ordering::SeqCst)
+    }
+
+    /// Number of HTTP probes recorded so far.
+    pub fn http_probes(&self) -> u64 {
+        self.http_probes.load(Ordering::SeqCst)
+    }
+
+    /// Number of HTTP probes that reused a pooled connection.
+    pub fn http_reused(&self) -> u64 {
+        self.http_reused.load(Ordering::SeqCst)
+    }
+
+    /// Average time of a successful TCP connect in milliseconds, if there was any.
+    pub fn avg_connect_ms(&self) -> Option<f64> {
+        average_ms(self.connect_micros.load(Ordering::SeqCst), self.connects.load(Ordering::SeqCst))
+    }
+
+    /// Average time of an HTTP probe in milliseconds, if there was any.
+    pub fn avg_http_probe_ms(&self) -> Option<f64> {
+        average_ms(self.http_probe_micros.load(Ordering::SeqCst), self.http_probes())
+    }
+}
+
+/// Average of `count` samples totalling `total_micros`, in milliseconds.
+fn average_ms(total_micros: u64, count: u64) -> Option<f64> {
+    (count > 0).then(|| total_micros as f64 / count as f64 / 1000.0)
+}
+
+/// Check whether an I/O error means the local host ran out of resources.
+///
+/// # Arguments
+/// * `error` - The error returned by a connect or bind.
+///
+/// # Returns
+/// * `true` - For too many open files (`EMFILE`/`ENFILE`), no free local address or out of memory.
+/// * `false` - Otherwise, e.g. for refused or timed out connects.
+///
+pub fn is_resource_error(error: &std::io::Error) -> bool {
+    const ENFILE: i32 = 23;
+    const EMFILE: i32 = 24;
+    matches!(error.kind(), ErrorKind::AddrNotAvailable | ErrorKind::OutOfMemory)
+        || (cfg!(unix) && matches!(error.raw_os_error(), Some(ENFILE | EMFILE)))
+}
+
+/// Check whether a failed TCP connect is worth retrying.
+///
+/// # Arguments
+/// * `error` - The error returned by the connect.
+///
+/// # Returns
+/// * `true` - For timeouts and local resource errors, which may pass on another attempt.
+/// * `false` - For definitive answers like a refused connect or an unreachable host, and any other error.
+///
+pub fn is_transient_error(error: &std::io::Error) -> bool {
+    is_resource_error(error) || classify_failure(error) == ConnectFailure::TimedOut
+}
+
+/// Adjusts the thread count of a scan based on measured throughput.
+///
+/// Concurrency starts low and is doubled as long as the throughput over a sliding window
+/// improves by at least 10%. Once it plateaus, the tuner settles on the best thread count
+/// seen. Resource errors halve the thread count and stop any further increase.
+///
+#[derive(Debug)]
+pub struct AutoTuner {
+    threads: usize,
+    ceiling: usize,
+    best_threads: usize,
+    best_rate: f64,
+    samples: VecDeque<(Instant, u64)>,
+    errors: u64,
+    settled: bool,
+}
+
+impl AutoTuner {
+    /// Create a tuner that never goes above `ceiling` threads.
+    pub fn new(ceiling: usize) -> Self {
+        let threads = AUTO_TUNE_START.min(ceiling).max(1);
+        AutoTuner {
+            threads,
+            ceiling: ceiling.max(1),
+            best_threads: threads,
+            best_rate: 0.0,
+            samples: VecDeque::with_capacity(AUTO_TUNE_SAMPLES),
+            errors: 0,
+            settled: false,
+        }
+    }
+
+    /// The current thread count.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Whether the tuner has stopped increasing the thread count.
+    pub fn settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Feed a sample and get the thread count to use from now on.
+    ///
+    /// # Arguments
+    /// * `now` - When the sample was taken.
+    /// * `completed` - Total number of ports completed so far.
+    /// * `errors` - Total number of resource errors so far.
+    /// * `saturated` - Whether work was still queued, i.e. all threads were busy.
+    ///
+    /// # Returns
+    /// * The thread count to use.
+    ///
+    pub fn observe(&mut self, now: Instant, completed: u64, errors: u64, saturated: bool) -> usize {
+        if errors > self.errors {
+            self.errors = errors;
+            self.threads = (self.threads / 2).max(1);
+            self.best_threads = self.best_threads.min(self.threads);
+            self.settled = true;
+            self.samples.clear();
+            return self.threads;
+        }
+        if self.settled {
+            return self.threads;
+        }
+        self.samples.push_back((now, completed));
+        if self.samples.len() < AUTO_TUNE_SAMPLES {
+            return self.threads;
+        }
+        let (first_at, first_done) = self.samples[0];
+        let elapsed = now.saturating_duration_since(first_at).as_secs_f64();
+        let rate = completed.saturating_sub(first_done) as f64 / elapsed.max(f64::EPSILON);
+        self.samples.pop_front();
+        // Only a pool with queued work shows what the current thread count can do
+        if !saturated {
+            return self.threads;
+        }
+        if rate >= self.best_rate * AUTO_TUNE_MIN_GAIN {
+            self.best_rate = rate;
+            self.best_threads = self.threads;
+            if self.threads < self.ceiling {
+                self.threads = (self.threads * 2).min(self.ceiling);
+                self.samples.clear();
+            } else {
+                self.settled = true;
+            }
+        } else {
+            self.threads = self.best_threads;
+            self.settled = true;
+        }
+        self.threads
+    }
+}
+
+/// Thread count allowed by the ramp-up at a point of a scan.
+///
+/// # Arguments
+/// * `max_threads` - The thread count at the end of the ramp.
+/// * `elapsed` - Time since the scan started.
+/// * `ramp_up` - Length of the ramp; zero means no ramp.
+///
+/// # Returns
+/// * The thread count, rising linearly from 1 to `max_threads` over `ramp_up`.
+///
+pub fn ramp_threads(max_threads: usize, elapsed: Duration, ramp_up: Duration) -> usize {
+    if elapsed >= ramp_up {
+        return max_threads.max(1);
+    }
+    let fraction = elapsed.as_secs_f64() / ramp_up.as_secs_f64();
+    ((max_threads as f64 * fraction).ceil() as usize).clamp(1, max_threads.max(1))
+}
+
+/// Statistics collected over a scan.
+///
+/// # Fields
+/// * `retries_used` - Number of retry attempts made across all ports.
+/// * `retry_cap` - The configured cap on total retries, if any.
+/// * `retry_cap_reached` - Whether the cap was hit, so later ports were scanned without retries.
+/// * `source_ports_exhausted` - Connects that failed because no port of the source port range could be bound.
+/// * `threads` - The thread count at the end of the scan, as chosen by the auto-tuner if enabled.
+/// * `avg_connect_ms` - Average time of a successful TCP connect, if any port was open.
+/// * `http_probes` - Number of HTTP probes sent to open TCP ports.
+/// * `http_reused` - HTTP probes that reused a pooled connection.
+/// * `http_fresh` - HTTP probes that opened a fresh connection.
+/// * `avg_http_probe_ms` - Average time of an HTTP probe, if any was sent.
+/// * `closed` - TCP ports that answered with a reset.
+/// * `filtered` - TCP ports that didn't answer or were reported unreachable.
+/// * `failures` - The closed and filtered TCP ports by the precise reason of the failed connect.
+/// * `cancelled` - Whether the scan was cancelled, so not every port was scanned.
+/// * `network_failure` - Whether the scan was aborted due to network failure (see
+///   `ScanOptions::network_failure_threshold`), so the ports after the failure were not scanned
+///   rather than found closed.
+/// * `latency_histogram` - The latencies of all TCP connect attempts, if `latency_histogram` was
+///   enabled in the scan options.
+/// * `bytes_received` - Bytes the probes read from banners, HTTP responses (headers and body) and
+///   UDP replies.
+/// * `cached_services` - Open ports that weren't probed because their service was cached, see
+///   `ScanOptions::service_cache`.
+/// * `timed_out` - Whether the scan was cut short by `ScanOptions::target_timeout`.
+/// * `ports_timed_out` - The ports that weren't scanned because of it.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScanStats {
+    pub retries_used: u64,
+    pub retry_cap: Option<u64>,
+    pub retry_cap_reached: bool,
+    pub source_ports_exhausted: u64,
+    pub threads: usize,
+    pub avg_connect_ms: Option<f64>,
+    pub http_probes: u64,
+    pub http_reused: u64,
+    pub http_fresh: u64,
+    pub avg_http_probe_ms: Option<f64>,
+    pub closed: u64,
+    pub filtered: u64,
+    pub failures: ConnectFailures,
+    pub cancelled: bool,
+    pub network_failure: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_histogram: Option<LatencyHistogram>,
+    pub bytes_received: u64,
+    pub cached_services: u64,
+    pub timed_out: bool,
+    pub ports_timed_out: u64,
+}
+
+impl ScanStats {
+    /// The statistics gathered in the state of a scan; the flags about how the scan ended
+    /// (`cancelled`, `network_failure`, `timed_out`) and `source_ports_exhausted` are left unset.
+    ///
+    /// # Arguments
+    /// * `state` - The state shared across the scan.
+    /// * `options` - The scan options.
+    /// * `threads` - The thread count at the end of the scan.
+    ///
+    pub fn from_state(state: &ScanState, options: &ScanOptions, threads: usize) -> Self {
+        ScanStats {
+            retries_used: state.retries.used(),
+            retry_cap: options.max_retries_total,
+            retry_cap_reached: state.retries.exhausted(),
+            threads,
+            avg_connect_ms: state.avg_connect_ms(),
+            http_probes: state.http_probes(),
+            http_reused: state.http_reused(),
+            http_fresh: state.http_probes() - state.http_reused(),
+            avg_http_probe_ms: state.avg_http_probe_ms(),
+            closed: state.closed(),
+            filtered: state.filtered(),
+            failures: state.failures(),
+            latency_histogram: options.latency_histogram.then(|| state.latency_histogram()),
+            bytes_received: state.bytes_received(),
+            cached_services: state.cached_services(),
+            ..ScanStats::default()
+        }
+    }
+}
+
+/// Upper bounds (exclusive) of the buckets of a `LatencyHistogram` in milliseconds. A last bucket
+/// holds everything slower.
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000];
+
+/// Histogram of TCP connect latencies, telling e.g. fast refusals from slow timeouts.
+///
+/// # Fields
+/// * `buckets` - One bucket per bound of `LATENCY_BUCKETS_MS` plus one for slower attempts, fastest first.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyHistogram {
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// A bucket of a `LatencyHistogram`.
+///
+/// # Fields
+/// * `upper_ms` - The bucket holds attempts faster than this many milliseconds (and slower than
+///   the previous bucket), or `None` for the last bucket.
+/// * `open` - Number of connect attempts that succeeded.
+/// * `failed` - Number of connect attempts that failed (refused, timed out, unreachable).
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyBucket {
+    pub upper_ms: Option<u64>,
+    pub open: u64,
+    pub failed: u64,
+}
+
+impl LatencyHistogram {
+    /// Add up two histograms bucket by bucket.
+    pub fn merge(&self, other: &LatencyHistogram) -> LatencyHistogram {
+        LatencyHistogram {
+            buckets: self
+                .buckets
+                .iter()
+                .zip(&other.buckets)
+                .map(|(a, b)| LatencyBucket { upper_ms: a.upper_ms, open: a.open + b.open, failed: a.failed + b.failed })
+                .collect(),
+        }
+    }
+}
+
+/// Report of a complete scan of one target, as emitted in batch mode.
+///
+/// # Fields
+/// * `target` - The scan target as given (IP address or hostname).
+/// * `ip` - The scanned IP address.
+/// * `port_range` - Description of the scanned ports (e.g., "tcp 1-1024, udp 53").
+/// * `duration_ms` - How long the scan took in milliseconds.
+/// * `open_ports` - The open ports, in the configured sort order.
+/// * `identification_rate` - Fraction of open ports whose service was identified, if any port is open.
+/// * `stats` - The scan statistics.
+/// * `conflicts` - Ports that merged reports identified as different services (see `ScanReport::merge`).
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanReport {
+    pub target: String,
+    pub ip: IpAddr,
+    pub port_range: String,
+    pub duration_ms: u64,
+    pub open_ports: Vec<ScanResult>,
+    pub identification_rate: Option<f64>,
+    pub stats: ScanStats,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ServiceConflict>,
+}
+
+/// A port that merged reports identified as different services.
+///
+/// # Fields
+/// * `port` - The port number.
+/// * `protocol` - The transport protocol of the port.
+/// * `services` - The distinct service names, in the order the reports were merged. The merged
+///   report keeps the first one.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceConflict {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub services: Vec<String>,
+}
+
+impl ScanReport {
+    /// Merge reports of the same host into this one, e.g. of protocol-specific or time-sliced scans.
+    ///
+    /// Open ports are unioned; a port found by several reports is listed once. If only some
+    /// reports identified its service, the identification is kept. If they identified different
+    /// services (ignoring case), the first one is kept and the port is noted in `conflicts`.
+    /// Durations and counters add up, the port ranges are listed side by side, and the open ports
+    /// end up sorted by port. Reports of other hosts are skipped; use `merge_reports` to combine
+    /// reports of several hosts.
+    ///
+    /// # Arguments
+    /// * `others` - The reports to merge into this one.
+    ///
+    /// # Returns
+    /// * The merged report.
+    ///
+    pub fn merge(&self, others: &[ScanReport]) -> ScanReport {
+        let tcp_ports = |report: &ScanReport| {
+            report.open_ports.iter().filter(|res| res.protocol == Protocol::Tcp).count() as u64
+        };
+        let mut merged = self.clone();
+        let mut port_ranges = vec![self.port_range.clone()];
+        let mut connects = tcp_ports(self);
+        for other in others.iter().filter(|other| other.ip == self.ip) {
+            merged.stats = merge_stats(&merged.stats, connects, &other.stats, tcp_ports(other));
+            connects += tcp_ports(other);
+            merged.duration_ms += other.duration_ms;
+            if !port_ranges.contains(&other.port_range) {
+                port_ranges.push(other.port_range.clone());
+            }
+            for conflict in &other.conflicts {
+                for service in &conflict.services {
+                    note_conflict(&mut merged.conflicts, conflict.port, conflict.protocol, service, service);
+                }
+            }
+            for res in &other.open_ports {
+                let Some(known) = merged
+                    .open_ports
+                    .iter_mut()
+                    .find(|known| known.port == res.port && known.protocol == res.protocol)
+                else {
+                    merged.open_ports.push(res.clone());
+                    continue;
+                };
+                if known.state == PortState::OpenFiltered {
+                    known.state = res.state;
+                }
+                if known.latency.is_none() {
+                    known.latency = res.latency;
+                }
+                match (&known.service, &res.service) {
+                    (None, Some(_)) => {
+                        known.service = res.service.clone();
+                        known.identified_by = res.identified_by;
+                    }
+                    (Some(kept), Some(found)) if !kept.eq_ignore_ascii_case(found) => {
+                        note_conflict(&mut merged.conflicts, res.port, res.protocol, kept, found);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        merged.port_range = port_ranges.join(", ");
+        merged.open_ports.sort_by_key(|res| (res.port, res.protocol));
+        merged.identification_rate = crate::output::identification_rate(&merged.open_ports).1;
+        merged
+    }
+}
+
+/// Record that a port was identified as both `kept` and `found`.
+fn note_conflict(conflicts: &mut Vec<ServiceConflict>, port: u16, protocol: Protocol, kept: &str, found: &str) {
+    let index = match conflicts.iter().position(|c| c.port == port && c.protocol == protocol) {
+        Some(index) => index,
+        None => {
+            conflicts.push(ServiceConflict { port, protocol, services: Vec::new() });
+            conflicts.len() - 1
+        }
+    };
+    let services = &mut conflicts[index].services;
+    for service in [kept, found] {
+        if !services.iter().any(|known| known.eq_ignore_ascii_case(service)) {
+            services.push(service.to_string());
+        }
+    }
+}
+
+/// Combine the statistics of two scans. Counters add up, averages are weighted by their sample
+/// counts (`connects` successful TCP connects per scan, and the HTTP probes).
+fn merge_stats(a: &ScanStats, a_connects: u64, b: &ScanStats, b_connects: u64) -> ScanStats {
+    let weighted = |a_ms: Option<f64>, a_n: u64, b_ms: Option<f64>, b_n: u64| match (a_ms, b_ms) {
+        (Some(a_ms), Some(b_ms)) if a_n + b_n > 0 => {
+            Some((a_ms * a_n as f64 + b_ms * b_n as f64) / (a_n + b_n) as f64)
+        }
+        (a_ms, b_ms) => a_ms.or(b_ms),
+    };
+    ScanStats {
+        retries_used: a.retries_used + b.retries_used,
+        retry_cap: a.retry_cap,
+        retry_cap_reached: a.retry_cap_reached || b.retry_cap_reached,
+        source_ports_exhausted: a.source_ports_exhausted + b.source_ports_exhausted,
+        threads: a.threads.max(b.threads),
+        avg_connect_ms: weighted(a.avg_connect_ms, a_connects, b.avg_connect_ms, b_connects),
+        http_probes: a.http_probes + b.http_probes,
+        http_reused: a.http_reused + b.http_reused,
+        http_fresh: a.http_fresh + b.http_fresh,
+        avg_http_probe_ms: weighted(a.avg_http_probe_ms, a.http_probes, b.avg_http_probe_ms, b.http_probes),
+        closed: a.closed + b.closed,
+        filtered: a.filtered + b.filtered,
+        failures: ConnectFailures {
+            refused: a.failures.refused + b.failures.refused,
+            timed_out: a.failures.timed_out + b.failures.timed_out,
+            host_unreachable: a.failures.host_unreachable + b.failures.host_unreachable,
+            network_unreachable: a.failures.network_unreachable + b.failures.network_unreachable,
+            other: a.failures.other + b.failures.other,
+        },
+        cancelled: a.cancelled || b.cancelled,
+        network_failure: a.network_failure || b.network_failure,
+        latency_histogram: match (&a.latency_histogram, &b.latency_histogram) {
+            (Some(a), Some(b)) => Some(a.merge(b)),
+            (a, b) => a.clone().or_else(|| b.clone()),
+        },
+        bytes_received: a.bytes_received + b.bytes_received,
+        cached_services: a.cached_services + b.cached_services,
+        timed_out: a.timed_out || b.timed_out,
+        ports_timed_out: a.ports_timed_out + b.ports_timed_out,
+    }
+}
+
+/// Merge reports per host, see `ScanReport::merge`.
+///
+/// # Arguments
+/// * `reports` - The reports of any number of hosts.
+///
+/// # Returns
+/// * One merged report per host, in the order the hosts first appear.
+///
+pub fn merge_reports(reports: &[ScanReport]) -> Vec<ScanReport> {
+    let mut merged: Vec<ScanReport> = Vec::new();
+    for (index, report) in reports.iter().enumerate() {
+        if merged.iter().any(|known| known.ip == report.ip) {
+            continue;
+        }
+        merged.push(report.merge(&reports[index + 1..]));
+    }
+    merged
+}
+
+/// Delay before retry number `attempt` (starting at 0), doubling the backoff every time.
+pub fn backoff_delay(options: &ScanOptions, attempt: u32) -> Duration {
+    options.retry_backoff.saturating_mul(1u32 << attempt.min(16))
+}
+
+/// Wait before retry number `attempt` (starting at 0), see `backoff_delay`.
+fn backoff(options: &ScanOptions, attempt: u32) {
+    let delay = backoff_delay(options, attempt);
+    if !delay.is_zero() {
+        std::thread::sleep(delay);
+    }
+}
+
+/// Record a failed TCP connect and decide whether it is retried, see `scan_port_with_options`.
+///
+/// Only transient failures (see `is_transient_error`) are retried, while the options and the
+/// scan's retry budget allow it. A port that isn't retried is settled as closed or filtered and
+/// dropped from the service cache.
+///
+/// # Arguments
+/// * `ip` - The target IP address.
+/// * `port` - The port number.
+/// * `error` - Why the connect failed.
+/// * `elapsed` - How long the connect attempt took.
+/// * `attempt` - The number of the attempt, starting at 0.
+/// * `options` - The scan options holding the retry settings.
+/// * `state` - The state shared across the scan (retry budget, resource errors).
+///
+/// # Returns
+/// * `true` - If the connect is retried after `backoff_delay`.
+/// * `false` - If the port is settled.
+///
+pub fn connect_failed(
+    ip: &IpAddr,
+    port: u16,
+    error: &std::io::Error,
+    elapsed: Duration,
+    attempt: u32,
+    options: &ScanOptions,
+    state: &ScanState,
+) -> bool {
+    if !is_resource_error(error) {
+        state.record_attempt(elapsed, false);
+    }
+    state.record_error(error);
+    if !is_transient_error(error) || attempt >= options.retries || !state.retries.try_take() {
+        state.record_failure(error);
+        // A local resource error says nothing about the port
+        if let Some(cache) = options.service_cache.as_ref().filter(|_| !is_resource_error(error)) {
+            cache.remove(*ip, Protocol::Tcp, port);
+        }
+        return false;
+    }
+    true
+}
+
+/// Scan a single TCP port on the given IP address.
+/// 
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `port` - The port number to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+///
+/// # Returns
+/// * `Some(ScanResult)` - The open port and an optional identified service name.
+/// * `None` - If the port is closed or unreachable.
+///
+pub fn scan_port(
+    ip: Arc<IpAddr>,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+) -> Option<ScanResult> {
+    scan_port_with_options(ip, port, signatures, &ScanOptions::default(), &ScanState::default())
+}
+
+/// A connection to a scanned port: made directly, or forwarded through the SSH jump host.
+///
+pub enum Connection {
+    Direct(TcpStream),
+    Tunnel(TunnelStream),
+}
+
+impl Connection {
+    /// Set how long a read may block, `None` for no limit.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            Connection::Direct(stream) => stream.set_read_timeout(timeout),
+            Connection::Tunnel(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Direct(stream) => stream.read(buf),
+            Connection::Tunnel(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Direct(stream) => stream.write(buf),
+            Connection::Tunnel(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Direct(stream) => stream.flush(),
+            Connection::Tunnel(stream) => stream.flush(),
+        }
+    }
+}
+
+/// The socket address of a port of the target, with the scope id of a link-local IPv6 target.
+///
+/// # Arguments
+/// * `ip` - The target IP address.
+/// * `port` - The port number.
+/// * `scope_id` - The interface index for IPv6 addresses (`ScanOptions::scope_id`), `0` for none.
+///   IPv4 addresses ignore it.
+///
+/// # Returns
+/// * The address to connect to.
+///
+pub fn target_addr(ip: IpAddr, port: u16, scope_id: u32) -> SocketAddr {
+    match ip {
+        IpAddr::V4(v4) => SocketAddr::new(IpAddr::V4(v4), port),
+        IpAddr::V6(v6) => SocketAddr::V6(SocketAddrV6::new(v6, port, 0, scope_id)),
+    }
+}
+
+/// Connect to a port the way the options ask for: through the SSH jump host, from the source
+/// port range, or directly.
+///
+/// # Arguments
+/// * `options` - The scan options holding the jump host and source port range.
+/// * `addr` - The address to connect to.
+/// * `timeout` - The connect timeout of a direct connect. Connects through the jump host wait at
+///   least `TUNNEL_CONNECT_TIMEOUT`.
+///
+/// # Returns
+/// * `Ok(Connection)` - The connection.
+/// * `Err(std::io::Error)` - If the connect failed.
+///
+pub fn connect_port(options: &ScanOptions, addr: SocketAddr, timeout: Duration) -> std::io::Result<Connection> {
+    if let Some(jump) = &options.ssh_jump {
+        let result = jump.open(addr, timeout.max(TUNNEL_CONNECT_TIMEOUT));
+        if let Err(e) = &result {
+            if e.kind() == ErrorKind::PermissionDenied {
+                SSH_FORWARDING_WARNING.call_once(|| {
+                    eprintln!("{}: {}", crate::localisator::get("warning_ssh_forwarding_denied"), e)
+                });
+            }
+        }
+        return result.map(Connection::Tunnel);
+    }
+    match &options.source_ports {
+        Some(range) => range.connect(addr, timeout),
+        None => TcpStream::connect_timeout(&addr, timeout),
     }
+    .map(Connection::Direct)
 }
 
-/// Scan a single port on the given IP address.
-/// 
+/// Scan a single TCP port, retrying failed connects as allowed by the options and the scan's retry budget.
+/// Only transient failures (see `is_transient_error`) are retried; a refused connect settles the port at once.
+///
 /// # Arguments
 /// * `ip` - An Arc containing the target IP address.
 /// * `port` - The port number to scan.
 /// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options holding the retry settings.
+/// * `state` - The state shared across the scan (retry budget, resource errors).
 ///
 /// # Returns
-/// * `Some((u16, Option<String>))` - A tuple containing the open port and an optional identified service name.
+/// * `Some(ScanResult)` - The open port and an optional identified service name.
 /// * `None` - If the port is closed or unreachable.
 ///
-pub fn scan_port(
+pub fn scan_port_with_options(
+    ip: Arc<IpAddr>,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    state: &ScanState,
+) -> Option<ScanResult> {
+    let addr = target_addr(*ip, port, options.scope_id);
+    let connect = || connect_port(options, addr, options.connect_timeout);
+    let mut attempt = 0;
+    let (stream, latency) = loop {
+        let started = Instant::now();
+        let error = match connect() {
+            Ok(stream) => {
+                let latency = started.elapsed();
+                state.record_attempt(latency, true);
+                break (stream, latency);
+            }
+            Err(e) => e,
+        };
+        if !connect_failed(&ip, port, &error, started.elapsed(), attempt, options, state) {
+            return None;
+        }
+        backoff(options, attempt);
+        attempt += 1;
+    };
+    Some(identify_open_port(ip, port, stream, latency, signatures, options, state))
+}
+
+/// Identify the service of a TCP port that accepted a connection, see `scan_port_with_options`.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `port` - The open port.
+/// * `stream` - The connection the port accepted, used for the first probe.
+/// * `latency` - How long the connect took.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options holding the probe settings.
+/// * `state` - The state shared across the scan.
+///
+/// # Returns
+/// * The open port and an optional identified service name.
+///
+pub fn identify_open_port(
     ip: Arc<IpAddr>,
     port: u16,
+    stream: Connection,
+    latency: Duration,
     signatures: Arc<Vec<Signature>>,
-) -> Option<(u16, Option<String>)> {
-    let addr = std::net::SocketAddr::new(*ip, port);
-    if TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok() {
-        let url = format!("http://{}:{}", ip, port);
-        let client = Client::builder()
-            .timeout(Duration::from_secs(1))
-            .build();
-        if let Ok(client) = client {
-            if let Ok(resp) = client.get(&url).header(USER_AGENT, "port-explorer").send() {
-                if let Ok(text) = resp.text() {
-                    let service = identify_service(&text, &signatures);
-                    return Some((port, service));
+    options: &ScanOptions,
+    state: &ScanState,
+) -> ScanResult {
+    let addr = target_addr(*ip, port, options.scope_id);
+    let connect = || connect_port(options, addr, options.connect_timeout);
+    state.record_connect(latency);
+    // The port stayed open since it was last probed, so its service is known
+    if let Some(cached) = options.service_cache.as_ref().and_then(|cache| cache.get(*ip, Protocol::Tcp, port)) {
+        state.record_cached_service();
+        return ScanResult { latency: Some(latency), ..cached };
+    }
+    // The port state is settled; the probe only identifies the service
+    let banner_wanted = banner_bytes_wanted(&signatures);
+    let identified = match &options.probe {
+        Probe::Http if options.http_probe_ports.probes(port) => {
+            // The HTTP client can't connect through the jump host, and URLs can't carry the zone
+            // of a link-local address, so speak HTTP on the connection itself
+            let raw = matches!(stream, Connection::Tunnel(_))
+                || matches!(addr, SocketAddr::V6(v6) if v6.scope_id() != 0);
+            let mut stream = Some(stream);
+            let mut responded = false;
+            let mut found = None;
+            let identify = |response: &HttpResponse, tls: bool| {
+                match_http_response(response, port, &signatures).map(|found| {
+                    let source = match (found.signature.header.is_some(), tls) {
+                        (true, false) => IdentificationSource::HttpHeader,
+                        (false, false) => IdentificationSource::HttpBody,
+                        (true, true) => IdentificationSource::HttpsHeader,
+                        (false, true) => IdentificationSource::HttpsBody,
+                    };
+                    (found, source)
+                })
+            };
+            // Paths are tried in order until a signature matches
+            for path in &options.probe_paths {
+                let response = if raw {
+                    stream
+                        .take()
+                        .or_else(|| connect().map_err(|e| state.record_error(&e)).ok())
+                        .and_then(|stream| probe_http_raw(stream, addr, path, options.banner_max_bytes, state))
+                } else {
+                    drop(stream.take());
+                    match HTTP_POOL.as_ref() {
+                        Ok(pool) => probe_http(pool, &format!("http://{}", addr), path, state),
+                        Err(e) => {
+                            HTTP_CLIENT_WARNING.call_once(|| {
+                                eprintln!("{}: {}", crate::localisator::get("warning_http_client"), e)
+                            });
+                            None
+                        }
+                    }
+                };
+                let Some(response) = response else {
+                    continue;
+                };
+                responded = true;
+                found = identify(&response, false);
+                if found.is_some() {
+                    break;
                 }
             }
+            // A TLS-only service (e.g. on 443) doesn't answer plaintext HTTP, so try HTTPS
+            let pool = if options.insecure_tls { &INSECURE_HTTP_POOL } else { &HTTP_POOL };
+            if let (false, false, Ok(pool)) = (responded, raw, pool.as_ref()) {
+                for path in &options.probe_paths {
+                    // Nothing that failed the handshake will answer another path
+                    let Some(response) = probe_http(pool, &format!("https://{}", addr), path, state) else {
+                        break;
+                    };
+                    responded = true;
+                    found = identify(&response, true);
+                    if found.is_some() {
+                        break;
+                    }
+                }
+            }
+            match found {
+                Some(found) => Some(found),
+                None if responded => None,
+                // Not an HTTP service, so match whatever it sends on its own
+                None => connect()
+                    .map_err(|e| state.record_error(&e))
+                    .ok()
+                    .and_then(|stream| read_banner(stream, banner_wanted, options.banner_max_bytes, state))
+                    .and_then(|banner| match_banner(&banner, port, &signatures))
+                    .map(|found| (found, IdentificationSource::Banner)),
+            }
+        }
+        // Ports excluded from the HTTP probe are identified by what they send by themselves
+        Probe::Http => read_banner(stream, banner_wanted, options.banner_max_bytes, state)
+            .and_then(|banner| match_banner(&banner, port, &signatures))
+            .map(|found| (found, IdentificationSource::Banner)),
+        Probe::Tcp(payload) => {
+            let mut stream = stream;
+            (payload.is_empty() || stream.write_all(payload).is_ok())
+                .then(|| read_banner(stream, banner_wanted, options.banner_max_bytes, state))
+                .flatten()
+                .and_then(|banner| match_banner(&banner, port, &signatures))
+                .map(|found| (found, IdentificationSource::Banner))
+        }
+    };
+    let cert = options
+        .tls_ports
+        .binary_search(&port)
+        .is_ok()
+        .then(|| connect().map_err(|e| state.record_error(&e)).ok())
+        .flatten()
+        .and_then(|mut stream| {
+            stream.set_read_timeout(Some(HTTP_TIMEOUT)).ok()?;
+            probe_certificate(stream, options.tls_server_name.as_deref(), options.tls_expiry_warning_days)
+        });
+    // A service the probes couldn't identify may still be named by its certificate
+    let identified = identified.or_else(|| {
+        cert.as_ref()
+            .and_then(|cert| match_banner(&cert.identity_text(), port, &signatures))
+            .map(|found| (found, IdentificationSource::TlsCert))
+    });
+    let (service, version, identified_by, signature_file) = match identified {
+        Some((found, source)) => (
+            Some(reported(render_service_name(found.signature.name.clone(), &ip, port), options)),
+            found.version.map(|version| reported(version, options)),
+            source,
+            found.signature.source.clone().map(|file| reported(file, options)),
+        ),
+        None => (None, None, IdentificationSource::None, None),
+    };
+    let tls = cert.map(|cert| reported_certificate(cert, options));
+    let result = ScanResult {
+        port,
+        service,
+        version,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: Some(latency),
+        identified_by,
+        signature_file,
+        tls,
+    };
+    if let Some(cache) = &options.service_cache {
+        cache.insert(*ip, &result);
+    }
+    result
+}
+
+/// Send an HTTP probe over the shared pool.
+///
+/// # Arguments
+/// * `pool` - The shared HTTP client and its idle connections.
+/// * `url` - The origin to probe (e.g., `http://10.0.0.1:80`).
+/// * `path` - The path requested from the origin (e.g., `/` or `/server-status`).
+/// * `state` - The scan state the probe is recorded in.
+///
+/// # Returns
+/// * `Some(HttpResponse)` - The response of the service.
+/// * `None` - If the port didn't answer with an HTTP response.
+///
+fn probe_http(pool: &HttpProbePool, url: &str, path: &str, state: &ScanState) -> Option<HttpResponse> {
+    let started = Instant::now();
+    // Taking the origin out marks its idle connection as in use until the response is read
+    let reused = pool
+        .idle
+        .lock()
+        .ok()
+        .and_then(|mut idle| idle.remove(url))
+        .is_some_and(|since| since.elapsed() < HTTP_POOL_IDLE_TIMEOUT);
+    let probe = || {
+        let resp = pool.client.get(format!("{}{}", url, path)).header(USER_AGENT, "port-explorer").send().ok()?;
+        let status = resp.status().as_u16();
+        let keep_alive = resp.version() >= reqwest::Version::HTTP_11
+            && !resp
+                .headers()
+                .get(reqwest::header::CONNECTION)
+                .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"close"));
+        let headers = resp
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+            })
+            .collect();
+        let body = resp.text().ok()?;
+        Some((HttpResponse { status, headers, body }, keep_alive))
+    };
+    let response = probe();
+    state.record_http_probe(started.elapsed(), reused);
+    let (response, keep_alive) = response?;
+    // reqwest hands out the decoded response only, so count each header as "name: value\r\n"
+    let header_bytes: usize = response.headers.iter().map(|(name, value)| name.len() + value.len() + 4).sum();
+    state.record_bytes(header_bytes + response.body.len());
+    if keep_alive {
+        if let Ok(mut idle) = pool.idle.lock() {
+            idle.insert(url.to_string(), Instant::now());
         }
-        Some((port, None))
+    }
+    Some(response)
+}
+
+/// Send an HTTP probe on an open connection, for connections the HTTP client can't make itself
+/// (through the jump host). The request asks the service to close the connection after the
+/// response, which is read like a banner of up to `max_bytes` bytes.
+///
+/// # Arguments
+/// * `stream` - The connection to the port.
+/// * `addr` - The address of the port, sent as the `Host` header.
+/// * `path` - The path to request.
+/// * `max_bytes` - The most bytes of the response to read.
+/// * `state` - The scan state the probe is recorded in.
+///
+/// # Returns
+/// * `Some(HttpResponse)` - The response of the service.
+/// * `None` - If the port didn't answer with an HTTP response.
+///
+fn probe_http_raw(
+    mut stream: Connection,
+    addr: SocketAddr,
+    path: &str,
+    max_bytes: usize,
+    state: &ScanState,
+) -> Option<HttpResponse> {
+    let started = Instant::now();
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: port-explorer\r\nConnection: close\r\n\r\n",
+        path, addr
+    );
+    let response = stream
+        .write_all(request.as_bytes())
+        .ok()
+        .and_then(|()| read_banner(stream, max_bytes, max_bytes, state))
+        .and_then(|raw| HttpResponse::parse(&raw));
+    state.record_http_probe(started.elapsed(), false);
+    response
+}
+
+/// Sanitize a string reported for a port unless `options.raw_banners` is set.
+///
+/// Service names and signature file labels come from signature files, which may be third-party
+/// bundles, so they are treated as untrusted like the banners they are matched against.
+///
+/// # Arguments
+/// * `text` - The string to report.
+/// * `options` - The scan options.
+///
+/// # Returns
+/// * The string, sanitized with `output::sanitize_text` unless raw banners are requested.
+///
+fn reported(text: String, options: &ScanOptions) -> String {
+    if options.raw_banners {
+        text
     } else {
-        None
+        crate::output::sanitize_text(&text)
     }
 }
 
-/// Scan multiple ports in parallel using a thread pool.\
-/// 
+/// Sanitize the names of a reported certificate like `reported`, as they come from the server.
+fn reported_certificate(cert: TlsCertificate, options: &ScanOptions) -> TlsCertificate {
+    TlsCertificate {
+        subject: reported(cert.subject, options),
+        issuer: reported(cert.issuer, options),
+        sans: cert.sans.into_iter().map(|san| reported(san, options)).collect(),
+        ..cert
+    }
+}
+
+/// Read the banner a service sends on its own after a connect.
+///
+/// Reading stops once `wanted` bytes were received, the service closes the connection,
+/// or `BANNER_TIMEOUT` has passed since the first read. Never more than `max_bytes` bytes are
+/// read, even if the service keeps sending.
+///
+/// # Arguments
+/// * `stream` - The connected stream.
+/// * `wanted` - The number of bytes the signatures need (see `banner_bytes_wanted`).
+/// * `max_bytes` - The most bytes to read (`banner_max_bytes`).
+/// * `state` - The scan state the bytes read are recorded in.
+///
+/// # Returns
+/// * `Some(String)` - The banner of at most `max_bytes` bytes, lossily decoded.
+/// * `None` - If the service sent nothing.
+///
+fn read_banner(mut stream: Connection, wanted: usize, max_bytes: usize, state: &ScanState) -> Option<String> {
+    let wanted = wanted.min(max_bytes);
+    let deadline = Instant::now() + BANNER_TIMEOUT;
+    let mut banner = Vec::new();
+    let mut buf = [0u8; 4096];
+    while banner.len() < wanted {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        let limit = buf.len().min(max_bytes - banner.len());
+        match stream.read(&mut buf[..limit]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => banner.extend_from_slice(&buf[..n]),
+        }
+    }
+    state.record_bytes(banner.len());
+    (!banner.is_empty()).then(|| String::from_utf8_lossy(&banner).into_owned())
+}
+
+/// Scan a single UDP port on the given IP address.
+///
+/// An empty datagram is sent to the port. A reply marks the port open and is matched
+/// against the signatures, an ICMP port unreachable (reported as a refused connection)
+/// marks it closed, and silence until the timeout marks it open or filtered.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `port` - The port number to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+///
+/// # Returns
+/// * `Some(ScanResult)` - The open or possibly filtered port and an optional identified service name.
+/// * `None` - If the port is closed or the probe could not be sent.
+///
+pub fn scan_udp_port(
+    ip: Arc<IpAddr>,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+) -> Option<ScanResult> {
+    scan_udp_port_with_options(ip, port, signatures, &ScanOptions::default(), &ScanState::default())
+}
+
+/// Scan a single UDP port, resending unanswered probes as allowed by the options and the scan's retry budget.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `port` - The port number to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options holding the retry settings.
+/// * `state` - The state shared across the scan (retry budget, resource errors).
+///
+/// # Returns
+/// * `Some(ScanResult)` - The open or possibly filtered port and an optional identified service name.
+/// * `None` - If the port is closed or the probe could not be sent.
+///
+pub fn scan_udp_port_with_options(
+    ip: Arc<IpAddr>,
+    port: u16,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    state: &ScanState,
+) -> Option<ScanResult> {
+    let bind_addr = match *ip {
+        IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    let socket = UdpSocket::bind(bind_addr)
+        .map_err(|e| state.record_error(&e))
+        .ok()?;
+    socket.connect(target_addr(*ip, port, options.scope_id)).ok()?;
+    socket.set_read_timeout(Some(UDP_TIMEOUT)).ok()?;
+    let mut buf = [0u8; 4096];
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        socket.send(&[]).ok()?;
+        match socket.recv(&mut buf) {
+            Ok(n) => {
+                let latency = started.elapsed();
+                state.record_bytes(n);
+                let response = String::from_utf8_lossy(&buf[..n]);
+                let matched = match_banner(&response, port, &signatures);
+                return Some(ScanResult {
+                    port,
+                    service: matched
+                        .as_ref()
+                        .map(|found| reported(render_service_name(found.signature.name.clone(), &ip, port), options)),
+                    version: matched.as_ref().and_then(|found| found.version.clone()).map(|version| reported(version, options)),
+                    identified_by: match matched {
+                        Some(_) => IdentificationSource::Banner,
+                        None => IdentificationSource::None,
+                    },
+                    signature_file: matched
+                        .and_then(|found| found.signature.source.clone())
+                        .map(|file| reported(file, options)),
+                    tls: None,
+                    protocol: Protocol::Udp,
+                    state: PortState::Open,
+                    latency: Some(latency),
+                });
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if attempt >= options.retries || !state.retries.try_take() {
+                    return Some(ScanResult {
+                        port,
+                        service: None,
+                        version: None,
+                        protocol: Protocol::Udp,
+                        state: PortState::OpenFiltered,
+                        latency: None,
+                        identified_by: IdentificationSource::None,
+                        signature_file: None,
+                        tls: None,
+                    });
+                }
+                backoff(options, attempt);
+                attempt += 1;
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Scan multiple TCP ports in parallel using a thread pool.
+///
 /// # Arguments
 /// * `ip` - An Arc containing the target IP address.
 /// * `ports` - A vector of port numbers to scan.
@@ -83,7 +2109,7 @@ pub fn scan_port(
 /// * `pb` - A reference to a ProgressBar to update progress.
 ///
 /// # Returns
-/// * `Ok(Vec<(u16, Option<String>)>)` - A vector of tuples containing open ports and their identified services.
+/// * `Ok(Vec<ScanResult>)` - The open ports and their identified services, sorted by port.
 /// * `Err(ScanError)` - If there was an error during scanning.
 ///
 pub fn scan_ports_parallel(
@@ -92,24 +2118,526 @@ pub fn scan_ports_parallel(
     signatures: Arc<Vec<Signature>>,
     max_threads: usize,
     pb: &ProgressBar,
-) -> Result<Vec<(u16, Option<String>)>, ScanError> {
-    let pool = ThreadPool::new(max_threads);
-    let open_ports = Arc::new(std::sync::Mutex::new(Vec::new()));
+) -> Result<Vec<ScanResult>, ScanError> {
+    scan_ports_parallel_with_callback(ip, ports, signatures, max_threads, pb, |_| {})
+}
+
+/// Scan multiple TCP ports in parallel, invoking a callback for every open port as it is discovered.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `ports` - A vector of port numbers to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `max_threads` - The maximum number of threads to use for scanning.
+/// * `pb` - A reference to a ProgressBar to update progress.
+/// * `on_result` - A callback receiving each open port as it is found.
+///
+/// # Returns
+/// * `Ok(Vec<ScanResult>)` - The open ports and their identified services, sorted by port.
+/// * `Err(ScanError)` - If there was an error during scanning.
+///
+pub fn scan_ports_parallel_with_callback<F>(
+    ip: Arc<IpAddr>,
+    ports: Vec<u16>,
+    signatures: Arc<Vec<Signature>>,
+    max_threads: usize,
+    pb: &ProgressBar,
+    on_result: F,
+) -> Result<Vec<ScanResult>, ScanError>
+where
+    F: FnMut(&ScanResult),
+{
+    let work = ports.into_iter().map(|port| (Protocol::Tcp, port));
+    let options = ScanOptions {
+        max_threads,
+        ..ScanOptions::default()
+    };
+    scan_work_parallel(ip, work, signatures, &options, pb, on_result).map(|(result, _)| result)
+}
+
+/// Scan multiple TCP ports in parallel, handing every open port to a result sink as it is discovered.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `ports` - A vector of port numbers to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `max_threads` - The maximum number of threads to use for scanning.
+/// * `pb` - A reference to a ProgressBar to update progress.
+/// * `sink` - The sink receiving each open port as it is found, e.g. `sinks::Sinks`.
+///
+/// # Returns
+/// * `Ok(Vec<ScanResult>)` - The open ports and their identified services, sorted by port.
+/// * `Err(ScanError)` - If there was an error during scanning.
+///
+pub fn scan_ports_parallel_into<S>(
+    ip: Arc<IpAddr>,
+    ports: Vec<u16>,
+    signatures: Arc<Vec<Signature>>,
+    max_threads: usize,
+    pb: &ProgressBar,
+    sink: &S,
+) -> Result<Vec<ScanResult>, ScanError>
+where
+    S: crate::sinks::ResultSink + ?Sized,
+{
+    scan_ports_parallel_with_callback(ip, ports, signatures, max_threads, pb, |res| sink.on_result(res))
+}
+
+/// Check that the work of a scan can be done with the options, before any port is scanned.
+fn check_work(mut work: impl Iterator<Item = (Protocol, u16)>, options: &ScanOptions) -> Result<(), ScanError> {
+    if options.ssh_jump.is_some() && work.any(|(protocol, _)| protocol == Protocol::Udp) {
+        return Err(ScanError::Config(crate::localisator::get("error_ssh_jump_udp")));
+    }
+    if options.probe == Probe::Http && options.ssh_jump.is_none() {
+        check_http_client()?;
+    }
+    Ok(())
+}
+
+/// Queue jobs for work units until the pool holds `QUEUED_PER_THREAD` waiting jobs per thread.
+///
+/// Work is only pulled from the iterator when the pool runs low, so the memory of a scan stays
+/// bounded however many `(host, port)` units it has. Collectors call this again whenever a job
+/// completes.
+///
+/// # Arguments
+/// * `pool` - The thread pool of the scan.
+/// * `work` - The remaining work units.
+/// * `job` - Creates the job scanning a work unit.
+///
+/// # Returns
+/// * `true` - If work is left.
+/// * `false` - If every work unit has been queued.
+///
+fn queue_work<T, J>(pool: &ThreadPool, work: &mut impl Iterator<Item = T>, mut job: impl FnMut(T) -> J) -> bool
+where
+    J: FnOnce() + Send + 'static,
+{
+    while pool.queued_count() < pool.max_count().saturating_mul(QUEUED_PER_THREAD) {
+        match work.next() {
+            Some(unit) => pool.execute(job(unit)),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Scan protocol-tagged work units in parallel, invoking a callback for every open port as it is discovered.
+///
+/// TCP and UDP work units share the same thread pool. Work units are pulled from `work` only as
+/// the pool needs them (see `queue_work`), so a lazily generated work list is never held in
+/// memory as a whole. Each worker sends its result over a channel and a single collector drains
+/// it, so open ports are gathered without locking a shared vector. The callback runs on the
+/// collector (calling) thread, in discovery order. With `auto_tune` the collector also samples
+/// throughput and resizes the pool, and with `ramp_up` it raises the pool size gradually at the
+/// start, which caps the auto-tuner too. With `Engine::Async` the work is handed to
+/// `async_engine::scan_work_async` instead.
+///
+/// # Arguments
+/// * `ip` - An Arc containing the target IP address.
+/// * `work` - The `(protocol, port)` work units to scan, e.g. a vector or a lazy iterator. It is
+///   iterated once up front to check the work against the options.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options (thread count, retries).
+/// * `pb` - A reference to a ProgressBar to update progress.
+/// * `on_result` - A callback receiving each open port as it is found.
+///
+/// # Returns
+/// * `Ok((Vec<ScanResult>, ScanStats))` - The open ports sorted by port then protocol, and the scan statistics.
+///   If the options' cancel token is triggered, the open ports found so far are returned within
+///   `AUTO_TUNE_TICK` and `stats.cancelled` is set. If `network_failure_threshold` ports in a row
+///   fail with a network-level error, the remaining ports are skipped and `stats.network_failure`
+///   is set.
+/// * `Err(ScanError)` - If there was an error during scanning.
+///
+pub fn scan_work_parallel<W, F>(
+    ip: Arc<IpAddr>,
+    work: W,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+    on_result: F,
+) -> Result<(Vec<ScanResult>, ScanStats), ScanError>
+where
+    W: IntoIterator<Item = (Protocol, u16)>,
+    W::IntoIter: Clone,
+    F: FnMut(&ScanResult),
+{
+    let work = work.into_iter();
+    check_work(work.clone(), options)?;
+    if options.engine == Engine::Async {
+        return crate::async_engine::scan_work_async(ip, work, signatures, options, pb, on_result);
+    }
+    let mut pool = ThreadPool::new(initial_threads(options));
+    scan_work_in_pool(&mut pool, ip, work, signatures, options, pb, on_result)
+}
+
+/// The thread count a scan starts with: the first step of `auto_tune`, capped by `ramp_up`.
+fn initial_threads(options: &ScanOptions) -> usize {
+    let threads = if options.auto_tune { AutoTuner::new(options.max_threads).threads() } else { options.max_threads };
+    threads.min(ramp_threads(options.max_threads, Duration::ZERO, options.ramp_up))
+}
+
+/// Scan work units already checked against the options on an existing thread pool, see
+/// `scan_work_parallel`. The pool is resized to `initial_threads` first, so it can be shared by
+/// the scans of several hosts.
+///
+/// # Arguments
+/// * `pool` - The thread pool running the scan.
+/// * `ip` - An Arc containing the target IP address.
+/// * `work` - The `(protocol, port)` work units to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options (thread count, retries).
+/// * `pb` - A reference to a ProgressBar to update progress.
+/// * `on_result` - A callback receiving each open port as it is found.
+///
+/// # Returns
+/// * `Ok((Vec<ScanResult>, ScanStats))` - As with `scan_work_parallel`.
+/// * `Err(ScanError)` - If there was an error during scanning.
+///
+fn scan_work_in_pool<F>(
+    pool: &mut ThreadPool,
+    ip: Arc<IpAddr>,
+    mut work: impl Iterator<Item = (Protocol, u16)>,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+    mut on_result: F,
+) -> Result<(Vec<ScanResult>, ScanStats), ScanError>
+where
+    F: FnMut(&ScanResult),
+{
+    let started = Instant::now();
+    let ramp = |now: Instant| ramp_threads(options.max_threads, now - started, options.ramp_up);
+    let mut tuner = options.auto_tune.then(|| AutoTuner::new(options.max_threads));
+    if pool.max_count() != initial_threads(options) {
+        pool.set_num_threads(initial_threads(options));
+    }
+    let (tx, rx) = mpsc::channel();
     let progress = Arc::new(pb.clone());
-    for port in ports {
-        let ip = Arc::clone(&ip);
-        let signatures = Arc::clone(&signatures);
-        let open_ports = Arc::clone(&open_ports);
-        let progress = Arc::clone(&progress);
+    let shared_options = Arc::new(options.clone());
+    let state = Arc::new(ScanState::new(options.max_retries_total));
+    let completed = Arc::new(AtomicU64::new(0));
+    let found_open = Arc::new(AtomicBool::new(false));
+    let network_failure = Arc::new(AtomicBool::new(false));
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let ports_timed_out = Arc::new(AtomicU64::new(0));
+    let cancelled = || options.cancel.as_ref().is_some_and(CancelToken::is_cancelled);
+    let source_ports_exhausted = || options.source_ports.as_ref().map_or(0, |range| range.exhausted());
+    // The range counter is shared by every clone of the options, so only count this scan
+    let exhausted_before = source_ports_exhausted();
+    // Every worker reports back, even without a result, so the collector can queue more work.
+    // The sender is dropped once all work is queued, so the collector stops once every worker is done.
+    let mut sender = Some(tx);
+    let mut result = Vec::new();
+    let mut next_tick = Instant::now() + AUTO_TUNE_TICK;
+    loop {
+        if let Some(tx) = &sender {
+            let more = queue_work(pool, &mut work, |(protocol, port)| {
+                let ip = Arc::clone(&ip);
+                let signatures = Arc::clone(&signatures);
+                let tx = tx.clone();
+                let progress = Arc::clone(&progress);
+                let options = Arc::clone(&shared_options);
+                let state = Arc::clone(&state);
+                let completed = Arc::clone(&completed);
+                let found_open = Arc::clone(&found_open);
+                let network_failure = Arc::clone(&network_failure);
+                let timed_out = Arc::clone(&timed_out);
+                let ports_timed_out = Arc::clone(&ports_timed_out);
+                move || {
+                    // Ports still queued after the first open one are skipped in first-open-only mode
+                    let res = match protocol {
+                        _ if options.first_open_only && found_open.load(Ordering::SeqCst) => None,
+                        _ if options.cancel.as_ref().is_some_and(CancelToken::is_cancelled) => None,
+                        _ if network_failure.load(Ordering::SeqCst) => None,
+                        _ if timed_out.load(Ordering::SeqCst) => {
+                            ports_timed_out.fetch_add(1, Ordering::SeqCst);
+                            None
+                        }
+                        Protocol::Tcp => scan_port_with_options(ip, port, signatures, &options, &state),
+                        Protocol::Udp => {
+                            scan_udp_port_with_options(ip, port, signatures, &options, &state)
+                        }
+                    };
+                    if res.as_ref().is_some_and(|res| res.state == PortState::Open) {
+                        found_open.store(true, Ordering::SeqCst);
+                    }
+                    let threshold = options.network_failure_threshold;
+                    if threshold > 0 && state.network_errors_in_row() >= threshold {
+                        network_failure.store(true, Ordering::SeqCst);
+                    }
+                    completed.fetch_add(1, Ordering::SeqCst);
+                    progress.inc(1);
+                    let _ = tx.send(res);
+                }
+            });
+            if !more {
+                sender = None;
+            }
+        }
+        match rx.recv_timeout(AUTO_TUNE_TICK) {
+            // Ports in flight may still turn up open; only the first one is kept
+            Ok(Some(res)) if options.first_open_only && (res.state != PortState::Open || !result.is_empty()) => {}
+            Ok(Some(res)) => {
+                on_result(&res);
+                result.push(res);
+            }
+            Ok(None) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        // Nothing more is queued; the ports already queued are skipped by the workers
+        if network_failure.load(Ordering::SeqCst) {
+            sender = None;
+        }
+        if options.target_timeout.is_some_and(|timeout| started.elapsed() >= timeout) && sender.is_some() {
+            timed_out.store(true, Ordering::SeqCst);
+            sender = None;
+            ports_timed_out.fetch_add(work.by_ref().count() as u64, Ordering::SeqCst);
+        }
+        if cancelled() {
+            break;
+        }
+        let now = Instant::now();
+        if now >= next_tick && (tuner.is_some() || !options.ramp_up.is_zero()) {
+            next_tick = now + AUTO_TUNE_TICK;
+            let threads = match tuner.as_mut() {
+                Some(tuner) => tuner.observe(
+                    now,
+                    completed.load(Ordering::SeqCst),
+                    state.resource_errors(),
+                    pool.queued_count() > 0,
+                ),
+                None => options.max_threads,
+            };
+            let threads = threads.min(ramp(now));
+            if threads != pool.max_count() {
+                pool.set_num_threads(threads);
+            }
+        }
+    }
+    let cancelled = cancelled();
+    // A cancelled scan returns right away; ports in flight finish in the background unreported
+    if !cancelled {
+        pool.join();
+    }
+    result.sort_by_key(|r| (r.port, r.protocol));
+    let stats = ScanStats {
+        source_ports_exhausted: source_ports_exhausted() - exhausted_before,
+        cancelled,
+        network_failure: network_failure.load(Ordering::SeqCst),
+        timed_out: timed_out.load(Ordering::SeqCst),
+        ports_timed_out: ports_timed_out.load(Ordering::SeqCst),
+        ..ScanStats::from_state(&state, options, pool.max_count())
+    };
+    Ok((result, stats))
+}
+
+/// Scan the same ports on several hosts, e.g. every host of a network, one host after another.
+///
+/// Each host is scanned like with `scan_work_parallel`, so the thread count, retries and probes of
+/// `options` apply per host, but the thread engine keeps one pool for all hosts instead of
+/// starting its threads anew for each. The progress bar counts the ports of all hosts; give it a
+/// length of `hosts.len() * work.len()` for one bar across the whole run.
+///
+/// # Arguments
+/// * `hosts` - The hosts to scan.
+/// * `work` - The protocol-tagged ports scanned on every host.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options.
+/// * `pb` - A reference to a ProgressBar counting scanned ports across all hosts.
+/// * `on_result` - Called with the host and every open port as soon as it is found.
+/// * `on_host` - Called with every host, its open ports and its statistics once its scan is done,
+///   before the next host is started.
+///
+/// # Returns
+/// * `Ok(HashMap<IpAddr, Vec<ScanResult>>)` - The open ports of every scanned host, sorted by
+///   port then protocol; a host without open ports maps to an empty list. If the options' cancel
+///   token is triggered, the hosts not started yet are left out.
+/// * `Err(ScanError)` - If the work can't be scanned with the options.
+///
+pub fn scan_hosts_parallel<F, H>(
+    hosts: &[IpAddr],
+    work: &[(Protocol, u16)],
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+    mut on_result: F,
+    mut on_host: H,
+) -> Result<HashMap<IpAddr, Vec<ScanResult>>, ScanError>
+where
+    F: FnMut(&IpAddr, &ScanResult),
+    H: FnMut(&IpAddr, &[ScanResult], ScanStats),
+{
+    check_work(work.iter().copied(), options)?;
+    let mut pool = (options.engine == Engine::Threads).then(|| ThreadPool::new(initial_threads(options)));
+    let mut results = HashMap::with_capacity(hosts.len());
+    for host in hosts {
+        if options.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            break;
+        }
+        let (ip, signatures) = (Arc::new(*host), Arc::clone(&signatures));
+        let on_open = |res: &ScanResult| on_result(host, res);
+        let (open, stats) = match pool.as_mut() {
+            Some(pool) => scan_work_in_pool(pool, ip, work.iter().copied(), signatures, options, pb, on_open)?,
+            None => crate::async_engine::scan_work_async(ip, work.iter().copied(), signatures, options, pb, on_open)?,
+        };
+        on_host(host, &open, stats);
+        results.insert(*host, open);
+    }
+    Ok(results)
+}
+
+/// Find one open port on each of several hosts, sharing one thread pool across all hosts.
+///
+/// Every host is scanned until its first open port is found; its remaining ports are then
+/// skipped while the pool moves on to the other hosts. Ports that are only open|filtered (UDP
+/// without a reply) don't count as open. The `(host, port)` work units are generated lazily and
+/// queued only as the pool needs them (see `queue_work`), so memory stays bounded however large
+/// the product of hosts and ports is.
+///
+/// # Arguments
+/// * `hosts` - The hosts to scan.
+/// * `work` - The protocol-tagged ports scanned on every host, in scan order.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options (thread count, retries, probe, cancel token).
+/// * `pb` - A reference to a ProgressBar counting scanned and skipped ports.
+///
+/// # Returns
+/// * `Ok(Vec<(IpAddr, ScanResult)>)` - The responsive hosts with their first open port, in the
+///   order the hosts were given. If the options' cancel token is triggered, the hosts found so
+///   far are returned within `AUTO_TUNE_TICK`.
+/// * `Err(ScanError)` - If the work can't be scanned with the options.
+///
+pub fn find_first_open(
+    hosts: Vec<IpAddr>,
+    work: &[(Protocol, u16)],
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+) -> Result<Vec<(IpAddr, ScanResult)>, ScanError> {
+    check_work(work.iter().copied(), options)?;
+    let pool = ThreadPool::new(options.max_threads.max(1));
+    let (tx, rx) = mpsc::channel();
+    let shared_options = Arc::new(options.clone());
+    let state = Arc::new(ScanState::new(options.max_retries_total));
+    let found_open: Arc<Vec<AtomicBool>> = Arc::new(hosts.iter().map(|_| AtomicBool::new(false)).collect());
+    // When the first port of each host was scanned, for its `target_timeout`
+    let host_started: Arc<Vec<OnceLock<Instant>>> = Arc::new(hosts.iter().map(|_| OnceLock::new()).collect());
+    let cancelled = || options.cancel.as_ref().is_some_and(CancelToken::is_cancelled);
+    let mut units = hosts
+        .iter()
+        .enumerate()
+        .flat_map(|(index, host)| work.iter().map(move |&(protocol, port)| (index, *host, protocol, port)));
+    let mut sender = Some(tx);
+    let mut first: Vec<Option<ScanResult>> = vec![None; hosts.len()];
+    loop {
+        if let Some(tx) = &sender {
+            let more = queue_work(&pool, &mut units, |(index, host, protocol, port)| {
+                let ip = Arc::new(host);
+                let signatures = Arc::clone(&signatures);
+                let tx = tx.clone();
+                let progress = pb.clone();
+                let options = Arc::clone(&shared_options);
+                let state = Arc::clone(&state);
+                let found_open = Arc::clone(&found_open);
+                let host_started = Arc::clone(&host_started);
+                move || {
+                    let mut found = None;
+                    let started = host_started[index].get_or_init(Instant::now);
+                    let timed_out = options.target_timeout.is_some_and(|timeout| started.elapsed() >= timeout);
+                    if !found_open[index].load(Ordering::SeqCst)
+                        && !timed_out
+                        && !options.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+                    {
+                        let res = match protocol {
+                            Protocol::Tcp => scan_port_with_options(ip, port, signatures, &options, &state),
+                            Protocol::Udp => scan_udp_port_with_options(ip, port, signatures, &options, &state),
+                        };
+                        if let Some(res) = res.filter(|res| res.state == PortState::Open) {
+                            found_open[index].store(true, Ordering::SeqCst);
+                            found = Some((index, res));
+                        }
+                    }
+                    progress.inc(1);
+                    let _ = tx.send(found);
+                }
+            });
+            if !more {
+                sender = None;
+            }
+        }
+        match rx.recv_timeout(AUTO_TUNE_TICK) {
+            Ok(Some((index, res))) => {
+                first[index].get_or_insert(res);
+            }
+            Ok(None) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if cancelled() {
+            break;
+        }
+    }
+    // A cancelled sweep returns right away; ports in flight finish in the background unreported
+    if !cancelled() {
+        pool.join();
+    }
+    Ok(hosts
+        .into_iter()
+        .zip(first)
+        .filter_map(|(host, res)| res.map(|res| (host, res)))
+        .collect())
+}
+
+/// Sweep hosts for signs of life before a full port scan.
+///
+/// Every host gets a TCP connect to each discovery port until one answers. A refused
+/// connection counts as an answer too, since only a live host sends the reset. Hosts are
+/// probed in parallel with the scan's thread count and source port range.
+///
+/// # Arguments
+/// * `hosts` - The hosts to sweep.
+/// * `ports` - The ports probed on every host (e.g., `[80, 443]`).
+/// * `options` - The scan options (thread count, source ports).
+/// * `pb` - A reference to a ProgressBar counting swept hosts.
+///
+/// # Returns
+/// * The live hosts, in the order they were given.
+///
+pub fn discover_hosts(
+    hosts: Vec<IpAddr>,
+    ports: &[u16],
+    options: &ScanOptions,
+    pb: &ProgressBar,
+) -> Vec<IpAddr> {
+    let pool = ThreadPool::new(options.max_threads.max(1));
+    let (tx, rx) = mpsc::channel();
+    let ports: Arc<Vec<u16>> = Arc::new(ports.to_vec());
+    let shared_options = Arc::new(options.clone());
+    for (index, host) in hosts.into_iter().enumerate() {
+        let tx = tx.clone();
+        let ports = Arc::clone(&ports);
+        let options = Arc::clone(&shared_options);
+        let progress = pb.clone();
         pool.execute(move || {
-            if let Some(res) = scan_port(ip, port, signatures) {
-                open_ports.lock().unwrap().push(res);
+            let alive = ports.iter().any(|port| {
+                let result = connect_port(&options, target_addr(host, *port, options.scope_id), DISCOVERY_TIMEOUT);
+                match classify_connect(&result) {
+                    ConnectOutcome::Open => true,
+                    // A jump host reports any failed connect as refused, so only open ports count
+                    ConnectOutcome::Closed => options.ssh_jump.is_none(),
+                    ConnectOutcome::Filtered | ConnectOutcome::LocalError => false,
+                }
+            });
+            if alive {
+                let _ = tx.send((index, host));
             }
             progress.inc(1);
         });
     }
+    drop(tx);
+    let mut live: Vec<(usize, IpAddr)> = rx.iter().collect();
     pool.join();
-    let mut result = Arc::try_unwrap(open_ports).unwrap().into_inner().unwrap();
-    result.sort_by_key(|k| k.0);
-    Ok(result)
-}
\ No newline at end of file
+    live.sort_by_key(|(index, _)| *index);
+    live.into_iter().map(|(_, host)| host).collect()
+}
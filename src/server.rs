@@ -0,0 +1,362 @@
+use crate::batch::job_config;
+use crate::error::ScanError;
+use crate::scanner::ScanReport;
+use crate::signatures::Signature;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Largest accepted request body in bytes.
+const MAX_BODY: u64 = 64 * 1024;
+
+/// Options a scan request may set. Everything else (threads, allowlist, DNS) comes from the
+/// server's own configuration.
+pub const REQUEST_OPTIONS: &[&str] = &["retries", "retry_backoff_ms", "max_retries_total", "sort_by"];
+
+/// HTTP server accepting scan requests as JSON.
+///
+/// Endpoints:
+/// * `POST /scan` - Body `{"ip": ..., "ports": ..., "options": {...}}` in the batch job format.
+///   Responds with the scan report, or `{"error": ...}`.
+/// * `GET /health`, `GET /healthz` - Respond with `{"status": "ok", "active_scans": n}`.
+/// * `GET /metrics` - Responds with the server's counters in the Prometheus text format, see
+///   `ServerMetrics`.
+///
+/// Scans always run in safe mode, so only targets in `allowed_targets` (private networks by
+/// default) can be scanned. At most `max_scans` scans run at a time; further requests are
+/// rejected with `429 Too Many Requests`.
+///
+pub struct ScanServer {
+    server: Server,
+    base: Arc<HashMap<String, YamlValue>>,
+    signatures: Arc<Vec<Signature>>,
+    max_scans: usize,
+    active: Arc<AtomicUsize>,
+    metrics: Arc<ServerMetrics>,
+}
+
+/// Totals of all scans a server has run, summed up from the `ScanStats` of their reports.
+///
+/// # Fields
+/// * `scans` - Scans that completed with a report.
+/// * `errors` - Scan requests that failed (bad request, target not allowed, scan error).
+/// * `rejected` - Scan requests rejected because `max_scans` scans were running.
+/// * `open` / `closed` / `filtered` - Scanned ports by their state.
+/// * `retries` - Connect retries used.
+/// * `http_probes` - HTTP probes sent to open ports.
+/// * `bytes_received` - Bytes the probes read.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricTotals {
+    pub scans: u64,
+    pub errors: u64,
+    pub rejected: u64,
+    pub open: u64,
+    pub closed: u64,
+    pub filtered: u64,
+    pub retries: u64,
+    pub http_probes: u64,
+    pub bytes_received: u64,
+}
+
+/// Counters of a running server, exported at `GET /metrics`.
+///
+pub struct ServerMetrics {
+    started: Instant,
+    totals: Mutex<MetricTotals>,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        ServerMetrics {
+            started: Instant::now(),
+            totals: Mutex::new(MetricTotals::default()),
+        }
+    }
+}
+
+impl ServerMetrics {
+    /// Create counters starting at zero.
+    pub fn new() -> Self {
+        ServerMetrics::default()
+    }
+
+    /// Count a completed scan and add its statistics to the totals.
+    pub fn record_report(&self, report: &ScanReport) {
+        let mut totals = self.lock();
+        totals.scans += 1;
+        totals.open += report.open_ports.len() as u64;
+        totals.closed += report.stats.closed;
+        totals.filtered += report.stats.filtered;
+        totals.retries += report.stats.retries_used;
+        totals.http_probes += report.stats.http_probes;
+        totals.bytes_received += report.stats.bytes_received;
+    }
+
+    /// Count a failed scan request.
+    pub fn record_error(&self) {
+        self.lock().errors += 1;
+    }
+
+    /// Count a scan request rejected for too many running scans.
+    pub fn record_rejected(&self) {
+        self.lock().rejected += 1;
+    }
+
+    /// A snapshot of the totals.
+    pub fn totals(&self) -> MetricTotals {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MetricTotals> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Format the counters in the Prometheus text format.
+    ///
+    /// # Arguments
+    /// * `active` - The number of scans running right now.
+    ///
+    /// # Returns
+    /// * The metrics, ending with a newline.
+    ///
+    pub fn format_metrics(&self, active: usize) -> String {
+        let totals = self.totals();
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            let _ = writeln!(out, "# HELP port_explorer_server_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE port_explorer_server_{} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "port_explorer_server_{}{} {}", name, labels, value);
+            }
+        };
+        metric("up", "gauge", "Whether the server is running.", &[("", 1.0)]);
+        metric(
+            "uptime_seconds",
+            "gauge",
+            "Seconds since the server started.",
+            &[("", self.started.elapsed().as_secs_f64())],
+        );
+        metric("active_scans", "gauge", "Scans running right now.", &[("", active as f64)]);
+        metric("scans_total", "counter", "Scans completed with a report.", &[("", totals.scans as f64)]);
+        metric("scan_errors_total", "counter", "Scan requests that failed.", &[("", totals.errors as f64)]);
+        metric(
+            "scans_rejected_total",
+            "counter",
+            "Scan requests rejected because too many scans were running.",
+            &[("", totals.rejected as f64)],
+        );
+        metric(
+            "ports_scanned_total",
+            "counter",
+            "Scanned ports by state.",
+            &[
+                ("{state=\"open\"}", totals.open as f64),
+                ("{state=\"closed\"}", totals.closed as f64),
+                ("{state=\"filtered\"}", totals.filtered as f64),
+            ],
+        );
+        metric("retries_total", "counter", "Connect retries used.", &[("", totals.retries as f64)]);
+        metric("http_probes_total", "counter", "HTTP probes sent to open ports.", &[("", totals.http_probes as f64)]);
+        metric(
+            "bytes_received_total",
+            "counter",
+            "Bytes read from banners, HTTP responses and UDP replies.",
+            &[("", totals.bytes_received as f64)],
+        );
+        out
+    }
+}
+
+impl ScanServer {
+    /// Bind the server to an address.
+    ///
+    /// # Arguments
+    /// * `addr` - The address to listen on (port 0 picks a free port).
+    /// * `base` - The base configuration every request is applied on top of.
+    /// * `signatures` - An Arc containing a vector of service signatures.
+    /// * `max_scans` - The maximum number of concurrent scans (at least 1).
+    ///
+    /// # Returns
+    /// * `Ok(ScanServer)` - The bound server, ready to `run`.
+    /// * `Err(ScanError)` - If the address can't be bound.
+    ///
+    pub fn bind(
+        addr: SocketAddr,
+        base: HashMap<String, YamlValue>,
+        signatures: Arc<Vec<Signature>>,
+        max_scans: usize,
+    ) -> Result<Self, ScanError> {
+        let server = Server::http(addr).map_err(|e| ScanError::Io(std::io::Error::other(e.to_string())))?;
+        Ok(ScanServer {
+            server,
+            base: Arc::new(base),
+            signatures,
+            max_scans: max_scans.max(1),
+            active: Arc::new(AtomicUsize::new(0)),
+            metrics: Arc::new(ServerMetrics::new()),
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Serve requests until the process exits. Each scan runs on its own thread.
+    pub fn run(self) {
+        for request in self.server.incoming_requests() {
+            match (request.method(), request.url()) {
+                (Method::Get, "/health") | (Method::Get, "/healthz") => {
+                    let body = json!({"status": "ok", "active_scans": self.active.load(Ordering::SeqCst)});
+                    respond(request, 200, &body);
+                }
+                (Method::Get, "/metrics") => {
+                    let body = self.metrics.format_metrics(self.active.load(Ordering::SeqCst));
+                    respond_text(request, 200, body, "text/plain; version=0.0.4");
+                }
+                (Method::Post, "/scan") => {
+                    let taken = self
+                        .active
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < self.max_scans).then_some(n + 1))
+                        .is_ok();
+                    if !taken {
+                        self.metrics.record_rejected();
+                        respond(request, 429, &error_body(&crate::localisator::get("error_too_many_scans")));
+                        continue;
+                    }
+                    let base = Arc::clone(&self.base);
+                    let signatures = Arc::clone(&self.signatures);
+                    let active = Arc::clone(&self.active);
+                    let metrics = Arc::clone(&self.metrics);
+                    std::thread::spawn(move || {
+                        handle_request(request, &base, signatures, &metrics);
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                (_, "/scan") | (_, "/health") | (_, "/healthz") | (_, "/metrics") => {
+                    respond(request, 405, &error_body(&crate::localisator::get("error_method_not_allowed")));
+                }
+                _ => respond(request, 404, &error_body(&crate::localisator::get("error_not_found"))),
+            }
+        }
+    }
+}
+
+/// Read the body of a scan request, run the scan, count it in the metrics and send the response.
+fn handle_request(
+    mut request: Request,
+    base: &HashMap<String, YamlValue>,
+    signatures: Arc<Vec<Signature>>,
+    metrics: &ServerMetrics,
+) {
+    let mut body = String::new();
+    let read = request.as_reader().take(MAX_BODY + 1).read_to_string(&mut body);
+    if read.is_err() || body.len() as u64 > MAX_BODY {
+        metrics.record_error();
+        respond(request, 413, &error_body(&crate::localisator::get("error_request_body")));
+        return;
+    }
+    let (status, body) = match run_job(base, &body, signatures) {
+        Ok(report) => {
+            metrics.record_report(&report);
+            report_body(report)
+        }
+        Err(error) => {
+            metrics.record_error();
+            error
+        }
+    };
+    respond(request, status, &body);
+}
+
+/// Handle the body of a `POST /scan` request.
+///
+/// # Arguments
+/// * `base` - The server's base configuration.
+/// * `body` - The JSON request body.
+/// * `signatures` - An Arc containing a vector of service signatures.
+///
+/// # Returns
+/// * `(200, report)` - The scan report.
+/// * `(400, error)` - If the request is malformed or sets an option outside `REQUEST_OPTIONS`.
+/// * `(403, error)` - If the target is not in the allowlist.
+/// * `(500, error)` - If the scan failed.
+///
+pub fn handle_scan(
+    base: &HashMap<String, YamlValue>,
+    body: &str,
+    signatures: Arc<Vec<Signature>>,
+) -> (u16, JsonValue) {
+    match run_job(base, body, signatures) {
+        Ok(report) => report_body(report),
+        Err(error) => error,
+    }
+}
+
+/// Run the scan of a `POST /scan` request body, see `handle_scan`.
+fn run_job(
+    base: &HashMap<String, YamlValue>,
+    body: &str,
+    signatures: Arc<Vec<Signature>>,
+) -> Result<ScanReport, (u16, JsonValue)> {
+    let job: JsonValue = match serde_json::from_str(body) {
+        Ok(job) => job,
+        Err(e) => {
+            let msg = format!("{}: {}", crate::localisator::get("error_batch_parse"), e);
+            return Err((400, error_body(&msg)));
+        }
+    };
+    let options = job.get("options").and_then(|o| o.as_object());
+    if let Some(key) = options.and_then(|o| o.keys().find(|k| !REQUEST_OPTIONS.contains(&k.as_str()))) {
+        let msg = format!("{}: {}", crate::localisator::get("error_option_not_allowed"), key);
+        return Err((400, error_body(&msg)));
+    }
+    let mut config = match job_config(base, &job) {
+        Ok(config) => config,
+        Err(e) => return Err((400, error_body(&e.to_string()))),
+    };
+    config.insert("safe_mode".to_string(), YamlValue::Bool(true));
+    crate::run_scan(&config, signatures).map_err(|e| {
+        let status = match e {
+            ScanError::NotAllowed(_) => 403,
+            ScanError::Config(_) | ScanError::Resolve(_) => 400,
+            ScanError::Io(_) => 500,
+        };
+        (status, error_body(&e.to_string()))
+    })
+}
+
+/// Status and JSON body of a successful scan.
+fn report_body(report: ScanReport) -> (u16, JsonValue) {
+    match serde_json::to_value(report) {
+        Ok(report) => (200, report),
+        Err(e) => (500, error_body(&e.to_string())),
+    }
+}
+
+/// JSON body of an error response.
+fn error_body(message: &str) -> JsonValue {
+    json!({ "error": message })
+}
+
+/// Send a JSON response, ignoring clients that have gone away.
+fn respond(request: Request, status: u16, body: &JsonValue) {
+    respond_text(request, status, body.to_string(), "application/json");
+}
+
+/// Send a response with the given content type, ignoring clients that have gone away.
+fn respond_text(request: Request, status: u16, body: String, content_type: &str) {
+    let mut response = Response::from_string(body).with_status_code(status);
+    if let Ok(header) = Header::from_bytes("Content-Type", content_type) {
+        response = response.with_header(header);
+    }
+    let _ = request.respond(response);
+}
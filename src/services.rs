@@ -0,0 +1,103 @@
+use crate::error::ScanError;
+use crate::scanner::Protocol;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Service database of the operating system, consulted before the embedded table.
+pub const SERVICES_FILE: &str = "/etc/services";
+
+/// Embedded service table in `/etc/services` format, for systems without one or names it lacks.
+pub const EMBEDDED_SERVICES: &str = include_str!("../resources/services");
+
+/// Parse a service database in `/etc/services` format.
+///
+/// Every line is `name port/protocol [aliases...] [# comment]`. Aliases resolve like the name,
+/// names are case-insensitive, and protocols other than TCP and UDP are ignored.
+///
+/// # Arguments
+/// * `text` - The content of the service database.
+///
+/// # Returns
+/// * A map from lower-case service name or alias to its `(protocol, port)` entries.
+///
+pub fn parse_services(text: &str) -> HashMap<String, Vec<(Protocol, u16)>> {
+    let mut services: HashMap<String, Vec<(Protocol, u16)>> = HashMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_protocol)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((port, protocol)) = port_protocol.split_once('/') else {
+            continue;
+        };
+        let (Ok(port), Ok(protocol)) = (port.parse::<u16>(), protocol.parse::<Protocol>()) else {
+            continue;
+        };
+        if port == 0 {
+            continue;
+        }
+        for name in std::iter::once(name).chain(fields) {
+            let entries = services.entry(name.to_ascii_lowercase()).or_default();
+            if !entries.contains(&(protocol, port)) {
+                entries.push((protocol, port));
+            }
+        }
+    }
+    services
+}
+
+/// Resolve service names to the ports to scan, using `/etc/services` first and the embedded table as fallback.
+///
+/// # Arguments
+/// * `names` - The service names (e.g., `["http", "domain"]`).
+///
+/// # Returns
+/// * `Ok(BTreeMap<Protocol, Vec<u16>>)` - The sorted ports per protocol.
+/// * `Err(ScanError)` - If any name is unknown, listing all unresolved names.
+///
+pub fn resolve_services(names: &[String]) -> Result<BTreeMap<Protocol, Vec<u16>>, ScanError> {
+    let system = std::fs::read_to_string(SERVICES_FILE).unwrap_or_default();
+    resolve_services_from(names, &system)
+}
+
+/// Resolve service names against the given system service database, falling back to the embedded table.
+///
+/// # Arguments
+/// * `names` - The service names (e.g., `["http", "domain"]`).
+/// * `system` - The content of the system service database (empty if there is none).
+///
+/// # Returns
+/// * `Ok(BTreeMap<Protocol, Vec<u16>>)` - The sorted ports per protocol.
+/// * `Err(ScanError)` - If any name is unknown, listing all unresolved names.
+///
+pub fn resolve_services_from(
+    names: &[String],
+    system: &str,
+) -> Result<BTreeMap<Protocol, Vec<u16>>, ScanError> {
+    let system = parse_services(system);
+    let embedded = parse_services(EMBEDDED_SERVICES);
+    let mut ports: BTreeMap<Protocol, BTreeSet<u16>> = BTreeMap::new();
+    let mut unresolved = Vec::new();
+    for name in names {
+        let key = name.trim().to_ascii_lowercase();
+        match system.get(&key).or_else(|| embedded.get(&key)) {
+            Some(entries) => {
+                for (protocol, port) in entries {
+                    ports.entry(*protocol).or_default().insert(*port);
+                }
+            }
+            None => unresolved.push(name.trim()),
+        }
+    }
+    if !unresolved.is_empty() {
+        return Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_unknown_services"),
+            unresolved.join(", ")
+        )));
+    }
+    Ok(ports
+        .into_iter()
+        .map(|(protocol, ports)| (protocol, ports.into_iter().collect()))
+        .collect())
+}
@@ -1,50 +1,636 @@
 use crate::error::ScanError;
+use regex::Regex;
 use serde::Deserialize;
 use serde_yaml::Value as YamlValue;
+use std::net::IpAddr;
 use std::path::Path;
 
 /// Represents a service signature with a name and a matching string.
 ///
 /// # Fields
-/// * `name` - The name of the service (e.g., "HTTP", "FTP").
-/// * `match_` - A substring to match in the response to identify the service
+/// * `name` - The name of the service (e.g., "HTTP", "FTP"). `{port}` and `{host}` are filled in
+///   with the matched port and IP address (see `render_service_name`).
+/// * `match_` - A substring to match in the response to identify the service, or a regular
+///   expression if the signature sets `regex: true`.
+/// * `status` - Optional inclusive range of HTTP status codes the response must have (e.g., `(401, 401)`).
+/// * `header` - Optional HTTP header name. If set, `match_` is searched in that header's value instead of the body.
+/// * `min_bytes` - Optional minimum number of bytes (banner, UDP reply or HTTP body) that must have been
+///   received before the signature may match, for services that reveal themselves only after a while.
+/// * `ports` - Optional list of ports the signature is limited to (e.g., `[22]` for an SSH banner);
+///   without it, the signature matches on any port.
+/// * `source` - The file the signature was loaded from (for a bundle, the bundle path followed by
+///   the section label in brackets), or None if it wasn't loaded from a file. Not part of `dedup_key`.
+/// * `pattern` - `match_` compiled as a regular expression when the signature was loaded with
+///   `regex: true`, or None to match `match_` as a plain substring.
 ///
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Signature {
     pub name: String,
     pub match_: String,
+    #[serde(default)]
+    pub status: Option<(u16, u16)>,
+    #[serde(default)]
+    pub header: Option<String>,
+    #[serde(default)]
+    pub min_bytes: Option<usize>,
+    #[serde(default)]
+    pub ports: Option<Vec<u16>>,
+    #[serde(skip)]
+    pub source: Option<String>,
+    #[serde(skip)]
+    pub pattern: Option<Regex>,
+}
+
+/// Identity of a signature for deduplication, see `Signature::dedup_key`.
+pub type SignatureKey<'a> =
+    (&'a str, &'a str, bool, Option<(u16, u16)>, Option<&'a str>, Option<usize>, Option<&'a [u16]>);
+
+impl Signature {
+    /// The key two signatures are duplicates by: every field that affects what a signature
+    /// matches, in sort order (name, match string, regex or not, status range, header, `min_bytes`,
+    /// ports). Signatures
+    /// with the same name and match string but e.g. a different header or status range match
+    /// different responses and are therefore not duplicates.
+    ///
+    /// # Returns
+    /// * The key; signatures with equal keys are interchangeable.
+    ///
+    pub fn dedup_key(&self) -> SignatureKey<'_> {
+        (
+            &self.name,
+            &self.match_,
+            self.pattern.is_some(),
+            self.status,
+            self.header.as_deref(),
+            self.min_bytes,
+            self.ports.as_deref(),
+        )
+    }
+
+    /// Check if the signature may match on a port, i.e. it has no `ports` list or the list contains the port.
+    pub fn applies_to(&self, port: u16) -> bool {
+        self.ports.as_ref().is_none_or(|ports| ports.contains(&port))
+    }
+
+    /// Match a text against the signature's match string, or its regular expression if it has one.
+    ///
+    /// # Arguments
+    /// * `text` - The banner, body or header value to search.
+    ///
+    /// # Returns
+    /// * `Some(SignatureMatch)` - If the text matches, with the text of the expression's `version`
+    ///   capture group if it has one that took part in the match.
+    /// * `None` - Otherwise.
+    ///
+    pub fn find(&self, text: &str) -> Option<SignatureMatch<'_>> {
+        let version = match &self.pattern {
+            Some(pattern) => {
+                let captures = pattern.captures(text)?;
+                captures.name("version").map(|v| v.as_str().trim()).filter(|v| !v.is_empty()).map(str::to_string)
+            }
+            None if text.contains(&self.match_) => None,
+            None => return None,
+        };
+        Some(SignatureMatch { signature: self, version })
+    }
+}
+
+/// A signature that matched a response, see `match_banner` and `match_http_response`.
+///
+/// # Fields
+/// * `signature` - The matching signature.
+/// * `version` - The version captured by the signature's `(?P<version>...)` group, if any.
+///
+#[derive(Debug, Clone)]
+pub struct SignatureMatch<'a> {
+    pub signature: &'a Signature,
+    pub version: Option<String>,
+}
+
+/// Signature set compiled into the binary, so services are identified without a signatures
+/// directory. Signatures loaded from files override embedded ones of the same name.
+pub const EMBEDDED_SIGNATURES: &str = include_str!("../signatures/common.yaml");
+
+/// Largest `min_bytes` a signature may require, bounding how much of a banner is read.
+pub const MAX_MIN_BYTES: usize = 65536;
+
+/// An HTTP response as seen by the identification step.
+///
+/// # Fields
+/// * `status` - The HTTP status code.
+/// * `headers` - The response headers as `(name, value)` pairs.
+/// * `body` - The response body.
+///
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Parse a raw HTTP/1.x response as read from a connection. Header names are lowercased like
+    /// those of the HTTP client; the body is taken as is, without undoing a transfer encoding.
+    ///
+    /// # Arguments
+    /// * `raw` - The response, starting with the status line.
+    ///
+    /// # Returns
+    /// * `Some(HttpResponse)` - The parsed response.
+    /// * `None` - If `raw` doesn't start with an HTTP status line.
+    ///
+    pub fn parse(raw: &str) -> Option<HttpResponse> {
+        let (head, body) = raw
+            .split_once("\r\n\r\n")
+            .or_else(|| raw.split_once("\n\n"))
+            .unwrap_or((raw, ""));
+        let mut lines = head.lines();
+        let mut status_line = lines.next()?.split_whitespace();
+        if !status_line.next()?.starts_with("HTTP/1.") {
+            return None;
+        }
+        let status = status_line.next()?.parse().ok()?;
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        Some(HttpResponse { status, headers, body: body.to_string() })
+    }
 }
 
 /// Identify the service based on response content and known signatures.
 ///
 /// # Arguments
 /// * `response` - The response string from the scanned port.
+/// * `port` - The scanned port; signatures limited to other `ports` are skipped.
 /// * `signatures` - A slice of known service signatures.
 ///
 /// # Returns
 /// * `Some(String)` - The name of the identified service, if a matching signature is found.
 /// * `None` - If no matching signature is found.
 ///
-pub fn identify_service(response: &str, signatures: &[Signature]) -> Option<String> {
-    for sig in signatures {
-        if response.contains(&sig.match_) {
-            return Some(sig.name.clone());
+pub fn identify_service(response: &str, port: u16, signatures: &[Signature]) -> Option<String> {
+    match_banner(response, port, signatures).map(|found| found.signature.name.clone())
+}
+
+/// Find the first signature matching a banner or UDP reply, see `identify_service`.
+///
+/// # Arguments
+/// * `response` - The response string from the scanned port.
+/// * `port` - The scanned port; signatures limited to other `ports` are skipped.
+/// * `signatures` - A slice of known service signatures.
+///
+/// # Returns
+/// * `Some(SignatureMatch)` - The first matching signature and the version it captured.
+/// * `None` - If no signature matches.
+///
+pub fn match_banner<'a>(response: &str, port: u16, signatures: &'a [Signature]) -> Option<SignatureMatch<'a>> {
+    for sig in signatures.iter().filter(|sig| sig.applies_to(port)) {
+        // Status and header conditions can only hold for HTTP responses
+        if sig.status.is_some() || sig.header.is_some() {
+            continue;
+        }
+        if sig.min_bytes.is_some_and(|min| response.len() < min) {
+            continue;
+        }
+        if let Some(found) = sig.find(response) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Fill in the placeholders of a signature name for the port it was matched on.
+///
+/// `{port}` is replaced by the port number and `{host}` by the scanned IP address, e.g.
+/// `"Unknown HTTP on {port}"` becomes `"Unknown HTTP on 8080"`. Names without placeholders are
+/// returned unchanged.
+///
+/// # Arguments
+/// * `name` - The name of the matching signature.
+/// * `host` - The scanned IP address.
+/// * `port` - The scanned port.
+///
+/// # Returns
+/// * The service name to report.
+///
+pub fn render_service_name(name: String, host: &IpAddr, port: u16) -> String {
+    if !name.contains('{') {
+        return name;
+    }
+    name.replace("{port}", &port.to_string()).replace("{host}", &host.to_string())
+}
+
+/// Identify the service based on an HTTP response's status code, headers and body.
+///
+/// A signature matches if the port is in its `ports` (when set), the status code is within its
+/// `status` range (when set), the body has at least `min_bytes` bytes (when set) and `match_` is
+/// found in its `header` (when set) or otherwise in the body.
+///
+/// # Arguments
+/// * `response` - The HTTP response from the scanned port.
+/// * `port` - The scanned port.
+/// * `signatures` - A slice of known service signatures.
+///
+/// # Returns
+/// * `Some(String)` - The name of the identified service, if a matching signature is found.
+/// * `None` - If no matching signature is found.
+///
+pub fn identify_http_response(response: &HttpResponse, port: u16, signatures: &[Signature]) -> Option<String> {
+    match_http_response(response, port, signatures).map(|found| found.signature.name.clone())
+}
+
+/// Find the first signature matching an HTTP response, see `identify_http_response`.
+///
+/// # Arguments
+/// * `response` - The HTTP response from the scanned port.
+/// * `port` - The scanned port.
+/// * `signatures` - A slice of known service signatures.
+///
+/// # Returns
+/// * `Some(SignatureMatch)` - The first matching signature and the version it captured.
+/// * `None` - If no signature matches.
+///
+pub fn match_http_response<'a>(
+    response: &HttpResponse,
+    port: u16,
+    signatures: &'a [Signature],
+) -> Option<SignatureMatch<'a>> {
+    for sig in signatures.iter().filter(|sig| sig.applies_to(port)) {
+        if let Some((low, high)) = sig.status {
+            if !(low..=high).contains(&response.status) {
+                continue;
+            }
+        }
+        if sig.min_bytes.is_some_and(|min| response.body.len() < min) {
+            continue;
+        }
+        let found = match &sig.header {
+            Some(header) => response
+                .headers
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(header))
+                .find_map(|(_, value)| sig.find(value)),
+            None => sig.find(&response.body),
+        };
+        if found.is_some() {
+            return found;
         }
     }
     None
 }
 
+/// Number of bytes to read from a banner before matching it against the signatures.
+///
+/// # Arguments
+/// * `signatures` - A slice of known service signatures.
+///
+/// # Returns
+/// * The largest `min_bytes` of any signature, or 1 (match on whatever is read first) if none is set.
+///
+pub fn banner_bytes_wanted(signatures: &[Signature]) -> usize {
+    signatures
+        .iter()
+        .filter_map(|sig| sig.min_bytes)
+        .max()
+        .unwrap_or(1)
+        .clamp(1, MAX_MIN_BYTES)
+}
+
+/// Parse an HTTP status code condition from a signature.
+///
+/// # Arguments
+/// * `value` - A single status code (e.g., `401`) or an inclusive range string (e.g., `"400-499"`).
+///
+/// # Returns
+/// * `Some((u16, u16))` - The inclusive status range.
+/// * `None` - If the value is not a valid status code or range.
+///
+fn parse_status(value: &YamlValue) -> Option<(u16, u16)> {
+    let parse_code = |s: &str| s.trim().parse::<u16>().ok().filter(|c| (100..=599).contains(c));
+    match value {
+        YamlValue::Number(n) => {
+            let code = parse_code(&n.to_string())?;
+            Some((code, code))
+        }
+        YamlValue::String(s) => match s.split_once('-') {
+            Some((low, high)) => {
+                let (low, high) = (parse_code(low)?, parse_code(high)?);
+                (low <= high).then_some((low, high))
+            }
+            None => parse_code(s).map(|code| (code, code)),
+        },
+        _ => None,
+    }
+}
+
+/// Parse the ports a signature is limited to.
+///
+/// # Arguments
+/// * `value` - A non-empty sequence of port numbers (e.g., `[22, 2222]`).
+///
+/// # Returns
+/// * `Some(Vec<u16>)` - The ports, sorted and without duplicates.
+/// * `None` - If the value is not a sequence of ports between 1 and 65535.
+///
+fn parse_ports(value: &YamlValue) -> Option<Vec<u16>> {
+    let mut ports = value
+        .as_sequence()?
+        .iter()
+        .map(|port| port.as_u64().and_then(|n| u16::try_from(n).ok()).filter(|&n| n != 0))
+        .collect::<Option<Vec<u16>>>()?;
+    ports.sort_unstable();
+    ports.dedup();
+    (!ports.is_empty()).then_some(ports)
+}
+
 /// Load signatures from YAML files in the "signatures" directory and its subdirectories.
 ///
-/// Returns
-/// * `Ok(Vec<Signature>)` - A vector of loaded signatures.
-/// * `Err(ScanError)` - If there was an error reading or parsing the signature files.
+/// Unreadable files and malformed signatures are reported on stderr and skipped.
 ///
 /// Returns
 /// * `Ok(Vec<Signature>)` - A vector of loaded signatures.
-/// * `Err(ScanError)` - If there was an error reading or parsing the signature files.
+/// * `Err(ScanError)` - If the signatures directory does not exist.
 ///
 pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
+    let (signatures, problems) = load_signatures_with_problems(Some(Path::new("signatures")), true, false, true)?;
+    for problem in problems {
+        match problem {
+            Problem::Invalid(problem) => eprintln!("{}", problem),
+            Problem::Skipped(problem) => {
+                eprintln!("{}: {}", crate::localisator::get("warning_skipped_signature"), problem)
+            }
+        }
+    }
+    Ok(signatures)
+}
+
+/// Load signatures and validate them before a scan starts, so a broken signature file fails
+/// fast instead of surfacing mid-scan.
+///
+/// Every problem is collected: unreadable or unparsable files, entries missing required
+/// fields, and signatures rejected by `validate_signature`.
+///
+/// # Arguments
+/// * `lenient` - If `true`, print a warning for every problem and skip the malformed signatures
+///   instead of failing.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If the signatures directory does not exist, or any problem was found
+///   and `lenient` is `false`. The error lists all problems.
+///
+pub fn load_validated_signatures(lenient: bool) -> Result<Vec<Signature>, ScanError> {
+    load_validated_signatures_from(Path::new("signatures"), lenient)
+}
+
+/// Load and validate signatures from a given directory or bundle file, see `load_validated_signatures`.
+///
+/// # Arguments
+/// * `base` - The signatures directory, or a single YAML bundle holding a list of signature file sections.
+/// * `lenient` - If `true`, skip malformed signatures with a warning instead of failing.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If the directory does not exist, or any problem was found and `lenient` is `false`.
+///
+pub fn load_validated_signatures_from(base: &Path, lenient: bool) -> Result<Vec<Signature>, ScanError> {
+    load_validated_signatures_with(base, lenient, true)
+}
+
+/// Load and validate signatures from a given directory or bundle file, optionally keeping the raw
+/// loaded order, see `load_validated_signatures`.
+///
+/// With `dedupe`, signatures are sorted by name and then match string, and of several signatures
+/// with the same `Signature::dedup_key` only one is kept. Without it, they are returned in the
+/// order they were loaded, duplicates included: files in the order the directory listing yields
+/// them (which depends on the file system), subdirectories descended into where they are listed,
+/// bundle sections in file order, and signatures in the order of their file. As the first
+/// matching signature wins, the mode can change which service is reported.
+///
+/// # Arguments
+/// * `base` - The signatures directory, or a single YAML bundle holding a list of signature file sections.
+/// * `lenient` - If `true`, skip malformed signatures with a warning instead of failing.
+/// * `dedupe` - If `true`, sort and deduplicate the signatures; if `false`, keep the loaded order.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If the directory does not exist, or any problem was found and `lenient` is `false`.
+///
+pub fn load_validated_signatures_with(base: &Path, lenient: bool, dedupe: bool) -> Result<Vec<Signature>, ScanError> {
+    let (signatures, problems) = load_signatures_with_problems(Some(base), true, false, dedupe)?;
+    validate_loaded(signatures, problems, lenient)
+}
+
+/// Load and validate signatures from a directory or a single signature file, see
+/// `load_validated_signatures_with`.
+///
+/// Unlike `load_validated_signatures_with`, a file is read as one signature file (a `signatures`
+/// list with optional `defaults`), not as a bundle of sections.
+///
+/// # Arguments
+/// * `path` - The signatures directory, or a single YAML signature file.
+/// * `lenient` - If `true`, skip malformed signatures with a warning instead of failing.
+/// * `dedupe` - If `true`, sort and deduplicate the signatures; if `false`, keep the loaded order.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If the path does not exist, or any problem was found and `lenient` is `false`.
+///
+pub fn load_validated_signatures_at(path: &Path, lenient: bool, dedupe: bool) -> Result<Vec<Signature>, ScanError> {
+    let (signatures, problems) = load_signatures_with_problems(Some(path), false, false, dedupe)?;
+    validate_loaded(signatures, problems, lenient)
+}
+
+/// Load and validate the `EMBEDDED_SIGNATURES` merged with those of a directory, signature file
+/// or bundle, see `load_validated_signatures_with`.
+///
+/// A loaded signature replaces every embedded signature of the same name. Deduplication applies
+/// to the merged set; without it, the loaded signatures come first, in loaded order.
+///
+/// # Arguments
+/// * `path` - The signatures directory, signature file or bundle, or None for the embedded signatures only.
+/// * `bundle` - Whether a file `path` is a bundle rather than a single signature file.
+/// * `lenient` - If `true`, skip malformed signatures with a warning instead of failing.
+/// * `dedupe` - If `true`, sort and deduplicate the signatures; if `false`, keep the loaded order.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If the path does not exist, or any problem was found and `lenient` is `false`.
+///
+pub fn load_validated_signatures_with_builtin(
+    path: Option<&Path>,
+    bundle: bool,
+    lenient: bool,
+    dedupe: bool,
+) -> Result<Vec<Signature>, ScanError> {
+    let (signatures, problems) = load_signatures_with_problems(path, bundle, true, dedupe)?;
+    validate_loaded(signatures, problems, lenient)
+}
+
+/// A problem found while loading signatures.
+///
+/// Variants:
+/// * `Invalid` - An unreadable file or malformed entry, failing the load unless it is lenient.
+/// * `Skipped` - A signature that is always only skipped with a warning, e.g. one with an
+///   invalid regular expression.
+///
+enum Problem {
+    Invalid(String),
+    Skipped(String),
+}
+
+impl Problem {
+    /// Prefix the description with where the problem was found, e.g. the file.
+    fn located(self, location: &str) -> Problem {
+        match self {
+            Problem::Invalid(problem) => Problem::Invalid(format!("{}: {}", location, problem)),
+            Problem::Skipped(problem) => Problem::Skipped(format!("{}: {}", location, problem)),
+        }
+    }
+}
+
+impl From<String> for Problem {
+    fn from(problem: String) -> Self {
+        Problem::Invalid(problem)
+    }
+}
+
+/// Validate loaded signatures, see `load_validated_signatures_with`.
+///
+/// # Arguments
+/// * `signatures` - The well-formed signatures as loaded.
+/// * `loaded` - The problems found while loading them.
+/// * `lenient` - If `true`, print a warning for every problem and drop the invalid signatures.
+///   Skipped signatures are always only warned about.
+///
+/// # Returns
+/// * `Ok(Vec<Signature>)` - The valid signatures.
+/// * `Err(ScanError)` - If any invalid signature or file was found and `lenient` is `false`.
+///
+fn validate_loaded(signatures: Vec<Signature>, loaded: Vec<Problem>, lenient: bool) -> Result<Vec<Signature>, ScanError> {
+    let mut problems = Vec::new();
+    for problem in loaded {
+        match problem {
+            Problem::Invalid(problem) => problems.push(problem),
+            Problem::Skipped(problem) => {
+                eprintln!("{}: {}", crate::localisator::get("warning_skipped_signature"), problem)
+            }
+        }
+    }
+    let mut valid = Vec::with_capacity(signatures.len());
+    for sig in signatures {
+        match validate_signature(&sig) {
+            Ok(()) => valid.push(sig),
+            Err(problem) => problems.push(problem),
+        }
+    }
+    if problems.is_empty() {
+        return Ok(valid);
+    }
+    if lenient {
+        for problem in &problems {
+            eprintln!("{}: {}", crate::localisator::get("warning_skipped_signature"), problem);
+        }
+        return Ok(valid);
+    }
+    Err(ScanError::Config(format!(
+        "{} ({}):\n  - {}",
+        crate::localisator::get("error_invalid_signatures"),
+        problems.len(),
+        problems.join("\n  - ")
+    )))
+}
+
+/// Check that loading produced any signature at all. An existing signatures directory or bundle
+/// can still yield none, e.g. if it holds no YAML files or, with `lenient`, every file was skipped;
+/// the scan then can't identify any service.
+///
+/// # Arguments
+/// * `signatures` - The loaded signatures.
+/// * `base` - The signatures directory or bundle they were loaded from.
+/// * `required` - If `true`, an empty set is an error; otherwise it only prints a warning.
+///
+/// # Returns
+/// * `Ok(())` - If there are signatures, or none and `required` is `false`.
+/// * `Err(ScanError)` - If there are none and `required` is `true`.
+///
+pub fn check_signatures_loaded(signatures: &[Signature], base: &Path, required: bool) -> Result<(), ScanError> {
+    if !signatures.is_empty() {
+        return Ok(());
+    }
+    if required {
+        return Err(ScanError::Config(format!(
+            "{}: {}",
+            crate::localisator::get("error_no_signatures_loaded"),
+            base.display()
+        )));
+    }
+    eprintln!("{}: {}", crate::localisator::get("warning_no_signatures_loaded"), base.display());
+    Ok(())
+}
+
+/// Check a parsed signature for values that could never match or would fail at scan time.
+///
+/// # Arguments
+/// * `sig` - The signature to check.
+///
+/// # Returns
+/// * `Ok(())` - If the signature is valid.
+/// * `Err(String)` - A description of the first problem found.
+///
+pub fn validate_signature(sig: &Signature) -> Result<(), String> {
+    let problem = |key: &str| {
+        format!("{} '{}': {}", crate::localisator::get("signature"), sig.name, crate::localisator::get(key))
+    };
+    if sig.name.trim().is_empty() {
+        return Err(crate::localisator::get("signature_missing_name"));
+    }
+    if sig.match_.is_empty() && sig.status.is_none() {
+        return Err(problem("signature_missing_match"));
+    }
+    if let Some((low, high)) = sig.status {
+        if low > high || !(100..=599).contains(&low) || !(100..=599).contains(&high) {
+            return Err(problem("signature_invalid_status"));
+        }
+    }
+    if let Some(header) = &sig.header {
+        let is_token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+        if header.is_empty() || !header.chars().all(is_token) {
+            return Err(problem("signature_invalid_header"));
+        }
+    }
+    if sig.min_bytes.is_some_and(|min| !(1..=MAX_MIN_BYTES).contains(&min)) {
+        return Err(problem("signature_invalid_min_bytes"));
+    }
+    if sig.ports.as_ref().is_some_and(|ports| ports.is_empty() || ports.contains(&0)) {
+        return Err(problem("signature_invalid_ports"));
+    }
+    Ok(())
+}
+
+/// Load signatures from a directory and its subdirectories, or from a bundle or signature file,
+/// keeping the problems found on the way.
+///
+/// # Arguments
+/// * `base` - The signatures directory, or a bundle or signature file; None to load no files.
+/// * `bundle` - Whether a file is a bundle (see `load_signatures_from_bundle`) rather than a
+///   single signature file.
+/// * `builtin` - Whether to add the `EMBEDDED_SIGNATURES` not overridden by a loaded signature.
+/// * `dedupe` - Whether to sort and deduplicate the signatures (see `load_validated_signatures_with`).
+///
+/// # Returns
+/// * `Ok((Vec<Signature>, Vec<Problem>))` - The well-formed signatures, sorted and deduplicated if
+///   `dedupe` is set and in loaded order otherwise, and every unreadable file, malformed entry
+///   and skipped signature.
+/// * `Err(ScanError)` - If the signatures directory or file does not exist.
+///
+fn load_signatures_with_problems(
+    base: Option<&Path>,
+    bundle: bool,
+    builtin: bool,
+    dedupe: bool,
+) -> Result<(Vec<Signature>, Vec<Problem>), ScanError> {
     /// Check if a file has a .yml or .yaml extension.
     ///
     /// # Arguments
@@ -67,23 +653,69 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
     /// * `m` - A reference to a serde_yaml::Mapping representing a signature.
     ///
     /// # Returns
-    /// * `Some(Signature)` - If the mapping contains valid fields.
-    /// * `None` - If the mapping is missing required fields.
+    /// * `Ok(Signature)` - If the mapping contains a name and a match string or status condition.
+    /// * `Err(Problem)` - Why the mapping is not a valid signature. `Problem::Skipped` if
+    ///   `regex: true` is set but `match_` isn't a valid regular expression, which doesn't fail
+    ///   the load.
     ///
-    fn extract_signature_from_mapping(m: &serde_yaml::Mapping) -> Option<Signature> {
-        let name = m.get(&YamlValue::from("name")).and_then(|v| v.as_str());
+    fn extract_signature_from_mapping(m: &serde_yaml::Mapping) -> Result<Signature, Problem> {
+        let name = m
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| crate::localisator::get("signature_missing_name"))?;
+        let problem = |key: &str| format!("{} '{}': {}", crate::localisator::get("signature"), name, crate::localisator::get(key));
         let match_str = m
-            .get(&YamlValue::from("match_"))
+            .get("match_")
             .and_then(|v| v.as_str())
-            .or_else(|| m.get(&YamlValue::from("match")).and_then(|v| v.as_str()));
+            .or_else(|| m.get("match").and_then(|v| v.as_str()));
 
-        match (name, match_str) {
-            (Some(n), Some(ms)) => Some(Signature {
-                name: n.to_string(),
-                match_: ms.to_string(),
-            }),
-            _ => None,
-        }
+        let status = match m.get("status") {
+            Some(v) => Some(parse_status(v).ok_or_else(|| problem("signature_invalid_status"))?),
+            None => None,
+        };
+        let header = m
+            .get("header")
+            .and_then(|v| v.as_str())
+            .map(|h| h.to_string());
+        let min_bytes = match m.get("min_bytes") {
+            Some(v) => Some(
+                v.as_u64()
+                    .map(|n| n as usize)
+                    .ok_or_else(|| problem("signature_invalid_min_bytes"))?,
+            ),
+            None => None,
+        };
+
+        // A status condition alone is enough to identify a service
+        let match_str = match (match_str, status) {
+            (Some(ms), _) => ms,
+            (None, Some(_)) => "",
+            (None, None) => return Err(problem("signature_missing_match").into()),
+        };
+        let ports = match m.get("ports") {
+            Some(v) => Some(parse_ports(v).ok_or_else(|| problem("signature_invalid_ports"))?),
+            None => None,
+        };
+        let pattern = match m.get("regex") {
+            Some(YamlValue::Bool(true)) => match Regex::new(match_str) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    return Err(Problem::Skipped(format!("{}: {}", problem("signature_invalid_regex"), e)));
+                }
+            },
+            Some(YamlValue::Bool(false)) | None => None,
+            Some(_) => return Err(problem("signature_invalid_regex_flag").into()),
+        };
+        Ok(Signature {
+            name: name.to_string(),
+            match_: match_str.to_string(),
+            status,
+            header,
+            min_bytes,
+            ports,
+            source: None,
+            pattern,
+        })
     }
 
     /// Process a YAML mapping to extract signatures.
@@ -91,22 +723,25 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
     /// # Arguments
     /// * `map` - A reference to a serde_yaml::Mapping.
     /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect malformed signatures.
     ///
     /// # Returns
     /// * `None` - If the mapping is missing the "signatures" key.
     ///
-    fn process_mapping(map: &serde_yaml::Mapping, out: &mut Vec<Signature>) {
+    fn process_mapping(map: &serde_yaml::Mapping, out: &mut Vec<Signature>, problems: &mut Vec<Problem>) {
         if let Some(seq) = map
-            .get(&YamlValue::from("signatures"))
+            .get("signatures")
             .and_then(|v| v.as_sequence())
         {
-            for item in seq {
-                if let Some(m) = item.as_mapping() {
-                    if let Some(sig) = extract_signature_from_mapping(m) {
-                        out.push(sig);
-                    }
+            let defaults = match map.get("defaults") {
+                Some(YamlValue::Mapping(defaults)) => Some(defaults),
+                Some(_) => {
+                    problems.push(crate::localisator::get("signature_invalid_defaults").into());
+                    return;
                 }
-            }
+                None => None,
+            };
+            process_sequence(seq, defaults, out, problems);
             return;
         }
 
@@ -116,25 +751,60 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
                 out.push(Signature {
                     name: name.to_string(),
                     match_: ms.to_string(),
+                    ..Default::default()
                 });
             }
         }
     }
 
+    /// Merge a file's `defaults` block into a signature mapping. Keys set on the signature win,
+    /// with `match` and `match_` counting as the same key.
+    ///
+    /// # Arguments
+    /// * `m` - The signature mapping.
+    /// * `defaults` - The defaults of the file.
+    ///
+    /// # Returns
+    /// * The signature mapping with the missing keys taken from the defaults.
+    ///
+    fn apply_defaults(m: &serde_yaml::Mapping, defaults: &serde_yaml::Mapping) -> serde_yaml::Mapping {
+        let has_match = m.contains_key("match") || m.contains_key("match_");
+        let mut merged = m.clone();
+        for (key, value) in defaults {
+            let overridden = match key.as_str() {
+                Some("match" | "match_") => has_match,
+                _ => merged.contains_key(key),
+            };
+            if !overridden {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        merged
+    }
+
     /// Process a YAML sequence to extract signatures.
     ///
     /// # Arguments
     /// * `seq` - A reference to a vector of YamlValue.
+    /// * `defaults` - Optional values applied to every signature that doesn't set them itself.
     /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect malformed signatures.
     ///
     /// # Returns
     /// * `None` - If the sequence is empty or contains no valid mappings.
     ///
-    fn process_sequence(seq: &Vec<YamlValue>, out: &mut Vec<Signature>) {
+    fn process_sequence(
+        seq: &Vec<YamlValue>,
+        defaults: Option<&serde_yaml::Mapping>,
+        out: &mut Vec<Signature>,
+        problems: &mut Vec<Problem>,
+    ) {
         for item in seq {
             if let Some(m) = item.as_mapping() {
-                if let Some(sig) = extract_signature_from_mapping(m) {
-                    out.push(sig);
+                let merged = defaults.map(|defaults| apply_defaults(m, defaults));
+                match extract_signature_from_mapping(merged.as_ref().unwrap_or(m)) {
+                    Ok(sig) => out.push(sig),
+                    Err(problem) => problems.push(problem),
                 }
             }
         }
@@ -145,32 +815,56 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
     /// # Arguments
     /// * `val` - A reference to a YamlValue.
     /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect malformed signatures.
     ///
     /// # Returns
     /// * `None` - If the value is neither a mapping nor a sequence.
     ///
-    fn process_value(val: &YamlValue, out: &mut Vec<Signature>) {
+    fn process_value(val: &YamlValue, out: &mut Vec<Signature>, problems: &mut Vec<Problem>) {
         match val {
-            YamlValue::Mapping(map) => process_mapping(map, out),
-            YamlValue::Sequence(seq) => process_sequence(seq, out),
+            YamlValue::Mapping(map) => process_mapping(map, out, problems),
+            YamlValue::Sequence(seq) => process_sequence(seq, None, out, problems),
             _ => {}
         }
     }
 
-    /// Parse signatures from a YAML string.
+    /// Read and parse a YAML file.
     ///
     /// # Arguments
-    /// * `content` - A string slice containing the YAML content.
+    /// * `path` - A reference to a Path of the YAML file.
+    /// * `problems` - A mutable reference to a vector to collect unreadable or unparsable files.
     ///
     /// # Returns
-    /// * `Ok(Vec<Signature>)` - If parsing is successful.
-    /// * `Err(serde_yaml::Error)` - If parsing fails.
+    /// * `Some(YamlValue)` - The parsed content.
+    /// * `None` - If there was an error reading or parsing the file.
+    ///
+    fn read_yaml_file(path: &Path, problems: &mut Vec<Problem>) -> Option<YamlValue> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                problems.push(format!("{}: {:?}: {}", crate::localisator::get("error_read_file"), path, e).into());
+                return None;
+            }
+        };
+        match serde_yaml::from_str(&content) {
+            Ok(val) => Some(val),
+            Err(e) => {
+                problems.push(format!("{}: {:?}: {}", crate::localisator::get("error_parse_yaml"), path, e).into());
+                None
+            }
+        }
+    }
+
+    /// Record the file a batch of freshly loaded signatures came from.
+    ///
+    /// # Arguments
+    /// * `signatures` - The signatures loaded from the file.
+    /// * `source` - The file, as shown to the user.
     ///
-    fn parse_signatures_from_str(content: &str) -> Result<Vec<Signature>, serde_yaml::Error> {
-        let val: YamlValue = serde_yaml::from_str(content)?;
-        let mut out = Vec::new();
-        process_value(&val, &mut out);
-        Ok(out)
+    fn tag_source(signatures: &mut [Signature], source: &str) {
+        for sig in signatures {
+            sig.source = Some(source.to_string());
+        }
     }
 
     /// Load signatures from a YAML file and append them to the output vector.
@@ -178,27 +872,63 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
     /// # Arguments
     /// * `path` - A reference to a Path of the YAML file.
     /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect unreadable files and malformed signatures.
     ///
     /// # Returns
     /// * `None` - If there was an error reading or parsing the file.
     ///
-    fn load_signatures_from_file(path: &Path, out: &mut Vec<Signature>) {
-        match std::fs::read_to_string(path) {
-            Ok(content) => match parse_signatures_from_str(&content) {
-                Ok(mut sigs) => out.append(&mut sigs),
-                Err(e) => eprintln!(
-                    "{}: {:?}: {}",
-                    crate::localisator::get("error_parse_yaml"),
-                    path,
-                    e
-                ),
-            },
-            Err(e) => eprintln!(
-                "{}: {:?}: {}",
-                crate::localisator::get("error_read_file"),
-                path,
-                e
-            ),
+    fn load_signatures_from_file(path: &Path, out: &mut Vec<Signature>, problems: &mut Vec<Problem>) {
+        if let Some(val) = read_yaml_file(path, problems) {
+            let mut file_problems = Vec::new();
+            let start = out.len();
+            process_value(&val, out, &mut file_problems);
+            tag_source(&mut out[start..], &path.display().to_string());
+            problems.extend(file_problems.into_iter().map(|p| p.located(&format!("{:?}", path))));
+        }
+    }
+
+    /// Load signatures from a bundle: a YAML list of sections, each laid out like a signature
+    /// file (`signatures` and optional `defaults`) plus an optional `file` label.
+    ///
+    /// Unlike a single file, a bundle reports sections without a `signatures` list and entries
+    /// that aren't mappings, so nothing is dropped silently.
+    ///
+    /// # Arguments
+    /// * `path` - A reference to a Path of the bundle file.
+    /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect malformed sections and signatures.
+    ///
+    fn load_signatures_from_bundle(path: &Path, out: &mut Vec<Signature>, problems: &mut Vec<Problem>) {
+        let Some(val) = read_yaml_file(path, problems) else {
+            return;
+        };
+        let Some(sections) = val.as_sequence() else {
+            problems.push(format!("{:?}: {}", path, crate::localisator::get("signature_invalid_bundle")).into());
+            return;
+        };
+        for (i, section) in sections.iter().enumerate() {
+            let label = section
+                .get("file")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("#{}", i + 1));
+            let mut section_problems = Vec::new();
+            match section.get("signatures").and_then(|v| v.as_sequence()) {
+                Some(seq) => {
+                    for _ in seq.iter().filter(|item| !item.is_mapping()) {
+                        section_problems.push(crate::localisator::get("signature_invalid_entry").into());
+                    }
+                    let start = out.len();
+                    process_value(section, out, &mut section_problems);
+                    tag_source(&mut out[start..], &format!("{} [{}]", path.display(), label));
+                }
+                None => section_problems.push(crate::localisator::get("signature_invalid_bundle_section").into()),
+            }
+            problems.extend(
+                section_problems
+                    .into_iter()
+                    .map(|p| p.located(&format!("{:?} [{}]", path, label))),
+            );
         }
     }
 
@@ -207,33 +937,52 @@ pub fn load_signatures() -> Result<Vec<Signature>, ScanError> {
     /// # Arguments
     /// * `dir` - A reference to a Path of the directory.
     /// * `out` - A mutable reference to a vector to collect signatures.
+    /// * `problems` - A mutable reference to a vector to collect unreadable files and malformed signatures.
     ///
     /// # Returns
     /// * `None` - If there was an error reading the directory.
     ///
-    fn collect_signatures_from_dir(dir: &Path, out: &mut Vec<Signature>) {
+    fn collect_signatures_from_dir(dir: &Path, out: &mut Vec<Signature>, problems: &mut Vec<Problem>) {
         if let Ok(entries) = std::fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
                 if path.is_dir() {
-                    collect_signatures_from_dir(&path, out);
+                    collect_signatures_from_dir(&path, out, problems);
                 } else if is_yaml_file(&path) {
-                    load_signatures_from_file(&path, out);
+                    load_signatures_from_file(&path, out, problems);
                 }
             }
         }
     }
 
     let mut results = Vec::new();
-    let base = Path::new("signatures");
-    if !base.exists() {
-        return Err(ScanError::Config(crate::localisator::get(
-            "error_signatures_dir_not_found",
-        )));
+    let mut problems = Vec::new();
+    if let Some(base) = base {
+        if !base.exists() {
+            return Err(ScanError::Config(crate::localisator::get(
+                "error_signatures_dir_not_found",
+            )));
+        }
+        if base.is_file() && bundle {
+            load_signatures_from_bundle(base, &mut results, &mut problems);
+        } else if base.is_file() {
+            load_signatures_from_file(base, &mut results, &mut problems);
+        } else {
+            collect_signatures_from_dir(base, &mut results, &mut problems);
+        }
     }
-
-    collect_signatures_from_dir(base, &mut results);
-    results.sort_by(|a, b| a.name.cmp(&b.name).then(a.match_.cmp(&b.match_)));
-    results.dedup_by(|a, b| a.name == b.name && a.match_ == b.match_);
-    Ok(results)
+    if builtin {
+        let mut embedded = Vec::new();
+        match serde_yaml::from_str(EMBEDDED_SIGNATURES) {
+            Ok(val) => process_value(&val, &mut embedded, &mut problems),
+            Err(e) => problems.push(format!("{}: {}", crate::localisator::get("error_parse_yaml"), e).into()),
+        }
+        let overridden: std::collections::HashSet<_> = results.iter().map(|sig| sig.name.clone()).collect();
+        results.extend(embedded.into_iter().filter(|sig| !overridden.contains(&sig.name)));
+    }
+    if dedupe {
+        results.sort_by(|a, b| a.dedup_key().cmp(&b.dedup_key()));
+        results.dedup_by(|a, b| a.dedup_key() == b.dedup_key());
+    }
+    Ok((results, problems))
 }
@@ -0,0 +1,190 @@
+use crate::error::ScanError;
+use crate::scanner::{ScanReport, ScanResult};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::net::TcpStream;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long connecting to a TCP sink may take before it is skipped.
+const TCP_SINK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Receiver of scan results as they are discovered, e.g. a file or a network endpoint.
+///
+/// A sink handles its own errors: it may stop writing after a failure, but must not return it,
+/// so one broken sink never affects the scan or the other sinks (see `Sinks`).
+///
+pub trait ResultSink: Send + Sync {
+    /// Called for every open port as soon as it is found, in discovery order.
+    fn on_result(&self, r: &ScanResult);
+
+    /// Called with the report once the scan of a host is done, after all its results.
+    fn finish(&self, report: &ScanReport);
+}
+
+/// Where a sink writes to, as given in `sinks` or with `--sink`.
+///
+/// Variants:
+/// * `JsonFile` - A file of JSON lines (`json:results.jsonl`), created or truncated when the run starts.
+/// * `Tcp` - A TCP endpoint receiving the same JSON lines (`tcp:127.0.0.1:9000`).
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkSpec {
+    JsonFile(PathBuf),
+    Tcp(String),
+}
+
+/// FromStr implementation for SinkSpec, accepting `json:<path>` and `tcp:<host>:<port>`.
+///
+impl FromStr for SinkSpec {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ScanError::Config(format!("{}: {}", crate::localisator::get("error_invalid_sink"), s));
+        match s.split_once(':') {
+            Some((kind, path)) if kind.eq_ignore_ascii_case("json") && !path.is_empty() => {
+                Ok(SinkSpec::JsonFile(PathBuf::from(path)))
+            }
+            Some((kind, addr)) if kind.eq_ignore_ascii_case("tcp") && addr.rsplit_once(':').is_some_and(|(host, port)| {
+                !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port > 0)
+            }) => Ok(SinkSpec::Tcp(addr.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Sink writing one JSON object per line: `{"event":"result","result":{...}}` for every open
+/// port and `{"event":"report","report":{...}}` when a host is done. The results of a host
+/// always come before its report, which names the host.
+///
+/// After the first failed write the sink prints a warning and drops the writer.
+///
+pub struct JsonLinesSink<W: Write + Send> {
+    label: String,
+    writer: Mutex<Option<W>>,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    /// Create a sink writing to `writer`. The label names the sink in warnings (e.g. the file).
+    pub fn new(label: impl Into<String>, writer: W) -> Self {
+        JsonLinesSink {
+            label: label.into(),
+            writer: Mutex::new(Some(writer)),
+        }
+    }
+
+    /// Write one line, disabling the sink if that fails.
+    fn write_line(&self, line: serde_json::Value) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(w) = writer.as_mut() else {
+            return;
+        };
+        if let Err(e) = writeln!(w, "{}", line).and_then(|()| w.flush()) {
+            eprintln!("{} ({}): {}", crate::localisator::get("warning_sink_failed"), self.label, e);
+            *writer = None;
+        }
+    }
+}
+
+impl<W: Write + Send> ResultSink for JsonLinesSink<W> {
+    fn on_result(&self, r: &ScanResult) {
+        self.write_line(serde_json::json!({ "event": "result", "result": r }));
+    }
+
+    fn finish(&self, report: &ScanReport) {
+        self.write_line(serde_json::json!({ "event": "report", "report": report }));
+    }
+}
+
+/// All sinks of a run. Every sink receives every result; a sink that panics is disabled with a
+/// warning, so the others keep receiving results.
+///
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<(Box<dyn ResultSink>, AtomicBool)>,
+}
+
+impl Sinks {
+    /// Create an empty set of sinks.
+    pub fn new() -> Self {
+        Sinks::default()
+    }
+
+    /// Add a sink.
+    pub fn push(&mut self, sink: Box<dyn ResultSink>) {
+        self.sinks.push((sink, AtomicBool::new(false)));
+    }
+
+    /// Check whether there is no sink.
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Call `f` for every sink that hasn't failed yet, catching panics.
+    fn each(&self, f: impl Fn(&dyn ResultSink)) {
+        for (sink, failed) in &self.sinks {
+            if failed.load(Ordering::SeqCst) {
+                continue;
+            }
+            if catch_unwind(AssertUnwindSafe(|| f(sink.as_ref()))).is_err() {
+                failed.store(true, Ordering::SeqCst);
+                eprintln!("{}", crate::localisator::get("warning_sink_failed"));
+            }
+        }
+    }
+}
+
+impl ResultSink for Sinks {
+    fn on_result(&self, r: &ScanResult) {
+        self.each(|sink| sink.on_result(r));
+    }
+
+    fn finish(&self, report: &ScanReport) {
+        self.each(|sink| sink.finish(report));
+    }
+}
+
+/// Open the sinks of a run. A sink that can't be opened (e.g. an unreachable TCP endpoint) is
+/// skipped with a warning rather than failing the scan.
+///
+/// # Arguments
+/// * `specs` - The sinks to open, see `config::get_sinks`.
+///
+/// # Returns
+/// * The sinks that could be opened.
+///
+pub fn open_sinks(specs: &[SinkSpec]) -> Sinks {
+    let mut sinks = Sinks::new();
+    for spec in specs {
+        let (label, opened) = match spec {
+            SinkSpec::JsonFile(path) => (path.display().to_string(), File::create(path).map(|file| {
+                Box::new(JsonLinesSink::new(path.display().to_string(), LineWriter::new(file))) as Box<dyn ResultSink>
+            })),
+            SinkSpec::Tcp(addr) => (addr.clone(), connect(addr).map(|stream| {
+                Box::new(JsonLinesSink::new(addr.clone(), stream)) as Box<dyn ResultSink>
+            })),
+        };
+        match opened {
+            Ok(sink) => sinks.push(sink),
+            Err(e) => eprintln!("{} ({}): {}", crate::localisator::get("warning_sink_failed"), label, e),
+        }
+    }
+    sinks
+}
+
+/// Connect to a TCP sink, trying every address the endpoint resolves to.
+fn connect(addr: &str) -> std::io::Result<TcpStream> {
+    use std::net::ToSocketAddrs;
+    let mut last_error = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TCP_SINK_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)))
+}
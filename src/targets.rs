@@ -0,0 +1,163 @@
+use crate::config::{get_allowed_targets, get_dns_server, get_max_dns_concurrent, is_dotted_number, parse_ip_range, MAX_NETWORK_HOSTS};
+use crate::error::ScanError;
+use ipnet::IpNet;
+use serde_yaml::Value as YamlValue;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// The hosts that were added to and removed from a target list since a previous version of it.
+///
+/// * `added` - Hosts of the current list that the previous one lacks, in the order of the current list.
+/// * `removed` - Hosts of the previous list that the current one lacks, in the order of the previous list.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetDelta {
+    pub added: Vec<IpAddr>,
+    pub removed: Vec<IpAddr>,
+}
+
+/// Parse a target list: one address, hostname, network (e.g. `10.0.0.0/24`) or address range
+/// (e.g. `10.0.0.10-10.0.0.50`) per line. Empty lines and lines starting with `#` are skipped.
+///
+/// Networks and ranges are expanded like an `ip` target, and hostnames are resolved to their
+/// first address, in parallel but at most `max_dns_concurrent` at a time. In safe mode every host
+/// has to be in `allowed_targets`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `content` - The target list.
+///
+/// # Returns
+/// * `Ok(Vec<IpAddr>)` - The hosts in order of first appearance, without duplicates.
+/// * `Err(ScanError)` - If a line is not a valid target (the error names its line number), a
+///   network or range is too large, a `ScanError::Resolve` if a hostname can't be resolved, or a
+///   `ScanError::NotAllowed` for a host outside `allowed_targets` in safe mode.
+///
+pub fn parse_target_list(config: &HashMap<String, YamlValue>, content: &str) -> Result<Vec<IpAddr>, ScanError> {
+    let allowed = get_allowed_targets(config)?;
+    let entries = parse_entries(content)?;
+    let names: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Name(name) => Some(*name),
+            Entry::Hosts(_) => None,
+        })
+        .collect();
+    let mut resolved = Vec::new();
+    if !names.is_empty() {
+        let dns_server = get_dns_server(config)?;
+        crate::resolver::set_max_concurrent_lookups(get_max_dns_concurrent(config)?);
+        resolved = crate::resolver::resolve_hosts(&names, dns_server);
+    }
+    let mut resolved = resolved.into_iter();
+    let mut seen = HashSet::new();
+    let mut hosts = Vec::new();
+    for entry in entries {
+        let expanded = match entry {
+            Entry::Hosts(expanded) => expanded,
+            Entry::Name(_) => match resolved.next() {
+                Some(result) => vec![result?[0]],
+                None => continue,
+            },
+        };
+        if let Some(allowed) = &allowed {
+            if let Some(host) = expanded.iter().find(|host| !allowed.iter().any(|net| net.contains(*host))) {
+                return Err(ScanError::NotAllowed(format!(
+                    "{}: {}",
+                    crate::localisator::get("error_target_not_allowed"),
+                    host
+                )));
+            }
+        }
+        hosts.extend(expanded.into_iter().filter(|host| seen.insert(*host)));
+    }
+    Ok(hosts)
+}
+
+/// A line of a target list, see `parse_target_list`.
+///
+/// * `Hosts` - The addresses of an address, network or range.
+/// * `Name` - A hostname that still has to be resolved.
+///
+enum Entry<'a> {
+    Hosts(Vec<IpAddr>),
+    Name(&'a str),
+}
+
+/// Parse the lines of a target list without resolving hostnames.
+///
+/// # Arguments
+/// * `content` - The target list.
+///
+/// # Returns
+/// * `Ok(Vec<Entry>)` - One entry per target line.
+/// * `Err(ScanError)` - If a line is not a valid target or a network or range is too large.
+///
+fn parse_entries(content: &str) -> Result<Vec<Entry<'_>>, ScanError> {
+    let mut entries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |key: &str| {
+            ScanError::Config(format!("{} {}: {}", crate::localisator::get(key), number + 1, line))
+        };
+        let expanded: Vec<IpAddr> = if let Ok(addr) = line.parse::<IpAddr>() {
+            vec![addr]
+        } else if let Ok(network) = line.parse::<IpNet>() {
+            let expanded: Vec<IpAddr> = network.hosts().take(MAX_NETWORK_HOSTS + 1).collect();
+            if expanded.len() > MAX_NETWORK_HOSTS {
+                return Err(invalid("error_network_too_large_line"));
+            }
+            expanded
+        } else {
+            match parse_ip_range(line) {
+                Ok(Some(range)) => range,
+                Ok(None) if crate::resolver::is_valid_hostname(line) && !is_dotted_number(line) => {
+                    entries.push(Entry::Name(line));
+                    continue;
+                }
+                Ok(None) | Err(_) => return Err(invalid("error_invalid_target_line")),
+            }
+        };
+        entries.push(Entry::Hosts(expanded));
+    }
+    Ok(entries)
+}
+
+/// Read and parse a target list file, see `parse_target_list`.
+///
+/// # Arguments
+/// * `config` - A reference to a HashMap containing configuration parameters.
+/// * `path` - The path of the target list.
+///
+/// # Returns
+/// * `Ok(Vec<IpAddr>)` - The hosts of the list.
+/// * `Err(ScanError)` - If the file can't be read or holds an invalid target.
+///
+pub fn read_target_list(config: &HashMap<String, YamlValue>, path: &Path) -> Result<Vec<IpAddr>, ScanError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ScanError::Config(format!("{}: {}: {}", crate::localisator::get("error_read_file"), path.display(), e))
+    })?;
+    parse_target_list(config, &content)
+}
+
+/// Compare a previous and a current target list.
+///
+/// # Arguments
+/// * `previous` - The hosts of the previous list (e.g. yesterday's inventory).
+/// * `current` - The hosts of the current list.
+///
+/// # Returns
+/// * The hosts added to and removed from the list. Hosts in both lists are in neither.
+///
+pub fn target_delta(previous: &[IpAddr], current: &[IpAddr]) -> TargetDelta {
+    let previous_set: HashSet<&IpAddr> = previous.iter().collect();
+    let current_set: HashSet<&IpAddr> = current.iter().collect();
+    TargetDelta {
+        added: current.iter().filter(|host| !previous_set.contains(host)).copied().collect(),
+        removed: previous.iter().filter(|host| !current_set.contains(host)).copied().collect(),
+    }
+}
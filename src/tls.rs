@@ -0,0 +1,167 @@
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::nid::Nid;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::{X509NameRef, X509};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::IpAddr;
+
+/// Default number of days before expiry from which a certificate is flagged, see
+/// `TlsCertificate::expires_soon`.
+pub const DEFAULT_TLS_EXPIRY_WARNING_DAYS: u32 = 30;
+
+/// The certificate a TLS port presented.
+///
+/// # Fields
+/// * `subject` - The subject's distinguished name (e.g., `CN=example.com, O=Example`).
+/// * `issuer` - The issuer's distinguished name.
+/// * `not_before` / `not_after` - The validity period as RFC 3339 timestamps in UTC.
+/// * `sans` - The subject alternative names: DNS names, IP addresses, emails and URIs.
+/// * `server_name` - The name sent with SNI, or `None` if the server chose its default certificate.
+/// * `days_left` - Whole days until the certificate expires, negative once it has.
+/// * `expired` - Whether the certificate is past `not_after`.
+/// * `expires_soon` - Whether it hasn't expired yet but will within the warning period
+///   (`tls_expiry_warning_days`).
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TlsCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub sans: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    pub days_left: i64,
+    pub expired: bool,
+    pub expires_soon: bool,
+}
+
+/// TLS client shared by all probes. Certificates are not verified, since the point is to report
+/// them, including self-signed and expired ones.
+static CONNECTOR: Lazy<Option<SslConnector>> = Lazy::new(|| {
+    let mut builder = SslConnector::builder(SslMethod::tls_client()).ok()?;
+    builder.set_verify(SslVerifyMode::NONE);
+    Some(builder.build())
+});
+
+/// Run a TLS handshake on an open connection and read the certificate the server presents.
+///
+/// A server hosting several certificates picks one by the name sent with SNI; without a name
+/// (e.g. when scanning an IP address) it presents its default certificate.
+///
+/// # Arguments
+/// * `stream` - The connection, with a read timeout set so the handshake can't hang.
+/// * `server_name` - The hostname sent with SNI, if any.
+/// * `warning_days` - How many days before expiry a certificate counts as expiring soon.
+///
+/// # Returns
+/// * `Some(TlsCertificate)` - The server's certificate.
+/// * `None` - If the handshake failed (e.g. not a TLS service) or no certificate was presented.
+///
+pub fn probe_certificate<S: Read + Write>(
+    stream: S,
+    server_name: Option<&str>,
+    warning_days: u32,
+) -> Option<TlsCertificate> {
+    let mut config = CONNECTOR.as_ref()?.configure().ok()?;
+    config.set_verify_hostname(false);
+    config.set_use_server_name_indication(server_name.is_some());
+    let stream = config.connect(server_name.unwrap_or(""), stream).ok()?;
+    let cert = stream.ssl().peer_certificate()?;
+    Some(describe_certificate(&cert, server_name, warning_days))
+}
+
+impl TlsCertificate {
+    /// The names in the certificate as one text for signatures to match: the subject, the issuer
+    /// and every subject alternative name, one per line.
+    pub fn identity_text(&self) -> String {
+        let mut text = format!("{}\n{}", self.subject, self.issuer);
+        for san in &self.sans {
+            text.push('\n');
+            text.push_str(san);
+        }
+        text
+    }
+}
+
+/// Describe a certificate, see `TlsCertificate`.
+///
+/// # Arguments
+/// * `cert` - The certificate.
+/// * `server_name` - The hostname it was requested for with SNI, if any.
+/// * `warning_days` - How many days before expiry a certificate counts as expiring soon.
+///
+/// # Returns
+/// * The certificate's details, with its expiry compared to the current time.
+///
+pub fn describe_certificate(cert: &X509, server_name: Option<&str>, warning_days: u32) -> TlsCertificate {
+    let days_left = Asn1Time::days_from_now(0)
+        .ok()
+        .and_then(|now| now.diff(cert.not_after()).ok())
+        // Days and seconds have the same sign; a part of a day past expiry counts as a whole one
+        .map_or(0, |diff| i64::from(diff.days) - i64::from(diff.secs < 0));
+    let sans = cert
+        .subject_alt_names()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| {
+                    name.dnsname()
+                        .map(str::to_string)
+                        .or_else(|| name.ipaddress().and_then(format_ip))
+                        .or_else(|| name.email().map(str::to_string))
+                        .or_else(|| name.uri().map(str::to_string))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    TlsCertificate {
+        subject: format_name(cert.subject_name()),
+        issuer: format_name(cert.issuer_name()),
+        not_before: format_time(cert.not_before()),
+        not_after: format_time(cert.not_after()),
+        sans,
+        server_name: server_name.map(str::to_string),
+        days_left,
+        expired: days_left < 0,
+        expires_soon: (0..i64::from(warning_days)).contains(&days_left),
+    }
+}
+
+/// Format a distinguished name like `CN=example.com, O=Example`.
+fn format_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = match entry.object().nid() {
+                Nid::UNDEF => entry.object().to_string(),
+                nid => nid.short_name().map_or_else(|_| entry.object().to_string(), str::to_string),
+            };
+            let value = entry
+                .data()
+                .to_string()
+                .unwrap_or_else(|_| String::from_utf8_lossy(entry.data().as_slice()).into_owned());
+            format!("{}={}", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format a certificate time as RFC 3339 (e.g., `2025-03-01T00:00:00Z`), falling back to
+/// OpenSSL's own format if it can't be parsed.
+fn format_time(time: &Asn1TimeRef) -> String {
+    let text = time.to_string();
+    chrono::NaiveDateTime::parse_from_str(&text, "%b %e %H:%M:%S %Y GMT")
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or(text)
+}
+
+/// Format the raw address of an IP SAN.
+fn format_ip(bytes: &[u8]) -> Option<String> {
+    match bytes.len() {
+        4 => <[u8; 4]>::try_from(bytes).ok().map(|b| IpAddr::from(b).to_string()),
+        16 => <[u8; 16]>::try_from(bytes).ok().map(|b| IpAddr::from(b).to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,236 @@
+use crate::error::ScanError;
+use crate::localisator;
+use crate::scanner::{
+    format_duration, scan_work_parallel, Protocol, ScanOptions, ScanResult, ScanStats,
+};
+use crate::signatures::Signature;
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::IsTerminal;
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a key press before redrawing.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Number of rows to move on PageUp/PageDown.
+const PAGE: usize = 10;
+
+/// Live state shown by the TUI.
+///
+/// # Fields
+/// * `target` - The scan target as entered by the user.
+/// * `open_ports` - Open ports discovered so far, sorted by port.
+/// * `table` - Scroll/selection state of the results table.
+/// * `started` - When the scan was started, for elapsed time and throughput.
+/// * `done` - Whether the scan has finished.
+///
+struct TuiState {
+    target: String,
+    open_ports: Vec<ScanResult>,
+    table: TableState,
+    started: Instant,
+    done: bool,
+}
+
+impl TuiState {
+    /// Insert a newly discovered open port, keeping the list sorted by port.
+    fn insert(&mut self, res: ScanResult) {
+        let idx = self
+            .open_ports
+            .partition_point(|r| (r.port, r.protocol) < (res.port, res.protocol));
+        self.open_ports.insert(idx, res);
+    }
+
+    /// Move the table selection by `delta` rows, clamped to the result list.
+    fn scroll(&mut self, delta: isize) {
+        if self.open_ports.is_empty() {
+            return;
+        }
+        let last = self.open_ports.len() - 1;
+        let current = self.table.selected().unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(last);
+        self.table.select(Some(next));
+    }
+}
+
+/// Run a scan while showing a live-updating terminal UI.
+///
+/// The scan runs on a background thread and streams open ports to the UI through the
+/// result callback. The UI can be closed at any time with `q`/`Esc`; if the scan is still
+/// running, the regular progress bar takes over until it finishes so the caller can
+/// still write the log and print the summary.
+///
+/// # Arguments
+/// * `target` - The scan target as entered by the user, shown in the header.
+/// * `ip` - An Arc containing the target IP address.
+/// * `work` - A vector of `(protocol, port)` work units to scan.
+/// * `signatures` - An Arc containing a vector of service signatures.
+/// * `options` - The scan options (thread count, retries).
+/// * `pb` - The progress bar tracking the scan. It is hidden while the UI is shown.
+/// * `on_result` - A callback receiving each open port as it is found, on the scan thread.
+///
+/// # Returns
+/// * `Ok((Vec<ScanResult>, ScanStats))` - Open ports sorted by port, and the scan statistics.
+/// * `Err(ScanError)` - If there was an error during scanning.
+///
+pub fn scan_with_tui<F>(
+    target: &str,
+    ip: Arc<IpAddr>,
+    work: Vec<(Protocol, u16)>,
+    signatures: Arc<Vec<Signature>>,
+    options: &ScanOptions,
+    pb: &ProgressBar,
+    mut on_result: F,
+) -> Result<(Vec<ScanResult>, ScanStats), ScanError>
+where
+    F: FnMut(&ScanResult) + Send + 'static,
+{
+    pb.set_draw_target(ProgressDrawTarget::hidden());
+    let (tx, rx) = mpsc::channel();
+    let scan_pb = pb.clone();
+    let options = options.clone();
+    let handle = std::thread::spawn(move || {
+        scan_work_parallel(ip, work, signatures, &options, &scan_pb, |res| {
+            on_result(res);
+            let _ = tx.send(res.clone());
+        })
+    });
+
+    let mut state = TuiState {
+        target: target.to_string(),
+        open_ports: Vec::new(),
+        table: TableState::default(),
+        started: Instant::now(),
+        done: false,
+    };
+    let ui_result = ratatui::try_init().and_then(|mut terminal| {
+        let res = event_loop(&mut terminal, &mut state, &rx, pb);
+        ratatui::try_restore().and(res)
+    });
+    if let Err(e) = ui_result {
+        eprintln!("{}: {}", localisator::get("error_tui"), e);
+    }
+
+    if std::io::stderr().is_terminal() {
+        pb.set_draw_target(ProgressDrawTarget::stderr());
+    }
+    handle
+        .join()
+        .map_err(|_| ScanError::Config(localisator::get("error_thread_panic")))?
+}
+
+/// Draw the UI and handle key presses until the user quits.
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut TuiState,
+    rx: &Receiver<ScanResult>,
+    pb: &ProgressBar,
+) -> std::io::Result<()> {
+    loop {
+        loop {
+            match rx.try_recv() {
+                Ok(res) => state.insert(res),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    state.done = true;
+                    break;
+                }
+            }
+        }
+        terminal.draw(|frame| draw(frame, state, pb))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => state.scroll(1),
+                    KeyCode::Up | KeyCode::Char('k') => state.scroll(-1),
+                    KeyCode::PageDown => state.scroll(PAGE as isize),
+                    KeyCode::PageUp => state.scroll(-(PAGE as isize)),
+                    KeyCode::Home => state.scroll(isize::MIN),
+                    KeyCode::End => state.scroll(isize::MAX),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Render a single frame: header, progress gauge, results table and key help.
+fn draw(frame: &mut Frame, state: &mut TuiState, pb: &ProgressBar) {
+    let [header, gauge, table, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let elapsed = state.started.elapsed();
+    let done = pb.position();
+    let total = pb.length().unwrap_or(0);
+    let throughput = done as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    let status = if state.done {
+        localisator::get("scan_complete")
+    } else {
+        localisator::get("tui_scanning")
+    };
+    let summary = format!(
+        "{} {}  |  {} {}  |  {:.0} {}  |  {} {}  |  {}",
+        localisator::get("target"),
+        state.target,
+        localisator::get("duration"),
+        format_duration(elapsed),
+        throughput,
+        localisator::get("tui_ports_per_second"),
+        localisator::get("open_ports_count"),
+        state.open_ports.len(),
+        status
+    );
+    frame.render_widget(
+        Paragraph::new(summary).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Port Explorer "),
+        ),
+        header,
+    );
+
+    let ratio = if total == 0 { 1.0 } else { done as f64 / total as f64 };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!("{}/{} ({:.0}%)", done, total, ratio * 100.0)),
+        gauge,
+    );
+
+    let rows = state
+        .open_ports
+        .iter()
+        .map(|r| Row::new(vec![format!("{}/{}", r.port, r.protocol), r.label()]));
+    let results = Table::new(rows, [Constraint::Length(10), Constraint::Min(10)])
+        .header(
+            Row::new(vec![localisator::get("tui_port"), localisator::get("tui_service")])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            " {} {} ",
+            localisator::get("open_ports"),
+            state.target
+        )))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(results, table, &mut state.table);
+
+    frame.render_widget(Paragraph::new(localisator::get("tui_help")), help);
+}
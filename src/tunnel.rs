@@ -0,0 +1,437 @@
+use crate::error::ScanError;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "ssh")]
+use std::sync::{Condvar, Mutex};
+
+/// Port of a jump host if the destination doesn't name one.
+const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Number of SSH connections to the jump host a scan opens at most. Every port being scanned
+/// occupies one of them, so this is the concurrency of a scan through the jump host.
+#[cfg(feature = "ssh")]
+pub const SSH_JUMP_SESSIONS: usize = 8;
+
+/// How long connecting, the handshake and authentication with the jump host may take.
+#[cfg(feature = "ssh")]
+const SSH_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// libssh2 error code of a blocking call that ran into the session timeout.
+#[cfg(feature = "ssh")]
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+
+/// libssh2 error code of a channel the server refused to open.
+#[cfg(feature = "ssh")]
+const LIBSSH2_ERROR_CHANNEL_FAILURE: i32 = -21;
+
+/// Jump host of `ssh_jump`, written as `[user@]host[:port]`.
+///
+/// # Fields
+/// * `user` - The login name, if given. Defaults to `$USER`.
+/// * `host` - The hostname or IP address of the jump host (IPv6 in brackets if a port follows).
+/// * `port` - The SSH port, 22 by default.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshDestination {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: u16,
+}
+
+/// FromStr implementation for SshDestination, accepting `host`, `user@host`, `user@host:2222`
+/// and `user@[2001:db8::1]:2222`.
+///
+impl FromStr for SshDestination {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ScanError::Config(format!("{}: {}", crate::localisator::get("error_invalid_ssh_jump"), s))
+        };
+        let (user, host_port) = match s.rsplit_once('@') {
+            Some((user, host_port)) if !user.is_empty() => (Some(user.to_string()), host_port),
+            Some(_) => return Err(invalid()),
+            None => (None, s),
+        };
+        let (host, port) = if let Some(rest) = host_port.strip_prefix('[') {
+            let (host, after) = rest.split_once(']').ok_or_else(invalid)?;
+            match after {
+                "" => (host, None),
+                _ => (host, Some(after.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else {
+            match host_port.split_once(':') {
+                // More than one colon is a bare IPv6 address without a port
+                Some((host, port)) if !port.contains(':') => (host, Some(port)),
+                _ => (host_port, None),
+            }
+        };
+        let port = match port {
+            Some(port) => port.parse::<u16>().ok().filter(|port| *port != 0).ok_or_else(invalid)?,
+            None => DEFAULT_SSH_PORT,
+        };
+        if host.is_empty() || host.starts_with('-') || host.chars().any(char::is_whitespace) {
+            return Err(invalid());
+        }
+        Ok(SshDestination { user, host: host.to_string(), port })
+    }
+}
+
+/// Display implementation for SshDestination, in the form it is parsed from.
+///
+impl std::fmt::Display for SshDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(f, "{}@", user)?;
+        }
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Connections to an SSH jump host that scan connects are forwarded through, as `direct-tcpip`
+/// channels opened by the jump host.
+///
+/// Up to `SSH_JUMP_SESSIONS` SSH connections are opened on demand and shared by the workers of
+/// all scans; a worker holds one connection while it scans a port.
+///
+#[cfg(feature = "ssh")]
+pub struct SshJump {
+    destination: SshDestination,
+    addr: SocketAddr,
+    idle: Mutex<(Vec<ssh2::Session>, usize)>,
+    available: Condvar,
+}
+
+/// Fallback when Port Explorer is built without the `ssh` feature. No value can exist, as
+/// `ssh_jump` always fails.
+///
+#[cfg(not(feature = "ssh"))]
+#[derive(Debug, PartialEq)]
+pub enum SshJump {}
+
+/// A connection to a scanned port through the jump host, see `SshJump::open`.
+///
+#[cfg(feature = "ssh")]
+pub struct TunnelStream {
+    channel: Option<ssh2::Channel>,
+    session: Option<ssh2::Session>,
+    jump: Arc<SshJump>,
+}
+
+/// Fallback when Port Explorer is built without the `ssh` feature.
+///
+#[cfg(not(feature = "ssh"))]
+pub enum TunnelStream {}
+
+/// Debug implementation for SshJump, showing the destination only.
+///
+#[cfg(feature = "ssh")]
+impl std::fmt::Debug for SshJump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshJump").field("destination", &self.destination).finish()
+    }
+}
+
+/// PartialEq implementation for SshJump: jump hosts are equal if they have the same destination.
+///
+#[cfg(feature = "ssh")]
+impl PartialEq for SshJump {
+    fn eq(&self, other: &Self) -> bool {
+        self.destination == other.destination
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl SshJump {
+    /// Connect to a jump host. The first SSH connection is opened right away, so an unreachable
+    /// host, an unknown host key or failed authentication is reported before any scan starts.
+    ///
+    /// # Arguments
+    /// * `destination` - The jump host.
+    ///
+    /// # Returns
+    /// * `Ok(SshJump)` - The connected jump host.
+    /// * `Err(ScanError)` - If the jump host can't be resolved, reached, verified or logged into.
+    ///
+    pub fn connect(destination: &SshDestination) -> Result<SshJump, ScanError> {
+        use std::net::ToSocketAddrs;
+        let addr = (destination.host.as_str(), destination.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| ssh_error(destination, crate::localisator::get("ssh_unknown_host")))?;
+        let session = open_session(destination, addr)?;
+        Ok(SshJump {
+            destination: destination.clone(),
+            addr,
+            idle: Mutex::new((vec![session], 1)),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Open a connection to a port through the jump host.
+    ///
+    /// The jump host only reports whether it could connect, so a refused or unreachable port
+    /// both fail with `ConnectionRefused`; no answer within `timeout` fails with `TimedOut`.
+    ///
+    /// # Arguments
+    /// * `target` - The address to connect to, as seen from the jump host.
+    /// * `timeout` - How long to wait for the jump host to open the channel.
+    ///
+    /// # Returns
+    /// * `Ok(TunnelStream)` - The connection.
+    /// * `Err(std::io::Error)` - If the port couldn't be connected to.
+    ///
+    pub fn open(self: &Arc<Self>, target: SocketAddr, timeout: Duration) -> std::io::Result<TunnelStream> {
+        let session = self.checkout()?;
+        session.set_timeout(timeout.as_millis().max(1) as u32);
+        match session.channel_direct_tcpip(&target.ip().to_string(), target.port(), None) {
+            Ok(channel) => Ok(TunnelStream {
+                channel: Some(channel),
+                session: Some(session),
+                jump: Arc::clone(self),
+            }),
+            Err(e) => {
+                let kind = match e.code() {
+                    ssh2::ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) => std::io::ErrorKind::TimedOut,
+                    ssh2::ErrorCode::Session(LIBSSH2_ERROR_CHANNEL_FAILURE)
+                        if e.message().contains("prohibited") =>
+                    {
+                        std::io::ErrorKind::PermissionDenied
+                    }
+                    ssh2::ErrorCode::Session(LIBSSH2_ERROR_CHANNEL_FAILURE) => {
+                        std::io::ErrorKind::ConnectionRefused
+                    }
+                    _ => std::io::ErrorKind::Other,
+                };
+                // A channel that timed out may still be opened later, so the connection is
+                // left in an unknown state and replaced
+                let healthy = matches!(
+                    kind,
+                    std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::PermissionDenied
+                );
+                self.checkin(session, healthy);
+                Err(std::io::Error::new(kind, e.message().to_string()))
+            }
+        }
+    }
+
+    /// Take an idle SSH connection, opening a new one if fewer than `SSH_JUMP_SESSIONS` exist, or
+    /// wait until one is returned.
+    fn checkout(&self) -> std::io::Result<ssh2::Session> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(session) = idle.0.pop() {
+                return Ok(session);
+            }
+            if idle.1 < SSH_JUMP_SESSIONS {
+                idle.1 += 1;
+                drop(idle);
+                return open_session(&self.destination, self.addr).map_err(|e| {
+                    self.checkin_failed();
+                    std::io::Error::other(e.to_string())
+                });
+            }
+            idle = self.available.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Return an SSH connection; `healthy` is `false` if it must not be used again.
+    fn checkin(&self, session: ssh2::Session, healthy: bool) {
+        if !healthy {
+            return self.checkin_failed();
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        idle.0.push(session);
+        self.available.notify_one();
+    }
+
+    /// Give up the slot of an SSH connection that was dropped or couldn't be opened.
+    fn checkin_failed(&self) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        idle.1 -= 1;
+        self.available.notify_one();
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+impl SshJump {
+    /// Fallback when Port Explorer is built without the `ssh` feature.
+    pub fn open(self: &Arc<Self>, _target: SocketAddr, _timeout: Duration) -> std::io::Result<TunnelStream> {
+        match **self {}
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl TunnelStream {
+    /// Set how long a read may block, `None` for no limit.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+        if let Some(session) = &self.session {
+            session.set_timeout(timeout.map_or(0, |timeout| timeout.as_millis().max(1) as u32));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+impl TunnelStream {
+    /// Fallback when Port Explorer is built without the `ssh` feature.
+    pub fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> std::io::Result<()> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl Read for TunnelStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.channel {
+            Some(channel) => channel.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl Write for TunnelStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.channel {
+            Some(channel) => channel.write(buf),
+            None => Err(std::io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.channel {
+            Some(channel) => channel.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Drop implementation for TunnelStream: closes the channel and returns the SSH connection.
+///
+#[cfg(feature = "ssh")]
+impl Drop for TunnelStream {
+    fn drop(&mut self) {
+        drop(self.channel.take());
+        if let Some(session) = self.session.take() {
+            self.jump.checkin(session, true);
+        }
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+impl Read for TunnelStream {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        match *self {}
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+impl Write for TunnelStream {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        match *self {}
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match *self {}
+    }
+}
+
+/// Build the error of a jump host that couldn't be used.
+#[cfg(feature = "ssh")]
+fn ssh_error(destination: &SshDestination, detail: impl std::fmt::Display) -> ScanError {
+    ScanError::Io(std::io::Error::other(format!(
+        "{} {}: {}",
+        crate::localisator::get("error_ssh_jump"),
+        destination,
+        detail
+    )))
+}
+
+/// Open an SSH connection to the jump host: connect, check its host key against
+/// `~/.ssh/known_hosts`, and log in with the SSH agent or an unencrypted default key
+/// (`~/.ssh/id_ed25519`, `id_ecdsa`, `id_rsa`).
+#[cfg(feature = "ssh")]
+fn open_session(destination: &SshDestination, addr: SocketAddr) -> Result<ssh2::Session, ScanError> {
+    let fail = |detail: &dyn std::fmt::Display| ssh_error(destination, detail);
+    let ssh_dir = std::env::var_os("HOME")
+        .map(|home| std::path::PathBuf::from(home).join(".ssh"))
+        .unwrap_or_default();
+    let tcp = std::net::TcpStream::connect_timeout(&addr, SSH_SETUP_TIMEOUT).map_err(|e| fail(&e))?;
+    let mut session = ssh2::Session::new().map_err(|e| fail(&e))?;
+    session.set_tcp_stream(tcp);
+    session.set_timeout(SSH_SETUP_TIMEOUT.as_millis() as u32);
+    session.handshake().map_err(|e| fail(&e))?;
+
+    let mut known_hosts = session.known_hosts().map_err(|e| fail(&e))?;
+    // A missing file just means that no host is known yet
+    let _ = known_hosts.read_file(&ssh_dir.join("known_hosts"), ssh2::KnownHostFileKind::OpenSSH);
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| fail(&crate::localisator::get("ssh_host_key_unknown")))?;
+    match known_hosts.check_port(&destination.host, destination.port, key) {
+        ssh2::CheckResult::Match => {}
+        ssh2::CheckResult::Mismatch => return Err(fail(&crate::localisator::get("ssh_host_key_mismatch"))),
+        _ => return Err(fail(&crate::localisator::get("ssh_host_key_unknown"))),
+    }
+
+    let user = destination
+        .user
+        .clone()
+        .or_else(|| std::env::var("USER").ok())
+        .ok_or_else(|| fail(&crate::localisator::get("ssh_auth_failed")))?;
+    let _ = session.userauth_agent(&user);
+    for key in ["id_ed25519", "id_ecdsa", "id_rsa"] {
+        let path = ssh_dir.join(key);
+        if session.authenticated() {
+            break;
+        }
+        if path.exists() {
+            let _ = session.userauth_pubkey_file(&user, None, &path, None);
+        }
+    }
+    if !session.authenticated() {
+        return Err(fail(&crate::localisator::get("ssh_auth_failed")));
+    }
+    Ok(session)
+}
+
+/// Get the connected jump host for a destination, connecting on first use. Later scans of the
+/// same run (e.g. every host of a network or batch scan) share the connections.
+///
+/// # Arguments
+/// * `destination` - The jump host.
+///
+/// # Returns
+/// * `Ok(Arc<SshJump>)` - The connected jump host.
+/// * `Err(ScanError)` - If the jump host can't be used, see `SshJump::connect`.
+///
+#[cfg(feature = "ssh")]
+pub fn ssh_jump(destination: &SshDestination) -> Result<Arc<SshJump>, ScanError> {
+    static JUMPS: Mutex<Vec<Arc<SshJump>>> = Mutex::new(Vec::new());
+    let mut jumps = JUMPS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(jump) = jumps.iter().find(|jump| jump.destination == *destination) {
+        return Ok(Arc::clone(jump));
+    }
+    let jump = Arc::new(SshJump::connect(destination)?);
+    jumps.push(Arc::clone(&jump));
+    Ok(jump)
+}
+
+/// Fallback when Port Explorer is built without the `ssh` feature.
+///
+/// # Returns
+/// * `Err(ScanError)` - Always, as scanning through a jump host is not available.
+///
+#[cfg(not(feature = "ssh"))]
+pub fn ssh_jump(_destination: &SshDestination) -> Result<Arc<SshJump>, ScanError> {
+    Err(ScanError::Config(crate::localisator::get("error_ssh_disabled")))
+}
@@ -0,0 +1,95 @@
+use port_explorer::baseline::{compare, format_deviation, load_baseline, parse_baseline, Deviation};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+use std::net::IpAddr;
+
+fn result(port: u16, protocol: Protocol, service: Option<&str>) -> ScanResult {
+    ScanResult {
+        port,
+        service: service.map(str::to_string),
+        version: None,
+        protocol,
+        state: PortState::Open,
+        latency: None,
+        identified_by: IdentificationSource::None,
+        signature_file: None,
+        tls: None,
+    }
+}
+
+fn report(ip: &str, open_ports: Vec<ScanResult>) -> ScanReport {
+    ScanReport {
+        target: ip.into(),
+        ip: ip.parse().unwrap(),
+        port_range: "1-1024".into(),
+        duration_ms: 10,
+        identification_rate: None,
+        open_ports,
+        stats: ScanStats::default(),
+        conflicts: Vec::new(),
+    }
+}
+
+#[test]
+fn test_parse_baseline_formats() {
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let single = r#"{"ip": "10.0.0.1", "open_ports": [{"port": 22, "protocol": "tcp", "service": "SSH"}]}"#;
+    let baseline = parse_baseline(single).unwrap();
+    assert_eq!(baseline.ports(&ip).unwrap(), &[result(22, Protocol::Tcp, Some("SSH"))]);
+
+    // Batch output: failed jobs are skipped and reports of the same host merged
+    let batch = r#"[
+        {"job": 0, "report": {"ip": "10.0.0.1", "open_ports": [{"port": 22, "protocol": "tcp", "service": "SSH"}]}},
+        {"job": 1, "error": "Resolve error"},
+        {"job": 2, "report": {"ip": "10.0.0.1", "open_ports": [{"port": 53, "protocol": "udp", "service": null}]}},
+        {"job": 3, "report": {"ip": "10.0.0.2", "open_ports": []}}
+    ]"#;
+    let baseline = parse_baseline(batch).unwrap();
+    assert_eq!(baseline.ports(&ip).unwrap().len(), 2);
+    assert_eq!(baseline.ports(&"10.0.0.2".parse().unwrap()).unwrap().len(), 0);
+    assert!(baseline.ports(&"10.0.0.3".parse().unwrap()).is_none());
+
+    for invalid in ["nope", r#"{"ip": "host", "open_ports": []}"#, r#"[{"ip": "10.0.0.1"}]"#,
+        r#"{"ip": "10.0.0.1", "open_ports": [{"port": 22, "protocol": "sctp"}]}"#] {
+        assert!(parse_baseline(invalid).is_err(), "{}", invalid);
+    }
+    assert!(load_baseline(std::path::Path::new("does/not/exist.json")).is_err());
+}
+
+#[test]
+fn test_compare_reports_new_ports_and_changed_services() {
+    port_explorer::localisator::init("en");
+    let baseline = parse_baseline(
+        r#"{"ip": "10.0.0.1", "open_ports": [
+            {"port": 22, "protocol": "tcp", "service": "SSH"},
+            {"port": 80, "protocol": "tcp", "service": "HTTP"},
+            {"port": 443, "protocol": "tcp", "service": null},
+            {"port": 8080, "protocol": "tcp", "service": "Jellyfin"}]}"#,
+    )
+    .unwrap();
+    let scan = report(
+        "10.0.0.1",
+        vec![
+            result(22, Protocol::Tcp, Some("ssh")),
+            result(80, Protocol::Tcp, Some("SSH")),
+            result(443, Protocol::Tcp, Some("Grafana")),
+            result(8080, Protocol::Tcp, None),
+            result(53, Protocol::Udp, None),
+        ],
+    );
+    let deviations = compare(&baseline, &scan);
+    assert_eq!(
+        deviations,
+        vec![
+            Deviation::ChangedService { result: result(80, Protocol::Tcp, Some("SSH")), baseline: Some("HTTP".into()) },
+            Deviation::ChangedService { result: result(443, Protocol::Tcp, Some("Grafana")), baseline: None },
+            Deviation::NewPort(result(53, Protocol::Udp, None)),
+        ]
+    );
+    let ip = scan.ip;
+    assert_eq!(format_deviation(&ip, &deviations[0]), "10.0.0.1: service changed 80/tcp (HTTP -> SSH)");
+    assert_eq!(format_deviation(&ip, &deviations[2]), "10.0.0.1: new port 53/udp (unidentified)");
+
+    // A host missing from the baseline only has new ports
+    let other = report("10.0.0.9", vec![result(22, Protocol::Tcp, Some("SSH"))]);
+    assert_eq!(compare(&baseline, &other), vec![Deviation::NewPort(result(22, Protocol::Tcp, Some("SSH")))]);
+}
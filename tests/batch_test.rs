@@ -0,0 +1,143 @@
+use port_explorer::batch::{job_config, parse_batch, run_batch, run_batch_with_cancel};
+use port_explorer::scanner::CancelToken;
+use port_explorer::scanner::Protocol;
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[test]
+fn test_parse_batch_requires_array() {
+    assert_eq!(parse_batch(r#"[{"ip": "127.0.0.1"}]"#).unwrap().len(), 1);
+    assert!(parse_batch(r#"{"ip": "127.0.0.1"}"#).is_err());
+    assert!(parse_batch("not json").is_err());
+}
+
+#[test]
+fn test_job_config_merges_options_and_ports() {
+    let mut base = HashMap::new();
+    base.insert("ip".to_string(), YamlValue::String("10.0.0.1".into()));
+    base.insert("max_threads".to_string(), YamlValue::Number(50.into()));
+    let job = serde_json::json!({
+        "ip": "127.0.0.1",
+        "ports": [22, "80-81"],
+        "options": {"retries": 2}
+    });
+    let config = job_config(&base, &job).unwrap();
+    assert_eq!(config.get("ip").and_then(|v| v.as_str()), Some("127.0.0.1"));
+    assert_eq!(config.get("max_threads").and_then(|v| v.as_u64()), Some(50));
+    assert_eq!(config.get("retries").and_then(|v| v.as_u64()), Some(2));
+    let ports = port_explorer::config::get_protocol_ports(&config).unwrap().unwrap();
+    assert_eq!(ports.get(&Protocol::Tcp), Some(&vec![22, 80, 81]));
+}
+
+#[test]
+fn test_job_config_does_not_inherit_ip() {
+    let mut base = HashMap::new();
+    base.insert("ip".to_string(), YamlValue::String("10.0.0.1".into()));
+    let config = job_config(&base, &serde_json::json!({"ports": "80"})).unwrap();
+    assert!(!config.contains_key("ip"));
+}
+
+#[test]
+fn test_job_config_rejects_malformed_job() {
+    let base = HashMap::new();
+    assert!(job_config(&base, &serde_json::json!("127.0.0.1")).is_err());
+    assert!(job_config(&base, &serde_json::json!({"ip": "127.0.0.1", "options": 5})).is_err());
+}
+
+#[test]
+fn test_run_batch_reports_per_job_errors() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let jobs = vec![
+        serde_json::json!({"ip": "127.0.0.1", "ports": [port], "options": {"max_threads": 4}}),
+        serde_json::json!({"ip": "not a host!", "ports": [port]}),
+        serde_json::json!(42),
+    ];
+    let results = run_batch(&HashMap::new(), jobs, Arc::new(vec![]), 2);
+    drop(listener);
+
+    assert_eq!(results.len(), 3);
+    let report = results[0].report.as_ref().unwrap();
+    assert_eq!(report.target, "127.0.0.1");
+    assert_eq!(report.open_ports.len(), 1);
+    assert_eq!(report.open_ports[0].port, port);
+    assert!(results[1].report.is_none() && results[1].error.is_some());
+    assert!(results[2].report.is_none() && results[2].error.is_some());
+
+    let json = serde_json::to_value(&results).unwrap();
+    assert_eq!(json[0]["report"]["open_ports"][0]["protocol"], "tcp");
+    assert_eq!(json[0]["report"]["open_ports"][0]["state"], "open");
+    assert_eq!(json[0]["report"]["identification_rate"], 0.0);
+    assert!(json[2].get("report").is_none());
+}
+
+#[test]
+fn test_run_batch_rejects_invalid_sort_by() {
+    let jobs = vec![serde_json::json!({"ip": "127.0.0.1", "ports": "1", "options": {"sort_by": "size"}})];
+    let results = run_batch(&HashMap::new(), jobs, Arc::new(vec![]), 1);
+    assert!(results[0].error.is_some());
+}
+
+#[test]
+fn test_run_batch_with_cancel_skips_jobs() {
+    port_explorer::localisator::init("en");
+    let jobs = vec![serde_json::json!({"ip": "127.0.0.1", "ports": "1-10"}); 2];
+    let token = CancelToken::new();
+    token.cancel();
+    let results = run_batch_with_cancel(&HashMap::new(), jobs, Arc::new(vec![]), 1, &token);
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert!(result.report.is_none());
+        assert!(result.error.as_ref().unwrap().ends_with("Not started, the run was interrupted"));
+    }
+}
+
+#[test]
+fn test_rescan_jobs_from_batch_output() {
+    let previous = r#"[
+        {"job": 0, "report": {"target": "a", "ip": "10.0.0.1", "open_ports": [
+            {"port": 80, "protocol": "tcp"}, {"port": 53, "protocol": "udp"}, {"port": 22, "protocol": "tcp"}]}},
+        {"job": 1, "error": "Resolve error"},
+        {"job": 2, "report": {"target": "b", "ip": "10.0.0.2", "open_ports": []}},
+        {"job": 3, "report": {"target": "a", "ip": "10.0.0.1", "open_ports": [{"port": 443, "protocol": "tcp"}]}}
+    ]"#;
+    let jobs = port_explorer::batch::rescan_jobs(previous).unwrap();
+    assert_eq!(
+        jobs,
+        vec![serde_json::json!({"ip": "10.0.0.1", "ports": {"tcp": [22, 80, 443], "udp": [53]}})]
+    );
+
+    let config = job_config(&HashMap::new(), &jobs[0]).unwrap();
+    let ports = port_explorer::config::get_protocol_ports(&config).unwrap().unwrap();
+    assert_eq!(ports.get(&Protocol::Tcp), Some(&vec![22, 80, 443]));
+    assert_eq!(ports.get(&Protocol::Udp), Some(&vec![53]));
+}
+
+#[test]
+fn test_rescan_jobs_single_report_and_invalid_input() {
+    let single = r#"{"target": "x", "ip": "127.0.0.1", "open_ports": [{"port": 8080, "protocol": "tcp"}]}"#;
+    let jobs = port_explorer::batch::rescan_jobs(single).unwrap();
+    assert_eq!(jobs[0]["ports"]["tcp"], serde_json::json!([8080]));
+
+    assert!(port_explorer::batch::rescan_jobs("{\"foo\": 1}").is_err());
+    assert!(port_explorer::batch::rescan_jobs("[]").is_err());
+    assert!(port_explorer::batch::rescan_jobs("nope").is_err());
+}
+
+#[test]
+fn test_rescan_roundtrip_through_run_batch() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let first = run_batch(
+        &HashMap::new(),
+        vec![serde_json::json!({"ip": "127.0.0.1", "ports": [port, 65516]})],
+        Arc::new(vec![]),
+        1,
+    );
+    let previous = serde_json::to_string(&first).unwrap();
+    let jobs = port_explorer::batch::rescan_jobs(&previous).unwrap();
+    assert_eq!(jobs[0]["ports"]["tcp"], serde_json::json!([port]));
+    let second = run_batch(&HashMap::new(), jobs, Arc::new(vec![]), 1);
+    assert_eq!(second[0].report.as_ref().unwrap().open_ports.len(), 1);
+}
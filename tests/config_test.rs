@@ -1,5 +1,6 @@
 // Unit tests for config parsing
 use port_explorer::config;
+use port_explorer::error::ScanError;
 use serde_yaml::Value as YamlValue;
 use std::collections::HashMap;
 
@@ -22,6 +23,36 @@ fn test_read_config_valid() {
     assert_eq!(language, "en");
 }
 
+#[test]
+fn test_read_config_ipv6() {
+    for ip in ["127.0.0.1", "::1", "fe80::1"] {
+        let mut config = HashMap::new();
+        config.insert("ip".to_string(), YamlValue::String(ip.into()));
+        let (parsed, ..) = config::get_config(&config).unwrap();
+        assert_eq!(*parsed, ip.parse::<std::net::IpAddr>().unwrap());
+    }
+}
+
+#[test]
+fn test_ports_list() {
+    use port_explorer::scanner::Protocol;
+    let mut config = HashMap::new();
+    config.insert("ports".to_string(), YamlValue::String("443,22,80,1000-1002,80".into()));
+    let (work, description) = config::get_scan_work(&config, 1, 65535).unwrap();
+    let ports: Vec<u16> = work.iter().map(|(protocol, port)| {
+        assert_eq!(*protocol, Protocol::Tcp);
+        *port
+    }).collect();
+    assert_eq!(ports, vec![22, 80, 443, 1000, 1001, 1002]);
+    assert_eq!(description, "tcp 22,80,443,1000-1002");
+    config.insert("ports".to_string(), serde_yaml::from_str("[22, \"8000-8001\"]").unwrap());
+    assert_eq!(config::get_scan_work(&config, 1, 65535).unwrap().0.len(), 3);
+    for spec in ["22,http", "80-", "0", "22,,80"] {
+        config.insert("ports".to_string(), YamlValue::String(spec.into()));
+        assert!(matches!(config::get_scan_work(&config, 1, 65535), Err(ScanError::Config(_))), "{}", spec);
+    }
+}
+
 #[test]
 fn test_missing_ip() {
     let yaml = r#"
@@ -65,10 +96,52 @@ fn test_defaults() {
     let (_ip, start_port, end_port, max_threads, language) = result.unwrap();
     assert_eq!(start_port, 1);
     assert_eq!(end_port, 65535);
-    assert_eq!(max_threads, 100);
+    assert_eq!(max_threads, config::default_max_threads());
     assert_eq!(language, "en");
 }
 
+#[test]
+fn test_unknown_config_keys() {
+    use config::ConfigWarning;
+    let yaml = r#"
+    ip: "127.0.0.1"
+    treads: 50
+    max_thread: 50
+    colour: blue
+    "#;
+    let mut config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let unknown = |key: &str, suggestion| ConfigWarning::UnknownKey { key: key.into(), suggestion };
+    assert_eq!(
+        config::get_config_warnings(&config),
+        vec![unknown("colour", None), unknown("max_thread", Some("max_threads")), unknown("treads", Some("max_threads"))]
+    );
+    // Unknown keys only warn unless strict_config is set
+    assert!(config::get_config(&config).is_ok());
+    config.insert("strict_config".to_string(), YamlValue::Bool(true));
+    match config::get_config(&config) {
+        Err(ScanError::Config(msg)) => assert!(msg.contains("\"treads\"") && msg.contains("\"colour\"")),
+        other => panic!("expected a config error, got {:?}", other),
+    }
+    config.retain(|key, _| config::KNOWN_KEYS.contains(&key.as_str()));
+    assert!(config::get_config_warnings(&config).is_empty());
+    assert!(config::get_config(&config).is_ok());
+}
+
+#[test]
+fn test_known_keys_cover_sources() {
+    // Every key the sources read or set must be known, or it would be reported as a typo
+    for file in ["src/config.rs", "src/main.rs", "src/lib.rs", "src/batch.rs", "src/server.rs"] {
+        let source = std::fs::read_to_string(file).unwrap();
+        for pattern in ["config.get(\"", "config.insert(\""] {
+            for (start, _) in source.match_indices(pattern) {
+                let rest = &source[start + pattern.len()..];
+                let key = &rest[..rest.find('"').unwrap()];
+                assert!(config::KNOWN_KEYS.contains(&key), "{} uses unknown config key {}", file, key);
+            }
+        }
+    }
+}
+
 #[test]
 fn test_read_config_file_not_found() {
     let result = config::read_config("/this/file/does/not/exist.yaml");
@@ -87,4 +160,1033 @@ fn test_read_config_invalid_yaml() {
     assert!(result.is_err());
     let err = format!("{}", result.unwrap_err());
     assert!(err.contains("Config error"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_read_config_toml() {
+    use std::io::Write;
+    let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+    write!(
+        file,
+        "ip = \"127.0.0.1\"\nstart_port = 1\nend_port = 10\nrandomize = true\ntls_ports = [443, \"8443-8444\"]\n\n[protocol_ports]\ntcp = \"22,80\"\n"
+    )
+    .unwrap();
+    let config = config::read_config(file.path().to_str().unwrap()).unwrap();
+    let yaml: HashMap<String, YamlValue> = serde_yaml::from_str(
+        "ip: 127.0.0.1\nstart_port: 1\nend_port: 10\nrandomize: true\ntls_ports: [443, \"8443-8444\"]\nprotocol_ports:\n  tcp: \"22,80\"\n",
+    )
+    .unwrap();
+    assert_eq!(config, yaml);
+    let (_ip, start_port, end_port, _, _) = config::get_config(&config).unwrap();
+    assert_eq!((start_port, end_port), (1, 10));
+
+    let mut broken = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+    write!(broken, "ip = [unclosed").unwrap();
+    let result = config::read_config(broken.path().to_str().unwrap());
+    assert!(matches!(result, Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_apply_env_from() {
+    let vars: HashMap<&str, &str> = [
+        ("PORT_EXPLORER_IP", "fe80::1%eth0"),
+        ("PORT_EXPLORER_MAX_THREADS", " 50 "),
+        ("PORT_EXPLORER_RANDOMIZE", "true"),
+        ("PORT_EXPLORER_TLS_PORTS", "[443, \"8443-8444\"]"),
+        ("PORT_EXPLORER_PROTOCOL_PORTS", "{tcp: \"22,80\"}"),
+        ("PORT_EXPLORER_UNKNOWN", "1"),
+    ]
+    .into_iter()
+    .collect();
+    let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+    let mut config: HashMap<String, YamlValue> = serde_yaml::from_str("ip: 127.0.0.1\nmax_threads: 8\nretries: 2").unwrap();
+    config::apply_env_from(&mut config, lookup).unwrap();
+    let expected: HashMap<String, YamlValue> = serde_yaml::from_str(
+        "ip: fe80::1%eth0\nmax_threads: 50\nretries: 2\nrandomize: true\ntls_ports: [443, \"8443-8444\"]\nprotocol_ports:\n  tcp: \"22,80\"\n",
+    )
+    .unwrap();
+    assert_eq!(config, expected);
+
+    for value in ["-1", "many", "1.5", ""] {
+        let mut config = HashMap::new();
+        let result = config::apply_env_from(&mut config, |name| (name == "PORT_EXPLORER_START_PORT").then(|| value.to_string()));
+        match result {
+            Err(ScanError::Config(msg)) => assert!(msg.contains("PORT_EXPLORER_START_PORT"), "{}", msg),
+            other => panic!("{:?} should be rejected: {:?}", value, other),
+        }
+    }
+}
+
+#[test]
+fn test_apply_env_ignores_hook_vars() {
+    use port_explorer::hooks::HOOK_PLACEHOLDERS;
+    let lookup = |name: &str| {
+        HOOK_PLACEHOLDERS
+            .iter()
+            .any(|(_, var)| *var == name)
+            .then(|| "10.0.0.9".to_string())
+    };
+    let original: HashMap<String, YamlValue> = serde_yaml::from_str("ip: 127.0.0.1\nprotocol: tcp").unwrap();
+    let mut config = original.clone();
+    config::apply_env_from(&mut config, lookup).unwrap();
+    assert_eq!(config, original);
+}
+
+#[test]
+fn test_apply_env() {
+    assert_eq!(config::env_var_name("port_step"), "PORT_EXPLORER_PORT_STEP");
+    std::env::set_var("PORT_EXPLORER_PORT_STEP", "10");
+    let mut config: HashMap<String, YamlValue> = serde_yaml::from_str("port_step: 2").unwrap();
+    let result = config::apply_env(&mut config);
+    std::env::remove_var("PORT_EXPLORER_PORT_STEP");
+    result.unwrap();
+    assert_eq!(config["port_step"].as_u64(), Some(10));
+    assert_eq!(config::get_port_step(&config).unwrap(), 10);
+}
+
+#[test]
+fn test_hostname_resolved() {
+    let yaml = r#"
+    ip: "localhost"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let (ip, _, _, _, _) = config::get_config(&config).unwrap();
+    assert!(ip.is_loopback());
+}
+
+#[test]
+fn test_resolve_all() {
+    let mut config = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("localhost".into()));
+    assert_eq!(config::get_network_hosts(&config).unwrap(), None);
+    config.insert("resolve_all".to_string(), YamlValue::Bool(true));
+    // localhost may have one address or several (127.0.0.1 and ::1)
+    if let Some(hosts) = config::get_network_hosts(&config).unwrap() {
+        assert!(hosts.len() > 1 && hosts.iter().all(|host| host.is_loopback()), "{:?}", hosts);
+    }
+    config.insert("ip".to_string(), YamlValue::String("127.0.0.1".into()));
+    assert_eq!(config::get_network_hosts(&config).unwrap(), None);
+    config.insert("ip".to_string(), YamlValue::String("does-not-exist.invalid".into()));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::Resolve(_))));
+}
+
+#[test]
+fn test_invalid_dns_server() {
+    let yaml = r#"
+    ip: "localhost"
+    dns_server: "not a server"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let result = config::get_config(&config);
+    assert!(result.is_err());
+    let err = format!("{}", result.unwrap_err());
+    assert!(err.contains("Config error"));
+}
+
+#[test]
+fn test_parse_port_list() {
+    assert_eq!(config::parse_port_list("80").unwrap(), vec![80]);
+    assert_eq!(
+        config::parse_port_list("443, 22,80-82,22").unwrap(),
+        vec![22, 80, 81, 82, 443]
+    );
+}
+
+#[test]
+fn test_parse_port_list_invalid() {
+    for spec in ["", "22,,80", "abc", "0", "70000", "90-80", "1-2-3"] {
+        let result = config::parse_port_list(spec);
+        assert!(result.is_err(), "{:?} should be rejected", spec);
+        let err = format!("{}", result.unwrap_err());
+        assert!(err.contains("Config error"));
+    }
+}
+
+#[test]
+fn test_port_step() {
+    use port_explorer::scanner::Protocol;
+    let expected: Vec<u16> = vec![1, 11, 21, 31, 41];
+    assert_eq!(config::parse_port_list("1-50:10").unwrap(), expected);
+    assert_eq!(config::parse_port_list("1-65535:10").unwrap().last(), Some(&65531));
+    assert_eq!(config::parse_port_list("100-100:7,3-9:3").unwrap(), vec![3, 6, 9, 100]);
+    for spec in ["1-50:0", "1-50:", "1-50:x", "80:10", "1-50:70000"] {
+        assert!(config::parse_port_list(spec).is_err(), "{:?} should be rejected", spec);
+    }
+
+    let yaml = "port_step: 10\nrandomize: true\n";
+    let cfg: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let (work, description) = config::get_scan_work(&cfg, 1, 50).unwrap();
+    let mut ports: Vec<u16> = work.iter().map(|(protocol, port)| {
+        assert_eq!(*protocol, Protocol::Tcp);
+        *port
+    }).collect();
+    ports.sort_unstable();
+    assert_eq!(ports, expected);
+    assert_eq!(description, "1-50:10");
+
+    for step in ["0", "-1", "\"ten\""] {
+        let cfg: HashMap<String, YamlValue> = serde_yaml::from_str(&format!("port_step: {}", step)).unwrap();
+        assert!(config::get_port_step(&cfg).is_err(), "{} should be rejected", step);
+    }
+    assert_eq!(config::get_port_step(&HashMap::new()).unwrap(), 1);
+}
+
+#[test]
+fn test_randomize_with_seed() {
+    let range: Vec<u16> = (1..=1000).collect();
+    let shuffled = |yaml: &str| {
+        let cfg: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        let (work, _) = config::get_scan_work(&cfg, 1, 1000).unwrap();
+        work.iter().map(|(_, port)| *port).collect::<Vec<u16>>()
+    };
+    let first = shuffled("randomize: true\nseed: 42");
+    assert_ne!(first, range);
+    let mut sorted = first.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, range);
+    // The same seed gives the same order, another seed a different one
+    assert_eq!(shuffled("randomize: true\nseed: 42"), first);
+    assert_ne!(shuffled("randomize: true\nseed: 7"), first);
+    // The seed alone doesn't shuffle
+    assert_eq!(shuffled("seed: 42"), range);
+    // The order of a seed is pinned, so it stays reproducible across releases
+    let cfg: HashMap<String, YamlValue> = serde_yaml::from_str("randomize: true\nseed: 42").unwrap();
+    let (work, _) = config::get_scan_work(&cfg, 1, 10).unwrap();
+    let ports: Vec<u16> = work.iter().map(|(_, port)| *port).collect();
+    assert_eq!(ports, [1, 4, 6, 8, 10, 5, 9, 2, 7, 3]);
+
+    for seed in ["-1", "\"abc\"", "1.5"] {
+        let cfg: HashMap<String, YamlValue> = serde_yaml::from_str(&format!("randomize: true\nseed: {}", seed)).unwrap();
+        assert!(matches!(config::get_scan_work(&cfg, 1, 10), Err(ScanError::Config(_))), "{} should be rejected", seed);
+    }
+}
+
+#[test]
+fn test_format_port_list() {
+    assert_eq!(config::format_port_list(&[]), "");
+    assert_eq!(config::format_port_list(&[22, 80, 81, 82, 443]), "22,80-82,443");
+    assert_eq!(config::format_port_list(&[65534, 65535]), "65534-65535");
+}
+
+#[test]
+fn test_get_protocol_ports() {
+    use port_explorer::scanner::Protocol;
+    let yaml = r#"
+    protocol_ports:
+      tcp: "1-3"
+      UDP: [53, "123", "160-161"]
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let map = config::get_protocol_ports(&config).unwrap().unwrap();
+    assert_eq!(map[&Protocol::Tcp], vec![1, 2, 3]);
+    assert_eq!(map[&Protocol::Udp], vec![53, 123, 160, 161]);
+}
+
+#[test]
+fn test_get_protocol_ports_absent_or_invalid() {
+    let config: HashMap<String, YamlValue> = HashMap::new();
+    assert!(config::get_protocol_ports(&config).unwrap().is_none());
+
+    let yaml = r#"
+    protocol_ports:
+      sctp: "1-3"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_protocol_ports(&config).is_err());
+
+    let yaml = r#"
+    protocol_ports: "1-3"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_protocol_ports(&config).is_err());
+}
+
+#[test]
+fn test_get_scan_options() {
+    let yaml = r#"
+    retries: 2
+    retry_backoff_ms: 50
+    max_retries_total: 100
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let options = config::get_scan_options(&config, 8).unwrap();
+    assert_eq!(options.max_threads, 8);
+    assert_eq!(options.retries, 2);
+    assert_eq!(options.retry_backoff, std::time::Duration::from_millis(50));
+    assert_eq!(options.max_retries_total, Some(100));
+}
+
+#[test]
+fn test_get_scan_options_defaults() {
+    let config: HashMap<String, YamlValue> = HashMap::new();
+    let options = config::get_scan_options(&config, config::default_max_threads()).unwrap();
+    assert_eq!(options, port_explorer::scanner::ScanOptions::default());
+}
+
+#[test]
+fn test_get_scan_options_network_failure_threshold() {
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("network_failure_threshold: 0").unwrap();
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().network_failure_threshold, 0);
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("network_failure_threshold: 10").unwrap();
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().network_failure_threshold, 10);
+    for value in ["-1", "many"] {
+        let config: HashMap<String, YamlValue> =
+            serde_yaml::from_str(&format!("network_failure_threshold: {}", value)).unwrap();
+        assert!(config::get_scan_options(&config, 10).is_err(), "{} should be rejected", value);
+    }
+}
+
+#[test]
+fn test_default_max_threads() {
+    use std::num::NonZeroUsize;
+    let cores = |n| Ok(NonZeroUsize::new(n).unwrap());
+    assert_eq!(config::default_max_threads_for(cores(1)), config::THREADS_PER_CORE);
+    assert_eq!(config::default_max_threads_for(cores(4)), 4 * config::THREADS_PER_CORE);
+    assert_eq!(config::default_max_threads_for(cores(1000)), config::MAX_DEFAULT_THREADS);
+    // Without a known core count the default stays at 100
+    let unknown = Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+    assert_eq!(config::default_max_threads_for(unknown), config::FALLBACK_MAX_THREADS);
+    assert_eq!(config::FALLBACK_MAX_THREADS, 100);
+    // Library callers using the default options get the same thread count
+    assert_eq!(port_explorer::scanner::ScanOptions::default().max_threads, config::default_max_threads());
+    // An explicit value wins
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("max_threads: 7").unwrap();
+    assert_eq!(config::get_max_threads(&config).unwrap(), 7);
+}
+
+#[test]
+fn test_max_threads_below_one_rejected() {
+    for value in ["0", "-1"] {
+        let mut config: HashMap<String, YamlValue> = serde_yaml::from_str(&format!("max_threads: {}", value)).unwrap();
+        assert!(matches!(config::get_max_threads(&config), Err(ScanError::Config(_))));
+        config.insert("ip".to_string(), YamlValue::String("127.0.0.1".into()));
+        assert!(matches!(config::get_config(&config), Err(ScanError::Config(_))));
+    }
+    // The environment can't sneak a zero past the check either
+    let mut config = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("127.0.0.1".into()));
+    config::apply_env_from(&mut config, |name| (name == "PORT_EXPLORER_MAX_THREADS").then(|| "0".to_string())).unwrap();
+    assert!(matches!(config::get_config(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_get_scan_options_engine() {
+    use port_explorer::scanner::Engine;
+    for (value, expected) in [("threads", Engine::Threads), ("async", Engine::Async), ("ASYNC", Engine::Async)] {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(&format!("engine: {}", value)).unwrap();
+        assert_eq!(config::get_scan_options(&config, 10).unwrap().engine, expected);
+    }
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("engine: fibers").unwrap();
+    assert!(matches!(config::get_scan_options(&config, 10), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_get_ssh_jump() {
+    port_explorer::localisator::init("en");
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("ssh_jump: ops@127.0.0.1:1").unwrap();
+    let destination = config::get_ssh_jump(&config).unwrap().unwrap();
+    assert_eq!(destination.to_string(), "ops@127.0.0.1:1");
+    // Only the destination is parsed, nothing is connected to
+    assert!(config::get_scan_options(&config, 10).unwrap().ssh_jump.is_none());
+
+    assert_eq!(config::get_ssh_jump(&HashMap::new()).unwrap(), None);
+    for invalid in ["ssh_jump: 22", "ssh_jump: 'bastion:0'"] {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(invalid).unwrap();
+        assert!(matches!(config::get_ssh_jump(&config), Err(ScanError::Config(_))), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_get_scan_options_tls() {
+    let options = |yaml: &str| {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        config::get_scan_options(&config, 10)
+    };
+    let tls = options("ip: www.example.com\ntls_ports: \"443,8443\"\ntls_expiry_warning_days: 14").unwrap();
+    assert_eq!(tls.tls_ports, vec![443, 8443]);
+    assert_eq!(tls.tls_server_name.as_deref(), Some("www.example.com"));
+    assert_eq!(tls.tls_expiry_warning_days, 14);
+    // Addresses and networks send no name, unless one is configured
+    assert_eq!(options("ip: 10.0.0.1").unwrap().tls_server_name, None);
+    assert_eq!(options("ip: 10.0.0.0/24").unwrap().tls_server_name, None);
+    let named = options("ip: 10.0.0.1\ntls_server_name: mail.example.com").unwrap();
+    assert_eq!(named.tls_server_name.as_deref(), Some("mail.example.com"));
+    assert!(options("tls_server_name: \"not a name\"").is_err());
+    assert!(options("tls_expiry_warning_days: -1").is_err());
+    assert!(options("tls_ports: \"0\"").is_err());
+}
+
+#[test]
+fn test_get_scan_options_source_port_range() {
+    let yaml = r#"
+    source_port_range: "40000-40100"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let range = config::get_scan_options(&config, 8).unwrap().source_ports.unwrap();
+    assert_eq!((range.start(), range.end()), (40000, 40100));
+
+    for invalid in ["\"40100-40000\"", "\"0-10\"", "\"abc\"", "[1, 2]"] {
+        let config: HashMap<String, YamlValue> =
+            serde_yaml::from_str(&format!("source_port_range: {}", invalid)).unwrap();
+        assert!(config::get_scan_options(&config, 8).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_safe_mode_allowed_targets() {
+    let yaml = r#"
+    ip: "127.0.0.1"
+    safe_mode: true
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_config(&config).is_ok());
+
+    let yaml = r#"
+    ip: "8.8.8.8"
+    safe_mode: true
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(matches!(config::get_config(&config), Err(ScanError::NotAllowed(_))));
+
+    let yaml = r#"
+    ip: "8.8.8.8"
+    safe_mode: true
+    allowed_targets: ["8.8.8.0/24", "1.1.1.1"]
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_config(&config).is_ok());
+
+    let yaml = r#"
+    ip: "8.8.8.8"
+    allowed_targets: ["10.0.0.0/8"]
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_config(&config).is_ok(), "allowlist only applies in safe mode");
+
+    let yaml = r#"
+    ip: "127.0.0.1"
+    safe_mode: true
+    allowed_targets: ["not-a-network"]
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(matches!(config::get_config(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_get_protocol_ports_from_services() {
+    use port_explorer::scanner::Protocol;
+    let yaml = r#"
+    protocol_ports:
+      tcp: "1-2"
+    ports_from_services: "ssh, ssh"
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let map = config::get_protocol_ports(&config).unwrap().unwrap();
+    assert_eq!(map[&Protocol::Tcp], vec![1, 2, 22]);
+
+    let yaml = r#"
+    ports_from_services: [ssh, no-such-service]
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    let err = config::get_protocol_ports(&config).unwrap_err().to_string();
+    assert!(err.contains("no-such-service"), "{}", err);
+
+    let yaml = r#"
+    ports_from_services: 22
+    "#;
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+    assert!(config::get_ports_from_services(&config).is_err());
+}
+
+#[test]
+fn test_get_network_hosts() {
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("127.0.0.1".to_string()));
+    assert!(config::get_network_hosts(&config).unwrap().is_none());
+
+    config.insert("ip".to_string(), YamlValue::String("127.0.0.0/30".to_string()));
+    let hosts = config::get_network_hosts(&config).unwrap().unwrap();
+    let hosts: Vec<String> = hosts.iter().map(|h| h.to_string()).collect();
+    assert_eq!(hosts, vec!["127.0.0.1", "127.0.0.2"]);
+
+    config.insert("ip".to_string(), YamlValue::String("10.0.0.0/8".to_string()));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::Config(_))));
+
+    config.insert("ip".to_string(), YamlValue::String("198.51.100.0/24".to_string()));
+    config.insert("safe_mode".to_string(), YamlValue::Bool(true));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::NotAllowed(_))));
+}
+
+#[test]
+fn test_allow_large_range() {
+    let mut config = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("10.0.0.0/15".into()));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::Config(_))));
+    assert_eq!(config::get_max_network_hosts(&config), config::MAX_NETWORK_HOSTS);
+    config.insert("allow_large_range".to_string(), YamlValue::Bool(true));
+    assert_eq!(config::get_network_hosts(&config).unwrap().unwrap().len(), 131070);
+    config.insert("ip".to_string(), YamlValue::String("10.0.0.0-10.1.255.255".into()));
+    assert_eq!(config::get_network_hosts(&config).unwrap().unwrap().len(), 131072);
+    // Even then anything beyond a /8 is rejected
+    config.insert("ip".to_string(), YamlValue::String("10.0.0.0-11.0.0.0".into()));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_parse_ip_range() {
+    let range = |value: &str| {
+        config::parse_ip_range(value).map(|hosts| hosts.map(|hosts| hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>()))
+    };
+    assert_eq!(
+        range("192.168.0.250-192.168.1.5").unwrap().unwrap(),
+        vec![
+            "192.168.0.250", "192.168.0.251", "192.168.0.252", "192.168.0.253", "192.168.0.254", "192.168.0.255",
+            "192.168.1.0", "192.168.1.1", "192.168.1.2", "192.168.1.3", "192.168.1.4", "192.168.1.5",
+        ]
+    );
+    assert_eq!(range("10.0.0.255-10.0.1.0").unwrap().unwrap(), vec!["10.0.0.255", "10.0.1.0"]);
+    assert_eq!(range(" 10.0.0.7 - 10.0.0.7 ").unwrap().unwrap(), vec!["10.0.0.7"]);
+    assert_eq!(range("fe80::ffff-fe80::1:1").unwrap().unwrap(), vec!["fe80::ffff", "fe80::1:0", "fe80::1:1"]);
+    assert_eq!(range("255.255.255.254-255.255.255.255").unwrap().unwrap().len(), 2);
+
+    // Not a range, so a single address or hostname
+    assert_eq!(range("10.0.0.1").unwrap(), None);
+    assert_eq!(range("my-host.local").unwrap(), None);
+
+    assert!(matches!(config::parse_ip_range("10.0.1.5-10.0.0.250"), Err(ScanError::Config(_))));
+    assert!(matches!(config::parse_ip_range("10.0.0.1-::1"), Err(ScanError::Config(_))));
+    assert!(matches!(config::parse_ip_range("10.0.0.0-10.255.255.255"), Err(ScanError::Config(_))));
+    assert!(config::parse_ip_range("10.0.0.0-10.0.255.255").unwrap().is_some());
+}
+
+#[test]
+fn test_get_network_hosts_range() {
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("127.0.0.254-127.0.1.1".to_string()));
+    let hosts = config::get_network_hosts(&config).unwrap().unwrap();
+    assert_eq!(hosts.len(), 4);
+
+    config.insert("safe_mode".to_string(), YamlValue::Bool(true));
+    assert_eq!(config::get_network_hosts(&config).unwrap().unwrap().len(), 4);
+    // Every address must be allowed, not just the ends
+    config.insert("allowed_targets".to_string(), serde_yaml::from_str("[10.0.0.1, 10.0.0.3]").unwrap());
+    config.insert("ip".to_string(), YamlValue::String("10.0.0.1-10.0.0.3".to_string()));
+    assert!(matches!(config::get_network_hosts(&config), Err(ScanError::NotAllowed(_))));
+}
+
+#[test]
+fn test_get_discovery_settings() {
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    assert!(!config::get_discover_first(&config));
+    assert_eq!(config::get_discovery_ports(&config).unwrap(), vec![80, 443]);
+    config.insert("discover_first".to_string(), YamlValue::Bool(true));
+    config.insert("discovery_ports".to_string(), YamlValue::String("22,3389".to_string()));
+    assert!(config::get_discover_first(&config));
+    assert_eq!(config::get_discovery_ports(&config).unwrap(), vec![22, 3389]);
+
+    assert!(!config::get_scan_options(&config, 10).unwrap().first_open_only);
+    config.insert("first_open_only".to_string(), YamlValue::Bool(true));
+    assert!(config::get_first_open_only(&config));
+    assert!(config::get_scan_options(&config, 10).unwrap().first_open_only);
+}
+
+#[test]
+fn test_get_scan_options_probe() {
+    use port_explorer::scanner::Probe;
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().probe, Probe::Http);
+
+    config.insert("no_probe".to_string(), YamlValue::Bool(true));
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().probe, Probe::Tcp(vec![]));
+
+    config.insert("probe_payload".to_string(), YamlValue::String("HELP\\r\\n\\x00".to_string()));
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().probe, Probe::Tcp(b"HELP\r\n\0".to_vec()));
+
+    config.insert("probe_payload".to_string(), YamlValue::String("bad\\q".to_string()));
+    assert!(config::get_scan_options(&config, 10).is_err());
+    config.insert("probe_payload".to_string(), YamlValue::String("short\\x4".to_string()));
+    assert!(config::get_scan_options(&config, 10).is_err());
+}
+
+#[test]
+fn test_get_scan_options_probe_paths() {
+    port_explorer::localisator::init("en");
+    let options = |yaml: &str| {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        config::get_scan_options(&config, 10).map(|options| options.probe_paths)
+    };
+    assert_eq!(options("{}").unwrap(), vec!["/"]);
+    assert_eq!(options("probe_path: /server-status").unwrap(), vec!["/server-status"]);
+    assert_eq!(
+        options("probe_path: [/api/version, '/status?full=1&x=%2F']").unwrap(),
+        vec!["/api/version", "/status?full=1&x=%2F"]
+    );
+    for invalid in ["server-status", "'/a b'", "'/a#b'", "'/%zz'", "'/%2'", "[]", "'/a\r\nHost: x'"] {
+        let result = options(&format!("probe_path: {}", invalid));
+        assert!(matches!(result, Err(ScanError::Config(_))), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_get_scan_options_http_probe_ports() {
+    use port_explorer::scanner::HttpProbePorts;
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().http_probe_ports, HttpProbePorts::All);
+
+    config.insert("http_probe_ports".to_string(), YamlValue::String("80,443,8000-8002".to_string()));
+    assert_eq!(
+        config::get_scan_options(&config, 10).unwrap().http_probe_ports,
+        HttpProbePorts::Only([80, 443, 8000, 8001, 8002].into())
+    );
+
+    config.insert("http_probe_skip_ports".to_string(), serde_yaml::from_str("[22, 3306]").unwrap());
+    assert!(matches!(config::get_scan_options(&config, 10), Err(ScanError::Config(_))));
+    config.remove("http_probe_ports");
+    assert_eq!(
+        config::get_scan_options(&config, 10).unwrap().http_probe_ports,
+        HttpProbePorts::Skip([22, 3306].into())
+    );
+
+    config.insert("http_probe_skip_ports".to_string(), YamlValue::String("22-x".to_string()));
+    assert!(config::get_scan_options(&config, 10).is_err());
+}
+
+#[test]
+fn test_millis_durations_with_units() {
+    use std::time::Duration;
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    assert_eq!(config::get_millis_duration(&config, "retry_backoff_ms").unwrap(), None);
+    for (value, expected) in [
+        (YamlValue::Number(250.into()), Duration::from_millis(250)),
+        (YamlValue::String("250".into()), Duration::from_millis(250)),
+        (YamlValue::String("200ms".into()), Duration::from_millis(200)),
+        (YamlValue::String(" 1s".into()), Duration::from_secs(1)),
+        (YamlValue::String("2m".into()), Duration::from_secs(120)),
+    ] {
+        config.insert("retry_backoff_ms".to_string(), value.clone());
+        config.insert("ramp_up_ms".to_string(), value);
+        let options = config::get_scan_options(&config, 10).unwrap();
+        assert_eq!((options.retry_backoff, options.ramp_up), (expected, expected));
+    }
+    for value in ["fast", "1.5s", "10 parsecs", "-5", ""] {
+        config.insert("ramp_up_ms".to_string(), YamlValue::String(value.into()));
+        match config::get_scan_options(&config, 10) {
+            Err(ScanError::Config(msg)) => assert!(msg.contains("ramp_up_ms"), "{}", msg),
+            other => panic!("{}: {:?}", value, other.map(|o| o.ramp_up)),
+        }
+    }
+    config.insert("ramp_up_ms".to_string(), YamlValue::Bool(true));
+    assert!(config::get_scan_options(&config, 10).is_err());
+}
+
+#[test]
+fn test_repeat_interval() {
+    use std::time::Duration;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_repeat_interval(&config).unwrap(), None);
+    config.insert("repeat".to_string(), YamlValue::Bool(true));
+    assert_eq!(config::get_repeat_interval(&config).unwrap(), Some(config::DEFAULT_REPEAT_INTERVAL));
+    for (value, expected) in [("5m", 300), ("90s", 90), ("2", 2), ("1h", 3600)] {
+        config.insert("interval".to_string(), YamlValue::String(value.into()));
+        assert_eq!(config::get_repeat_interval(&config).unwrap(), Some(Duration::from_secs(expected)));
+    }
+    for value in ["500ms", "0", "fast", "5d", ""] {
+        config.insert("interval".to_string(), YamlValue::String(value.into()));
+        assert!(matches!(config::get_repeat_interval(&config), Err(ScanError::Config(_))), "{}", value);
+    }
+    config.insert("interval".to_string(), YamlValue::Number(30.into()));
+    assert_eq!(config::get_repeat_interval(&config).unwrap(), Some(Duration::from_secs(30)));
+    assert!(!config::get_changes_only(&config));
+}
+
+#[test]
+fn test_get_deadline() {
+    use std::time::Duration;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_deadline(&config).unwrap(), None);
+    for (value, expected) in [("90s", 90), ("10m", 600), ("5", 5)] {
+        config.insert("deadline".to_string(), YamlValue::String(value.into()));
+        assert_eq!(config::get_deadline(&config).unwrap(), Some(Duration::from_secs(expected)));
+    }
+    config.insert("deadline".to_string(), YamlValue::Number(30.into()));
+    assert_eq!(config::get_deadline(&config).unwrap(), Some(Duration::from_secs(30)));
+    for value in [YamlValue::String("0".into()), YamlValue::String("soon".into()), YamlValue::Number((-1).into())] {
+        config.insert("deadline".to_string(), value.clone());
+        assert!(matches!(config::get_deadline(&config), Err(ScanError::Config(_))), "{:?}", value);
+    }
+}
+
+#[test]
+fn test_get_target_timeout() {
+    use std::time::Duration;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_target_timeout(&config).unwrap(), None);
+    config.insert("per_target_timeout".to_string(), YamlValue::String("30s".into()));
+    assert_eq!(config::get_target_timeout(&config).unwrap(), Some(Duration::from_secs(30)));
+    config.insert("per_target_timeout".to_string(), YamlValue::Number(5.into()));
+    assert_eq!(config::get_target_timeout(&config).unwrap(), Some(Duration::from_secs(5)));
+    assert_eq!(config::get_scan_options(&config, 4).unwrap().target_timeout, Some(Duration::from_secs(5)));
+    for value in [YamlValue::String("0s".into()), YamlValue::Bool(true)] {
+        config.insert("per_target_timeout".to_string(), value.clone());
+        assert!(matches!(config::get_target_timeout(&config), Err(ScanError::Config(_))), "{:?}", value);
+    }
+}
+
+#[test]
+fn test_get_progress_format() {
+    use port_explorer::output::ProgressFormat;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_progress_format(&config).unwrap(), ProgressFormat::Bar);
+    config.insert("progress_format".to_string(), YamlValue::String("json".into()));
+    assert_eq!(config::get_progress_format(&config).unwrap(), ProgressFormat::Json);
+    config.insert("progress_format".to_string(), YamlValue::String("dots".into()));
+    assert!(matches!(config::get_progress_format(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_signatures_path() {
+    let mut config = HashMap::new();
+    assert_eq!(config::get_signatures_path(&config), std::path::PathBuf::from("signatures"));
+    config.insert("signatures_path".to_string(), YamlValue::String("/opt/sigs/web.yaml".into()));
+    assert_eq!(config::get_signatures_path(&config), std::path::PathBuf::from("/opt/sigs/web.yaml"));
+    assert!(!config::get_signatures_bundle(&config));
+    // A bundle wins over a signatures path
+    config.insert("signatures_bundle".to_string(), YamlValue::String("dist/bundle.yaml".into()));
+    assert_eq!(config::get_signatures_path(&config), std::path::PathBuf::from("dist/bundle.yaml"));
+    assert!(config::get_signatures_bundle(&config));
+}
+
+#[test]
+fn test_expected_services() {
+    use port_explorer::scanner::Protocol;
+    let mut config = HashMap::new();
+    config.insert(
+        "expected_services".to_string(),
+        serde_yaml::from_str("{22: SSH, \"80\": HTTP, \"53/udp\": DNS}").unwrap(),
+    );
+    assert_eq!(config::get_expected_services(&config).unwrap(), None);
+
+    config.insert("compare_services".to_string(), YamlValue::Bool(true));
+    let expected = config::get_expected_services(&config).unwrap().unwrap();
+    assert_eq!(expected.len(), 3);
+    assert_eq!(expected.get(&(22, Protocol::Tcp)).map(String::as_str), Some("SSH"));
+    assert_eq!(expected.get(&(80, Protocol::Tcp)).map(String::as_str), Some("HTTP"));
+    assert_eq!(expected.get(&(53, Protocol::Udp)).map(String::as_str), Some("DNS"));
+
+    for invalid in ["{70000: SSH}", "{\"22/sctp\": SSH}", "{22: [SSH]}", "[22]"] {
+        config.insert("expected_services".to_string(), serde_yaml::from_str(invalid).unwrap());
+        assert!(matches!(config::get_expected_services(&config), Err(ScanError::Config(_))), "{}", invalid);
+    }
+    config.remove("expected_services");
+    assert!(config::get_expected_services(&config).is_err());
+}
+
+#[test]
+fn test_invalid_ip_suggestions() {
+    let error_for = |ip: &str| {
+        let mut config = HashMap::new();
+        config.insert("language".to_string(), YamlValue::String("en".into()));
+        config.insert("ip".to_string(), YamlValue::String(ip.into()));
+        match config::get_config(&config) {
+            Err(ScanError::Config(msg)) => msg,
+            other => panic!("{}: {:?}", ip, other.map(|c| c.0)),
+        }
+    };
+    let cases = [
+        ("1.2.3.4:80", "contains a port", Some("\"1.2.3.4\"")),
+        ("[::1]:8080", "contains a port", Some("\"::1\"")),
+        ("router.local:443", "contains a port", Some("\"router.local\"")),
+        ("10.0.0.0/24", "is a network", Some("\"10.0.0.1\"")),
+        ("10.0.0.1/33", "invalid prefix length", None),
+        ("10.0.0.1-10.0.0.9", "address range", Some("\"10.0.0.1\"")),
+        ("http://10.0.0.5:8080/admin", "is a URL", Some("\"10.0.0.5\"")),
+        (" 10.0.0.5", "spaces", Some("\"10.0.0.5\"")),
+        ("192.168.0.256", "above 255", None),
+        ("192.168.1", "four parts", None),
+    ];
+    for (ip, hint, suggestion) in cases {
+        let msg = error_for(ip);
+        assert!(msg.contains(&format!("\"{}\"", ip)) && msg.contains(hint), "{}", msg);
+        if let Some(suggestion) = suggestion {
+            assert!(msg.ends_with(suggestion), "{}", msg);
+        }
+    }
+    assert!(!error_for("not_an_ip").contains(':'));
+}
+
+#[test]
+fn test_output_sink() {
+    use port_explorer::output::OutputSink;
+    use std::path::PathBuf;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_output_sink(&config).unwrap(), None);
+    config.insert("output_format".to_string(), YamlValue::String("TEXT".into()));
+    config.insert("db".to_string(), YamlValue::String("scans.db".into()));
+    assert_eq!(config::get_output_sink(&config).unwrap(), None);
+    config.insert("output_format".to_string(), YamlValue::String("csv".into()));
+    assert!(config::get_output_sink(&config).is_err());
+
+    config.insert("output_format".to_string(), YamlValue::String("sqlite".into()));
+    if cfg!(feature = "sqlite") {
+        assert_eq!(config::get_output_sink(&config).unwrap(), Some(OutputSink::Sqlite(PathBuf::from("scans.db"))));
+        config.remove("db");
+    }
+    assert!(matches!(config::get_output_sink(&config), Err(ScanError::Config(_))));
+
+    config.insert("output_format".to_string(), YamlValue::String("prometheus".into()));
+    assert!(matches!(config::get_output_sink(&config), Err(ScanError::Config(_))));
+    config.insert("prometheus_file".to_string(), YamlValue::String("scan.prom".into()));
+    assert_eq!(config::get_output_sink(&config).unwrap(), Some(OutputSink::Prometheus(PathBuf::from("scan.prom"))));
+
+    config.insert("output_format".to_string(), YamlValue::String("msgpack".into()));
+    if cfg!(feature = "msgpack") {
+        assert!(matches!(config::get_output_sink(&config), Err(ScanError::Config(_))));
+        config.insert("msgpack_file".to_string(), YamlValue::String("scan.msgpack".into()));
+        assert_eq!(config::get_output_sink(&config).unwrap(), Some(OutputSink::MsgPack(PathBuf::from("scan.msgpack"))));
+    } else {
+        assert!(matches!(config::get_output_sink(&config), Err(ScanError::Config(_))));
+    }
+
+    config.insert("output_format".to_string(), YamlValue::String("Markdown".into()));
+    assert!(matches!(config::get_output_sink(&config), Err(ScanError::Config(_))));
+    config.insert("markdown_file".to_string(), YamlValue::String("scan.md".into()));
+    assert_eq!(config::get_output_sink(&config).unwrap(), Some(OutputSink::Markdown(PathBuf::from("scan.md"))));
+}
+
+#[test]
+fn test_output_format_json() {
+    use port_explorer::output::{OutputFormat, StdoutFormat};
+    assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+    let mut config = HashMap::new();
+    config.insert("output_format".to_string(), YamlValue::String("json".into()));
+    assert_eq!(config::get_output_sink(&config).unwrap(), None);
+    assert_eq!(config::get_stdout_format(&config).unwrap(), StdoutFormat::Json);
+    config.insert("stdout_format".to_string(), YamlValue::String("csv".into()));
+    assert_eq!(config::get_stdout_format(&config).unwrap(), StdoutFormat::Csv);
+}
+
+#[test]
+fn test_banner_max_bytes() {
+    let mut config = HashMap::new();
+    let options = config::get_scan_options(&config, 10).unwrap();
+    assert_eq!(options.banner_max_bytes, port_explorer::scanner::DEFAULT_BANNER_MAX_BYTES);
+    config.insert("banner_max_bytes".to_string(), YamlValue::Number(512.into()));
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().banner_max_bytes, 512);
+    assert_eq!(options.ramp_up, std::time::Duration::ZERO);
+    config.insert("ramp_up_ms".to_string(), YamlValue::Number(3000.into()));
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().ramp_up, std::time::Duration::from_secs(3));
+    for invalid in [YamlValue::Number(0.into()), YamlValue::Number(2_000_000.into()), YamlValue::String("1k".into())] {
+        config.insert("banner_max_bytes".to_string(), invalid);
+        assert!(matches!(config::get_scan_options(&config, 10), Err(ScanError::Config(_))));
+    }
+}
+
+#[test]
+fn test_latency_filter() {
+    use port_explorer::output::LatencyFilter;
+    use std::time::Duration;
+    let mut config = HashMap::new();
+    assert_eq!(config::get_latency_filter(&config).unwrap(), None);
+    config.insert("min_latency".to_string(), YamlValue::String("100ms".into()));
+    assert_eq!(
+        config::get_latency_filter(&config).unwrap(),
+        Some(LatencyFilter { min: Some(Duration::from_millis(100)), max: None })
+    );
+    config.insert("max_latency".to_string(), YamlValue::String("2s".into()));
+    assert_eq!(
+        config::get_latency_filter(&config).unwrap(),
+        Some(LatencyFilter { min: Some(Duration::from_millis(100)), max: Some(Duration::from_secs(2)) })
+    );
+    config.insert("max_latency".to_string(), YamlValue::String("50ms".into()));
+    assert!(matches!(config::get_latency_filter(&config), Err(ScanError::Config(_))));
+    config.insert("max_latency".to_string(), YamlValue::String("slow".into()));
+    assert!(matches!(config::get_latency_filter(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_precheck_ports() {
+    use port_explorer::scanner::Protocol;
+    let mut config = HashMap::new();
+    let work = vec![(Protocol::Udp, 53), (Protocol::Tcp, 22), (Protocol::Tcp, 80), (Protocol::Tcp, 8080), (Protocol::Tcp, 9000)];
+    assert_eq!(config::get_precheck_ports(&config, &work).unwrap(), vec![22, 80, 443, 8080]);
+    config.insert("discovery_ports".to_string(), YamlValue::String("7".into()));
+    assert_eq!(config::get_precheck_ports(&config, &work[..2]).unwrap(), vec![7, 22]);
+    assert!(!config::get_require_reachable(&config));
+}
+
+#[test]
+fn test_endpoints() {
+    let mut config = HashMap::new();
+    assert_eq!(config::get_endpoints(&config).unwrap(), None);
+    config.insert(
+        "endpoints".to_string(),
+        YamlValue::String("5.6.7.8:443, 1.2.3.4:22, [::1]:80, 5.6.7.8:22, 5.6.7.8:443".into()),
+    );
+    let endpoints = config::get_endpoints(&config).unwrap().unwrap();
+    assert_eq!(
+        endpoints,
+        vec![
+            ("5.6.7.8".parse().unwrap(), vec![22, 443]),
+            ("1.2.3.4".parse().unwrap(), vec![22]),
+            ("::1".parse().unwrap(), vec![80]),
+        ]
+    );
+    config.insert("endpoints".to_string(), serde_yaml::from_str("['10.0.0.1:8080']").unwrap());
+    assert_eq!(config::get_endpoints(&config).unwrap().unwrap(), vec![("10.0.0.1".parse().unwrap(), vec![8080])]);
+
+    for invalid in ["1.2.3.4", "1.2.3.4:0", "host.local:22", "1.2.3.4:70000", ""] {
+        config.insert("endpoints".to_string(), YamlValue::String(invalid.into()));
+        assert!(matches!(config::get_endpoints(&config), Err(ScanError::Config(_))), "{}", invalid);
+    }
+    config.insert("endpoints".to_string(), YamlValue::String("::1:80".into()));
+    let err = config::get_endpoints(&config).unwrap_err().to_string();
+    assert!(err.contains("[::1]:80"), "{}", err);
+}
+
+#[test]
+fn test_scan_work_protocol_both() {
+    use port_explorer::scanner::Protocol;
+    let mut config = HashMap::new();
+    let (work, range) = config::get_scan_work(&config, 20, 21).unwrap();
+    assert_eq!(work, vec![(Protocol::Tcp, 20), (Protocol::Tcp, 21)]);
+    assert_eq!(range, "20-21");
+
+    config.insert("protocol".to_string(), YamlValue::String("both".into()));
+    let (work, range) = config::get_scan_work(&config, 20, 21).unwrap();
+    assert_eq!(
+        work,
+        vec![(Protocol::Tcp, 20), (Protocol::Udp, 20), (Protocol::Tcp, 21), (Protocol::Udp, 21)]
+    );
+    assert_eq!(range, "tcp 20-21, udp 20-21");
+
+    config.insert("protocol".to_string(), YamlValue::String("UDP".into()));
+    assert_eq!(config::get_scan_work(&config, 53, 53).unwrap(), (vec![(Protocol::Udp, 53)], "udp 53-53".to_string()));
+
+    config.insert("protocol".to_string(), YamlValue::String("sctp".into()));
+    assert!(matches!(config::get_scan_work(&config, 1, 2), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_presets() {
+    use port_explorer::scanner::{Probe, Protocol};
+    let preset = |name: &str, yaml: &str| {
+        let mut config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        config.insert("preset".to_string(), YamlValue::String(name.into()));
+        config::apply_preset(&mut config).map(|_| config)
+    };
+
+    let quick = preset("quick", "ip: 127.0.0.1").unwrap();
+    let (work, _) = config::get_scan_work(&quick, 1, 65535).unwrap();
+    assert_eq!(work.len(), 100);
+    assert!(work.iter().all(|(protocol, port)| *protocol == Protocol::Tcp && config::TOP_PORTS.contains(port)));
+    let options = config::get_scan_options(&quick, 500).unwrap();
+    assert_eq!(options.connect_timeout, std::time::Duration::from_millis(100));
+
+    // Explicit settings win, and any port selection replaces the preset's ports
+    let quick = preset("QUICK", "max_threads: 8\nstart_port: 20\nend_port: 30").unwrap();
+    assert_eq!(quick["max_threads"].as_u64(), Some(8));
+    assert!(!quick.contains_key("protocol_ports"));
+    assert_eq!(config::get_scan_work(&quick, 20, 30).unwrap().1, "20-30");
+
+    let stealth = preset("stealth", "{}").unwrap();
+    assert!(config::get_randomize(&stealth));
+    let options = config::get_scan_options(&stealth, 4).unwrap();
+    assert_eq!(options.probe, Probe::Tcp(Vec::new()));
+    assert_eq!(options.connect_timeout, std::time::Duration::from_secs(1));
+    let (work, _) = config::get_scan_work(&stealth, 1, 1000).unwrap();
+    let mut ports: Vec<u16> = work.iter().map(|(_, port)| *port).collect();
+    ports.sort_unstable();
+    assert_eq!(ports, (1..=1000).collect::<Vec<_>>());
+
+    let full = preset("full", "end_port: 1024").unwrap();
+    assert_eq!(full.get("start_port"), None);
+
+    assert!(matches!(preset("turbo", "{}"), Err(ScanError::Config(_))));
+    let mut unset: HashMap<String, YamlValue> = HashMap::new();
+    config::apply_preset(&mut unset).unwrap();
+    assert!(unset.is_empty());
+
+    unset.insert("connect_timeout_ms".to_string(), YamlValue::from(0u64));
+    assert!(matches!(config::get_scan_options(&unset, 1), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_dump_config_round_trip() {
+    let mut config: HashMap<String, YamlValue> = serde_yaml::from_str("ip: 127.0.0.1\nmax_threads: 8").unwrap();
+    config.insert("preset".to_string(), YamlValue::String("stealth".into()));
+    config::apply_preset(&mut config).unwrap();
+    let yaml = config::dump_config(&config).unwrap();
+    assert!(yaml.starts_with("connect_timeout_ms: 1000\nip: 127.0.0.1\nmax_threads: 8\n"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("profile.yaml");
+    std::fs::write(&path, &yaml).unwrap();
+    assert_eq!(config::read_config(path.to_str().unwrap()).unwrap(), config);
+}
+
+#[test]
+fn test_get_sinks() {
+    use port_explorer::sinks::SinkSpec;
+    port_explorer::localisator::init("en");
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("ip: 127.0.0.1").unwrap();
+    assert!(config::get_sinks(&config).unwrap().is_empty());
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("sinks: json:out.jsonl").unwrap();
+    assert_eq!(config::get_sinks(&config).unwrap(), vec![SinkSpec::JsonFile("out.jsonl".into())]);
+    let config: HashMap<String, YamlValue> =
+        serde_yaml::from_str("sinks: [json:out.jsonl, tcp:127.0.0.1:9000]").unwrap();
+    assert_eq!(config::get_sinks(&config).unwrap().len(), 2);
+    let config: HashMap<String, YamlValue> = serde_yaml::from_str("sinks: [9000]").unwrap();
+    assert!(matches!(config::get_sinks(&config), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_get_open_hook() {
+    let hook = |yaml: &str| {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        config::get_open_hook(&config).map(|hook| hook.is_some())
+    };
+    assert!(!hook("ip: 127.0.0.1").unwrap());
+    assert!(hook("on_open: \"echo {port}\"").unwrap());
+    assert!(hook("on_open: \"echo {port}\"\non_open_concurrency: 8").unwrap());
+    for yaml in ["on_open: \"  \"", "on_open: [echo]", "on_open: echo\non_open_concurrency: 0"] {
+        assert!(matches!(hook(yaml), Err(ScanError::Config(_))), "{}", yaml);
+    }
+}
+
+#[test]
+fn test_parse_scoped_ip() {
+    let fe80: std::net::Ipv6Addr = "fe80::1".parse().unwrap();
+    assert_eq!(config::parse_scoped_ip("fe80::1%2").unwrap(), (fe80, 2));
+    #[cfg(target_os = "linux")]
+    assert!(config::parse_scoped_ip("fe80::1%lo").unwrap().1 > 0);
+    for value in ["fe80::1%no-such-if0", "fe80::1%", "fe80::1%0", "10.0.0.1%eth0", "fe80::zz%2"] {
+        assert!(matches!(config::parse_scoped_ip(value), Err(ScanError::Config(_))), "{}", value);
+    }
+
+    let mut config = HashMap::new();
+    config.insert("ip".to_string(), YamlValue::String("::1%1".into()));
+    let (ip, ..) = config::get_config(&config).unwrap();
+    assert_eq!(*ip, "::1".parse::<std::net::IpAddr>().unwrap());
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().scope_id, 1);
+    config.insert("ip".to_string(), YamlValue::String("::1".into()));
+    assert_eq!(config::get_scan_options(&config, 10).unwrap().scope_id, 0);
+    config.insert("ip".to_string(), YamlValue::String("fe80::1%no-such-if0".into()));
+    assert!(config::get_config(&config).is_err());
+}
+
+#[test]
+fn test_get_service_cache() {
+    let cache = |yaml: &str| {
+        let config: HashMap<String, YamlValue> = serde_yaml::from_str(yaml).unwrap();
+        config::get_service_cache(&config).map(|cache| cache.is_some())
+    };
+    assert!(!cache("ip: 127.0.0.1").unwrap());
+    assert!(!cache("service_cache: false").unwrap());
+    assert!(cache("service_cache: true").unwrap());
+    assert!(cache("service_cache: true\nservice_cache_refresh: 1h").unwrap());
+    assert!(cache("service_cache: true\nservice_cache_refresh: 300").unwrap());
+    for refresh in ["0", "soon", "[1]"] {
+        let yaml = format!("service_cache: true\nservice_cache_refresh: {}", refresh);
+        assert!(matches!(cache(&yaml), Err(ScanError::Config(_))), "{}", refresh);
+    }
+}
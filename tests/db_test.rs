@@ -0,0 +1,111 @@
+// SQLite output, only built with `--features sqlite`
+#![cfg(feature = "sqlite")]
+use port_explorer::db::save_report;
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+use std::time::Duration;
+
+/// A row of the `results` table: port, protocol, state, service and latency.
+type ResultRow = (u16, String, String, Option<String>, Option<f64>);
+
+fn report(open_ports: Vec<ScanResult>) -> ScanReport {
+    ScanReport {
+        target: "localhost".into(),
+        ip: "127.0.0.1".parse().unwrap(),
+        port_range: "tcp 1-1024".into(),
+        duration_ms: 1500,
+        identification_rate: Some(0.5),
+        open_ports,
+        stats: ScanStats::default(),
+        conflicts: Vec::new(),
+    }
+}
+
+#[test]
+fn test_save_report_appends_scans() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scans.db");
+    let ports = vec![
+        ScanResult {
+            port: 22,
+            service: Some("SSH".into()),
+            version: None,
+            protocol: Protocol::Tcp,
+            state: PortState::Open,
+            latency: Some(Duration::from_millis(3)),
+            identified_by: IdentificationSource::None,
+            signature_file: None,
+            tls: None,
+        },
+        ScanResult {
+            port: 53,
+            service: None,
+            version: None,
+            protocol: Protocol::Udp,
+            state: PortState::OpenFiltered,
+            latency: None,
+            identified_by: IdentificationSource::None,
+            signature_file: None,
+            tls: None,
+        },
+    ];
+    let first = save_report(&path, &report(ports), chrono::Local::now()).unwrap();
+    let second = save_report(&path, &report(vec![]), chrono::Local::now()).unwrap();
+    assert_ne!(first, second);
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let scans: i64 = conn.query_row("SELECT COUNT(*) FROM scans", [], |row| row.get(0)).unwrap();
+    assert_eq!(scans, 2);
+    let (target, open_ports): (String, i64) = conn
+        .query_row("SELECT target, open_ports FROM scans WHERE id = ?1", [first], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!((target.as_str(), open_ports), ("localhost", 2));
+    let mut rows = conn
+        .prepare("SELECT port, protocol, state, service, latency_ms FROM results WHERE scan_id = ?1 ORDER BY port")
+        .unwrap();
+    let results: Vec<ResultRow> = rows
+        .query_map([first], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(results[0], (22, "tcp".into(), "open".into(), Some("SSH".into()), Some(3.0)));
+    assert_eq!(results[1], (53, "udp".into(), "open_filtered".into(), None, None));
+}
+
+#[test]
+fn test_save_report_unwritable_path() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(save_report(&dir.path().join("missing/scans.db"), &report(vec![]), chrono::Local::now()).is_err());
+}
+
+#[test]
+fn test_save_report_adds_version_column() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scans.db");
+    // A results table as written before versions were captured
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE results (id INTEGER PRIMARY KEY AUTOINCREMENT, scan_id INTEGER NOT NULL, port INTEGER NOT NULL,
+             protocol TEXT NOT NULL, state TEXT NOT NULL, service TEXT, latency_ms REAL);",
+        )
+        .unwrap();
+    let nginx = ScanResult {
+        port: 80,
+        service: Some("nginx".into()),
+        version: Some("1.25.3".into()),
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: None,
+        identified_by: IdentificationSource::HttpHeader,
+        signature_file: None,
+        tls: None,
+    };
+    let scan = save_report(&path, &report(vec![nginx]), chrono::Local::now()).unwrap();
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let version: Option<String> = conn
+        .query_row("SELECT version FROM results WHERE scan_id = ?1", [scan], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version.as_deref(), Some("1.25.3"));
+}
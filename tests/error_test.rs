@@ -10,7 +10,7 @@ fn test_scanerror_config_display() {
 
 #[test]
 fn test_scanerror_io_display() {
-    let io_err = io::Error::new(io::ErrorKind::Other, "fail");
+    let io_err = io::Error::other("fail");
     let err = ScanError::Io(io_err);
     let s = format!("{}", err);
     assert!(s.contains("IO error: fail"));
@@ -18,9 +18,21 @@ fn test_scanerror_io_display() {
 
 #[test]
 fn test_scanerror_from_io() {
-    let io_err = io::Error::new(io::ErrorKind::Other, "fail-from");
+    let io_err = io::Error::other("fail-from");
     let err: ScanError = io_err.into();
     let s = format!("{}", err);
     assert!(matches!(err, ScanError::Io(_)));
     assert!(s.contains("IO error: fail-from"));
-}
\ No newline at end of file
+}
+#[test]
+fn test_scanerror_resolve_display() {
+    let err = ScanError::Resolve("no such host".to_string());
+    let s = format!("{}", err);
+    assert!(s.contains("Resolve error: no such host"));
+}
+#[test]
+fn test_scanerror_not_allowed_display() {
+    let err = ScanError::NotAllowed("8.8.8.8".to_string());
+    let s = format!("{}", err);
+    assert!(s.contains("Not allowed: 8.8.8.8"));
+}
@@ -0,0 +1,199 @@
+// Exit codes of the port-explorer binary
+use std::net::TcpListener;
+use std::process::Command;
+
+/// Run the binary from the repository root (for signatures and localisation) against 127.0.0.1.
+fn run(args: &[&str]) -> i32 {
+    run_against("127.0.0.1", args)
+}
+
+/// Run the binary from the repository root against `ip`.
+fn run_against(ip: &str, args: &[&str]) -> i32 {
+    let log = tempfile::NamedTempFile::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_port-explorer"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["--ip", ip, "--append-log", log.path().to_str().unwrap()])
+        .args(args)
+        .output()
+        .unwrap();
+    output.status.code().unwrap()
+}
+
+/// A local port nothing listens on.
+fn closed_port() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port().to_string()
+}
+
+#[test]
+fn test_exit_code_config_error() {
+    assert_eq!(run(&["--tcp-ports", &closed_port(), "--sort-by", "colour"]), 1);
+    assert_eq!(run(&["--ports-from-services", "no-such-service"]), 1);
+    assert_eq!(run(&["--tcp-ports", &closed_port(), "--ssh-jump", "ops@"]), 1);
+}
+
+#[test]
+fn test_exit_code_no_open_ports() {
+    let port = closed_port();
+    assert_eq!(run(&["--tcp-ports", &port]), 0);
+    assert_eq!(run(&["--tcp-ports", &port, "--fail-on-empty"]), 3);
+}
+
+#[test]
+fn test_exit_code_open_ports() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    assert_eq!(run(&["--tcp-ports", &port, "--fail-on-empty"]), 0);
+}
+
+#[test]
+fn test_exit_code_endpoints() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let closed = format!("127.0.0.1:{}", closed_port());
+    assert_eq!(run(&["--endpoints", &format!("{},{}", closed, open), "--fail-on-empty"]), 0);
+    assert_eq!(run(&["--endpoints", &closed, "--fail-on-empty"]), 3);
+    assert_eq!(run(&["--endpoints", "::1:80"]), 1);
+}
+
+#[test]
+fn test_exit_code_baseline_deviation() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let baseline = tempfile::NamedTempFile::new().unwrap();
+    let path = baseline.path().to_str().unwrap();
+
+    std::fs::write(path, r#"{"ip": "127.0.0.1", "open_ports": []}"#).unwrap();
+    assert_eq!(run(&["--tcp-ports", &port.to_string(), "--baseline", path]), 0);
+    assert_eq!(run(&["--tcp-ports", &port.to_string(), "--baseline", path, "--fail-on-deviation"]), 6);
+
+    let known = format!(r#"{{"ip": "127.0.0.1", "open_ports": [{{"port": {}, "protocol": "tcp"}}]}}"#, port);
+    std::fs::write(path, known).unwrap();
+    assert_eq!(run(&["--tcp-ports", &port.to_string(), "--baseline", path, "--fail-on-deviation"]), 0);
+}
+
+#[test]
+fn test_exit_code_deadline() {
+    let started = std::time::Instant::now();
+    assert_eq!(run(&["--tcp-ports", &closed_port(), "--repeat", "--interval", "1h", "--deadline", "1"]), 4);
+    assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    assert_eq!(run(&["--tcp-ports", &closed_port(), "--deadline", "0"]), 1);
+}
+
+#[test]
+fn test_exit_code_unreachable_target() {
+    // A documentation address (RFC 3849) never answers, so the precheck fails
+    assert_eq!(run_against("2001:db8::1", &["--tcp-ports", "1", "--require-reachable"]), 2);
+    assert_eq!(run_against("2001:db8::1", &["--tcp-ports", "1"]), 0);
+    assert_eq!(run(&["--tcp-ports", &closed_port(), "--require-reachable"]), 0);
+}
+
+#[test]
+fn test_bundled_localisation_without_resources() {
+    // Outside the repository there is no resources/localisation, only the bundled files
+    let dir = tempfile::tempdir().unwrap();
+    let run_in = |dir: &std::path::Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_port-explorer")).current_dir(dir).args(args).output().unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(run_in(dir.path(), &["--list-languages"]), "de\tDeutsch\nen\tEnglish\n");
+    std::fs::write(dir.path().join("signatures.yaml"), "[]\n").unwrap();
+    let port = closed_port();
+    let scan = ["--ip", "127.0.0.1", "--tcp-ports", &port, "--language", "de", "--signatures-bundle", "signatures.yaml"];
+    assert!(run_in(dir.path(), &scan).contains("Keine offenen Ports"));
+
+    // Files on disk override bundled strings and add languages
+    let localisation = dir.path().join("resources/localisation");
+    std::fs::create_dir_all(&localisation).unwrap();
+    std::fs::write(localisation.join("de.yaml"), "no_open_ports: \"Nichts offen auf\"\n").unwrap();
+    std::fs::write(localisation.join("xx.yaml"), "language_name: \"Xx\"\nno_open_ports: \"xx\"\n").unwrap();
+    assert_eq!(run_in(dir.path(), &["--list-languages"]), "de\tDeutsch\nen\tEnglish\nxx\tXx\n");
+    let output = run_in(dir.path(), &scan);
+    assert!(output.contains("Nichts offen auf 127.0.0.1") && output.contains("Dauer"), "{}", output);
+}
+
+#[test]
+fn test_progress_format_json() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("signatures.yaml"), "[]\n").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || for _ in listener.incoming() {});
+    let ports = format!("{},{}", open, closed_port());
+    let output = Command::new(env!("CARGO_BIN_EXE_port-explorer"))
+        .current_dir(dir.path())
+        .args(["--ip", "127.0.0.1", "--tcp-ports", &ports, "--signatures-bundle", "signatures.yaml"])
+        .args(["--no-probe", "--language", "en", "--progress-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let events: Vec<serde_json::Value> = stderr.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    assert!(events.iter().any(|e| e["event"] == "open" && e["port"] == open), "{}", stderr);
+    let last = events.iter().rfind(|e| e["event"] == "progress").unwrap();
+    assert_eq!((last["done"].as_u64(), last["total"].as_u64()), (Some(2), Some(2)));
+}
+
+#[test]
+fn test_format_alias_json() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("signatures.yaml"), "[]\n").unwrap();
+    let listeners: Vec<TcpListener> = (0..2).map(|_| TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let ports: Vec<String> = listeners.iter().map(|l| l.local_addr().unwrap().port().to_string()).collect();
+    let ports = format!("{},{}", ports.join(","), closed_port());
+    let output = Command::new(env!("CARGO_BIN_EXE_port-explorer"))
+        .current_dir(dir.path())
+        .args(["--ip", "127.0.0.1", "--tcp-ports", &ports, "--signatures-bundle", "signatures.yaml"])
+        .args(["--no-probe", "--language", "en", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["open_ports"].as_array().map(Vec::len), Some(2));
+    assert!(report["port_range"].as_str().is_some() && report["duration_ms"].is_u64());
+}
+
+#[test]
+fn test_stdout_format_json() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("signatures.yaml"), "[]\n").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || for _ in listener.incoming() {});
+    let ports = format!("{},{}", open, closed_port());
+    let output = Command::new(env!("CARGO_BIN_EXE_port-explorer"))
+        .current_dir(dir.path())
+        .args(["--ip", "127.0.0.1", "--tcp-ports", &ports, "--signatures-bundle", "signatures.yaml"])
+        .args(["--no-probe", "--language", "en", "--stdout-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    // stdout holds nothing but the report, and no text log is written
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["ip"], "127.0.0.1");
+    assert_eq!(report["open_ports"][0]["port"], open);
+    assert_eq!(report["stats"]["closed"], 1);
+    assert!(!dir.path().join("logs").exists());
+}
+
+#[test]
+fn test_builtin_signatures_without_directory() {
+    // A fresh directory has no signatures/, so only the built-in signatures are used
+    let dir = tempfile::tempdir().unwrap();
+    let port = closed_port();
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_port-explorer"))
+            .current_dir(dir.path())
+            .args(["--ip", "127.0.0.1", "--tcp-ports", &port, "--language", "en", "--require-signatures"])
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+    assert_eq!(run(&[]).status.code(), Some(0));
+    let output = run(&["--no-builtin-signatures"]);
+    assert_ne!(output.status.code(), Some(0));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Signatures directory not found"), "{}", stderr);
+}
@@ -0,0 +1,53 @@
+// Unit tests for the on_open hook
+use port_explorer::hooks::{substitute, OpenHook};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanResult};
+use std::net::IpAddr;
+
+fn result(port: u16, service: Option<&str>) -> ScanResult {
+    ScanResult {
+        port,
+        service: service.map(str::to_string),
+        version: None,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: None,
+        identified_by: IdentificationSource::None,
+        signature_file: None,
+        tls: None,
+    }
+}
+
+#[test]
+fn test_substitute() {
+    let values: Vec<String> = ["web", "10.0.0.1", "80", "tcp", "HTTP", "open"].map(str::to_string).to_vec();
+    assert_eq!(
+        substitute("notify-send \"port {port} open on {host} ({ip}, {service} {protocol} {state})\"", &values),
+        "notify-send \"port 80 open on web (10.0.0.1, HTTP tcp open)\""
+    );
+    assert_eq!(substitute("echo {unknown} {port", &values), "echo {unknown} {port");
+    // Values are not searched for placeholders again
+    let values: Vec<String> = ["{port}", "10.0.0.1", "80", "tcp", "", "open"].map(str::to_string).to_vec();
+    assert_eq!(substitute("{host}:{port} [{service}]", &values), "{port}:80 []");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_open_hook_runs_commands() {
+    port_explorer::localisator::init("en");
+    let dir = tempfile::tempdir().unwrap();
+    let command = format!(
+        "echo \"{{host}} {{port}}/{{protocol}} $PORT_EXPLORER_HOOK_SERVICE\" > {}/{{port}}; test {{port}} != 22",
+        dir.path().display()
+    );
+    let hook = OpenHook::new(command, 2);
+    let ip: IpAddr = "127.0.0.1".parse().unwrap();
+    for port in [22, 80, 443] {
+        hook.on_open("localhost", &ip, &result(port, Some("HTTP")));
+    }
+    hook.wait();
+    // The failing command for port 22 only prints a warning
+    for port in [22, 80, 443] {
+        let written = std::fs::read_to_string(dir.path().join(port.to_string())).unwrap();
+        assert_eq!(written, format!("localhost {}/tcp HTTP\n", port));
+    }
+}
@@ -109,7 +109,7 @@ fn test_config_overrides() {
     assert_eq!(ip.to_string(), "192.168.1.1");
     assert_eq!(start_port, 100);
     assert_eq!(end_port, 200);
-    assert_eq!(max_threads, 100); // default
+    assert_eq!(max_threads, port_explorer::config::default_max_threads()); // default
     assert_eq!(language, "en"); // default
 }
 
@@ -117,3 +117,67 @@ fn test_config_overrides() {
 fn test_dummy() {
     assert_eq!(2 + 2, 4);
 }
+
+#[test]
+fn test_run_scan_with_cancel_returns_partial_results() {
+    use port_explorer::scanner::CancelToken;
+    use serde_yaml::Value as YamlValue;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    // Silent services keep every port busy for the whole banner timeout (1s)
+    let listeners: Vec<_> = (0..10).map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let ports: Vec<String> = listeners.iter().map(|l| l.local_addr().unwrap().port().to_string()).collect();
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    config.insert("ip".into(), YamlValue::String("127.0.0.1".into()));
+    config.insert("max_threads".into(), YamlValue::from(1));
+    config.insert("no_probe".into(), YamlValue::Bool(true));
+    let mut protocol_ports = serde_yaml::Mapping::new();
+    protocol_ports.insert("tcp".into(), YamlValue::String(ports.join(",")));
+    config.insert("protocol_ports".into(), YamlValue::Mapping(protocol_ports));
+
+    let token = CancelToken::new();
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(1500));
+        canceller.cancel();
+    });
+    let started = Instant::now();
+    let report = port_explorer::run_scan_with_cancel(&config, Arc::new(vec![]), &token).unwrap();
+    // A full scan takes 10s; a cancelled one returns after the ports scanned so far
+    assert!(started.elapsed() < Duration::from_secs(4), "took {:?}", started.elapsed());
+    assert!(report.stats.cancelled);
+    assert!(!report.open_ports.is_empty() && report.open_ports.len() < ports.len());
+    assert!(token.is_cancelled());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_scan_ipv6_zone() {
+    use port_explorer::signatures::Signature;
+    use serde_yaml::Value as YamlValue;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+    let listener = std::net::TcpListener::bind("[::1]:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 11\r\n\r\nzoned hello");
+        }
+    });
+    let mut config: HashMap<String, YamlValue> = HashMap::new();
+    config.insert("ip".into(), YamlValue::String("::1%lo".into()));
+    let mut protocol_ports = serde_yaml::Mapping::new();
+    protocol_ports.insert("tcp".into(), YamlValue::String(port.to_string()));
+    config.insert("protocol_ports".into(), YamlValue::Mapping(protocol_ports));
+    let signatures = vec![Signature {
+        name: "Zoned".to_string(),
+        match_: "zoned hello".to_string(),
+        ..Default::default()
+    }];
+    let report = port_explorer::run_scan(&config, Arc::new(signatures)).unwrap();
+    assert_eq!(report.target, "::1%lo");
+    assert_eq!(report.open_ports.len(), 1);
+    assert_eq!(report.open_ports[0].service.as_deref(), Some("Zoned"));
+}
@@ -29,4 +29,63 @@ fn test_get_missing_key() {
     // Use a language with a known file
     localisator::init("testlang");
     assert_eq!(localisator::get("not_in_file"), "not_in_file");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_set_overrides_loaded_value() {
+    localisator::init("en");
+    localisator::set("scan_complete_override_test", "Done!");
+    assert_eq!(localisator::get("scan_complete_override_test"), "Done!");
+    // Overrides survive re-initialisation
+    localisator::init("de");
+    assert_eq!(localisator::get("scan_complete_override_test"), "Done!");
+}
+
+#[test]
+fn test_keys_include_overrides() {
+    localisator::set("keys_override_test", "value");
+    assert!(localisator::keys().contains(&"keys_override_test".to_string()));
+}
+
+#[test]
+fn test_plural_category() {
+    assert_eq!(localisator::plural_category("en", 1), "one");
+    assert_eq!(localisator::plural_category("en", 0), "other");
+    assert_eq!(localisator::plural_category("de", 2), "other");
+    assert_eq!(localisator::plural_category("fr", 0), "one");
+    assert_eq!(localisator::plural_category("ru", 21), "one");
+    assert_eq!(localisator::plural_category("ru", 3), "few");
+    assert_eq!(localisator::plural_category("ru", 12), "many");
+    assert_eq!(localisator::plural_category("pl", 22), "few");
+    assert_eq!(localisator::plural_category("pl", 21), "many");
+    assert_eq!(localisator::plural_category("ja", 1), "other");
+}
+
+#[test]
+fn test_get_plural_with_overrides() {
+    localisator::set("test_plural_ports.one", "{n} port");
+    localisator::set("test_plural_ports.other", "{n} ports");
+    assert_eq!(localisator::get_plural("test_plural_ports", 1), "1 port");
+    assert_eq!(localisator::get_plural("test_plural_ports", 3), "3 ports");
+
+    // Without plural forms the plain key is used
+    localisator::set("test_plural_plain", "{n} items");
+    assert_eq!(localisator::get_plural("test_plural_plain", 1), "1 items");
+    assert_eq!(localisator::get_plural("test_plural_missing", 2), "test_plural_missing");
+}
+
+#[test]
+fn test_bundled_languages() {
+    let bundled = localisator::bundled_languages();
+    assert!(bundled.contains(&"en") && bundled.contains(&"de"), "{:?}", bundled);
+    assert!(bundled.windows(2).all(|pair| pair[0] < pair[1]));
+    let available = localisator::available_languages();
+    assert!(bundled.iter().all(|code| available.iter().any(|a| a == code)));
+}
+
+#[test]
+fn test_language_name() {
+    assert_eq!(localisator::language_name("de").as_deref(), Some("Deutsch"));
+    assert_eq!(localisator::language_name("en").as_deref(), Some("English"));
+    assert_eq!(localisator::language_name("zz"), None);
+}
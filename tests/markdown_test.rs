@@ -0,0 +1,68 @@
+use port_explorer::markdown::{escape, format_report, write_report};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+use std::time::Duration;
+
+fn report(target: &str, ip: &str, open_ports: Vec<ScanResult>) -> ScanReport {
+    ScanReport {
+        target: target.into(),
+        ip: ip.parse().unwrap(),
+        port_range: "tcp 1-1024".into(),
+        duration_ms: 1500,
+        identification_rate: (!open_ports.is_empty()).then_some(0.5),
+        open_ports,
+        stats: ScanStats::default(),
+        conflicts: Vec::new(),
+    }
+}
+
+fn open(port: u16, service: Option<&str>, latency_ms: Option<u64>) -> ScanResult {
+    ScanResult {
+        port,
+        service: service.map(str::to_string),
+        version: None,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: latency_ms.map(Duration::from_millis),
+        identified_by: IdentificationSource::None,
+        signature_file: None,
+        tls: None,
+    }
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape("SSH"), "SSH");
+    assert_eq!(escape("a|b"), "a\\|b");
+    assert_eq!(escape("*x* <b>\\"), "\\*x\\* \\<b\\>\\\\");
+    assert_eq!(escape("line\r\nbreak"), "line  break");
+}
+
+#[test]
+fn test_format_report() {
+    port_explorer::localisator::init("en");
+    let web = report("web.example", "10.0.0.1", vec![open(22, Some("SSH"), Some(2)), open(80, Some("A | B"), None)]);
+    let empty = report("10.0.0.2", "10.0.0.2", Vec::new());
+    let markdown = format_report(&[&web, &empty]);
+    let lines: Vec<&str> = markdown.lines().collect();
+    assert_eq!(lines[0], "# Port Explorer Report");
+    assert!(lines.contains(&"| web.example | 10.0.0.1 | tcp 1-1024 | 2 | 50% | 1s 500ms |"), "{}", markdown);
+    assert!(lines.contains(&"| 10.0.0.2 | 10.0.0.2 | tcp 1-1024 | 0 | - | 1s 500ms |"), "{}", markdown);
+    assert!(lines.contains(&"## web.example (10.0.0.1)"));
+    assert!(lines.contains(&"| 22 | tcp | open | SSH | 2.0 ms |"));
+    // A pipe in a service name stays inside its cell
+    assert!(lines.contains(&"| 80 | tcp | open | A \\| B | - |"));
+    assert!(markdown.ends_with("## 10.0.0.2\n\nNo open ports found.\n"), "{}", markdown);
+}
+
+#[test]
+fn test_write_report_replaces_file() {
+    port_explorer::localisator::init("en");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scan.md");
+    write_report(&path, &[&report("10.0.0.1", "10.0.0.1", vec![open(22, None, None)])]).unwrap();
+    write_report(&path, &[&report("10.0.0.1", "10.0.0.1", Vec::new())]).unwrap();
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    assert!(!markdown.contains("| 22 |"));
+    assert!(markdown.contains("No open ports found."));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,62 @@
+// Memory use of scans with far more work units than fit in memory. This file has its own test
+// binary because the counting allocator sees every allocation of the process.
+use indicatif::ProgressBar;
+use port_explorer::scanner::{find_first_open, CancelToken, Probe, Protocol, ScanOptions};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Bytes currently allocated, and the most allocated at any time since the last reset.
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_find_first_open_memory_is_bounded() {
+    // 65534 hosts times 65535 ports: four billion work units, hundreds of gigabytes if all were queued
+    let hosts: Vec<IpAddr> = "127.1.0.0/16".parse::<ipnet::IpNet>().unwrap().hosts().collect();
+    let work: Vec<(Protocol, u16)> = (1..=u16::MAX).map(|port| (Protocol::Tcp, port)).collect();
+    let cancel = CancelToken::new();
+    let options = ScanOptions {
+        max_threads: 16,
+        probe: Probe::Tcp(Vec::new()),
+        cancel: Some(cancel.clone()),
+        ..ScanOptions::default()
+    };
+    let pb = ProgressBar::hidden();
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(500));
+        cancel.cancel();
+    });
+    find_first_open(hosts, &work, Arc::new(vec![]), &options, &pb).unwrap();
+    canceller.join().unwrap();
+
+    assert!(pb.position() > 0);
+    // Per-host bookkeeping and the queued jobs, not the product of hosts and ports
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    assert!(peak < 32 * 1024 * 1024, "peak allocation of {} bytes", peak);
+}
@@ -0,0 +1,68 @@
+// MessagePack output, only built with `--features msgpack`
+#![cfg(feature = "msgpack")]
+use port_explorer::msgpack::{encode, write_reports};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+use std::time::Duration;
+
+fn report(ip: &str, open_ports: Vec<ScanResult>) -> ScanReport {
+    ScanReport {
+        target: ip.into(),
+        ip: ip.parse().unwrap(),
+        port_range: "tcp 1-1024".into(),
+        duration_ms: 1500,
+        identification_rate: Some(0.5),
+        open_ports,
+        stats: ScanStats { closed: 1020, filtered: 2, ..ScanStats::default() },
+        conflicts: Vec::new(),
+    }
+}
+
+fn open_ports() -> Vec<ScanResult> {
+    vec![
+        ScanResult {
+            port: 22,
+            service: Some("SSH".into()),
+            version: None,
+            protocol: Protocol::Tcp,
+            state: PortState::Open,
+            latency: Some(Duration::from_micros(1250)),
+            identified_by: IdentificationSource::Banner,
+            signature_file: Some("signatures/ssh.yaml".into()),
+            tls: None,
+        },
+        ScanResult {
+            port: 53,
+            service: None,
+            version: None,
+            protocol: Protocol::Udp,
+            state: PortState::OpenFiltered,
+            latency: None,
+            identified_by: IdentificationSource::None,
+            signature_file: None,
+            tls: None,
+        },
+    ]
+}
+
+#[test]
+fn test_encode_round_trip() {
+    let report = report("10.0.0.1", open_ports());
+    let bytes = encode(&report).unwrap();
+    let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+    // Same fields and values as the JSON report, latency as fractional milliseconds included
+    assert_eq!(decoded, serde_json::to_value(&report).unwrap());
+    assert_eq!(decoded["open_ports"][0]["latency_ms"], 1.25);
+    assert!(bytes.len() < serde_json::to_vec(&report).unwrap().len());
+}
+
+#[test]
+fn test_write_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scan.msgpack");
+    let reports = [report("10.0.0.1", open_ports()), report("::1", Vec::new())];
+    write_reports(&path, &reports.iter().collect::<Vec<_>>()).unwrap();
+    write_reports(&path, &reports.iter().collect::<Vec<_>>()).unwrap();
+    let decoded: serde_json::Value = rmp_serde::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(decoded, serde_json::to_value(&reports).unwrap());
+    assert!(!dir.path().join("scan.msgpack.tmp").exists());
+}
@@ -0,0 +1,310 @@
+use port_explorer::output::{
+    append_log_section, format_service_groups, group_by_service, sanitize_text, sort_results, GroupBy, ProgressFormat, SortBy, StdoutFormat,
+    SECTION_DELIMITER,
+};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanResult};
+use std::time::Duration;
+use std::fs;
+
+#[test]
+fn test_append_log_section_creates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("daily.log");
+    append_log_section(&path, "Scan started: now\n").unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content, format!("{}\nScan started: now\n", SECTION_DELIMITER));
+}
+
+#[test]
+fn test_append_log_section_accumulates() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("daily.log");
+    append_log_section(&path, "first").unwrap();
+    append_log_section(&path, "second\n").unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        format!("{d}\nfirst\n{d}\nsecond\n", d = SECTION_DELIMITER)
+    );
+}
+
+#[test]
+fn test_append_log_section_concurrent_writers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.log");
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let body: String = (0..50).map(|l| format!("writer {} line {}\n", i, l)).collect();
+                append_log_section(&path, &body).unwrap();
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    let content = fs::read_to_string(&path).unwrap();
+    let sections: Vec<&str> = content.split(&format!("{}\n", SECTION_DELIMITER)).skip(1).collect();
+    assert_eq!(sections.len(), 8);
+    for section in sections {
+        // Every section must contain exactly one writer's lines, uninterrupted
+        let first = section.lines().next().unwrap();
+        let writer = first.split(" line").next().unwrap();
+        assert_eq!(section.lines().count(), 50);
+        assert!(section.lines().all(|l| l.starts_with(writer)));
+    }
+}
+
+fn result(port: u16, service: Option<&str>, latency_ms: Option<u64>) -> ScanResult {
+    ScanResult {
+        port,
+        service: service.map(str::to_string),
+        version: None,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: latency_ms.map(Duration::from_millis),
+        identified_by: IdentificationSource::None,
+        signature_file: None,
+        tls: None,
+    }
+}
+
+#[test]
+fn test_sort_results_by_service_and_latency() {
+    let mut results = vec![
+        result(443, Some("nginx"), Some(5)),
+        result(22, None, Some(1)),
+        result(80, Some("Apache"), None),
+        result(8080, None, Some(3)),
+    ];
+    sort_results(&mut results, SortBy::Service);
+    let ports: Vec<u16> = results.iter().map(|r| r.port).collect();
+    assert_eq!(ports, vec![80, 443, 22, 8080]);
+
+    sort_results(&mut results, SortBy::Latency);
+    let ports: Vec<u16> = results.iter().map(|r| r.port).collect();
+    assert_eq!(ports, vec![22, 8080, 443, 80]);
+
+    sort_results(&mut results, SortBy::Port);
+    let ports: Vec<u16> = results.iter().map(|r| r.port).collect();
+    assert_eq!(ports, vec![22, 80, 443, 8080]);
+}
+
+#[test]
+fn test_sort_by_from_str() {
+    assert_eq!("Service".parse::<SortBy>().unwrap(), SortBy::Service);
+    assert_eq!("latency".parse::<SortBy>().unwrap(), SortBy::Latency);
+    assert!("size".parse::<SortBy>().is_err());
+}
+
+#[test]
+fn test_progress_bar_invalid_template_falls_back() {
+    let pb = port_explorer::output::progress_bar(10, "{bar:abc", false);
+    pb.inc(3);
+    assert_eq!(pb.position(), 3);
+    assert_eq!(pb.length(), Some(10));
+}
+
+#[test]
+fn test_progress_bar_hidden_when_not_visible() {
+    let pb = port_explorer::output::progress_bar(5, port_explorer::output::PROGRESS_TEMPLATE, false);
+    assert!(pb.is_hidden());
+    pb.finish();
+    assert!(pb.is_finished());
+}
+
+#[test]
+fn test_identification_rate() {
+    let result = |port: u16, service: Option<&str>| ScanResult {
+        port,
+        service: service.map(str::to_string),
+        version: None,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: None,
+        identified_by: IdentificationSource::None,
+        signature_file: None,
+        tls: None,
+    };
+    assert_eq!(port_explorer::output::identification_rate(&[]), (0, None));
+    let results = vec![result(22, Some("SSH")), result(80, None), result(443, Some("HTTPS")), result(8080, None)];
+    assert_eq!(port_explorer::output::identification_rate(&results), (2, Some(0.5)));
+}
+
+#[test]
+fn test_diff_results_reports_opened_and_closed_ports() {
+    let previous = vec![result(22, Some("SSH"), None), result(80, Some("HTTP"), None)];
+    let mut udp = result(80, None, None);
+    udp.protocol = Protocol::Udp;
+    let current = vec![result(80, None, None), result(443, None, None), udp];
+    let (opened, closed) = port_explorer::output::diff_results(&previous, &current);
+    assert_eq!(
+        opened.iter().map(|r| (r.port, r.protocol)).collect::<Vec<_>>(),
+        vec![(443, Protocol::Tcp), (80, Protocol::Udp)]
+    );
+    assert_eq!(closed.iter().map(|r| r.port).collect::<Vec<_>>(), vec![22]);
+    assert_eq!(port_explorer::output::diff_results(&current, &current), (vec![], vec![]));
+}
+
+#[test]
+fn test_service_mismatch() {
+    let expected = std::collections::HashMap::from([
+        ((80, Protocol::Tcp), "HTTP".to_string()),
+        ((22, Protocol::Tcp), "SSH".to_string()),
+    ]);
+    let mismatch = |res: &ScanResult| port_explorer::output::service_mismatch(res, &expected);
+    assert_eq!(mismatch(&result(80, Some("SSH"), None)), Some("HTTP"));
+    assert_eq!(mismatch(&result(22, Some("ssh"), None)), None);
+    assert_eq!(mismatch(&result(22, None, None)), None);
+    assert_eq!(mismatch(&result(443, Some("SSH"), None)), None);
+    let mut udp = result(80, Some("DNS"), None);
+    udp.protocol = Protocol::Udp;
+    assert_eq!(mismatch(&udp), None);
+}
+
+#[test]
+fn test_group_by_protocol() {
+    let mut dns = result(53, Some("DNS"), None);
+    dns.protocol = Protocol::Udp;
+    let results = vec![dns.clone(), result(80, None, None), result(22, None, None)];
+    let groups = port_explorer::output::group_by_protocol(&results);
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].0, Protocol::Tcp);
+    assert_eq!(groups[0].1.iter().map(|r| r.port).collect::<Vec<_>>(), vec![80, 22]);
+    assert_eq!(groups[1], (Protocol::Udp, vec![&dns]));
+    assert!(port_explorer::output::group_by_protocol(&[]).is_empty());
+}
+
+#[test]
+fn test_filter_by_latency() {
+    use port_explorer::output::{filter_by_latency, LatencyFilter};
+    let all = vec![result(22, None, Some(5)), result(80, None, Some(150)), result(443, None, Some(900)), result(8080, None, None)];
+    let mut results = all.clone();
+    let filter = LatencyFilter { min: Some(Duration::from_millis(100)), max: None };
+    assert_eq!(filter_by_latency(&mut results, &filter), 2);
+    assert_eq!(results.iter().map(|r| r.port).collect::<Vec<_>>(), vec![80, 443]);
+    let mut results = all.clone();
+    let filter = LatencyFilter { min: Some(Duration::from_millis(100)), max: Some(Duration::from_millis(500)) };
+    assert_eq!(filter_by_latency(&mut results, &filter), 3);
+    assert_eq!(results.iter().map(|r| r.port).collect::<Vec<_>>(), vec![80]);
+    let mut results = all;
+    let filter = LatencyFilter { min: None, max: Some(Duration::from_millis(150)) };
+    filter_by_latency(&mut results, &filter);
+    assert_eq!(results.iter().map(|r| r.port).collect::<Vec<_>>(), vec![22, 80]);
+}
+
+#[test]
+fn test_format_latency_histogram() {
+    use port_explorer::output::format_latency_histogram;
+    use port_explorer::scanner::{LatencyBucket, LatencyHistogram};
+    port_explorer::localisator::init("en");
+    let bucket = |upper_ms, open, failed| LatencyBucket { upper_ms, open, failed };
+    let histogram = LatencyHistogram {
+        buckets: vec![bucket(Some(1), 0, 0), bucket(Some(2), 2, 38), bucket(Some(1000), 0, 0), bucket(Some(2000), 0, 20), bucket(None, 0, 0)],
+    };
+    let text = format_latency_histogram(&histogram);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", text);
+    assert!(lines[1].trim_start().starts_with("< 2ms") && lines[1].ends_with(&"#".repeat(40)), "{}", text);
+    assert!(lines[2].trim_start().starts_with("< 1s") && !lines[2].contains('#'), "{}", text);
+    assert!(lines[3].contains("20 failed") && lines[3].ends_with(&"#".repeat(20)), "{}", text);
+
+    let last = LatencyHistogram { buckets: vec![bucket(Some(2000), 0, 0), bucket(None, 0, 1)] };
+    assert!(format_latency_histogram(&last).contains(">= 2s"));
+    assert_eq!(format_latency_histogram(&LatencyHistogram::default()).lines().count(), 1);
+}
+
+#[test]
+fn test_group_by_from_str() {
+    assert_eq!("Service".parse::<GroupBy>().unwrap(), GroupBy::Service);
+    assert_eq!("host".parse::<GroupBy>().unwrap(), GroupBy::Host);
+    assert!("port".parse::<GroupBy>().is_err());
+}
+
+#[test]
+fn test_group_by_service() {
+    let results = vec![
+        ("10.0.0.1".to_string(), result(80, Some("nginx"), None)),
+        ("10.0.0.1".to_string(), result(22, Some("ssh"), None)),
+        ("10.0.0.1".to_string(), result(9000, None, None)),
+        ("10.0.0.2".to_string(), result(8080, Some("Nginx"), None)),
+        ("10.0.0.2".to_string(), result(2222, Some("ssh"), None)),
+    ];
+    let groups = group_by_service(&results);
+    let summary: Vec<_> = groups
+        .iter()
+        .map(|(service, group)| (*service, group.iter().map(|(host, res)| (host.as_str(), res.port)).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Some("nginx"), vec![("10.0.0.1", 80), ("10.0.0.2", 8080)]),
+            (Some("ssh"), vec![("10.0.0.1", 22), ("10.0.0.2", 2222)]),
+            (None, vec![("10.0.0.1", 9000)]),
+        ]
+    );
+}
+
+#[test]
+fn test_format_service_groups() {
+    port_explorer::localisator::init("en");
+    let results = vec![
+        ("10.0.0.1".to_string(), result(22, Some("ssh"), None)),
+        ("::1".to_string(), result(22, Some("ssh"), None)),
+        ("10.0.0.2".to_string(), result(9000, None, None)),
+    ];
+    assert_eq!(
+        format_service_groups(&results),
+        "Services:\nssh (2)\n├─ 10.0.0.1:22/tcp\n└─ [::1]:22/tcp\nunknown (1)\n└─ 10.0.0.2:9000/tcp\n"
+    );
+    assert!(group_by_service(&[]).is_empty());
+}
+
+#[test]
+fn test_stdout_format_from_str() {
+    assert_eq!("JSON".parse::<StdoutFormat>().unwrap(), StdoutFormat::Json);
+    assert_eq!("text".parse::<StdoutFormat>().unwrap(), StdoutFormat::Text);
+    assert!("xml".parse::<StdoutFormat>().is_err());
+}
+
+#[test]
+fn test_progress_events() {
+    use port_explorer::output::{open_event, progress_event};
+    assert_eq!("JSON".parse::<ProgressFormat>().unwrap(), ProgressFormat::Json);
+    assert_eq!("bar".parse::<ProgressFormat>().unwrap(), ProgressFormat::Bar);
+    assert!("text".parse::<ProgressFormat>().is_err());
+    assert_eq!(progress_event(1200, 65535), r#"{"event":"progress","done":1200,"total":65535}"#);
+    let ip = "10.0.0.1".parse().unwrap();
+    assert_eq!(
+        open_event("gateway", &ip, &result(22, Some("SSH"), None)),
+        r#"{"event":"open","host":"gateway","ip":"10.0.0.1","port":22,"protocol":"tcp","state":"open","service":"SSH"}"#
+    );
+}
+
+#[test]
+fn test_write_csv() {
+    use port_explorer::output::write_csv;
+    let ip = "10.0.0.1".parse().unwrap();
+    let mut out = Vec::new();
+    let ssh = ScanResult { version: Some("9.6".into()), ..result(22, Some("SSH"), Some(3)) };
+    write_csv(&ip, &[ssh, result(8080, Some("Acme, \"Pro\""), None)], &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "ip,port,service,latency_ms,version\n10.0.0.1,22,SSH,3.000,9.6\n10.0.0.1,8080,\"Acme, \"\"Pro\"\"\",,\n"
+    );
+    let mut out = Vec::new();
+    write_csv(&ip, &[], &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "ip,port,service,latency_ms,version\n");
+    assert_eq!("CSV".parse::<StdoutFormat>().unwrap(), StdoutFormat::Csv);
+}
+
+#[test]
+fn test_sanitize_text() {
+    assert_eq!(sanitize_text("nginx 1.24"), "nginx 1.24");
+    assert_eq!(sanitize_text("nginx\x1b[2J\x1b]0;pwned\x07 ok\r\n"), "nginx ok\\x0d\\x0a");
+    assert_eq!(sanitize_text("a\x1bP1;2q\x1b\\b\x1b7c"), "abc");
+    assert_eq!(sanitize_text("x\u{9b}31mred\x7f"), "xred\\x7f");
+    assert_eq!(sanitize_text("admin\u{202e}gpj.exe"), "admin\\u{202e}gpj.exe");
+    assert_eq!(sanitize_text("Ünïcode ✓"), "Ünïcode ✓");
+}
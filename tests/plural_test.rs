@@ -0,0 +1,16 @@
+// Kept in its own test binary: it checks state loaded by `localisator::init`, which other
+// localisator tests replace concurrently.
+use port_explorer::localisator;
+use std::fs;
+
+#[test]
+fn test_init_loads_plural_forms() {
+    let dir = "resources/localisation";
+    let path = format!("{}/pluraltest.yaml", dir);
+    fs::write(&path, "things:\n  one: \"{n} thing\"\n  other: \"{n} things\"\nplain: Plain\n").unwrap();
+    localisator::init("pluraltest");
+    let _ = fs::remove_file(&path);
+    assert_eq!(localisator::get_plural("things", 1), "1 thing");
+    assert_eq!(localisator::get_plural("things", 7), "7 things");
+    assert_eq!(localisator::get("plain"), "Plain");
+}
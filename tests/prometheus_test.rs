@@ -0,0 +1,58 @@
+use port_explorer::prometheus::{escape_label_value, format_metrics, write_metrics};
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+
+fn report(ip: &str, open_ports: Vec<ScanResult>) -> ScanReport {
+    ScanReport {
+        target: ip.into(),
+        ip: ip.parse().unwrap(),
+        port_range: "1-1024".into(),
+        duration_ms: 1500,
+        identification_rate: None,
+        open_ports,
+        stats: ScanStats::default(),
+        conflicts: Vec::new(),
+    }
+}
+
+fn open(port: u16, protocol: Protocol, service: Option<&str>) -> ScanResult {
+    ScanResult { port, service: service.map(str::to_string), version: None, protocol, state: PortState::Open, latency: None, identified_by: IdentificationSource::None, signature_file: None, tls: None }
+}
+
+#[test]
+fn test_escape_label_value() {
+    assert_eq!(escape_label_value("ssh"), "ssh");
+    assert_eq!(escape_label_value("a \"b\"\\c\nd"), "a \\\"b\\\"\\\\c\\nd");
+}
+
+#[test]
+fn test_format_metrics() {
+    let first = report("1.2.3.4", vec![open(22, Protocol::Tcp, Some("ssh")), open(53, Protocol::Udp, None)]);
+    let second = report("::1", vec![open(80, Protocol::Tcp, Some("My \"Router\""))]);
+    let metrics = format_metrics(&[&first, &second]);
+    let lines: Vec<&str> = metrics.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"port_explorer_open_port{host="1.2.3.4",port="22",protocol="tcp",service="ssh"} 1"#,
+            r#"port_explorer_open_port{host="1.2.3.4",port="53",protocol="udp",service=""} 1"#,
+            r#"port_explorer_open_port{host="::1",port="80",protocol="tcp",service="My \"Router\""} 1"#,
+            r#"port_explorer_open_ports{host="1.2.3.4"} 2"#,
+            r#"port_explorer_open_ports{host="::1"} 1"#,
+            r#"port_explorer_scan_duration_seconds{host="1.2.3.4"} 1.5"#,
+            r#"port_explorer_scan_duration_seconds{host="::1"} 1.5"#,
+        ]
+    );
+    assert!(metrics.contains("# TYPE port_explorer_scan_duration_seconds gauge\n"));
+}
+
+#[test]
+fn test_write_metrics_replaces_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scan.prom");
+    write_metrics(&path, &[&report("1.2.3.4", vec![open(22, Protocol::Tcp, None)])]).unwrap();
+    write_metrics(&path, &[&report("1.2.3.4", Vec::new())]).unwrap();
+    let metrics = std::fs::read_to_string(&path).unwrap();
+    assert!(!metrics.contains("port=\"22\""));
+    assert!(metrics.contains("port_explorer_open_ports{host=\"1.2.3.4\"} 0"));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
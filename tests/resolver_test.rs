@@ -0,0 +1,80 @@
+use port_explorer::error::ScanError;
+use port_explorer::resolver::{is_valid_hostname, parse_dns_server, resolve_host, resolve_hosts, LookupLimit};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[test]
+fn test_is_valid_hostname() {
+    assert!(is_valid_hostname("localhost"));
+    assert!(is_valid_hostname("example.com"));
+    assert!(is_valid_hostname("my-host.internal."));
+    assert!(!is_valid_hostname("not_an_ip"));
+    assert!(!is_valid_hostname("-bad.example.com"));
+    assert!(!is_valid_hostname("double..dot"));
+    assert!(!is_valid_hostname(""));
+}
+
+#[test]
+fn test_parse_dns_server_default_port() {
+    let addr = parse_dns_server("10.0.0.53").unwrap();
+    assert_eq!(addr.to_string(), "10.0.0.53:53");
+}
+
+#[test]
+fn test_parse_dns_server_with_port() {
+    assert_eq!(parse_dns_server("10.0.0.53:5353").unwrap().port(), 5353);
+    assert_eq!(parse_dns_server("[::1]:5353").unwrap().port(), 5353);
+    assert_eq!(parse_dns_server("::1").unwrap().port(), 53);
+}
+
+#[test]
+fn test_parse_dns_server_invalid() {
+    let result = parse_dns_server("dns.example.com");
+    assert!(matches!(result, Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_resolve_localhost_system() {
+    let addrs = resolve_host("localhost", None).unwrap();
+    assert!(addrs.iter().all(|ip| ip.is_loopback()));
+}
+
+#[test]
+fn test_resolve_unknown_host() {
+    let result = resolve_host("does-not-exist.invalid", None);
+    assert!(matches!(result, Err(ScanError::Resolve(_))));
+}
+
+#[test]
+fn test_lookup_limit_caps_concurrency() {
+    let limit = LookupLimit::new(2);
+    let running = AtomicUsize::new(0);
+    let peak = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                let _permit = limit.acquire();
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+    assert_eq!(LookupLimit::new(0).limit(), 1);
+}
+
+#[test]
+fn test_resolve_hosts_keeps_order() {
+    let hosts = ["localhost", "does-not-exist.invalid", "localhost", "localhost", "also-missing.invalid"];
+    let results = resolve_hosts(&hosts, None);
+    assert_eq!(results.len(), hosts.len());
+    for (host, result) in hosts.iter().zip(&results) {
+        match result {
+            Ok(addrs) => assert!(*host == "localhost" && addrs.iter().all(|ip| ip.is_loopback())),
+            Err(e) => assert!(host.ends_with(".invalid") && matches!(e, ScanError::Resolve(_)), "{}", e),
+        }
+    }
+    assert!(resolve_hosts::<&str>(&[], None).is_empty());
+}
@@ -1,4 +1,12 @@
-use port_explorer::scanner::{format_duration, scan_port, scan_ports_parallel};
+use port_explorer::scanner::{
+    format_duration, scan_port, scan_ports_parallel, scan_ports_parallel_into, scan_ports_parallel_with_callback,
+    scan_udp_port, scan_work_parallel, PortState, Protocol, RetryBudget, ScanOptions, ScanResult,
+    SourcePortRange, AutoTuner, is_resource_error, scan_port_with_options, ScanState, discover_hosts,
+    classify_connect, ConnectOutcome, Probe, classify_failure, ConnectFailure, ConnectFailures,
+    IdentificationSource, ramp_threads, merge_reports, ScanReport, ScanStats, ServiceConflict,
+    find_first_open, LATENCY_BUCKETS_MS, is_transient_error, HttpProbePorts, ServiceCache, Engine,
+    scan_hosts_parallel,
+};
 use port_explorer::signatures::Signature;
 use std::sync::Arc;
 use std::time::Duration;
@@ -51,6 +59,7 @@ fn test_scan_port_with_signatures() {
         Signature {
             name: "Test Service".to_string(),
             match_: "test".to_string(),
+            ..Default::default()
         }
     ]);
     let port = 65533; // Usually closed
@@ -94,10 +103,12 @@ fn test_scan_ports_parallel_with_signatures() {
         Signature {
             name: "HTTP Server".to_string(),
             match_: "HTTP".to_string(),
+            ..Default::default()
         },
         Signature {
             name: "SSH".to_string(),
             match_: "SSH".to_string(),
+            ..Default::default()
         }
     ]);
     let ports = vec![65529]; // Usually closed port
@@ -137,4 +148,1147 @@ fn test_scan_ports_parallel_many_threads() {
     assert!(result.is_ok());
     let open_ports = result.unwrap();
     assert!(open_ports.is_empty(), "Expected no open ports, but found: {:?}", open_ports);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_scan_ports_parallel_many_open_ports() {
+    // Stress the result collection with many concurrently open local ports
+    let listeners: Vec<std::net::TcpListener> = (0..100)
+        .map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap())
+        .collect();
+    let mut expected: Vec<u16> = listeners
+        .iter()
+        .map(|l| l.local_addr().unwrap().port())
+        .collect();
+    expected.sort();
+    for listener in listeners {
+        std::thread::spawn(move || {
+            // Accept and immediately close so the HTTP probe fails fast
+            for stream in listener.incoming() {
+                drop(stream);
+            }
+        });
+    }
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![]);
+    let pb = ProgressBar::hidden();
+
+    let result = scan_ports_parallel(ip, expected.clone(), signatures, 64, &pb);
+    assert!(result.is_ok());
+    let found: Vec<u16> = result.unwrap().iter().map(|r| r.port).collect();
+    assert_eq!(found, expected, "Some open ports were lost during collection");
+}
+
+#[test]
+fn test_scan_ports_parallel_into_sink() {
+    struct Collect(std::sync::Mutex<Vec<u16>>);
+    impl port_explorer::sinks::ResultSink for Collect {
+        fn on_result(&self, r: &ScanResult) {
+            self.0.lock().unwrap().push(r.port);
+        }
+        fn finish(&self, _report: &ScanReport) {}
+    }
+
+    let listeners: Vec<_> = (0..50).map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let mut expected: Vec<u16> = listeners.iter().map(|l| l.local_addr().unwrap().port()).collect();
+    expected.sort_unstable();
+    for listener in listeners {
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                drop(stream);
+            }
+        });
+    }
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let sink = Collect(std::sync::Mutex::new(Vec::new()));
+
+    let result = scan_ports_parallel_into(ip, expected.clone(), Arc::new(vec![]), 16, &pb, &sink).unwrap();
+    let mut received = sink.0.into_inner().unwrap();
+    received.sort_unstable();
+    assert_eq!(received, expected);
+    assert_eq!(result.iter().map(|r| r.port).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_scan_ports_parallel_with_callback_streams_results() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            drop(stream);
+        }
+    });
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![]);
+    let pb = ProgressBar::hidden();
+    let mut streamed = Vec::new();
+
+    let result = scan_ports_parallel_with_callback(ip, vec![port, 65525], signatures, 2, &pb, |res| {
+        streamed.push(res.port)
+    });
+    assert_eq!(streamed, vec![port]);
+    let result = result.unwrap();
+    assert!(result[0].latency.is_some());
+    assert_eq!(
+        result,
+        vec![ScanResult {
+            port,
+            service: None,
+            version: None,
+            protocol: Protocol::Tcp,
+            state: PortState::Open,
+            latency: result[0].latency,
+            identified_by: IdentificationSource::None,
+            signature_file: None,
+            tls: None,
+        }]
+    );
+}
+
+#[test]
+fn test_scan_udp_port_responding() {
+    // A local UDP service that answers every datagram
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((_, peer)) = socket.recv_from(&mut buf) {
+            let _ = socket.send_to(b"TEST-DNS ready", peer);
+        }
+    });
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![Signature {
+        name: "Test DNS".to_string(),
+        match_: "TEST-DNS".to_string(),
+        ..Default::default()
+    }]);
+    let result = scan_udp_port(ip, port, signatures).unwrap();
+    assert_eq!(result.protocol, Protocol::Udp);
+    assert_eq!(result.state, PortState::Open);
+    assert_eq!(result.service, Some("Test DNS".to_string()));
+}
+
+#[test]
+fn test_scan_udp_port_closed() {
+    // Bind and drop a socket to get a local UDP port that is very likely closed
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let result = scan_udp_port(ip, port, Arc::new(vec![]));
+    assert!(result.is_none(), "Closed UDP port should not be reported");
+}
+
+#[test]
+fn test_scan_work_parallel_mixed_protocols() {
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = tcp.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in tcp.incoming() {
+            drop(stream);
+        }
+    });
+    let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let udp_port = udp.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((_, peer)) = udp.recv_from(&mut buf) {
+            let _ = udp.send_to(b"pong", peer);
+        }
+    });
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let work = vec![(Protocol::Udp, udp_port), (Protocol::Tcp, port)];
+    for engine in [Engine::Threads, Engine::Async] {
+        let pb = ProgressBar::hidden();
+        let options = ScanOptions {
+            max_threads: 2,
+            engine,
+            ..ScanOptions::default()
+        };
+        let (result, _) = scan_work_parallel(ip.clone(), work.clone(), Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+        let found: Vec<(u16, Protocol)> = result.iter().map(|r| (r.port, r.protocol)).collect();
+        let mut expected = vec![(port, Protocol::Tcp), (udp_port, Protocol::Udp)];
+        expected.sort();
+        assert_eq!(found, expected, "{:?}", engine);
+        assert_eq!(pb.position(), 2);
+    }
+}
+
+#[test]
+fn test_retry_budget_cap() {
+    let budget = RetryBudget::new(Some(2));
+    assert!(budget.try_take());
+    assert!(budget.try_take());
+    assert!(!budget.exhausted());
+    assert!(!budget.try_take());
+    assert!(budget.exhausted());
+    assert_eq!(budget.used(), 2);
+}
+
+#[test]
+fn test_retry_budget_unlimited() {
+    let budget = RetryBudget::new(None);
+    for _ in 0..1000 {
+        assert!(budget.try_take());
+    }
+    assert!(!budget.exhausted());
+}
+
+/// A local port whose connects time out: its listener has a full accept queue, so the kernel
+/// drops further SYNs. Keep the returned listener and connection alive while using the port.
+fn unresponsive_port() -> (std::net::TcpListener, std::net::TcpStream, u16) {
+    let socket = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+    socket.bind(&"127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap().into()).unwrap();
+    socket.listen(0).unwrap();
+    let listener: std::net::TcpListener = socket.into();
+    let addr = listener.local_addr().unwrap();
+    let queued = std::net::TcpStream::connect(addr).unwrap();
+    (listener, queued, addr.port())
+}
+
+#[test]
+fn test_scan_work_parallel_max_retries_total() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let unresponsive: Vec<_> = (0..5).map(|_| unresponsive_port()).collect();
+    let work: Vec<_> = unresponsive.iter().map(|(_, _, port)| (Protocol::Tcp, *port)).collect();
+    for engine in [Engine::Threads, Engine::Async] {
+        let options = ScanOptions {
+            max_threads: 4,
+            retries: 3,
+            retry_backoff: Duration::from_millis(0),
+            max_retries_total: Some(5),
+            engine,
+            ..ScanOptions::default()
+        };
+        let (result, stats) =
+            scan_work_parallel(ip.clone(), work.clone(), Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+        assert!(result.is_empty());
+        // 5 timing out ports with 3 retries each would need 15 retries; the cap stops at 5
+        assert_eq!(stats.retries_used, 5, "{:?}", engine);
+        assert_eq!(stats.retry_cap, Some(5));
+        assert!(stats.retry_cap_reached);
+    }
+}
+
+#[test]
+fn test_scan_work_parallel_target_timeout() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let unresponsive: Vec<_> = (0..5).map(|_| unresponsive_port()).collect();
+    let work: Vec<_> = unresponsive.iter().map(|(_, _, port)| (Protocol::Tcp, *port)).collect();
+    for engine in [Engine::Threads, Engine::Async] {
+        let options = ScanOptions {
+            max_threads: 1,
+            connect_timeout: Duration::from_millis(300),
+            target_timeout: Some(Duration::from_millis(100)),
+            engine,
+            ..ScanOptions::default()
+        };
+        let started = std::time::Instant::now();
+        let (result, stats) =
+            scan_work_parallel(ip.clone(), work.clone(), Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+        assert!(result.is_empty());
+        assert!(stats.timed_out);
+        // The first port runs into its connect timeout; at most one more may have been started
+        assert!(stats.ports_timed_out >= 3, "{:?}: {}", engine, stats.ports_timed_out);
+        assert!(started.elapsed() < Duration::from_millis(1000), "{:?}: {:?}", engine, started.elapsed());
+    }
+}
+
+#[test]
+fn test_scan_work_parallel_retries_without_cap() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let options = ScanOptions {
+        max_threads: 2,
+        retries: 2,
+        retry_backoff: Duration::from_millis(0),
+        max_retries_total: None,
+        ..ScanOptions::default()
+    };
+    let (_a, _a_queued, a) = unresponsive_port();
+    let (_b, _b_queued, b) = unresponsive_port();
+    let work = vec![(Protocol::Tcp, a), (Protocol::Tcp, b)];
+    let (_, stats) = scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert_eq!(stats.retries_used, 4);
+    assert!(!stats.retry_cap_reached);
+}
+
+#[test]
+fn test_refused_connect_not_retried() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let options = ScanOptions {
+        max_threads: 2,
+        retries: 3,
+        retry_backoff: Duration::from_millis(0),
+        ..ScanOptions::default()
+    };
+    let work = vec![(Protocol::Tcp, 65517), (Protocol::Tcp, 65516)];
+    let (result, stats) = scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert!(result.is_empty());
+    assert_eq!(stats.retries_used, 0);
+    assert_eq!(stats.failures.refused, 2);
+}
+
+#[test]
+fn test_is_transient_error() {
+    use std::io::{Error, ErrorKind};
+    assert!(is_transient_error(&Error::from(ErrorKind::TimedOut)));
+    assert!(is_transient_error(&Error::from(ErrorKind::WouldBlock)));
+    assert!(is_transient_error(&Error::from(ErrorKind::AddrNotAvailable)));
+    assert!(!is_transient_error(&Error::from(ErrorKind::ConnectionRefused)));
+    assert!(!is_transient_error(&Error::from(ErrorKind::ConnectionReset)));
+    assert!(!is_transient_error(&Error::from(ErrorKind::HostUnreachable)));
+    assert!(!is_transient_error(&Error::from(ErrorKind::PermissionDenied)));
+}
+
+#[test]
+fn test_scan_port_identifies_by_http_status() {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(
+                b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"router\"\r\nContent-Length: 0\r\n\r\n",
+            );
+        }
+    });
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![Signature {
+        name: "IoT Router".to_string(),
+        match_: "Basic realm=\"router\"".to_string(),
+        status: Some((401, 401)),
+        header: Some("WWW-Authenticate".to_string()),
+        min_bytes: None,
+        ports: None,
+        source: None,
+        pattern: None,
+    }]);
+    let result = scan_port(ip, port, signatures).unwrap();
+    assert_eq!(result.service, Some("IoT Router".to_string()));
+}
+
+#[test]
+fn test_scan_port_captures_version() {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nServer: nginx/1.25.3\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![Signature {
+        name: "nginx".to_string(),
+        match_: "nginx/(?P<version>[0-9.]+)".to_string(),
+        header: Some("Server".to_string()),
+        pattern: Some(regex::Regex::new("nginx/(?P<version>[0-9.]+)").unwrap()),
+        ..Default::default()
+    }]);
+    let result = scan_port(ip, port, signatures).unwrap();
+    assert_eq!(result.service.as_deref(), Some("nginx"));
+    assert_eq!(result.version.as_deref(), Some("1.25.3"));
+    assert_eq!(result.label(), "nginx 1.25.3");
+    assert_eq!(serde_json::to_value(&result).unwrap()["version"], "1.25.3");
+}
+
+#[test]
+fn test_scan_port_http_probe_ipv6() {
+    use std::io::{Read, Write};
+    let Ok(listener) = std::net::TcpListener::bind("[::1]:0") else {
+        return; // No IPv6 loopback on this machine
+    };
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nServer: v6-server\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+
+    let ip = Arc::new("::1".parse::<IpAddr>().unwrap());
+    let signatures = Arc::new(vec![Signature {
+        name: "V6 Server".to_string(),
+        match_: "v6-server".to_string(),
+        status: None,
+        header: Some("Server".to_string()),
+        min_bytes: None,
+        ports: None,
+        source: None,
+        pattern: None,
+    }]);
+    let result = scan_port(ip, port, signatures).unwrap();
+    assert_eq!(result.service, Some("V6 Server".to_string()));
+    // The probe URL wraps the address in brackets, as the Host header shows
+    let request = rx.try_iter().find(|request| request.starts_with("GET ")).unwrap();
+    assert!(request.to_ascii_lowercase().contains(&format!("host: [::1]:{}", port)), "{}", request);
+}
+
+/// Find a free local port range of `len` ports by probing.
+fn free_port_range(len: u16) -> u16 {
+    (30000..60000)
+        .step_by(len as usize)
+        .find(|start| {
+            (*start..*start + len).all(|port| std::net::TcpListener::bind(("0.0.0.0", port)).is_ok())
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_source_port_range_binds_and_rotates() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let start = free_port_range(4);
+    let range = SourcePortRange::new(start, start + 3);
+
+    let mut sources = Vec::new();
+    for _ in 0..4 {
+        let stream = range.connect(addr, Duration::from_millis(500)).unwrap();
+        sources.push(stream.local_addr().unwrap().port());
+        let _ = listener.accept().unwrap();
+    }
+    sources.sort();
+    assert_eq!(sources, (start..=start + 3).collect::<Vec<_>>());
+    assert_eq!(range.exhausted(), 0);
+}
+
+#[test]
+fn test_source_port_range_exhausted() {
+    let target = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let blocker = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+    let busy = blocker.local_addr().unwrap().port();
+    let range = SourcePortRange::new(busy, busy);
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let options = ScanOptions {
+        max_threads: 1,
+        source_ports: Some(range),
+        ..ScanOptions::default()
+    };
+    let work = vec![(Protocol::Tcp, target.local_addr().unwrap().port())];
+    let (result, stats) =
+        scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert!(result.is_empty());
+    assert_eq!(stats.source_ports_exhausted, 1);
+}
+
+/// Feed `tuner` four samples 250ms apart at `rate` ports per second, starting at `start`.
+fn feed(tuner: &mut AutoTuner, start: std::time::Instant, done: &mut u64, rate: u64) -> usize {
+    let mut threads = tuner.threads();
+    for i in 0..4 {
+        *done += rate / 4;
+        threads = tuner.observe(start + Duration::from_millis(250 * (i + 1)), *done, 0, true);
+    }
+    threads
+}
+
+#[test]
+fn test_auto_tuner_grows_until_plateau() {
+    let mut tuner = AutoTuner::new(1000);
+    assert_eq!(tuner.threads(), 16);
+    let start = std::time::Instant::now();
+    let mut done = 0;
+    assert_eq!(feed(&mut tuner, start, &mut done, 400), 32);
+    assert_eq!(feed(&mut tuner, start + Duration::from_secs(1), &mut done, 800), 64);
+    // Less than 10% better: fall back to the best thread count and stop tuning
+    assert_eq!(feed(&mut tuner, start + Duration::from_secs(2), &mut done, 820), 32);
+    assert!(tuner.settled());
+    assert_eq!(feed(&mut tuner, start + Duration::from_secs(3), &mut done, 5000), 32);
+}
+
+#[test]
+fn test_auto_tuner_respects_ceiling_and_errors() {
+    let start = std::time::Instant::now();
+    let mut done = 0;
+    let mut tuner = AutoTuner::new(20);
+    assert_eq!(feed(&mut tuner, start, &mut done, 400), 20);
+    assert_eq!(feed(&mut tuner, start + Duration::from_secs(1), &mut done, 800), 20);
+    assert!(tuner.settled());
+
+    let mut tuner = AutoTuner::new(1000);
+    assert_eq!(tuner.observe(start, 10, 3, true), 8);
+    assert!(tuner.settled());
+    assert_eq!(tuner.observe(start, 20, 5, true), 4);
+}
+
+#[test]
+fn test_is_resource_error() {
+    use std::io::{Error, ErrorKind};
+    assert!(is_resource_error(&Error::from(ErrorKind::AddrNotAvailable)));
+    assert!(!is_resource_error(&Error::from(ErrorKind::ConnectionRefused)));
+    assert!(!is_resource_error(&Error::from(ErrorKind::TimedOut)));
+}
+
+#[test]
+fn test_scan_work_parallel_auto_tune() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let options = ScanOptions {
+        max_threads: 64,
+        auto_tune: true,
+        ..ScanOptions::default()
+    };
+    let work = vec![(Protocol::Tcp, port), (Protocol::Tcp, 65517)];
+    let (result, stats) =
+        scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert_eq!(result.len(), 1);
+    assert!((1..=64).contains(&stats.threads));
+}
+
+/// Start a keep-alive HTTP server answering every request with "Test Server", for `requests` requests.
+fn keep_alive_server(requests: usize) -> u16 {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests().take(requests) {
+            let _ = request.respond(tiny_http::Response::from_string("Test Server"));
+        }
+    });
+    port
+}
+
+#[test]
+fn test_http_probe_reuses_pooled_connection() {
+    let port = keep_alive_server(2);
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature {
+        name: "Test".into(),
+        match_: "Test Server".into(),
+        ..Default::default()
+    }]);
+    let state = ScanState::default();
+    for _ in 0..2 {
+        let res = scan_port_with_options(ip.clone(), port, sigs.clone(), &ScanOptions::default(), &state);
+        assert_eq!(res.unwrap().service.as_deref(), Some("Test"));
+    }
+    assert_eq!(state.http_probes(), 2);
+    assert_eq!(state.http_reused(), 1);
+    assert!(state.avg_http_probe_ms().is_some());
+    assert!(state.avg_connect_ms().is_some());
+}
+
+#[test]
+fn test_http_probe_paths_tried_in_order() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let body = match request.url() {
+                "/server-status" => "Apache Server Status",
+                _ => "It works!",
+            };
+            let _ = request.respond(tiny_http::Response::from_string(body));
+        }
+    });
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature {
+        name: "Apache".into(),
+        match_: "Apache Server Status".into(),
+        ..Default::default()
+    }]);
+    let scan = |paths: &[&str]| {
+        let options = ScanOptions {
+            probe_paths: paths.iter().map(|path| path.to_string()).collect(),
+            ..ScanOptions::default()
+        };
+        scan_port_with_options(ip.clone(), port, sigs.clone(), &options, &ScanState::default()).unwrap().service
+    };
+    assert_eq!(scan(&["/"]), None);
+    assert_eq!(scan(&["/", "/server-status"]).as_deref(), Some("Apache"));
+}
+
+#[test]
+fn test_scan_work_parallel_http_stats() {
+    let port = keep_alive_server(1);
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let (_, stats) = scan_work_parallel(
+        ip,
+        vec![(Protocol::Tcp, port), (Protocol::Tcp, 65514)],
+        Arc::new(vec![]),
+        &ScanOptions::default(),
+        &pb,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(stats.http_probes, 1);
+    assert_eq!(stats.http_reused + stats.http_fresh, 1);
+    assert!(stats.avg_connect_ms.is_some() && stats.avg_http_probe_ms.is_some());
+}
+
+/// Start a server that sends its banner in two parts, the second one after a delay.
+fn slow_banner_server(first: &'static str, second: &'static str) -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                use std::io::Write;
+                let _ = stream.write_all(first.as_bytes());
+                std::thread::sleep(Duration::from_millis(200));
+                let _ = stream.write_all(second.as_bytes());
+                std::thread::sleep(Duration::from_millis(200));
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn test_scan_work_parallel_counts_bytes_received() {
+    let first = slow_banner_server("220 mail ESMTP\r\n", "");
+    let second = slow_banner_server("SSH-2.0-", "OpenSSH\r\n");
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature { name: "SSH".into(), match_: "SSH-2.0-OpenSSH".into(), min_bytes: Some(17), ..Default::default() }]);
+    let options = ScanOptions { probe: Probe::Tcp(Vec::new()), ..ScanOptions::default() };
+    let work = vec![(Protocol::Tcp, first), (Protocol::Tcp, second), (Protocol::Tcp, 65515)];
+    let (result, stats) = scan_work_parallel(ip, work, sigs, &options, &ProgressBar::hidden(), |_| {}).unwrap();
+    assert_eq!(result.len(), 2);
+    // 16 bytes of the mail banner and 17 of the SSH banner; the closed port adds nothing
+    assert_eq!(stats.bytes_received, 33);
+}
+
+#[test]
+fn test_scan_port_waits_for_min_bytes() {
+    let port = slow_banner_server("SSH-2.0-", "OpenSSH_9.6 Ubuntu\r\n");
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = vec![
+        Signature {
+            name: "Generic SSH".into(),
+            match_: "SSH-2.0".into(),
+            min_bytes: Some(20),
+            ..Default::default()
+        },
+        Signature {
+            name: "Partial".into(),
+            match_: "SSH".into(),
+            min_bytes: Some(100),
+            ..Default::default()
+        },
+    ];
+    let res = scan_port(ip, port, Arc::new(sigs)).unwrap();
+    assert_eq!(res.service.as_deref(), Some("Generic SSH"));
+}
+
+#[test]
+fn test_scan_port_matches_first_read_by_default() {
+    let port = slow_banner_server("220 mail ESMTP\r\n", "");
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = vec![Signature {
+        name: "SMTP".into(),
+        match_: "ESMTP".into(),
+        ..Default::default()
+    }];
+    let res = scan_port(ip, port, Arc::new(sigs)).unwrap();
+    assert_eq!(res.service.as_deref(), Some("SMTP"));
+}
+
+#[test]
+fn test_scan_work_parallel_first_open_only() {
+    let listeners: Vec<_> = (0..3).map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let work = listeners.iter().map(|l| (Protocol::Tcp, l.local_addr().unwrap().port())).collect::<Vec<_>>();
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    for engine in [Engine::Threads, Engine::Async] {
+        let options = ScanOptions {
+            max_threads: 1,
+            probe: Probe::Tcp(Vec::new()),
+            first_open_only: true,
+            engine,
+            ..ScanOptions::default()
+        };
+        let pb = ProgressBar::hidden();
+        let (result, _) =
+            scan_work_parallel(ip.clone(), work.clone(), Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+        assert_eq!(result.len(), 1, "{:?}", engine);
+        assert_eq!(result[0].port, work[0].1);
+        assert_eq!(pb.position(), 3);
+    }
+}
+
+#[test]
+fn test_scan_hosts_parallel() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = listener.local_addr().unwrap().port();
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    // The listener only accepts on 127.0.0.1, so the same port is closed on 127.0.0.2
+    let hosts: Vec<IpAddr> = vec!["127.0.0.1".parse().unwrap(), "127.0.0.2".parse().unwrap()];
+    let work = [(Protocol::Tcp, open), (Protocol::Tcp, closed)];
+    let options = ScanOptions { probe: Probe::Tcp(Vec::new()), max_threads: 2, ..ScanOptions::default() };
+    let pb = ProgressBar::hidden();
+    pb.set_length((hosts.len() * work.len()) as u64);
+    let (mut found, mut done) = (Vec::new(), Vec::new());
+    let results = scan_hosts_parallel(
+        &hosts,
+        &work,
+        Arc::new(vec![]),
+        &options,
+        &pb,
+        |host, res| found.push((*host, res.port)),
+        |host, open, stats| done.push((*host, open.len(), stats.closed)),
+    )
+    .unwrap();
+    assert_eq!(results.len(), 2);
+    let ports = |host: &IpAddr| results[host].iter().map(|r| r.port).collect::<Vec<_>>();
+    assert_eq!(ports(&hosts[0]), vec![open]);
+    assert_eq!(ports(&hosts[1]), Vec::<u16>::new());
+    assert_eq!(pb.position(), 4);
+    // Every host is reported once it is done, in order, with its own statistics
+    assert_eq!(found, vec![(hosts[0], open)]);
+    assert_eq!(done, vec![(hosts[0], 1, 1), (hosts[1], 0, 2)]);
+}
+
+#[test]
+fn test_find_first_open() {
+    let listeners: Vec<_> = (0..2).map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut work = vec![(Protocol::Tcp, closed)];
+    work.extend(listeners.iter().map(|l| (Protocol::Tcp, l.local_addr().unwrap().port())));
+    let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+    // The listeners are bound to 127.0.0.1 only, so nothing answers on 127.0.0.2
+    let other: IpAddr = "127.0.0.2".parse().unwrap();
+    let options = ScanOptions {
+        max_threads: 1,
+        probe: Probe::Tcp(Vec::new()),
+        ..ScanOptions::default()
+    };
+    let pb = ProgressBar::hidden();
+    let found = find_first_open(vec![other, loopback], &work, Arc::new(vec![]), &options, &pb).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, loopback);
+    assert_eq!(found[0].1.port, work[1].1);
+    assert_eq!(pb.position(), 6);
+}
+
+#[test]
+fn test_discover_hosts() {
+    // Nothing listens on the port, but the refused connect still shows the host is up
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+    let other: IpAddr = "127.0.0.2".parse().unwrap();
+    let pb = ProgressBar::hidden();
+    let live = discover_hosts(vec![other, loopback], &[closed], &ScanOptions::default(), &pb);
+    assert_eq!(live, vec![other, loopback]);
+    assert_eq!(pb.position(), 2);
+
+    // Without discovery ports no host can answer
+    assert!(discover_hosts(vec![loopback], &[], &ScanOptions::default(), &pb).is_empty());
+}
+
+#[test]
+fn test_classify_connect() {
+    use std::io::{Error, ErrorKind};
+    assert_eq!(classify_connect(&Ok(())), ConnectOutcome::Open);
+    assert_eq!(classify_connect::<()>(&Err(Error::from(ErrorKind::ConnectionRefused))), ConnectOutcome::Closed);
+    assert_eq!(classify_connect::<()>(&Err(Error::from(ErrorKind::TimedOut))), ConnectOutcome::Filtered);
+    assert_eq!(classify_connect::<()>(&Err(Error::from(ErrorKind::AddrNotAvailable))), ConnectOutcome::LocalError);
+}
+
+#[test]
+fn test_classify_failure() {
+    use std::io::{Error, ErrorKind};
+    for (kind, failure, outcome) in [
+        (ErrorKind::ConnectionRefused, ConnectFailure::Refused, ConnectOutcome::Closed),
+        (ErrorKind::ConnectionReset, ConnectFailure::Refused, ConnectOutcome::Closed),
+        (ErrorKind::TimedOut, ConnectFailure::TimedOut, ConnectOutcome::Filtered),
+        (ErrorKind::HostUnreachable, ConnectFailure::HostUnreachable, ConnectOutcome::Filtered),
+        (ErrorKind::NetworkUnreachable, ConnectFailure::NetworkUnreachable, ConnectOutcome::Filtered),
+        (ErrorKind::PermissionDenied, ConnectFailure::Other, ConnectOutcome::Filtered),
+    ] {
+        let error = Error::from(kind);
+        assert_eq!(classify_failure(&error), failure, "{:?}", kind);
+        assert_eq!(failure.outcome(), outcome);
+        assert_eq!(classify_connect::<()>(&Err(error)), outcome, "{:?}", kind);
+    }
+}
+
+#[test]
+fn test_record_failure_counts_reasons() {
+    use std::io::{Error, ErrorKind};
+    let state = ScanState::default();
+    for kind in [ErrorKind::ConnectionRefused, ErrorKind::HostUnreachable, ErrorKind::HostUnreachable, ErrorKind::AddrNotAvailable] {
+        state.record_failure(&Error::from(kind));
+    }
+    assert_eq!((state.closed(), state.filtered()), (1, 2));
+    assert_eq!(state.failures(), ConnectFailures { refused: 1, host_unreachable: 2, ..ConnectFailures::default() });
+}
+
+#[test]
+fn test_network_errors_in_row() {
+    use std::io::{Error, ErrorKind};
+    let state = ScanState::default();
+    for kind in [ErrorKind::NetworkUnreachable, ErrorKind::HostUnreachable, ErrorKind::NetworkUnreachable] {
+        state.record_failure(&Error::from(kind));
+    }
+    assert_eq!(state.network_errors_in_row(), 3);
+    // Local resource errors say nothing about the network and keep the run going
+    state.record_failure(&Error::from(ErrorKind::AddrNotAvailable));
+    assert_eq!(state.network_errors_in_row(), 3);
+    // A closed or open port shows the network still works
+    state.record_failure(&Error::from(ErrorKind::ConnectionRefused));
+    assert_eq!(state.network_errors_in_row(), 0);
+    state.record_failure(&Error::from(ErrorKind::NetworkUnreachable));
+    state.record_connect(Duration::from_millis(1));
+    assert_eq!(state.network_errors_in_row(), 0);
+
+    // A scan of a healthy host is not aborted
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let work = vec![(Protocol::Tcp, listener.local_addr().unwrap().port())];
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let options = ScanOptions { probe: Probe::Tcp(b"x".to_vec()), network_failure_threshold: 1, ..ScanOptions::default() };
+    let (_, stats) = scan_work_parallel(ip, work, Arc::new(vec![]), &options, &ProgressBar::hidden(), |_| {}).unwrap();
+    assert!(!stats.network_failure);
+}
+
+#[test]
+fn test_latency_histogram() {
+    let state = ScanState::default();
+    state.record_attempt(Duration::from_micros(300), false);
+    state.record_attempt(Duration::from_micros(800), true);
+    state.record_attempt(Duration::from_millis(150), false);
+    state.record_attempt(Duration::from_secs(5), false);
+    let histogram = state.latency_histogram();
+    assert_eq!(histogram.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+    let counts: Vec<(Option<u64>, u64, u64)> =
+        histogram.buckets.iter().filter(|b| b.open + b.failed > 0).map(|b| (b.upper_ms, b.open, b.failed)).collect();
+    assert_eq!(counts, vec![(Some(1), 1, 1), (Some(200), 0, 1), (None, 0, 1)]);
+    let doubled = histogram.merge(&histogram);
+    assert_eq!(doubled.buckets[0].failed, 2);
+
+    // Every attempt is recorded, but only if the histogram was asked for
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let work = vec![(Protocol::Tcp, closed), (Protocol::Tcp, listener.local_addr().unwrap().port())];
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let options = ScanOptions { probe: Probe::Tcp(b"x".to_vec()), latency_histogram: true, ..ScanOptions::default() };
+    let pb = ProgressBar::hidden();
+    let (_, stats) = scan_work_parallel(Arc::clone(&ip), work.clone(), Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    let histogram = stats.latency_histogram.unwrap();
+    let total = |f: fn(&port_explorer::scanner::LatencyBucket) -> u64| histogram.buckets.iter().map(f).sum::<u64>();
+    assert_eq!((total(|b| b.open), total(|b| b.failed)), (1, 1));
+    let options = ScanOptions { latency_histogram: false, ..options };
+    let (_, stats) = scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert_eq!(stats.latency_histogram, None);
+}
+
+/// Start a server that answers "PONG service" only after it received "PING\r\n".
+fn ping_server() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                use std::io::{Read, Write};
+                let mut buf = [0u8; 16];
+                if let Ok(n) = stream.read(&mut buf) {
+                    if &buf[..n] == b"PING\r\n" {
+                        let _ = stream.write_all(b"PONG service");
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn test_scan_work_parallel_tcp_probe_and_port_states() {
+    let ping = ping_server();
+    let banner = slow_banner_server("220 mail ESMTP\r\n", "");
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let sigs = Arc::new(vec![
+        Signature { name: "Pong".into(), match_: "PONG".into(), ..Default::default() },
+        Signature { name: "SMTP".into(), match_: "ESMTP".into(), ..Default::default() },
+    ]);
+    let work = vec![(Protocol::Tcp, ping), (Protocol::Tcp, banner), (Protocol::Tcp, closed)];
+
+    for engine in [Engine::Threads, Engine::Async] {
+        let options = ScanOptions { probe: Probe::Tcp(b"PING\r\n".to_vec()), engine, ..ScanOptions::default() };
+        let (result, stats) =
+            scan_work_parallel(ip.clone(), work.clone(), sigs.clone(), &options, &pb, |_| {}).unwrap();
+        let service = |port: u16| result.iter().find(|r| r.port == port).and_then(|r| r.service.clone());
+        assert_eq!(service(ping).as_deref(), Some("Pong"), "{:?}", engine);
+        assert_eq!(result.len(), 2);
+        assert_eq!((stats.closed, stats.filtered, stats.http_probes), (1, 0, 0));
+    }
+
+    // Without a payload only services that talk first are identified
+    let options = ScanOptions { probe: Probe::Tcp(Vec::new()), ..ScanOptions::default() };
+    let (result, _) = scan_work_parallel(ip, work, sigs, &options, &pb, |_| {}).unwrap();
+    let service = |port: u16| result.iter().find(|r| r.port == port).and_then(|r| r.service.clone());
+    assert_eq!(service(ping), None);
+    assert_eq!(service(banner).as_deref(), Some("SMTP"));
+}
+
+/// Start a server that sends 1000 filler bytes, then `MARKER`, then streams filler until the client leaves.
+fn endless_banner_server() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                use std::io::Write;
+                let mut banner = vec![b'x'; 1000];
+                banner.extend_from_slice(b"MARKER");
+                let _ = stream.write_all(&banner);
+                while stream.write_all(&[b'x'; 1024]).is_ok() {}
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn test_scan_port_banner_max_bytes() {
+    let port = endless_banner_server();
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature {
+        name: "Marker".into(),
+        match_: "MARKER".into(),
+        min_bytes: Some(60000),
+        ..Default::default()
+    }]);
+    let scan = |banner_max_bytes: usize| {
+        let options = ScanOptions { probe: Probe::Tcp(Vec::new()), banner_max_bytes, ..ScanOptions::default() };
+        let started = std::time::Instant::now();
+        let result = scan_port_with_options(ip.clone(), port, sigs.clone(), &options, &ScanState::default()).unwrap();
+        (result.service, started.elapsed())
+    };
+
+    // The marker lies past the cap, and the read ends at the cap instead of the deadline
+    let (service, elapsed) = scan(500);
+    assert_eq!(service, None);
+    assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+
+    let (service, elapsed) = scan(ScanOptions::default().banner_max_bytes);
+    assert_eq!(service.as_deref(), Some("Marker"));
+    assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+}
+
+#[test]
+fn test_http_probe_skipped_ports_use_banner() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    // An SSH-like service that greets every connection; the count tells whether a probe was sent
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            counter.fetch_add(1, Ordering::SeqCst);
+            std::thread::spawn(move || {
+                use std::io::{Read, Write};
+                let _ = stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+                let _ = stream.read(&mut [0; 1024]);
+            });
+        }
+    });
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature { name: "SSH".into(), match_: "SSH-2.0".into(), ..Default::default() }]);
+    let scan = |http_probe_ports: HttpProbePorts| {
+        connections.store(0, Ordering::SeqCst);
+        let options = ScanOptions { http_probe_ports, ..ScanOptions::default() };
+        let result = scan_port_with_options(ip.clone(), port, sigs.clone(), &options, &ScanState::default()).unwrap();
+        assert_eq!(result.service.as_deref(), Some("SSH"));
+        assert_eq!(result.identified_by, IdentificationSource::Banner);
+        connections.load(Ordering::SeqCst)
+    };
+
+    // Skipped ports are identified on the connect itself, without an HTTP request and a reconnect
+    assert_eq!(scan(HttpProbePorts::Skip([port].into())), 1);
+    assert_eq!(scan(HttpProbePorts::Only([80, 443].into())), 1);
+    assert!(scan(HttpProbePorts::All) > 1);
+    assert!(HttpProbePorts::Only([80].into()).probes(80) && !HttpProbePorts::Skip([80].into()).probes(80));
+}
+
+#[test]
+fn test_check_http_client() {
+    // The scan only starts with a working probe client, so this must hold for every scan above
+    assert!(port_explorer::scanner::check_http_client().is_ok());
+}
+
+#[test]
+fn test_service_name_template() {
+    let banner = slow_banner_server("220 mail ESMTP\r\n", "");
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let sigs = Arc::new(vec![Signature { name: "Mail on {host}:{port}".into(), match_: "ESMTP".into(), ..Default::default() }]);
+    let options = ScanOptions { probe: Probe::Tcp(Vec::new()), ..ScanOptions::default() };
+    let result = scan_port_with_options(ip, banner, sigs, &options, &ScanState::default()).unwrap();
+    assert_eq!(result.service, Some(format!("Mail on 127.0.0.1:{}", banner)));
+    assert_eq!(result.identified_by, IdentificationSource::Banner);
+}
+
+#[test]
+fn test_identification_source_http() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let scan = |sig: Signature| {
+        let port = keep_alive_server(1);
+        scan_port_with_options(ip.clone(), port, Arc::new(vec![sig]), &ScanOptions::default(), &ScanState::default())
+            .unwrap()
+    };
+    let body = scan(Signature { name: "Body".into(), match_: "Test Server".into(), ..Default::default() });
+    assert_eq!((body.service.as_deref(), body.identified_by), (Some("Body"), IdentificationSource::HttpBody));
+    let header = scan(Signature {
+        name: "Header".into(),
+        match_: "tiny-http".into(),
+        header: Some("Server".into()),
+        ..Default::default()
+    });
+    assert_eq!((header.service.as_deref(), header.identified_by), (Some("Header"), IdentificationSource::HttpHeader));
+    let none = scan(Signature { name: "Other".into(), match_: "nginx".into(), ..Default::default() });
+    assert_eq!((none.service, none.identified_by), (None, IdentificationSource::None));
+    assert_eq!(serde_json::to_value(IdentificationSource::HttpHeader).unwrap(), "http-header");
+}
+
+#[test]
+fn test_ramp_threads() {
+    let ramp = Duration::from_secs(4);
+    assert_eq!(ramp_threads(100, Duration::ZERO, ramp), 1);
+    assert_eq!(ramp_threads(100, Duration::from_secs(1), ramp), 25);
+    assert_eq!(ramp_threads(100, Duration::from_secs(3), ramp), 75);
+    assert_eq!(ramp_threads(100, Duration::from_secs(5), ramp), 100);
+    assert_eq!(ramp_threads(100, Duration::ZERO, Duration::ZERO), 100);
+    assert_eq!(ramp_threads(3, Duration::from_millis(1), ramp), 1);
+}
+
+#[test]
+fn test_scan_work_parallel_ramp_up_starts_small() {
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let pb = ProgressBar::hidden();
+    let work: Vec<_> = (65300..65400).map(|port| (Protocol::Tcp, port)).collect();
+    let options = ScanOptions { max_threads: 50, ramp_up: Duration::from_secs(10), ..ScanOptions::default() };
+    let (_, stats) = scan_work_parallel(ip, work, Arc::new(vec![]), &options, &pb, |_| {}).unwrap();
+    assert!(stats.threads < 50, "{}", stats.threads);
+    assert_eq!(stats.closed + stats.filtered, 100);
+}
+
+fn merge_report(ip: &str, port_range: &str, open_ports: Vec<(u16, Protocol, Option<&str>)>) -> ScanReport {
+    ScanReport {
+        target: ip.into(),
+        ip: ip.parse().unwrap(),
+        port_range: port_range.into(),
+        duration_ms: 100,
+        identification_rate: None,
+        open_ports: open_ports
+            .into_iter()
+            .map(|(port, protocol, service)| ScanResult {
+                port,
+                service: service.map(str::to_string),
+                version: None,
+                protocol,
+                state: PortState::Open,
+                latency: None,
+                identified_by: IdentificationSource::None,
+                signature_file: None,
+                tls: None,
+            })
+            .collect(),
+        stats: ScanStats { closed: 10, http_probes: 1, avg_http_probe_ms: Some(2.0), ..ScanStats::default() },
+        conflicts: Vec::new(),
+    }
+}
+
+#[test]
+fn test_merge_reports_disjoint() {
+    let tcp = merge_report("10.0.0.1", "tcp 1-100", vec![(80, Protocol::Tcp, Some("HTTP")), (22, Protocol::Tcp, None)]);
+    let udp = merge_report("10.0.0.1", "udp 53", vec![(53, Protocol::Udp, Some("DNS"))]);
+    let merged = tcp.merge(&[udp]);
+    let ports: Vec<_> = merged.open_ports.iter().map(|r| (r.port, r.protocol)).collect();
+    assert_eq!(ports, vec![(22, Protocol::Tcp), (53, Protocol::Udp), (80, Protocol::Tcp)]);
+    assert_eq!(merged.port_range, "tcp 1-100, udp 53");
+    assert_eq!(merged.duration_ms, 200);
+    assert_eq!(merged.identification_rate, Some(2.0 / 3.0));
+    assert_eq!((merged.stats.closed, merged.stats.http_probes), (20, 2));
+    assert_eq!(merged.stats.avg_http_probe_ms, Some(2.0));
+    assert!(merged.conflicts.is_empty());
+}
+
+#[test]
+fn test_merge_reports_overlapping() {
+    let first = merge_report("10.0.0.1", "1-100", vec![(22, Protocol::Tcp, None), (80, Protocol::Tcp, Some("HTTP"))]);
+    let second = merge_report("10.0.0.1", "1-100", vec![(22, Protocol::Tcp, Some("SSH")), (80, Protocol::Tcp, Some("http"))]);
+    let third = merge_report("10.0.0.1", "1-100", vec![(22, Protocol::Tcp, Some("Dropbear")), (443, Protocol::Tcp, None)]);
+    let other_host = merge_report("10.0.0.2", "1-100", vec![(8080, Protocol::Tcp, None)]);
+    let merged = first.merge(&[second, third, other_host.clone()]);
+    let services: Vec<_> = merged.open_ports.iter().map(|r| (r.port, r.service.as_deref())).collect();
+    assert_eq!(services, vec![(22, Some("SSH")), (80, Some("HTTP")), (443, None)]);
+    assert_eq!(merged.port_range, "1-100");
+    assert_eq!(
+        merged.conflicts,
+        vec![ServiceConflict { port: 22, protocol: Protocol::Tcp, services: vec!["SSH".into(), "Dropbear".into()] }]
+    );
+
+    let all = merge_reports(&[first.clone(), other_host.clone(), first.clone()]);
+    assert_eq!(all.len(), 2);
+    assert_eq!((all[0].ip, all[0].open_ports.len(), all[0].duration_ms), (first.ip, 2, 200));
+    assert_eq!(all[1], other_host.merge(&[]));
+}
+
+#[test]
+fn test_service_cache() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let ip: IpAddr = "127.0.0.1".parse().unwrap();
+    let cache = ServiceCache::new(None);
+    let options = ScanOptions {
+        probe: Probe::Tcp(Vec::new()),
+        service_cache: Some(cache.clone()),
+        ..ScanOptions::default()
+    };
+    let scan = || {
+        let work = vec![(Protocol::Tcp, port)];
+        scan_work_parallel(Arc::new(ip), work, Arc::new(vec![]), &options, &ProgressBar::hidden(), |_| {}).unwrap()
+    };
+    let (results, stats) = scan();
+    assert_eq!(results.len(), 1);
+    assert_eq!(stats.cached_services, 0);
+    assert_eq!(cache.len(), 1);
+
+    // A port that stays open keeps its cached service without being probed
+    let mut cached = results[0].clone();
+    cached.service = Some("Cached".to_string());
+    cache.insert(ip, &cached);
+    let (results, stats) = scan();
+    assert_eq!(results[0].service.as_deref(), Some("Cached"));
+    assert!(results[0].latency.is_some());
+    assert_eq!(stats.cached_services, 1);
+
+    // A closed port drops its entry, so it is probed again once it reopens
+    drop(listener);
+    let (results, _) = scan();
+    assert!(results.is_empty());
+    assert!(cache.is_empty());
+
+    // Entries older than the refresh interval are probed again
+    let cache = ServiceCache::new(Some(Duration::from_millis(20)));
+    cache.insert(ip, &cached);
+    assert!(cache.get(ip, Protocol::Tcp, port).is_some());
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(cache.get(ip, Protocol::Tcp, port).is_none());
+}
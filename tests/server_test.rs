@@ -0,0 +1,110 @@
+use port_explorer::server::{handle_scan, ScanServer, ServerMetrics};
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[test]
+fn test_handle_scan_reports_open_port() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let body = format!(r#"{{"ip": "127.0.0.1", "ports": [{}], "options": {{"retries": 0}}}}"#, port);
+    let (status, report) = handle_scan(&HashMap::new(), &body, Arc::new(vec![]));
+    assert_eq!(status, 200, "{}", report);
+    assert_eq!(report["open_ports"][0]["port"], port);
+}
+
+#[test]
+fn test_handle_scan_rejects_public_target() {
+    let (status, body) = handle_scan(&HashMap::new(), r#"{"ip": "8.8.8.8", "ports": "53"}"#, Arc::new(vec![]));
+    assert_eq!(status, 403);
+    assert!(body["error"].is_string());
+}
+
+#[test]
+fn test_handle_scan_rejects_bad_requests() {
+    let sigs = Arc::new(vec![]);
+    let (status, _) = handle_scan(&HashMap::new(), "not json", sigs.clone());
+    assert_eq!(status, 400);
+    let (status, body) = handle_scan(
+        &HashMap::new(),
+        r#"{"ip": "127.0.0.1", "ports": "1", "options": {"max_threads": 100000}}"#,
+        sigs.clone(),
+    );
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("max_threads"));
+    let (status, _) = handle_scan(&HashMap::new(), r#"{"ip": "127.0.0.1", "ports": "0-5"}"#, sigs);
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn test_server_roundtrip() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut base = HashMap::new();
+    base.insert("max_threads".to_string(), YamlValue::Number(4.into()));
+    let server = ScanServer::bind("127.0.0.1:0".parse().unwrap(), base, Arc::new(vec![]), 2).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+
+    let client = reqwest::blocking::Client::new();
+    let health = client.get(format!("http://{}/health", addr)).send().unwrap();
+    assert_eq!(health.status().as_u16(), 200);
+
+    let resp = client
+        .post(format!("http://{}/scan", addr))
+        .body(format!(r#"{{"ip": "127.0.0.1", "ports": [{}]}}"#, port))
+        .send()
+        .unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    let report: serde_json::Value = resp.json().unwrap();
+    assert_eq!(report["open_ports"][0]["port"], port);
+    assert_eq!(report["stats"]["threads"], 4);
+
+    let missing = client.get(format!("http://{}/nope", addr)).send().unwrap();
+    assert_eq!(missing.status().as_u16(), 404);
+}
+
+#[test]
+fn test_server_metrics_format() {
+    let metrics = ServerMetrics::new();
+    metrics.record_error();
+    metrics.record_rejected();
+    let text = metrics.format_metrics(3);
+    assert!(text.contains("# TYPE port_explorer_server_scans_total counter\n"));
+    assert!(text.contains("port_explorer_server_active_scans 3\n"));
+    assert!(text.contains("port_explorer_server_scan_errors_total 1\n"));
+    assert!(text.contains("port_explorer_server_scans_rejected_total 1\n"));
+    assert!(text.contains("port_explorer_server_ports_scanned_total{state=\"open\"} 0\n"));
+    assert!(text.ends_with('\n'));
+}
+
+#[test]
+fn test_server_healthz_and_metrics() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = ScanServer::bind("127.0.0.1:0".parse().unwrap(), HashMap::new(), Arc::new(vec![]), 2).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+
+    let client = reqwest::blocking::Client::new();
+    let healthz = client.get(format!("http://{}/healthz", addr)).send().unwrap();
+    assert_eq!(healthz.status().as_u16(), 200);
+
+    let ok = client
+        .post(format!("http://{}/scan", addr))
+        .body(format!(r#"{{"ip": "127.0.0.1", "ports": [{}]}}"#, port))
+        .send()
+        .unwrap();
+    assert_eq!(ok.status().as_u16(), 200);
+    let denied = client.post(format!("http://{}/scan", addr)).body(r#"{"ip": "8.8.8.8", "ports": "53"}"#).send().unwrap();
+    assert_eq!(denied.status().as_u16(), 403);
+
+    let metrics = client.get(format!("http://{}/metrics", addr)).send().unwrap();
+    assert_eq!(metrics.status().as_u16(), 200);
+    assert!(metrics.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let text = metrics.text().unwrap();
+    assert!(text.contains("port_explorer_server_up 1\n"), "{}", text);
+    assert!(text.contains("port_explorer_server_scans_total 1\n"), "{}", text);
+    assert!(text.contains("port_explorer_server_scan_errors_total 1\n"), "{}", text);
+    assert!(text.contains("port_explorer_server_ports_scanned_total{state=\"open\"} 1\n"), "{}", text);
+}
@@ -0,0 +1,42 @@
+use port_explorer::scanner::Protocol;
+use port_explorer::services::*;
+
+#[test]
+fn test_parse_services() {
+    let text = "# comment\n\
+        http\t80/tcp\twww\t# WorldWideWeb HTTP\n\
+        domain\t53/tcp\n\
+        domain\t53/udp\n\
+        sctp-only\t9/sctp\n\
+        broken\tabc/tcp\n";
+    let services = parse_services(text);
+    assert_eq!(services["http"], vec![(Protocol::Tcp, 80)]);
+    assert_eq!(services["www"], vec![(Protocol::Tcp, 80)]);
+    assert_eq!(services["domain"], vec![(Protocol::Tcp, 53), (Protocol::Udp, 53)]);
+    assert!(!services.contains_key("sctp-only"));
+    assert!(!services.contains_key("broken"));
+}
+
+#[test]
+fn test_resolve_services_prefers_system_file() {
+    let system = "http\t8080/tcp\n";
+    let names = vec!["HTTP".to_string(), "ntp".to_string()];
+    let ports = resolve_services_from(&names, system).unwrap();
+    // http comes from the system file, ntp from the embedded table
+    assert_eq!(ports[&Protocol::Tcp], vec![8080]);
+    assert_eq!(ports[&Protocol::Udp], vec![123]);
+}
+
+#[test]
+fn test_resolve_services_unknown_names() {
+    let names = vec!["ssh".to_string(), "nope".to_string(), "also-nope".to_string()];
+    let err = resolve_services_from(&names, "").unwrap_err().to_string();
+    assert!(err.contains("nope, also-nope"), "{}", err);
+}
+
+#[test]
+fn test_embedded_services_table() {
+    let services = parse_services(EMBEDDED_SERVICES);
+    assert_eq!(services["https"], vec![(Protocol::Tcp, 443), (Protocol::Udp, 443)]);
+    assert_eq!(services["rdp"], vec![(Protocol::Tcp, 3389)]);
+}
@@ -7,9 +7,10 @@ fn test_identify_service_found() {
     let sigs = vec![Signature {
         name: "HTTP".into(),
         match_: "Server: Apache".into(),
+        ..Default::default()
     }];
     let resp = "Server: Apache\r\nContent-Type: text/html";
-    assert_eq!(identify_service(resp, &sigs), Some("HTTP".to_string()));
+    assert_eq!(identify_service(resp, 80, &sigs), Some("HTTP".to_string()));
 }
 
 #[test]
@@ -17,9 +18,23 @@ fn test_identify_service_not_found() {
     let sigs = vec![Signature {
         name: "HTTP".into(),
         match_: "Server: Apache".into(),
+        ..Default::default()
     }];
     let resp = "No match here";
-    assert_eq!(identify_service(resp, &sigs), None);
+    assert_eq!(identify_service(resp, 80, &sigs), None);
+}
+
+#[test]
+fn test_match_banner_keeps_source() {
+    let sigs = vec![Signature {
+        name: "nginx".into(),
+        match_: "nginx".into(),
+        source: Some("signatures/web/nginx.yaml".into()),
+        ..Default::default()
+    }];
+    let sig = match_banner("Server: nginx", 80, &sigs).unwrap().signature;
+    assert_eq!(sig.source.as_deref(), Some("signatures/web/nginx.yaml"));
+    assert!(match_banner("SSH-2.0", 22, &sigs).is_none());
 }
 
 #[test]
@@ -81,8 +96,8 @@ fn test_load_signatures_valid_and_invalid_files() {
     std::env::set_current_dir(original_dir).unwrap();
     
     println!("Result: {:?}", result);
-    if result.is_err() {
-        println!("Error: {:?}", result.as_ref().unwrap_err());
+    if let Err(e) = &result {
+        println!("Error: {:?}", e);
     }
     assert!(result.is_ok());
     let sigs = result.unwrap();
@@ -92,4 +107,497 @@ fn test_load_signatures_valid_and_invalid_files() {
     assert!(names.contains(&"FTP"));
     
     // tempfile automatically cleans up
-}
\ No newline at end of file
+}
+fn router_signature() -> Signature {
+    Signature {
+        name: "IoT Router".into(),
+        match_: "Basic realm=\"router\"".into(),
+        status: Some((401, 401)),
+        header: Some("WWW-Authenticate".into()),
+        min_bytes: None,
+        ports: None,
+        source: None,
+        pattern: None,
+    }
+}
+
+#[test]
+fn test_identify_http_response_status_and_header() {
+    let sigs = vec![router_signature()];
+    let resp = HttpResponse {
+        status: 401,
+        headers: vec![("www-authenticate".into(), "Basic realm=\"router\"".into())],
+        body: String::new(),
+    };
+    assert_eq!(identify_http_response(&resp, 80, &sigs), Some("IoT Router".to_string()));
+
+    // Wrong status code
+    let resp = HttpResponse { status: 200, ..resp };
+    assert_eq!(identify_http_response(&resp, 80, &sigs), None);
+}
+
+#[test]
+fn test_parse_raw_http_response() {
+    let raw = "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"router\"\r\nContent-Length: 4\r\n\r\nnope";
+    let resp = HttpResponse::parse(raw).unwrap();
+    assert_eq!(resp.status, 401);
+    assert_eq!(resp.body, "nope");
+    assert_eq!(identify_http_response(&resp, 80, &[router_signature()]), Some("IoT Router".to_string()));
+
+    assert!(HttpResponse::parse("SSH-2.0-OpenSSH_9.6\r\n").is_none());
+    assert!(HttpResponse::parse("HTTP/1.0 abc\r\n\r\n").is_none());
+}
+
+#[test]
+fn test_identify_http_response_status_range_and_body() {
+    let sigs = vec![Signature {
+        name: "Client error page".into(),
+        match_: "Not Found".into(),
+        status: Some((400, 499)),
+        header: None,
+        min_bytes: None,
+        ports: None,
+        source: None,
+        pattern: None,
+    }];
+    let resp = HttpResponse {
+        status: 404,
+        headers: vec![],
+        body: "<h1>Not Found</h1>".into(),
+    };
+    assert_eq!(identify_http_response(&resp, 80, &sigs), Some("Client error page".to_string()));
+}
+
+#[test]
+fn test_identify_service_skips_http_only_signatures() {
+    let sigs = vec![router_signature()];
+    assert_eq!(identify_service("Basic realm=\"router\"", 80, &sigs), None);
+}
+
+#[test]
+fn test_load_signatures_status_and_header() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let signatures_dir = temp_dir.path().join("signatures");
+    fs::create_dir_all(&signatures_dir).unwrap();
+    let yaml = r#"signatures:
+  - name: IoT Router
+    status: 401
+    header: WWW-Authenticate
+    match: 'Basic realm="router"'
+  - name: Any Server Error
+    status: "500-599"
+  - name: Broken Status
+    status: "abc"
+    match: whatever"#;
+    fs::write(signatures_dir.join("status.yaml"), yaml).unwrap();
+
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let result = load_signatures();
+    std::env::set_current_dir(original_dir).unwrap();
+
+    let sigs = result.unwrap();
+    assert_eq!(sigs.len(), 2);
+    let router = sigs.iter().find(|s| s.name == "IoT Router").unwrap();
+    assert_eq!(router.status, Some((401, 401)));
+    assert_eq!(router.header.as_deref(), Some("WWW-Authenticate"));
+    let errors = sigs.iter().find(|s| s.name == "Any Server Error").unwrap();
+    assert_eq!(errors.status, Some((500, 599)));
+    assert_eq!(errors.match_, "");
+}
+
+#[test]
+fn test_validate_signature() {
+    assert!(validate_signature(&router_signature()).is_ok());
+    let no_name = Signature { name: " ".into(), ..router_signature() };
+    assert!(validate_signature(&no_name).is_err());
+    let no_match = Signature { match_: String::new(), status: None, ..router_signature() };
+    assert!(validate_signature(&no_match).is_err());
+    let bad_status = Signature { status: Some((500, 400)), ..router_signature() };
+    assert!(validate_signature(&bad_status).is_err());
+    let bad_header = Signature { header: Some("WWW Authenticate".into()), ..router_signature() };
+    assert!(validate_signature(&bad_header).is_err());
+}
+
+#[test]
+fn test_load_validated_signatures_strict_and_lenient() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"signatures:
+  - name: SSH
+    match: SSH
+  - name: Broken Status
+    status: "abc"
+  - match: no name
+  - name: Bad Header
+    header: "not a header"
+    match: x"#;
+    fs::write(temp_dir.path().join("mixed.yaml"), yaml).unwrap();
+    fs::write(temp_dir.path().join("broken.yaml"), "not: [valid, yaml").unwrap();
+
+    let err = load_validated_signatures_from(temp_dir.path(), false).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("(4)"), "{}", msg);
+    assert!(msg.contains("Broken Status") && msg.contains("Bad Header") && msg.contains("broken.yaml"));
+
+    let sigs = load_validated_signatures_from(temp_dir.path(), true).unwrap();
+    let names: Vec<_> = sigs.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["SSH"]);
+}
+
+#[test]
+fn test_check_signatures_loaded() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(temp_dir.path().join("broken.yaml"), "not: [valid, yaml").unwrap();
+
+    // The directory exists, but leniently skipping its only file leaves nothing
+    let sigs = load_validated_signatures_from(temp_dir.path(), true).unwrap();
+    assert!(sigs.is_empty());
+    assert!(check_signatures_loaded(&sigs, temp_dir.path(), false).is_ok());
+    let err = check_signatures_loaded(&sigs, temp_dir.path(), true).unwrap_err();
+    assert!(matches!(err, ScanError::Config(_)));
+    assert!(err.to_string().contains(&temp_dir.path().display().to_string()), "{}", err);
+
+    let ssh = Signature { name: "SSH".into(), match_: "SSH-".into(), ..Default::default() };
+    assert!(check_signatures_loaded(&[ssh], temp_dir.path(), true).is_ok());
+}
+
+#[test]
+fn test_repository_signatures_are_valid() {
+    let sigs = load_validated_signatures_from(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("signatures"), false).unwrap();
+    assert!(!sigs.is_empty());
+}
+
+#[test]
+fn test_identify_service_min_bytes() {
+    let sigs = vec![Signature {
+        name: "Slow Service".into(),
+        match_: "HELLO".into(),
+        min_bytes: Some(10),
+        ..Default::default()
+    }];
+    assert_eq!(identify_service("HELLO", 80, &sigs), None);
+    assert_eq!(identify_service("HELLO there", 80, &sigs), Some("Slow Service".to_string()));
+    assert_eq!(banner_bytes_wanted(&sigs), 10);
+    assert_eq!(banner_bytes_wanted(&[]), 1);
+}
+
+#[test]
+fn test_load_signatures_min_bytes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"signatures:
+  - name: Slow Service
+    match: HELLO
+    min_bytes: 64
+  - name: Bad Min Bytes
+    match: x
+    min_bytes: lots
+  - name: Zero Min Bytes
+    match: x
+    min_bytes: 0"#;
+    fs::write(temp_dir.path().join("slow.yaml"), yaml).unwrap();
+
+    let err = load_validated_signatures_from(temp_dir.path(), false).unwrap_err().to_string();
+    assert!(err.contains("(2)") && err.contains("Bad Min Bytes") && err.contains("Zero Min Bytes"), "{}", err);
+
+    let sigs = load_validated_signatures_from(temp_dir.path(), true).unwrap();
+    assert_eq!(sigs.len(), 1);
+    assert_eq!(sigs[0].min_bytes, Some(64));
+}
+
+#[test]
+fn test_load_signatures_regex() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"signatures:
+  - name: OpenSSH
+    match: '^SSH-2\.0-OpenSSH_\d+\.\d+'
+    regex: true
+  - name: Literal
+    match: 'a.c'
+  - name: Bad Regex
+    match: '(unclosed'
+    regex: true
+  - name: Bad Flag
+    match: x
+    regex: maybe"#;
+    fs::write(temp_dir.path().join("regex.yaml"), yaml).unwrap();
+
+    // An invalid regular expression only skips its signature; a bad flag is still an error
+    let err = load_validated_signatures_from(temp_dir.path(), false).unwrap_err().to_string();
+    assert!(err.contains("(1)") && !err.contains("Bad Regex") && err.contains("Bad Flag"), "{}", err);
+
+    let sigs = load_validated_signatures_from(temp_dir.path(), true).unwrap();
+    assert_eq!(sigs.len(), 2);
+    assert_eq!(identify_service("SSH-2.0-OpenSSH_9.6p1", 22, &sigs), Some("OpenSSH".to_string()));
+    assert_eq!(identify_service("SSH-2.0-dropbear", 22, &sigs), None);
+    // Without `regex: true` the match string is a plain substring
+    assert_eq!(identify_service("abc", 80, &sigs), None);
+    assert_eq!(identify_service("a.c", 80, &sigs), Some("Literal".to_string()));
+
+    let header = vec![Signature {
+        name: "nginx".into(),
+        match_: "^nginx/1\\.2[0-9]".into(),
+        header: Some("Server".into()),
+        pattern: Some(regex::Regex::new("^nginx/1\\.2[0-9]").unwrap()),
+        ..Default::default()
+    }];
+    let response = HttpResponse {
+        status: 200,
+        headers: vec![("server".into(), "nginx/1.24.0".into())],
+        body: String::new(),
+    };
+    assert_eq!(identify_http_response(&response, 80, &header), Some("nginx".to_string()));
+
+    // A `version` group is captured, other groups and plain substrings capture nothing
+    let versioned = Signature {
+        name: "OpenSSH".into(),
+        match_: "OpenSSH_(?P<version>[0-9.]+)".into(),
+        pattern: Some(regex::Regex::new("OpenSSH_(?P<version>[0-9.]+)").unwrap()),
+        ..Default::default()
+    };
+    let found = match_banner("SSH-2.0-OpenSSH_9.6p1", 22, std::slice::from_ref(&versioned)).unwrap();
+    assert_eq!(found.version.as_deref(), Some("9.6"));
+    let found = match_http_response(&response, 80, &header).unwrap();
+    assert_eq!(found.version, None);
+}
+
+#[test]
+fn test_load_signatures_invalid_regex_skipped() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"signatures:
+  - name: Bad Regex
+    match: '(unclosed'
+    regex: true
+  - name: nginx
+    match: 'nginx/(?P<version>[0-9.]+)'
+    regex: true"#;
+    fs::write(temp_dir.path().join("mixed.yaml"), yaml).unwrap();
+
+    let sigs = load_validated_signatures_from(temp_dir.path(), false).unwrap();
+    assert_eq!(sigs.len(), 1);
+    assert_eq!(sigs[0].name, "nginx");
+    assert_eq!(identify_service("Server: nginx/1.25.3", 80, &sigs), Some("nginx".to_string()));
+}
+
+#[test]
+fn test_load_signatures_ports() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"signatures:
+  - name: SSH
+    match: SSH-
+    ports: [2222, 22, 22]
+  - name: Anything
+    match: hello
+  - name: Port Zero
+    match: x
+    ports: [0]
+  - name: Not A List
+    match: x
+    ports: 22"#;
+    fs::write(temp_dir.path().join("ports.yaml"), yaml).unwrap();
+
+    let err = load_validated_signatures_from(temp_dir.path(), false).unwrap_err().to_string();
+    assert!(err.contains("(2)") && err.contains("Port Zero") && err.contains("Not A List"), "{}", err);
+
+    let sigs = load_validated_signatures_from(temp_dir.path(), true).unwrap();
+    let ssh = sigs.iter().find(|sig| sig.name == "SSH").unwrap();
+    assert_eq!(ssh.ports, Some(vec![22, 2222]));
+    assert_eq!(identify_service("SSH-2.0-OpenSSH_9.6", 22, &sigs), Some("SSH".to_string()));
+    assert_eq!(identify_service("SSH-2.0-OpenSSH_9.6", 2222, &sigs), Some("SSH".to_string()));
+    // An SSH-like banner on an unrelated port is not reported as SSH
+    assert_eq!(identify_service("SSH-2.0-OpenSSH_9.6", 8080, &sigs), None);
+    // Signatures without ports match anywhere
+    assert_eq!(identify_service("hello", 8080, &sigs), Some("Anything".to_string()));
+}
+
+#[test]
+fn test_load_signatures_defaults_merge() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let yaml = r#"defaults:
+  status: 401
+  header: WWW-Authenticate
+  match: Basic
+signatures:
+  - name: Router A
+    match_: 'realm="router-a"'
+  - name: Any Basic Auth
+  - name: Maintenance
+    status: "500-599"
+    header: Server
+    min_bytes: 5"#;
+    fs::write(temp_dir.path().join("auth.yaml"), yaml).unwrap();
+    let sigs = load_validated_signatures_from(temp_dir.path(), false).unwrap();
+    let find = |name: &str| sigs.iter().find(|s| s.name == name).unwrap();
+
+    // Explicit values win, including `match_` over a default `match`
+    let router = find("Router A");
+    assert_eq!(router.match_, "realm=\"router-a\"");
+    assert_eq!(router.status, Some((401, 401)));
+    assert_eq!(router.header.as_deref(), Some("WWW-Authenticate"));
+
+    let any = find("Any Basic Auth");
+    assert_eq!(any.match_, "Basic");
+    assert_eq!(any.status, Some((401, 401)));
+
+    let maintenance = find("Maintenance");
+    assert_eq!(maintenance.status, Some((500, 599)));
+    assert_eq!(maintenance.header.as_deref(), Some("Server"));
+    assert_eq!(maintenance.match_, "Basic");
+    assert_eq!(maintenance.min_bytes, Some(5));
+
+    fs::write(temp_dir.path().join("auth.yaml"), "defaults: 3\nsignatures:\n  - name: X\n    match: X").unwrap();
+    assert!(load_validated_signatures_from(temp_dir.path(), false).is_err());
+}
+
+#[test]
+fn test_load_signatures_bundle() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let bundle = temp_dir.path().join("bundle.yaml");
+    let yaml = r#"- file: remote/ssh.yaml
+  signatures:
+    - name: SSH
+      match: SSH-
+- file: web/auth.yaml
+  defaults:
+    status: 401
+  signatures:
+    - name: Basic Auth
+      header: WWW-Authenticate
+"#;
+    fs::write(&bundle, yaml).unwrap();
+    let sigs = load_validated_signatures_from(&bundle, false).unwrap();
+    let names: Vec<_> = sigs.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Basic Auth", "SSH"]);
+    assert_eq!(sigs[0].status, Some((401, 401)));
+    assert_eq!(sigs[0].source, Some(format!("{} [web/auth.yaml]", bundle.display())));
+
+    // Malformed sections and entries are reported with their section
+    let broken = format!("{}- file: broken.yaml\n  signatures:\n    - just a string\n    - name: No Match\n- 42\n", yaml);
+    fs::write(&bundle, broken).unwrap();
+    let msg = load_validated_signatures_from(&bundle, false).unwrap_err().to_string();
+    assert!(msg.contains("(3)"), "{}", msg);
+    assert!(msg.contains("[broken.yaml]") && msg.contains("[#4]"), "{}", msg);
+    assert_eq!(load_validated_signatures_from(&bundle, true).unwrap().len(), 2);
+
+    fs::write(&bundle, "signatures:\n  - name: SSH\n    match: SSH-").unwrap();
+    assert!(load_validated_signatures_from(&bundle, false).is_err());
+    assert!(load_validated_signatures_from(&temp_dir.path().join("missing.yaml"), true).is_err());
+}
+
+#[test]
+fn test_load_signatures_at_file_or_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = temp_dir.path().join("web.yaml");
+    fs::write(&file, "defaults:\n  header: Server\nsignatures:\n  - name: nginx\n    match: nginx\n").unwrap();
+    fs::write(temp_dir.path().join("ssh.yaml"), "signatures:\n  - name: SSH\n    match: SSH-\n").unwrap();
+
+    // A file is read as one signature file, not as a bundle
+    let sigs = load_validated_signatures_at(&file, false, true).unwrap();
+    assert_eq!(sigs.len(), 1);
+    assert_eq!(sigs[0].header.as_deref(), Some("Server"));
+    assert_eq!(sigs[0].source, Some(file.display().to_string()));
+    assert!(load_validated_signatures_with(&file, false, true).is_err());
+
+    let names: Vec<_> = load_validated_signatures_at(temp_dir.path(), false, true)
+        .unwrap()
+        .into_iter()
+        .map(|sig| sig.name)
+        .collect();
+    assert_eq!(names, vec!["SSH", "nginx"]);
+
+    let err = load_validated_signatures_at(&temp_dir.path().join("missing.yaml"), true, true).unwrap_err();
+    assert!(matches!(err, ScanError::Config(_)));
+}
+
+#[test]
+fn test_load_signatures_with_builtin() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("own.yaml"),
+        "signatures:\n  - name: SSH\n    match: OpenSSH\n  - name: Custom\n    match: custom\n",
+    )
+    .unwrap();
+
+    let sigs = load_validated_signatures_with_builtin(Some(temp_dir.path()), false, false, true).unwrap();
+    // The file's SSH signature replaces the embedded one
+    let ssh: Vec<_> = sigs.iter().filter(|sig| sig.name == "SSH").collect();
+    assert_eq!(ssh.len(), 1);
+    assert_eq!(ssh[0].match_, "OpenSSH");
+    assert!(sigs.iter().any(|sig| sig.name == "Custom"));
+    assert!(sigs.iter().any(|sig| sig.name == "Grafana" && sig.source.is_none()));
+    assert!(sigs.windows(2).all(|pair| pair[0].dedup_key() < pair[1].dedup_key()));
+
+    let builtin = load_validated_signatures_with_builtin(None, false, false, true).unwrap();
+    assert!(builtin.iter().any(|sig| sig.name == "SSH" && sig.match_ == "SSH"));
+    let missing = temp_dir.path().join("missing");
+    assert!(load_validated_signatures_with_builtin(Some(&missing), false, true, true).is_err());
+}
+
+#[test]
+fn test_render_service_name() {
+    let host: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+    assert_eq!(render_service_name("Unknown HTTP on {port}".into(), &host, 8080), "Unknown HTTP on 8080");
+    assert_eq!(render_service_name("{host}:{port} {port}".into(), &host, 22), "10.0.0.1:22 22");
+    assert_eq!(render_service_name("SSH".into(), &host, 22), "SSH");
+    assert_eq!(render_service_name("odd {name}".into(), &host, 22), "odd {name}");
+}
+
+#[test]
+fn test_load_signatures_without_dedupe() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let bundle = temp_dir.path().join("bundle.yaml");
+    let yaml = r#"- file: web.yaml
+  signatures:
+    - name: nginx
+      match: nginx
+    - name: Apache
+      match: Apache
+- file: extra.yaml
+  signatures:
+    - name: nginx
+      match: nginx
+"#;
+    fs::write(&bundle, yaml).unwrap();
+    let names = |sigs: Vec<Signature>| sigs.into_iter().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names(load_validated_signatures_with(&bundle, false, true).unwrap()), vec!["Apache", "nginx"]);
+    assert_eq!(
+        names(load_validated_signatures_with(&bundle, false, false).unwrap()),
+        vec!["nginx", "Apache", "nginx"]
+    );
+}
+
+#[test]
+fn test_dedupe_keeps_signatures_differing_in_match_fields() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let bundle = temp_dir.path().join("bundle.yaml");
+    let yaml = r#"- file: a.yaml
+  signatures:
+    - name: Router
+      match: router
+    - name: Router
+      match: router
+      header: Server
+    - name: Router
+      match: router
+      status: 401
+    - name: Router
+      match: router
+      min_bytes: 64
+- file: b.yaml
+  signatures:
+    - name: Router
+      match: router
+      header: Server
+    - name: Router
+      match: router
+"#;
+    fs::write(&bundle, yaml).unwrap();
+    let sigs = load_validated_signatures_with(&bundle, false, true).unwrap();
+    // Only the exact copies from b.yaml are dropped
+    assert_eq!(sigs.len(), 4);
+    let keys: Vec<_> = sigs.iter().map(Signature::dedup_key).collect();
+    assert_eq!(keys[0], ("Router", "router", false, None, None, None, None));
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(keys.contains(&("Router", "router", false, None, Some("Server"), None, None)));
+    assert!(keys.contains(&("Router", "router", false, Some((401, 401)), None, None, None)));
+    assert!(keys.contains(&("Router", "router", false, None, None, Some(64), None)));
+}
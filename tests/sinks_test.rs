@@ -0,0 +1,112 @@
+// Unit tests for result sinks
+use port_explorer::scanner::{IdentificationSource, PortState, Protocol, ScanReport, ScanResult, ScanStats};
+use port_explorer::sinks::{JsonLinesSink, ResultSink, SinkSpec, Sinks};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Writer appending to a buffer the test keeps a handle to.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+/// Writer failing every write.
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink panicking on every call.
+struct Panicking;
+
+impl ResultSink for Panicking {
+    fn on_result(&self, _: &ScanResult) {
+        panic!("sink bug");
+    }
+
+    fn finish(&self, _: &ScanReport) {
+        panic!("sink bug");
+    }
+}
+
+fn result(port: u16) -> ScanResult {
+    ScanResult {
+        port,
+        service: Some("ssh".to_string()),
+        version: None,
+        protocol: Protocol::Tcp,
+        state: PortState::Open,
+        latency: None,
+        identified_by: IdentificationSource::Banner,
+        signature_file: None,
+        tls: None,
+    }
+}
+
+#[test]
+fn test_sink_spec_from_str() {
+    port_explorer::localisator::init("en");
+    assert_eq!("json:out.jsonl".parse::<SinkSpec>().unwrap(), SinkSpec::JsonFile(PathBuf::from("out.jsonl")));
+    assert_eq!("TCP:[::1]:9000".parse::<SinkSpec>().unwrap(), SinkSpec::Tcp("[::1]:9000".to_string()));
+    for invalid in ["json:", "tcp:collector", "tcp:collector:0", "udp:127.0.0.1:9000", "out.jsonl"] {
+        assert!(invalid.parse::<SinkSpec>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_sinks_isolate_failing_sinks() {
+    port_explorer::localisator::init("en");
+    let buffer = Buffer::default();
+    let mut sinks = Sinks::new();
+    sinks.push(Box::new(Panicking));
+    sinks.push(Box::new(JsonLinesSink::new("broken", Broken)));
+    sinks.push(Box::new(JsonLinesSink::new("buffer", buffer.clone())));
+    sinks.on_result(&result(22));
+    sinks.on_result(&result(80));
+    let report = ScanReport {
+        target: "127.0.0.1".to_string(),
+        ip: "127.0.0.1".parse().unwrap(),
+        port_range: "1-100".to_string(),
+        duration_ms: 5,
+        identification_rate: Some(1.0),
+        open_ports: vec![result(22), result(80)],
+        stats: ScanStats::default(),
+        conflicts: Vec::new(),
+    };
+    sinks.finish(&report);
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["event"], "result");
+    assert_eq!(lines[0]["result"]["port"], 22);
+    assert_eq!(lines[1]["result"]["port"], 80);
+    assert_eq!(lines[2]["event"], "report");
+    assert_eq!(lines[2]["report"]["ip"], "127.0.0.1");
+}
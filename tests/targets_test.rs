@@ -0,0 +1,70 @@
+use port_explorer::error::ScanError;
+use port_explorer::targets::{parse_target_list, read_target_list, target_delta, TargetDelta};
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+fn ips(list: &[&str]) -> Vec<IpAddr> {
+    list.iter().map(|ip| ip.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_parse_target_list() {
+    let config = HashMap::new();
+    let list = "# office\n10.0.0.1\n\n10.0.0.4-10.0.0.5\n  10.0.0.1  \n10.0.1.0/30\n::1\n";
+    assert_eq!(
+        parse_target_list(&config, list).unwrap(),
+        ips(&["10.0.0.1", "10.0.0.4", "10.0.0.5", "10.0.1.1", "10.0.1.2", "::1"])
+    );
+    assert!(parse_target_list(&config, "").unwrap().is_empty());
+
+    let err = parse_target_list(&config, "10.0.0.1\nnot_an_ip\n").unwrap_err();
+    assert!(matches!(err, ScanError::Config(_)));
+    assert!(err.to_string().contains(" 2: not_an_ip"), "{}", err);
+    assert!(matches!(parse_target_list(&config, "10.0.0.9-10.0.0.1"), Err(ScanError::Config(_))));
+    assert!(matches!(parse_target_list(&config, "10.0.0.0/8"), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_parse_target_list_safe_mode() {
+    let mut config = HashMap::new();
+    config.insert("safe_mode".to_string(), YamlValue::Bool(true));
+    assert_eq!(parse_target_list(&config, "192.168.0.1\n10.0.0.0/31").unwrap().len(), 3);
+    assert!(matches!(parse_target_list(&config, "192.168.0.1\n8.8.8.8"), Err(ScanError::NotAllowed(_))));
+}
+
+#[test]
+fn test_parse_target_list_hostnames() {
+    let mut config = HashMap::new();
+    config.insert("max_dns_concurrent".to_string(), YamlValue::from(2u64));
+    let hosts = parse_target_list(&config, "localhost\n10.0.0.1\nlocalhost\n").unwrap();
+    assert_eq!(hosts.len(), 2);
+    assert!(hosts[0].is_loopback());
+    assert_eq!(hosts[1], "10.0.0.1".parse::<IpAddr>().unwrap());
+
+    assert!(matches!(parse_target_list(&config, "does-not-exist.invalid"), Err(ScanError::Resolve(_))));
+    assert!(matches!(parse_target_list(&config, "10.0.0.256"), Err(ScanError::Config(_))));
+    config.insert("max_dns_concurrent".to_string(), YamlValue::from(0u64));
+    assert!(matches!(parse_target_list(&config, "localhost"), Err(ScanError::Config(_))));
+}
+
+#[test]
+fn test_read_target_list() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("hosts.txt");
+    std::fs::write(&path, "10.0.0.1\n10.0.0.2\n").unwrap();
+    assert_eq!(read_target_list(&HashMap::new(), &path).unwrap(), ips(&["10.0.0.1", "10.0.0.2"]));
+    assert!(read_target_list(&HashMap::new(), &temp_dir.path().join("missing.txt")).is_err());
+}
+
+#[test]
+fn test_target_delta() {
+    let previous = ips(&["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+    let current = ips(&["10.0.0.4", "10.0.0.2", "10.0.0.1", "10.0.0.5"]);
+    assert_eq!(
+        target_delta(&previous, &current),
+        TargetDelta { added: ips(&["10.0.0.4", "10.0.0.5"]), removed: ips(&["10.0.0.3"]) }
+    );
+    assert_eq!(target_delta(&previous, &previous), TargetDelta::default());
+    assert_eq!(target_delta(&[], &current).added, current);
+}
@@ -0,0 +1,194 @@
+use indicatif::ProgressBar;
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslAcceptor, SslMethod};
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509Builder, X509NameBuilder, X509};
+use port_explorer::output::format_certificate;
+use port_explorer::scanner::{scan_work_parallel, IdentificationSource, Probe, Protocol, ScanOptions};
+use port_explorer::signatures::Signature;
+use port_explorer::tls::describe_certificate;
+use std::net::{IpAddr, TcpListener};
+use std::sync::Arc;
+
+/// A self-signed certificate for `localhost`, valid from `not_before` to `not_after` (Unix times).
+fn certificate(not_before: i64, not_after: i64) -> (X509, PKey<Private>) {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("O", "Port Explorer").unwrap();
+    name.append_entry_by_text("CN", "localhost").unwrap();
+    let name = name.build();
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder.set_not_before(&Asn1Time::from_unix(not_before).unwrap()).unwrap();
+    builder.set_not_after(&Asn1Time::from_unix(not_after).unwrap()).unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&builder.x509v3_context(None, None))
+        .unwrap();
+    builder.append_extension(san).unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+    (builder.build(), key)
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[test]
+fn test_describe_certificate() {
+    port_explorer::localisator::init("en");
+    const DAY: i64 = 24 * 60 * 60;
+    let (cert, _) = certificate(1_700_000_000, now() + 100 * DAY + 60);
+    let described = describe_certificate(&cert, Some("localhost"), 30);
+    assert_eq!(described.subject, "O=Port Explorer, CN=localhost");
+    assert_eq!(described.issuer, described.subject);
+    assert_eq!(described.not_before, "2023-11-14T22:13:20Z");
+    assert_eq!(described.sans, vec!["localhost", "127.0.0.1"]);
+    assert_eq!(described.server_name.as_deref(), Some("localhost"));
+    assert_eq!(described.days_left, 100);
+    assert!(!described.expired && !described.expires_soon);
+
+    let (cert, _) = certificate(1_700_000_000, now() + 10 * DAY + 60);
+    let described = describe_certificate(&cert, None, 30);
+    assert!(!described.expired && described.expires_soon);
+    assert!(format_certificate(&described).ends_with("[expires in 10 days]"), "{}", format_certificate(&described));
+
+    let (cert, _) = certificate(1_700_000_000, now() - 60);
+    let described = describe_certificate(&cert, None, 30);
+    assert_eq!(described.days_left, -1);
+    assert!(described.expired && !described.expires_soon);
+    assert!(format_certificate(&described).ends_with("[expired]"));
+}
+
+#[test]
+fn test_scan_reports_tls_certificate() {
+    let (cert, key) = certificate(1_700_000_000, now() + 365 * 24 * 60 * 60);
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    let acceptor = Arc::new(acceptor.build());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let tls_port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let acceptor = Arc::clone(&acceptor);
+            std::thread::spawn(move || {
+                let _ = acceptor.accept(stream);
+            });
+        }
+    });
+    // A plain TCP port in the list gets no certificate
+    let plain = TcpListener::bind("127.0.0.1:0").unwrap();
+    let plain_port = plain.local_addr().unwrap().port();
+
+    let mut tls_ports = vec![tls_port, plain_port];
+    tls_ports.sort_unstable();
+    let options = ScanOptions {
+        probe: Probe::Tcp(Vec::new()),
+        tls_ports,
+        tls_server_name: Some("localhost".to_string()),
+        ..ScanOptions::default()
+    };
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let work = vec![(Protocol::Tcp, tls_port), (Protocol::Tcp, plain_port)];
+    // Nothing answers the probe, so only the certificate can name the service
+    let sigs = Arc::new(vec![Signature { name: "Explorer TLS".into(), match_: "O=Port Explorer".into(), ..Default::default() }]);
+    let (results, _) = scan_work_parallel(ip, work, sigs, &options, &ProgressBar::hidden(), |_| {}).unwrap();
+    let identified = results.iter().find(|r| r.port == tls_port).unwrap();
+    assert_eq!(identified.service.as_deref(), Some("Explorer TLS"));
+    assert_eq!(identified.identified_by, IdentificationSource::TlsCert);
+    let plain = results.iter().find(|r| r.port == plain_port).unwrap();
+    assert_eq!((plain.service.as_deref(), plain.identified_by), (None, IdentificationSource::None));
+    let tls = results.iter().find(|r| r.port == tls_port).and_then(|r| r.tls.as_ref()).expect("certificate");
+    assert_eq!(tls.subject, "O=Port Explorer, CN=localhost");
+    assert_eq!(tls.server_name.as_deref(), Some("localhost"));
+    assert!(!tls.expired);
+    assert_eq!(results.iter().find(|r| r.port == plain_port).unwrap().tls, None);
+
+    let json = serde_json::to_value(&results).unwrap();
+    let reported = json.as_array().unwrap().iter().find(|r| r["port"] == tls_port).unwrap();
+    assert_eq!(reported["tls"]["sans"][1], "127.0.0.1");
+    assert_eq!(reported["identified_by"], "tls-cert");
+}
+
+/// Start a service answering HTTP requests with `Server: secure-app`, over TLS if `acceptor` is
+/// given. The first byte of every connection is sent to the returned receiver.
+fn http_service(acceptor: Option<Arc<openssl::ssl::SslAcceptor>>) -> (u16, std::sync::mpsc::Receiver<u8>) {
+    use std::io::{Read, Write};
+    fn respond(stream: &mut (impl Read + Write)) {
+        let mut buf = [0u8; 1024];
+        if stream.read(&mut buf).unwrap_or(0) > 0 {
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nServer: secure-app\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        }
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (acceptor, tx) = (acceptor.clone(), tx.clone());
+            std::thread::spawn(move || {
+                let mut first = [0u8; 1];
+                if stream.peek(&mut first).unwrap_or(0) == 1 {
+                    let _ = tx.send(first[0]);
+                }
+                match acceptor {
+                    Some(acceptor) => {
+                        if let Ok(mut tls) = acceptor.accept(stream) {
+                            respond(&mut tls);
+                        }
+                    }
+                    None => respond(&mut { stream }),
+                }
+            });
+        }
+    });
+    (port, rx)
+}
+
+#[test]
+fn test_https_probe_fallback() {
+    use port_explorer::scanner::IdentificationSource;
+    use port_explorer::signatures::Signature;
+    let (cert, key) = certificate(1_700_000_000, now() + 365 * 24 * 60 * 60);
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    let (https_port, _) = http_service(Some(Arc::new(acceptor.build())));
+    let (http_port, first_bytes) = http_service(None);
+    let signatures = Arc::new(vec![Signature {
+        name: "Secure App".to_string(),
+        match_: "secure-app".to_string(),
+        status: None,
+        header: Some("Server".to_string()),
+        min_bytes: None,
+        ports: None,
+        source: None,
+        pattern: None,
+    }]);
+    let ip = Arc::new("127.0.0.1".parse::<IpAddr>().unwrap());
+    let work = vec![(Protocol::Tcp, https_port), (Protocol::Tcp, http_port)];
+    let scan = |options: &ScanOptions| {
+        let (results, _) = scan_work_parallel(Arc::clone(&ip), work.clone(), Arc::clone(&signatures), options, &ProgressBar::hidden(), |_| {})
+            .unwrap();
+        let find = |port| results.iter().find(|r| r.port == port).cloned().unwrap();
+        (find(https_port), find(http_port))
+    };
+
+    // The self-signed certificate fails validation unless it is turned off
+    let (https, http) = scan(&ScanOptions::default());
+    assert_eq!(https.service, None);
+    assert_eq!((http.service.as_deref(), http.identified_by), (Some("Secure App"), IdentificationSource::HttpHeader));
+    let (https, _) = scan(&ScanOptions { insecure_tls: true, ..ScanOptions::default() });
+    assert_eq!((https.service.as_deref(), https.identified_by), (Some("Secure App"), IdentificationSource::HttpsHeader));
+    assert_eq!(https.identified_by.to_string(), "https-header");
+    // A port that answered plaintext HTTP never sees a TLS handshake (a record starting with 0x16)
+    assert!(first_bytes.try_iter().all(|byte| byte != 0x16));
+}
@@ -0,0 +1,52 @@
+use port_explorer::error::ScanError;
+use port_explorer::tunnel::{ssh_jump, SshDestination};
+
+fn destination(user: Option<&str>, host: &str, port: u16) -> SshDestination {
+    SshDestination { user: user.map(str::to_string), host: host.into(), port }
+}
+
+#[test]
+fn test_parse_ssh_destination() {
+    port_explorer::localisator::init("en");
+    assert_eq!("bastion".parse::<SshDestination>().unwrap(), destination(None, "bastion", 22));
+    assert_eq!("ops@bastion".parse::<SshDestination>().unwrap(), destination(Some("ops"), "bastion", 22));
+    assert_eq!("ops@10.0.0.1:2222".parse::<SshDestination>().unwrap(), destination(Some("ops"), "10.0.0.1", 2222));
+    assert_eq!("ops@[2001:db8::1]:2222".parse::<SshDestination>().unwrap(), destination(Some("ops"), "2001:db8::1", 2222));
+    assert_eq!("2001:db8::1".parse::<SshDestination>().unwrap(), destination(None, "2001:db8::1", 22));
+    assert_eq!(destination(Some("ops"), "2001:db8::1", 2222).to_string(), "ops@[2001:db8::1]:2222");
+
+    for invalid in ["", "@bastion", "ops@", "bastion:0", "bastion:ssh", "-oProxyCommand=x", "[::1]2222", "ops@bas tion"] {
+        assert!(matches!(invalid.parse::<SshDestination>(), Err(ScanError::Config(_))), "{}", invalid);
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+#[test]
+fn test_ssh_jump_needs_feature() {
+    port_explorer::localisator::init("en");
+    assert!(matches!(ssh_jump(&destination(None, "127.0.0.1", 22)), Err(ScanError::Config(_))));
+}
+
+#[cfg(feature = "ssh")]
+#[test]
+fn test_ssh_jump_unreachable_aborts() {
+    port_explorer::localisator::init("en");
+    // Bind and drop a listener to get a local port nothing listens on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    assert!(matches!(ssh_jump(&destination(Some("ops"), "127.0.0.1", port)), Err(ScanError::Io(_))));
+}
+
+#[cfg(feature = "ssh")]
+#[test]
+fn test_ssh_jump_rejects_non_ssh_server() {
+    use std::io::Write;
+    port_explorer::localisator::init("en");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+        }
+    });
+    assert!(matches!(ssh_jump(&destination(Some("ops"), "127.0.0.1", port)), Err(ScanError::Io(_))));
+}